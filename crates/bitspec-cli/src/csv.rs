@@ -0,0 +1,126 @@
+//! CSV output: one row per record, one column per scalar value.
+//!
+//! Structs and arrays are flattened into columns named like field paths, e.g.
+//! `header.flags` and `items[2]`. Columns appear in schema order, and a record
+//! without a value for a column leaves its cell empty.
+
+use std::collections::BTreeMap;
+
+use bitspec::value::Value;
+
+/// Renders `records` as CSV with a header row. `order` lists the top-level field
+/// names in schema order.
+pub fn records_to_csv(records: &[BTreeMap<String, Value>], order: &[&str]) -> String {
+    let rows: Vec<Vec<(String, String)>> = records
+        .iter()
+        .map(|record| {
+            let mut cells = Vec::new();
+            for name in order {
+                if let Some(value) = record.get(*name) {
+                    flatten(name, value, &mut cells);
+                }
+            }
+            cells
+        })
+        .collect();
+
+    let mut columns: Vec<&str> = Vec::new();
+    for row in &rows {
+        for (column, _) in row {
+            if !columns.contains(&column.as_str()) {
+                columns.push(column);
+            }
+        }
+    }
+
+    let mut out = String::new();
+    push_line(&mut out, columns.iter().copied());
+    for row in &rows {
+        let cells: BTreeMap<&str, &str> = row
+            .iter()
+            .map(|(column, cell)| (column.as_str(), cell.as_str()))
+            .collect();
+        push_line(
+            &mut out,
+            columns
+                .iter()
+                .map(|column| cells.get(column).copied().unwrap_or("")),
+        );
+    }
+    out
+}
+
+/// Appends the cells of `value` under the column `path`.
+fn flatten(path: &str, value: &Value, cells: &mut Vec<(String, String)>) {
+    match value {
+        Value::Struct(map) => {
+            for (name, value) in map {
+                flatten(&format!("{path}.{name}"), value, cells);
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                flatten(&format!("{path}[{i}]"), item, cells);
+            }
+        }
+        Value::U64(v) => cells.push((path.to_string(), v.to_string())),
+        Value::I64(v) => cells.push((path.to_string(), v.to_string())),
+        Value::U128(v) => cells.push((path.to_string(), v.to_string())),
+        Value::I128(v) => cells.push((path.to_string(), v.to_string())),
+        Value::F32(v) => cells.push((path.to_string(), v.to_string())),
+        Value::F64(v) => cells.push((path.to_string(), v.to_string())),
+        Value::Bytes(bytes) => cells.push((path.to_string(), hex::encode(bytes))),
+        Value::String(s) => cells.push((path.to_string(), s.clone())),
+        Value::Absent => cells.push((path.to_string(), String::new())),
+    }
+}
+
+fn push_line<'a>(out: &mut String, cells: impl Iterator<Item = &'a str>) {
+    let cells: Vec<String> = cells.map(quote).collect();
+    out.push_str(&cells.join(","));
+    out.push('\n');
+}
+
+/// Quotes a cell holding a comma, quote, or line break.
+fn quote(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_flatten_into_columns_in_schema_order() {
+        let first = BTreeMap::from([
+            ("id".to_string(), Value::U64(1)),
+            (
+                "point".to_string(),
+                Value::Struct(BTreeMap::from([
+                    ("x".to_string(), Value::I64(-1)),
+                    ("y".to_string(), Value::U64(2)),
+                ])),
+            ),
+            (
+                "items".to_string(),
+                Value::Array(vec![Value::U64(3), Value::U64(4)]),
+            ),
+        ]);
+        let second = BTreeMap::from([
+            ("id".to_string(), Value::U64(2)),
+            ("name".to_string(), Value::String("a, \"b\"".to_string())),
+        ]);
+
+        let csv = records_to_csv(&[first, second], &["id", "point", "items", "name"]);
+        assert_eq!(
+            csv,
+            "id,point.x,point.y,items[0],items[1],name\n\
+             1,-1,2,3,4,\n\
+             2,,,,,\"a, \"\"b\"\"\"\n"
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! Errors reported by the command-line tool.
+
+use std::{fmt, io};
+
+use bitspec::{
+    errors::{CompileError, ReadError, WriteError},
+    transform::TransformError,
+};
+
+/// Everything that can make a command fail.
+#[derive(Debug)]
+pub enum CliError {
+    /// A file or stdin could not be read, or stdout could not be written.
+    Io {
+        /// File name, or `<stdin>` / `<stdout>`.
+        path: String,
+        /// Underlying error.
+        source: io::Error,
+    },
+    /// A schema or values file is not valid JSON of the expected shape.
+    Json {
+        /// File name, or `<stdin>`.
+        path: String,
+        /// Underlying error.
+        source: serde_json::Error,
+    },
+    /// A hex payload could not be decoded.
+    Hex(hex::FromHexError),
+    /// The schema does not compile.
+    Compile(CompileError),
+    /// The payload does not parse.
+    Read(ReadError),
+    /// The values do not serialize.
+    Write(WriteError),
+    /// A transform could not be applied to parsed values.
+    Transform(TransformError),
+    /// The input has a shape the command cannot use.
+    Input(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(f, "{path}: {source}"),
+            Self::Json { path, source } => write!(f, "{path}: invalid JSON: {source}"),
+            Self::Hex(e) => write!(f, "invalid hex payload: {e}"),
+            Self::Compile(e) => write!(f, "invalid schema: {e}"),
+            Self::Read(e) => write!(f, "cannot decode payload: {e}"),
+            Self::Write(e) => write!(f, "cannot encode values: {e}"),
+            Self::Transform(e) => write!(f, "cannot apply transforms: {e}"),
+            Self::Input(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for CliError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Json { source, .. } => Some(source),
+            Self::Hex(e) => Some(e),
+            Self::Compile(e) => Some(e),
+            Self::Read(e) => Some(e),
+            Self::Write(e) => Some(e),
+            Self::Transform(e) => Some(e),
+            Self::Input(_) => None,
+        }
+    }
+}
+
+impl From<hex::FromHexError> for CliError {
+    fn from(e: hex::FromHexError) -> Self {
+        Self::Hex(e)
+    }
+}
+
+impl From<CompileError> for CliError {
+    fn from(e: CompileError) -> Self {
+        Self::Compile(e)
+    }
+}
+
+impl From<ReadError> for CliError {
+    fn from(e: ReadError) -> Self {
+        Self::Read(e)
+    }
+}
+
+impl From<WriteError> for CliError {
+    fn from(e: WriteError) -> Self {
+        Self::Write(e)
+    }
+}
+
+impl From<TransformError> for CliError {
+    fn from(e: TransformError) -> Self {
+        Self::Transform(e)
+    }
+}
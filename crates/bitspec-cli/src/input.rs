@@ -0,0 +1,94 @@
+//! Reading schemas, payloads, and values from files or stdin.
+
+use std::{io::Read, path::Path};
+
+use bitspec::{schema::Schema, serde::SchemaDef};
+
+use crate::error::CliError;
+
+/// How a payload file or stdin is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PayloadFormat {
+    /// Raw bytes.
+    Binary,
+    /// Hex digits; whitespace and a leading `0x` are ignored.
+    Hex,
+}
+
+/// Reads `path`, or stdin when it is `None` or `-`.
+pub fn read_source(path: Option<&Path>) -> Result<(String, Vec<u8>), CliError> {
+    match path {
+        Some(path) if path != Path::new("-") => {
+            let name = path.display().to_string();
+            let bytes = std::fs::read(path).map_err(|source| CliError::Io {
+                path: name.clone(),
+                source,
+            })?;
+            Ok((name, bytes))
+        }
+        _ => {
+            let mut bytes = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut bytes)
+                .map_err(|source| CliError::Io {
+                    path: "<stdin>".to_string(),
+                    source,
+                })?;
+            Ok(("<stdin>".to_string(), bytes))
+        }
+    }
+}
+
+/// Reads and parses a JSON document from `path` or stdin.
+pub fn read_json<T: serde::de::DeserializeOwned>(path: Option<&Path>) -> Result<T, CliError> {
+    let (name, bytes) = read_source(path)?;
+    serde_json::from_slice(&bytes).map_err(|source| CliError::Json { path: name, source })
+}
+
+/// Loads the [`SchemaDef`] in `path` and compiles it.
+pub fn load_schema(path: &Path) -> Result<Schema, CliError> {
+    let def: SchemaDef = read_json(Some(path))?;
+    Ok(Schema::try_from(def)?)
+}
+
+/// Decodes a hex payload, ignoring whitespace and a leading `0x`.
+pub fn decode_hex(text: &str) -> Result<Vec<u8>, CliError> {
+    let digits: String = text.split_whitespace().collect();
+    let digits = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+        .unwrap_or(&digits);
+    Ok(hex::decode(digits)?)
+}
+
+/// Reads the payload given inline as `hex`, or from `path` / stdin in `format`.
+pub fn read_payload(
+    hex: Option<&str>,
+    path: Option<&Path>,
+    format: PayloadFormat,
+) -> Result<Vec<u8>, CliError> {
+    if let Some(hex) = hex {
+        return decode_hex(hex);
+    }
+    let (name, bytes) = read_source(path)?;
+    match format {
+        PayloadFormat::Binary => Ok(bytes),
+        PayloadFormat::Hex => {
+            let text = String::from_utf8(bytes)
+                .map_err(|_| CliError::Input(format!("{name}: hex payload is not text")))?;
+            decode_hex(&text)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_hex_ignores_whitespace_and_prefix() {
+        assert_eq!(decode_hex("0x01 ab\nFF").unwrap(), vec![0x01, 0xAB, 0xFF]);
+        assert_eq!(decode_hex("").unwrap(), Vec::<u8>::new());
+        assert!(matches!(decode_hex("abc"), Err(CliError::Hex(_))));
+    }
+}
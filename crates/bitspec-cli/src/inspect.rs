@@ -0,0 +1,465 @@
+//! Bit-layout table of a compiled schema.
+//!
+//! Every field, sub-field, array element, and switch case gets one row with its
+//! absolute bit ranges. Fields that follow a variable-length field in a sequential
+//! layout have no fixed position; their ranges are printed as `+a..b`, relative
+//! to where the previous field ends.
+
+use bitspec::{
+    assembly::{ArrayCount, BitOrder, Endian},
+    checksum::{ChecksumAlgorithm, ChecksumCoverage},
+    compiled::{CompiledField, CompiledFieldKind, CompiledScalar, Placement},
+    field::{BytesPadding, Condition, StringFraming},
+    schema::Schema,
+    transform::{Base, Transform},
+    value::Value,
+};
+
+/// One line of the layout table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    /// Field path, e.g. `header.flags`, `items[]`, or `body<2>.id`.
+    pub path: String,
+    /// Kind of field.
+    pub kind: String,
+    /// Bit ranges the field occupies.
+    pub bits: String,
+    /// Number of bits, where fixed.
+    pub len: String,
+    /// Signedness, bit order, presence condition, transform, and so on.
+    pub notes: String,
+}
+
+/// Lists the rows of every field in `schema`.
+pub fn layout_rows(schema: &Schema) -> Vec<Row> {
+    let mut rows = Vec::new();
+    push_fields(&schema.fields, "", 0, false, &mut rows);
+    rows
+}
+
+/// Renders `rows` as a table with a header line.
+pub fn render_table(rows: &[Row]) -> String {
+    let header = Row {
+        path: "FIELD".to_string(),
+        kind: "KIND".to_string(),
+        bits: "BITS".to_string(),
+        len: "LEN".to_string(),
+        notes: "NOTES".to_string(),
+    };
+    let all: Vec<&Row> = std::iter::once(&header).chain(rows).collect();
+    let width = |cell: fn(&Row) -> &str| all.iter().map(|row| cell(row).len()).max().unwrap_or(0);
+    let widths = [
+        width(|row| &row.path),
+        width(|row| &row.kind),
+        width(|row| &row.bits),
+        width(|row| &row.len),
+    ];
+
+    let mut out = String::new();
+    for row in all {
+        let line = format!(
+            "{:<w0$}  {:<w1$}  {:<w2$}  {:>w3$}  {}",
+            row.path,
+            row.kind,
+            row.bits,
+            row.len,
+            row.notes,
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+        );
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    if rows.iter().any(|row| row.bits.starts_with('+')) {
+        out.push_str("\n+a..b: bits after the end of the previous field\n");
+    }
+    out
+}
+
+fn push_fields(
+    fields: &[CompiledField],
+    prefix: &str,
+    base: usize,
+    relative: bool,
+    rows: &mut Vec<Row>,
+) {
+    // Once one field follows the cursor, so does everything after it.
+    let mut relative = relative;
+    for field in fields {
+        relative |= field.placement == Placement::Cursor;
+        let path = match field.kind {
+            CompiledFieldKind::Skip { .. } | CompiledFieldKind::Align(_) => String::new(),
+            _ => format!("{prefix}{}", field.name),
+        };
+        let mut notes = field_notes(field);
+        push_kind(&field.kind, path, base, relative, &mut notes, rows);
+    }
+}
+
+fn push_kind(
+    kind: &CompiledFieldKind,
+    path: String,
+    base: usize,
+    relative: bool,
+    notes: &mut Vec<String>,
+    rows: &mut Vec<Row>,
+) {
+    let range = |start: usize, end: Option<usize>| {
+        let sign = if relative { "+" } else { "" };
+        match end {
+            Some(end) => format!("{sign}{}..{}", base + start, base + end),
+            None => format!("{sign}{}..", base + start),
+        }
+    };
+    let mut row = |kind: &str, bits: String, len: Option<usize>, notes: &[String]| {
+        rows.push(Row {
+            path: path.clone(),
+            kind: kind.to_string(),
+            bits,
+            len: len.map(|len| len.to_string()).unwrap_or_default(),
+            notes: notes.join(", "),
+        })
+    };
+
+    match kind {
+        CompiledFieldKind::Scalar(scalar) => {
+            scalar_notes(scalar, notes);
+            row(
+                "scalar",
+                scalar_bits(scalar, &range),
+                Some(scalar.total_bits),
+                notes,
+            );
+        }
+        CompiledFieldKind::Reserved {
+            scalar,
+            fill,
+            check,
+        } => {
+            notes.push(format!("fill {fill:#x}"));
+            if *check {
+                notes.push("checked".to_string());
+            }
+            row(
+                "reserved",
+                scalar_bits(scalar, &range),
+                Some(scalar.total_bits),
+                notes,
+            );
+        }
+        CompiledFieldKind::Checksum {
+            scalar,
+            algorithm,
+            covers,
+            check,
+        } => {
+            notes.push(algorithm_name(algorithm));
+            notes.push(match covers {
+                ChecksumCoverage::Bits(bits) => {
+                    format!("over {}", range(bits.start, Some(bits.end)))
+                }
+                ChecksumCoverage::Fields(names) => format!("over {}", names.join(" + ")),
+            });
+            if *check {
+                notes.push("checked".to_string());
+            }
+            row(
+                "checksum",
+                scalar_bits(scalar, &range),
+                Some(scalar.total_bits),
+                notes,
+            );
+        }
+        CompiledFieldKind::Array(array) => {
+            let start = array.offset_bits;
+            let (count, end) = match &array.count {
+                ArrayCount::Fixed(0) => ("0".to_string(), Some(start)),
+                ArrayCount::Fixed(count) => (
+                    count.to_string(),
+                    Some(start + (count - 1) * array.stride_bits + array.element.end_bits()),
+                ),
+                ArrayCount::FromField { field, adjust } => {
+                    let count = match adjust {
+                        0 => field.clone(),
+                        adjust => format!("{field} {adjust:+}"),
+                    };
+                    (count, None)
+                }
+            };
+            notes.push(format!("stride {}", array.stride_bits));
+            row(
+                &format!("array[{count}]"),
+                range(start, end),
+                end.map(|end| end - start),
+                notes,
+            );
+            push_kind(
+                &array.element,
+                format!("{path}[]"),
+                base + start,
+                relative,
+                &mut Vec::new(),
+                rows,
+            );
+        }
+        CompiledFieldKind::Struct(group) => {
+            let end = kind.end_bits();
+            row("struct", range(kind.start_bits(), Some(end)), None, notes);
+            push_fields(
+                &group.fields,
+                &format!("{path}."),
+                base + group.offset_bits,
+                relative,
+                rows,
+            );
+        }
+        CompiledFieldKind::Switch(switch) => {
+            notes.push(format!("on {}", switch.on));
+            row("switch", range(switch.offset_bits, None), None, notes);
+            let cases = switch
+                .cases
+                .iter()
+                .map(|(case, fields)| (case.to_string(), fields))
+                .chain(
+                    switch
+                        .default
+                        .iter()
+                        .map(|fields| ("_".to_string(), fields)),
+                );
+            for (case, fields) in cases {
+                push_fields(
+                    fields,
+                    &format!("{path}<{case}>."),
+                    base + switch.offset_bits,
+                    relative,
+                    rows,
+                );
+            }
+        }
+        CompiledFieldKind::Bytes {
+            offset_bits,
+            len_bits,
+            padding,
+        } => {
+            if *padding == BytesPadding::Leading {
+                notes.push("leading pad".to_string());
+            }
+            row(
+                "bytes",
+                range(*offset_bits, Some(offset_bits + len_bits)),
+                Some(*len_bits),
+                notes,
+            );
+        }
+        CompiledFieldKind::String(string) => {
+            notes.push(format!("{:?}", string.encoding).to_lowercase());
+            notes.push(match string.framing {
+                StringFraming::LengthPrefixed { prefix_bits } => {
+                    format!("{prefix_bits}-bit length prefix")
+                }
+                StringFraming::NullTerminated => "null-terminated".to_string(),
+                StringFraming::Fixed { pad, .. } => format!("pad {pad:#04x}"),
+            });
+            let end = match string.framing {
+                StringFraming::Fixed { .. } => Some(kind.end_bits()),
+                _ => None,
+            };
+            row(
+                "string",
+                range(string.offset_bits, end),
+                end.map(|end| end - string.offset_bits),
+                notes,
+            );
+        }
+        CompiledFieldKind::Skip {
+            offset_bits,
+            len_bits,
+        } => row(
+            "skip",
+            range(*offset_bits, Some(offset_bits + len_bits)),
+            Some(*len_bits),
+            notes,
+        ),
+        CompiledFieldKind::Align(bits) => {
+            notes.push(format!("to a multiple of {bits} bits"));
+            row("align", String::new(), None, notes);
+        }
+    }
+}
+
+fn scalar_bits(scalar: &CompiledScalar, range: &dyn Fn(usize, Option<usize>) -> String) -> String {
+    scalar
+        .fragments
+        .iter()
+        .map(|fragment| {
+            range(
+                fragment.offset_bits,
+                Some(fragment.offset_bits + fragment.len_bits),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn scalar_notes(scalar: &CompiledScalar, notes: &mut Vec<String>) {
+    if scalar.signed {
+        notes.insert(0, "signed".to_string());
+    }
+    match scalar.endian {
+        Some(Endian::Big) => notes.push("big endian".to_string()),
+        Some(Endian::Little) => notes.push("little endian".to_string()),
+        Some(Endian::BigByteSwap) => notes.push("BADC byte order".to_string()),
+        Some(Endian::LittleByteSwap) => notes.push("CDAB byte order".to_string()),
+        None => {}
+    }
+    if scalar
+        .fragments
+        .iter()
+        .any(|fragment| fragment.bit_order == BitOrder::LsbFirst)
+    {
+        notes.push("lsb first".to_string());
+    }
+}
+
+/// Notes shared by every kind of field.
+fn field_notes(field: &CompiledField) -> Vec<String> {
+    let mut notes = Vec::new();
+    if let Some(condition) = &field.condition {
+        notes.push(format!("if {}", condition_text(condition)));
+    }
+    if let Some(constant) = &field.constant {
+        notes.push(format!("constant {}", value_text(constant)));
+    }
+    if let Some(default) = &field.default {
+        notes.push(format!("default {}", value_text(default)));
+    }
+    if let Some(transform) = &field.transform {
+        notes.push(transform_text(transform));
+    }
+    notes
+}
+
+fn condition_text(condition: &Condition) -> String {
+    match condition {
+        Condition::Equals { field, value } => format!("{field} == {value}"),
+        Condition::Mask {
+            field,
+            mask,
+            expected,
+        } => format!("{field} & {mask:#x} == {expected:#x}"),
+        Condition::Range { field, min, max } => format!("{min} <= {field} <= {max}"),
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    crate::json::value_to_json(value).to_string()
+}
+
+fn transform_text(transform: &Transform) -> String {
+    let base = match transform.base {
+        Base::Int => "int",
+        Base::Float32 => "float32",
+        Base::Float64 => "float64",
+        Base::Bytes => "bytes",
+    };
+    let mut parts = vec![format!("as {base}")];
+    if let Some(scale) = transform.scale {
+        parts.push(format!("* {scale}"));
+    }
+    if let Some(offset) = transform.offset {
+        parts.push(format!("+ {offset}"));
+    }
+    if let Some(encoding) = &transform.encoding {
+        parts.push(format!("{encoding:?}").to_lowercase());
+    }
+    if transform.enum_map.is_some() {
+        parts.push("enum".to_string());
+    }
+    parts.join(" ")
+}
+
+fn algorithm_name(algorithm: &ChecksumAlgorithm) -> String {
+    match algorithm {
+        ChecksumAlgorithm::Crc(crc) => format!("crc{}", crc.width),
+        ChecksumAlgorithm::Fletcher16 => "fletcher16".to_string(),
+        ChecksumAlgorithm::Fletcher32 => "fletcher32".to_string(),
+        ChecksumAlgorithm::Sum8 => "sum8".to_string(),
+        ChecksumAlgorithm::Xor8 => "xor8".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitspec::serde::SchemaDef;
+
+    fn schema(json: &str) -> Schema {
+        serde_json::from_str::<SchemaDef>(json)
+            .unwrap()
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_rows_give_absolute_bits_of_nested_fields() {
+        let schema = schema(
+            r#"{
+                "fields": [
+                    { "name": "id", "kind": { "type": "Scalar" }, "signed": false,
+                      "assemble": "ConcatMsb", "fragments": [{ "offset_bits": 0, "len_bits": 4 }] },
+                    { "name": "temp", "kind": { "type": "Scalar" }, "signed": true,
+                      "assemble": "ConcatMsb", "fragments": [{ "offset_bits": 4, "len_bits": 12 }],
+                      "transform": { "base": "Int", "scale": 0.5 } },
+                    { "name": "pos", "kind": { "type": "Struct", "fields": [
+                        { "name": "x", "kind": { "type": "Scalar" }, "signed": false,
+                          "assemble": "ConcatMsb", "fragments": [{ "offset_bits": 16, "len_bits": 8 }] }
+                    ] }, "signed": false, "assemble": "ConcatMsb", "fragments": [] },
+                    { "name": "items", "kind": { "type": "Array", "count": 2, "stride_bits": 8,
+                      "offset_bits": 24 }, "signed": false, "assemble": "ConcatMsb",
+                      "fragments": [{ "offset_bits": 0, "len_bits": 8 }] },
+                    { "name": "raw", "kind": { "type": "Bytes", "offset_bits": 40,
+                      "len_bits": 12, "padding": "Leading" }, "signed": false,
+                      "assemble": "ConcatMsb", "fragments": [] },
+                    { "name": "code", "kind": { "type": "String", "offset_bits": 56,
+                      "framing": { "type": "Fixed", "len_bytes": 2, "pad": 32 },
+                      "encoding": "Ascii" }, "signed": false, "assemble": "ConcatMsb",
+                      "fragments": [] }
+                ]
+            }"#,
+        );
+        let rows = layout_rows(&schema);
+        let summary: Vec<(&str, &str, &str, &str)> = rows
+            .iter()
+            .map(|row| {
+                (
+                    row.path.as_str(),
+                    row.kind.as_str(),
+                    row.bits.as_str(),
+                    row.len.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("id", "scalar", "0..4", "4"),
+                ("temp", "scalar", "4..16", "12"),
+                ("pos", "struct", "16..24", ""),
+                ("pos.x", "scalar", "16..24", "8"),
+                ("items", "array[2]", "24..40", "16"),
+                ("items[]", "scalar", "24..32", "8"),
+                ("raw", "bytes", "40..52", "12"),
+                ("code", "string", "56..72", "16"),
+            ]
+        );
+        assert_eq!(rows[1].notes, "signed, as int * 0.5");
+        assert_eq!(rows[6].notes, "leading pad");
+        assert_eq!(rows[7].notes, "ascii, pad 0x20");
+
+        let table = render_table(&rows);
+        assert!(table.starts_with("FIELD    KIND      BITS    LEN  NOTES\n"));
+        assert!(table.contains("\nitems[]  scalar    24..32    8\n"));
+    }
+}
@@ -0,0 +1,173 @@
+//! Conversion between bitspec [`Value`]s and plain JSON.
+//!
+//! Unlike the externally tagged serde form of [`Value`], values are written the
+//! way a person would type them: integers and floats as numbers, strings as
+//! strings, byte buffers as arrays of numbers, structs as objects, and absent
+//! fields as `null`. Integers outside the 64-bit range, which JSON numbers cannot
+//! hold exactly, are written as decimal strings.
+
+use std::collections::BTreeMap;
+
+use bitspec::value::Value;
+use serde_json::{Map, Number, Value as Json};
+
+/// Converts a parsed record into a JSON object.
+pub fn record_to_json(record: &BTreeMap<String, Value>) -> Json {
+    Json::Object(
+        record
+            .iter()
+            .map(|(name, value)| (name.clone(), value_to_json(value)))
+            .collect(),
+    )
+}
+
+/// Converts one value into JSON. Floats that JSON cannot hold (NaN, infinities)
+/// become `null`.
+pub fn value_to_json(value: &Value) -> Json {
+    match value {
+        Value::U64(v) => Json::from(*v),
+        Value::I64(v) => Json::from(*v),
+        Value::U128(v) => {
+            u64::try_from(*v).map_or_else(|_| Json::String(v.to_string()), Json::from)
+        }
+        Value::I128(v) => {
+            i64::try_from(*v).map_or_else(|_| Json::String(v.to_string()), Json::from)
+        }
+        Value::F32(v) => float_to_json(f64::from(*v)),
+        Value::F64(v) => float_to_json(*v),
+        Value::Bytes(bytes) => Json::Array(bytes.iter().map(|b| Json::from(*b)).collect()),
+        Value::String(s) => Json::String(s.clone()),
+        Value::Array(items) => Json::Array(items.iter().map(value_to_json).collect()),
+        Value::Struct(map) => record_to_json(map),
+        Value::Absent => Json::Null,
+    }
+}
+
+fn float_to_json(v: f64) -> Json {
+    Number::from_f64(v).map_or(Json::Null, Json::Number)
+}
+
+/// Converts a JSON object into a record for serialize. `null` entries are left
+/// out, so the field is treated as missing.
+pub fn json_to_record(json: &Json) -> Result<BTreeMap<String, Value>, String> {
+    match json {
+        Json::Object(map) => Ok(object_to_map(map)),
+        other => Err(format!(
+            "expected a JSON object of field values, found {other}"
+        )),
+    }
+}
+
+fn object_to_map(map: &Map<String, Json>) -> BTreeMap<String, Value> {
+    map.iter()
+        .filter(|(_, value)| !value.is_null())
+        .map(|(name, value)| (name.clone(), json_to_value(value)))
+        .collect()
+}
+
+/// Converts one JSON value. Integers become [`Value::U64`] (or [`Value::I64`] when
+/// negative), other numbers [`Value::F64`], and `true` / `false` 1 / 0. Strings of
+/// decimal digits outside the 64-bit range become [`Value::U128`] or
+/// [`Value::I128`], the way [`value_to_json`] writes them.
+pub fn json_to_value(json: &Json) -> Value {
+    match json {
+        Json::Null => Value::Absent,
+        Json::Bool(b) => Value::U64(u64::from(*b)),
+        Json::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(v), _) => Value::U64(v),
+            (None, Some(v)) => Value::I64(v),
+            (None, None) => Value::F64(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Json::String(s) => wide_int(s).unwrap_or_else(|| Value::String(s.clone())),
+        Json::Array(items) => Value::Array(items.iter().map(json_to_value).collect()),
+        Json::Object(map) => Value::Struct(object_to_map(map)),
+    }
+}
+
+/// Parses `s` as an integer that does not fit in 64 bits.
+fn wide_int(s: &str) -> Option<Value> {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    if s.parse::<u64>().is_ok() || s.parse::<i64>().is_ok() {
+        return None;
+    }
+    s.parse::<u128>()
+        .map(Value::U128)
+        .or_else(|_| s.parse::<i128>().map(Value::I128))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_values_convert_to_plain_json() {
+        let record = BTreeMap::from([
+            ("id".to_string(), Value::U64(7)),
+            ("temp".to_string(), Value::I64(-2)),
+            ("level".to_string(), Value::F64(1.5)),
+            ("name".to_string(), Value::String("ok".to_string())),
+            ("raw".to_string(), Value::Bytes(vec![1, 2])),
+            ("ext".to_string(), Value::Absent),
+            (
+                "point".to_string(),
+                Value::Struct(BTreeMap::from([("x".to_string(), Value::U64(1))])),
+            ),
+            ("nan".to_string(), Value::F32(f32::NAN)),
+            ("uuid".to_string(), Value::U128(u128::MAX)),
+            ("tag".to_string(), Value::I128(-3)),
+        ]);
+        assert_eq!(
+            record_to_json(&record),
+            json!({
+                "id": 7,
+                "temp": -2,
+                "level": 1.5,
+                "name": "ok",
+                "raw": [1, 2],
+                "ext": null,
+                "point": {"x": 1},
+                "nan": null,
+                "uuid": "340282366920938463463374607431768211455",
+                "tag": -3,
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_converts_to_values() {
+        let record = json_to_record(&json!({
+            "id": 7,
+            "temp": -2,
+            "level": 1.5,
+            "flag": true,
+            "ext": null,
+            "items": [1, 2],
+            "point": {"x": 1, "y": null},
+            "uuid": "340282366920938463463374607431768211455",
+            "code": "0042",
+        }))
+        .unwrap();
+        assert_eq!(record["id"], Value::U64(7));
+        assert_eq!(record["temp"], Value::I64(-2));
+        assert_eq!(record["level"], Value::F64(1.5));
+        assert_eq!(record["flag"], Value::U64(1));
+        assert!(!record.contains_key("ext"));
+        assert_eq!(record["uuid"], Value::U128(u128::MAX));
+        assert_eq!(record["code"], Value::String("0042".to_string()));
+        assert_eq!(
+            record["items"],
+            Value::Array(vec![Value::U64(1), Value::U64(2)])
+        );
+        assert_eq!(
+            record["point"],
+            Value::Struct(BTreeMap::from([("x".to_string(), Value::U64(1))]))
+        );
+
+        assert!(json_to_record(&json!([1, 2])).is_err());
+    }
+}
@@ -0,0 +1,237 @@
+//! # bitspec
+//!
+//! Command-line front end for `bitspec`: decode payloads with a JSON
+//! `SchemaDef`, encode values back into bytes, print a schema's bit layout, and
+//! check that a schema compiles.
+//!
+//! ```text
+//! bitspec decode --schema sensor.json --hex 1ffe80
+//! bitspec decode --schema sensor.json --records --format csv capture.bin
+//! bitspec encode --schema sensor.json values.json
+//! bitspec inspect --schema sensor.json
+//! bitspec validate --schema sensor.json
+//! ```
+
+mod csv;
+mod error;
+mod input;
+mod inspect;
+mod json;
+
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use bitspec::{schema::Schema, value::Value};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::{
+    error::CliError,
+    input::{PayloadFormat, load_schema, read_json, read_payload},
+};
+
+/// Decode, encode, and inspect binary data described by bitspec schemas.
+#[derive(Debug, Parser)]
+#[command(name = "bitspec", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Parse a payload and print its values as JSON or CSV.
+    Decode(DecodeArgs),
+    /// Serialize JSON values into a payload.
+    Encode(EncodeArgs),
+    /// Print the bit layout of every field in a schema.
+    Inspect(SchemaArgs),
+    /// Compile a schema and report any errors.
+    Validate(SchemaArgs),
+}
+
+#[derive(Debug, Args)]
+struct SchemaArgs {
+    /// JSON schema file, in the shape of `bitspec::serde::SchemaDef`.
+    #[arg(short, long)]
+    schema: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct DecodeArgs {
+    /// JSON schema file, in the shape of `bitspec::serde::SchemaDef`.
+    #[arg(short, long)]
+    schema: PathBuf,
+    /// Payload given as hex digits instead of reading FILE.
+    #[arg(long, conflicts_with = "file")]
+    hex: Option<String>,
+    /// Payload file; stdin when omitted or `-`.
+    file: Option<PathBuf>,
+    /// How FILE or stdin is encoded.
+    #[arg(long, value_enum, default_value_t = PayloadFormat::Binary)]
+    input: PayloadFormat,
+    /// Output format.
+    #[arg(short, long, value_enum, default_value_t = DecodeFormat::Json)]
+    format: DecodeFormat,
+    /// Print raw values without applying field transforms.
+    #[arg(long)]
+    raw: bool,
+    /// Parse back-to-back records until the payload ends.
+    #[arg(long)]
+    records: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DecodeFormat {
+    /// One JSON object, or an array of them with `--records`.
+    Json,
+    /// A header row and one row per record.
+    Csv,
+}
+
+#[derive(Debug, Args)]
+struct EncodeArgs {
+    /// JSON schema file, in the shape of `bitspec::serde::SchemaDef`.
+    #[arg(short, long)]
+    schema: PathBuf,
+    /// JSON file holding an object of field values, or an array of objects to
+    /// encode back to back; stdin when omitted or `-`.
+    file: Option<PathBuf>,
+    /// Output format.
+    #[arg(short, long, value_enum, default_value_t = PayloadFormat::Hex)]
+    format: PayloadFormat,
+    /// Treat the values as raw, without inverting field transforms.
+    #[arg(long)]
+    raw: bool,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<(), CliError> {
+    match command {
+        Command::Decode(args) => decode(&args),
+        Command::Encode(args) => encode(&args),
+        Command::Inspect(args) => {
+            let schema = load_schema(&args.schema)?;
+            let table = inspect::render_table(&inspect::layout_rows(&schema));
+            write_stdout(table.as_bytes())
+        }
+        Command::Validate(args) => validate(&args.schema),
+    }
+}
+
+fn decode(args: &DecodeArgs) -> Result<(), CliError> {
+    let schema = load_schema(&args.schema)?;
+    let data = read_payload(args.hex.as_deref(), args.file.as_deref(), args.input)?;
+
+    let records = if args.records {
+        schema.parse_many(&data).collect::<Result<Vec<_>, _>>()?
+    } else {
+        vec![schema.parse(&data)?]
+    };
+    let records = if args.raw {
+        records
+    } else {
+        records
+            .into_iter()
+            .map(|record| schema.apply_transforms(record))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let out = match args.format {
+        DecodeFormat::Json => {
+            let json = match records.as_slice() {
+                [record] if !args.records => json::record_to_json(record),
+                records => records.iter().map(json::record_to_json).collect(),
+            };
+            let mut text = serde_json::to_string_pretty(&json).expect("JSON values serialize");
+            text.push('\n');
+            text
+        }
+        DecodeFormat::Csv => {
+            let order: Vec<&str> = schema
+                .fields
+                .iter()
+                .filter(|field| field.kind.has_value())
+                .map(|field| field.name.as_str())
+                .collect();
+            csv::records_to_csv(&records, &order)
+        }
+    };
+    write_stdout(out.as_bytes())
+}
+
+fn encode(args: &EncodeArgs) -> Result<(), CliError> {
+    let schema = load_schema(&args.schema)?;
+    let json: serde_json::Value = read_json(args.file.as_deref())?;
+    let records = match &json {
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(json::json_to_record)
+            .collect::<Result<Vec<_>, _>>(),
+        other => json::json_to_record(other).map(|record| vec![record]),
+    }
+    .map_err(CliError::Input)?;
+
+    let mut bytes = Vec::new();
+    for record in &records {
+        bytes.extend(serialize(&schema, record, args.raw)?);
+    }
+    match args.format {
+        PayloadFormat::Binary => write_stdout(&bytes),
+        PayloadFormat::Hex => write_stdout(format!("{}\n", hex::encode(&bytes)).as_bytes()),
+    }
+}
+
+fn serialize(
+    schema: &Schema,
+    record: &BTreeMap<String, Value>,
+    raw: bool,
+) -> Result<Vec<u8>, CliError> {
+    if raw {
+        Ok(schema.serialize(record)?)
+    } else {
+        Ok(schema.serialize_transformed(record)?)
+    }
+}
+
+fn validate(path: &Path) -> Result<(), CliError> {
+    let schema = load_schema(path)?;
+    let mut out = format!(
+        "{}: ok, {} top-level fields\n",
+        path.display(),
+        schema.fields.len()
+    );
+    let uncovered = schema.uncovered_bits();
+    if !uncovered.is_empty() {
+        let ranges: Vec<String> = uncovered
+            .iter()
+            .map(|range| format!("{}..{}", range.start, range.end))
+            .collect();
+        out.push_str(&format!("uncovered bits: {}\n", ranges.join(", ")));
+    }
+    write_stdout(out.as_bytes())
+}
+
+fn write_stdout(bytes: &[u8]) -> Result<(), CliError> {
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(bytes)
+        .and_then(|()| stdout.flush())
+        .map_err(|source| CliError::Io {
+            path: "<stdout>".to_string(),
+            source,
+        })
+}
@@ -0,0 +1,107 @@
+//! Runs the `bitspec` binary on a small schema.
+
+use std::{
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+const SCHEMA: &str = r#"{
+    "fields": [
+        { "name": "version", "kind": { "type": "Scalar" }, "signed": false,
+          "assemble": "ConcatMsb", "fragments": [{ "offset_bits": 0, "len_bits": 4 }] },
+        { "name": "temp", "kind": { "type": "Scalar" }, "signed": true,
+          "assemble": "ConcatMsb", "fragments": [{ "offset_bits": 4, "len_bits": 12 }],
+          "transform": { "base": "Int", "scale": 0.5 } },
+        { "name": "flag", "kind": { "type": "Scalar" }, "signed": false,
+          "assemble": "ConcatMsb", "fragments": [{ "offset_bits": 16, "len_bits": 1 }] }
+    ]
+}"#;
+
+/// Writes `contents` to a file unique to the calling test.
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("bitspec-cli-{}-{name}", std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn bitspec(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_bitspec"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_decode_prints_transformed_json_and_csv() {
+    let schema = temp_file("decode.json", SCHEMA);
+    let schema = schema.to_str().unwrap();
+
+    let output = bitspec(&["decode", "--schema", schema, "--hex", "1ffe80"], b"");
+    let json: serde_json::Value = serde_json::from_str(stdout(&output)).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({"version": 1, "temp": -1.0, "flag": 1})
+    );
+
+    let output = bitspec(
+        &[
+            "decode",
+            "--schema",
+            schema,
+            "--records",
+            "--format",
+            "csv",
+            "--raw",
+        ],
+        &[0x1F, 0xFE, 0x80, 0x20, 0x04, 0x00],
+    );
+    assert_eq!(stdout(&output), "version,temp,flag\n1,-2,1\n2,4,0\n");
+}
+
+#[test]
+fn test_encode_inverts_transforms() {
+    let schema = temp_file("encode.json", SCHEMA);
+    let schema = schema.to_str().unwrap();
+
+    let output = bitspec(
+        &["encode", "--schema", schema],
+        br#"[{"version": 1, "temp": -1.0, "flag": 1}, {"version": 2, "temp": 2, "flag": 0}]"#,
+    );
+    assert_eq!(stdout(&output), "1ffe80200400\n");
+}
+
+#[test]
+fn test_inspect_and_validate_report_on_the_schema() {
+    let schema = temp_file("inspect.json", SCHEMA);
+    let schema = schema.to_str().unwrap();
+
+    let output = bitspec(&["inspect", "--schema", schema], b"");
+    assert!(stdout(&output).contains("temp     scalar  4..16    12  signed, as int * 0.5\n"));
+
+    let output = bitspec(&["validate", "--schema", schema], b"");
+    assert!(stdout(&output).ends_with(": ok, 3 top-level fields\n"));
+
+    let broken = temp_file(
+        "broken.json",
+        &SCHEMA.replace("\"len_bits\": 12", "\"len_bits\": 0"),
+    );
+    let output = bitspec(&["validate", "--schema", broken.to_str().unwrap()], b"");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: invalid schema:"), "{stderr}");
+}
@@ -0,0 +1,481 @@
+#![deny(missing_docs)]
+//! # bitspec-derive
+//!
+//! `#[derive(BitSpec)]` for structs with named fields. The derive implements
+//! `bitspec::derive::BitSpec`, which provides `fields()`, a cached `schema()`, and
+//! typed `from_bytes` / `to_bytes`. Enable it through the `derive` feature of
+//! `bitspec` rather than depending on this crate directly.
+//!
+//! ## Field attributes
+//!
+//! Every field may carry `#[bits(...)]` with:
+//!
+//! - `offset = N` — bit offset of the field. Defaults to the bit after the
+//!   previous field ends, or 0 for the first field.
+//! - `len = N` — number of bits. Defaults to the width of the type (1 for `bool`);
+//!   may not exceed it.
+//! - `signed` — sign-extend the value. Implied by `i8`..`i128`, and only valid on them.
+//! - `lsb` — read the bits least significant bit first.
+//! - `stride = N` — for arrays, bits between the start of consecutive elements.
+//!   Defaults to the element size.
+//!
+//! Field types are `u8`..`u128`, `i8`..`i128`, `bool`, other structs deriving
+//! `BitSpec` (laid out at `offset`), and fixed-size arrays `[T; N]` of those.
+//!
+//! Overlapping integer and `bool` fields, and arrays whose `stride` is smaller
+//! than their elements, fail to compile. The size of a nested struct is not known
+//! to the derive, so overlaps involving one are reported by a panic when
+//! `schema()` is first used.
+//!
+//! ## Example
+//!
+//! ```ignore
+//! use bitspec::BitSpec;
+//!
+//! #[derive(BitSpec)]
+//! struct Header {
+//!     #[bits(offset = 0, len = 4)]
+//!     version: u8,
+//!     #[bits(offset = 4, len = 12, signed)]
+//!     temperature: i16,
+//!     flag: bool,
+//! }
+//!
+//! let header = Header::from_bytes(&[0x1F, 0xFE, 0x80])?;
+//! assert_eq!(header.temperature, -2);
+//! ```
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Error, Expr, Fields, LitInt, Type, parse_macro_input, spanned::Spanned,
+};
+
+/// Derives `bitspec::derive::BitSpec` (and `BitValue`, so the struct can be nested
+/// in another one) from `#[bits(...)]` field attributes.
+#[proc_macro_derive(BitSpec, attributes(bits))]
+pub fn derive_bitspec(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Options from one field's `#[bits(...)]` attributes.
+#[derive(Default)]
+struct BitsAttr {
+    offset: Option<LitInt>,
+    len: Option<LitInt>,
+    signed: bool,
+    lsb: bool,
+    stride: Option<LitInt>,
+}
+
+impl BitsAttr {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let mut attr = BitsAttr::default();
+        for bits in field.attrs.iter().filter(|a| a.path().is_ident("bits")) {
+            bits.parse_nested_meta(|meta| {
+                if meta.path.is_ident("offset") {
+                    attr.offset = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("len") {
+                    attr.len = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("stride") {
+                    attr.stride = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("signed") {
+                    attr.signed = true;
+                } else if meta.path.is_ident("lsb") {
+                    attr.lsb = true;
+                } else {
+                    return Err(
+                        meta.error("expected `offset`, `len`, `signed`, `lsb`, or `stride`")
+                    );
+                }
+                Ok(())
+            })?;
+        }
+        Ok(attr)
+    }
+}
+
+/// What a field's Rust type maps to.
+enum Shape<'a> {
+    /// An integer or `bool` of this many bits.
+    Primitive { width: usize, signed: bool },
+    /// Another struct deriving `BitSpec`.
+    Nested(&'a Type),
+}
+
+fn shape(ty: &Type) -> Shape<'_> {
+    let primitive = |width, signed| Shape::Primitive { width, signed };
+    match ty {
+        Type::Path(path) if path.qself.is_none() => match path.path.get_ident() {
+            Some(ident) if ident == "bool" => primitive(1, false),
+            Some(ident) if ident == "u8" => primitive(8, false),
+            Some(ident) if ident == "u16" => primitive(16, false),
+            Some(ident) if ident == "u32" => primitive(32, false),
+            Some(ident) if ident == "u64" => primitive(64, false),
+            Some(ident) if ident == "u128" => primitive(128, false),
+            Some(ident) if ident == "i8" => primitive(8, true),
+            Some(ident) if ident == "i16" => primitive(16, true),
+            Some(ident) if ident == "i32" => primitive(32, true),
+            Some(ident) if ident == "i64" => primitive(64, true),
+            Some(ident) if ident == "i128" => primitive(128, true),
+            _ => Shape::Nested(ty),
+        },
+        _ => Shape::Nested(ty),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "#[derive(BitSpec)] does not support generic structs",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    input.ident.span(),
+                    "#[derive(BitSpec)] needs a struct with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                input.ident.span(),
+                "#[derive(BitSpec)] only supports structs",
+            ));
+        }
+    };
+
+    let mut builds = Vec::new();
+    let mut reads = Vec::new();
+    let mut writes = Vec::new();
+    let mut layout = StaticLayout::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let name = ident.to_string();
+        let attr = BitsAttr::parse(field)?;
+        builds.push(build_field(field, &name, &attr)?);
+        layout.add(field, &attr)?;
+        reads.push(quote! { #ident: ::bitspec::derive::take_field(values, #name)? });
+        writes.push(quote! {
+            values.insert(
+                #name.to_string(),
+                ::bitspec::derive::BitValue::to_value(&self.#ident),
+            );
+        });
+    }
+
+    layout.check_overlaps()?;
+
+    let ty = &input.ident;
+    Ok(quote! {
+        impl ::bitspec::derive::BitSpec for #ty {
+            fn fields() -> ::std::vec::Vec<::bitspec::field::Field> {
+                let mut fields = ::std::vec::Vec::new();
+                let mut cursor = 0usize;
+                #(#builds)*
+                let _ = cursor;
+                fields
+            }
+
+            fn schema() -> &'static ::bitspec::schema::Schema {
+                static SCHEMA: ::std::sync::OnceLock<::bitspec::schema::Schema> =
+                    ::std::sync::OnceLock::new();
+                SCHEMA.get_or_init(|| {
+                    ::bitspec::schema::Schema::compile(&<Self as ::bitspec::derive::BitSpec>::fields(), None)
+                        .unwrap_or_else(|e| panic!("invalid bit layout for {}: {e}", stringify!(#ty)))
+                })
+            }
+
+            fn from_values(
+                values: &::std::collections::BTreeMap<::std::string::String, ::bitspec::Value>,
+            ) -> ::std::result::Result<Self, ::bitspec::errors::ReadError> {
+                ::std::result::Result::Ok(Self { #(#reads),* })
+            }
+
+            fn to_values(
+                &self,
+            ) -> ::std::collections::BTreeMap<::std::string::String, ::bitspec::Value> {
+                let mut values = ::std::collections::BTreeMap::new();
+                #(#writes)*
+                values
+            }
+        }
+
+        impl ::bitspec::derive::BitValue for #ty {
+            fn from_value(value: &::bitspec::Value) -> ::std::option::Option<Self> {
+                match value {
+                    ::bitspec::Value::Struct(values) => {
+                        <Self as ::bitspec::derive::BitSpec>::from_values(values).ok()
+                    }
+                    _ => ::std::option::Option::None,
+                }
+            }
+
+            fn to_value(&self) -> ::bitspec::Value {
+                ::bitspec::Value::Struct(<Self as ::bitspec::derive::BitSpec>::to_values(self))
+            }
+        }
+    })
+}
+
+/// Bit ranges of the fields whose position and size are known from the attributes
+/// alone, so that overlaps are reported when the struct is compiled rather than
+/// when its schema is first used. A nested struct's size comes from its own
+/// derive, so it and the fields placed after it without an `offset` are left to
+/// the runtime check in `schema()`.
+struct StaticLayout {
+    /// End of the previous field, if known.
+    cursor: Option<usize>,
+    /// Number of fields added so far.
+    fields: usize,
+    /// Every known field or array element.
+    ranges: Vec<KnownRange>,
+}
+
+/// Bits `start..end` of the field declared at position `index`.
+struct KnownRange {
+    start: usize,
+    end: usize,
+    index: usize,
+    ident: syn::Ident,
+}
+
+impl StaticLayout {
+    fn new() -> Self {
+        StaticLayout {
+            cursor: Some(0),
+            fields: 0,
+            ranges: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, field: &syn::Field, attr: &BitsAttr) -> syn::Result<()> {
+        let index = self.fields;
+        self.fields += 1;
+        let offset = match &attr.offset {
+            Some(offset) => Some(offset.base10_parse::<usize>()?),
+            None => self.cursor,
+        };
+        let (element_ty, count) = match &field.ty {
+            Type::Array(array) => (&*array.elem, Some(&array.len)),
+            ty => (ty, None),
+        };
+        let count = match count {
+            None => Some(1),
+            Some(Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(count),
+                ..
+            })) => Some(count.base10_parse::<usize>()?),
+            Some(_) => None,
+        };
+        let (Some(offset), Some(count), Shape::Primitive { width, .. }) =
+            (offset, count, shape(element_ty))
+        else {
+            self.cursor = None;
+            return Ok(());
+        };
+        let len = match &attr.len {
+            Some(len) => len.base10_parse::<usize>()?,
+            None => width,
+        };
+        let stride = match &attr.stride {
+            Some(stride) => stride.base10_parse::<usize>()?,
+            None => len,
+        };
+        if count > 1 && stride < len {
+            return Err(Error::new(
+                attr.stride.as_ref().map_or(field.ty.span(), LitInt::span),
+                format!("`stride` is smaller than the element size ({len} bits)"),
+            ));
+        }
+
+        let ident = field.ident.clone().expect("named field");
+        for element in 0..count {
+            let start = offset + element * stride;
+            self.ranges.push(KnownRange {
+                start,
+                end: start + len,
+                index,
+                ident: ident.clone(),
+            });
+        }
+        self.cursor = Some(match count {
+            0 => offset,
+            count => offset + (count - 1) * stride + len,
+        });
+        Ok(())
+    }
+
+    /// Fails on the first two known fields that share a bit, pointing at the one
+    /// declared later.
+    fn check_overlaps(mut self) -> syn::Result<()> {
+        self.ranges.sort_by_key(|range| range.start);
+        let mut widest: Option<&KnownRange> = None;
+        for range in &self.ranges {
+            if let Some(previous) = widest.filter(|previous| range.start < previous.end) {
+                let (earlier, later) = if previous.index < range.index {
+                    (previous, range)
+                } else {
+                    (range, previous)
+                };
+                return Err(Error::new(
+                    later.ident.span(),
+                    format!(
+                        "field `{}` overlaps `{}` at bit {}",
+                        later.ident, earlier.ident, range.start
+                    ),
+                ));
+            }
+            if widest.is_none_or(|previous| range.end > previous.end) {
+                widest = Some(range);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Statements pushing `field`'s [`bitspec::field::Field`] onto `fields` and moving
+/// `cursor` past it.
+fn build_field(field: &syn::Field, name: &str, attr: &BitsAttr) -> syn::Result<TokenStream2> {
+    let (element_ty, count) = match &field.ty {
+        Type::Array(array) => (&*array.elem, Some(&array.len)),
+        ty => (ty, None),
+    };
+    if count.is_none() && attr.stride.is_some() {
+        return Err(Error::new(
+            field.ty.span(),
+            "`stride` only applies to arrays",
+        ));
+    }
+
+    let offset = match &attr.offset {
+        Some(offset) => quote! { #offset },
+        None => quote! { cursor },
+    };
+    let field_kind = |kind: TokenStream2, fragments: TokenStream2, signed: bool| {
+        quote! {
+            ::bitspec::field::Field {
+                name: #name.to_string(),
+                kind: #kind,
+                signed: #signed,
+                assemble: ::bitspec::assembly::Assemble::Concat(::bitspec::assembly::BitOrder::MsbFirst),
+                fragments: #fragments,
+                transform: ::std::option::Option::None,
+                condition: ::std::option::Option::None,
+                default: ::std::option::Option::None,
+                constant: ::std::option::Option::None,
+            }
+        }
+    };
+    let array_kind = |count: &Expr, stride: TokenStream2, element: TokenStream2| {
+        quote! {
+            ::bitspec::field::FieldKind::Array(::bitspec::field::ArraySpec {
+                count: ::bitspec::assembly::ArrayCount::Fixed(#count),
+                stride_bits: #stride,
+                offset_bits: offset,
+                element: #element,
+            })
+        }
+    };
+
+    let built = match shape(element_ty) {
+        Shape::Primitive { width, signed } => {
+            let len = match &attr.len {
+                Some(len) => {
+                    let bits = len.base10_parse::<usize>()?;
+                    if bits == 0 || bits > width {
+                        return Err(Error::new(
+                            len.span(),
+                            format!("`len` must be between 1 and {width} for this type"),
+                        ));
+                    }
+                    bits
+                }
+                None => width,
+            };
+            if attr.signed && !signed {
+                return Err(Error::new(
+                    field.ty.span(),
+                    "`signed` needs a signed integer type",
+                ));
+            }
+            let bit_order = if attr.lsb {
+                quote! { ::bitspec::assembly::BitOrder::LsbFirst }
+            } else {
+                quote! { ::bitspec::assembly::BitOrder::MsbFirst }
+            };
+            match count {
+                None => field_kind(
+                    quote! { ::bitspec::field::FieldKind::Scalar },
+                    quote! { ::std::vec![::bitspec::fragment::Fragment::new_with_bit_order(offset, #len, #bit_order)] },
+                    signed,
+                ),
+                Some(count) => {
+                    let stride = match &attr.stride {
+                        Some(stride) => quote! { #stride },
+                        None => quote! { #len },
+                    };
+                    field_kind(
+                        array_kind(count, stride, quote! { ::std::option::Option::None }),
+                        quote! { ::std::vec![::bitspec::fragment::Fragment::new_with_bit_order(0, #len, #bit_order)] },
+                        signed,
+                    )
+                }
+            }
+        }
+        Shape::Nested(ty) => {
+            if attr.len.is_some() || attr.signed || attr.lsb {
+                return Err(Error::new(
+                    field.ty.span(),
+                    "`len`, `signed`, and `lsb` only apply to integer and bool fields",
+                ));
+            }
+            let inner = quote! { <#ty as ::bitspec::derive::BitSpec>::fields() };
+            match count {
+                None => field_kind(
+                    quote! {
+                        ::bitspec::field::FieldKind::Struct(
+                            ::bitspec::derive::shift_fields(#inner, offset),
+                        )
+                    },
+                    quote! { ::std::vec::Vec::new() },
+                    false,
+                ),
+                Some(count) => {
+                    let stride = match &attr.stride {
+                        Some(stride) => quote! { #stride },
+                        None => quote! { ::bitspec::derive::fields_end_bits(&#inner) },
+                    };
+                    field_kind(
+                        array_kind(
+                            count,
+                            stride,
+                            quote! {
+                                ::std::option::Option::Some(::std::boxed::Box::new(
+                                    ::bitspec::field::FieldKind::Struct(#inner),
+                                ))
+                            },
+                        ),
+                        quote! { ::std::vec::Vec::new() },
+                        false,
+                    )
+                }
+            }
+        }
+    };
+
+    Ok(quote! {
+        let offset: usize = #offset;
+        let field = #built;
+        cursor = ::bitspec::derive::fields_end_bits(::std::slice::from_ref(&field)).max(offset);
+        fields.push(field);
+    })
+}
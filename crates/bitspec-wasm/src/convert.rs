@@ -7,13 +7,20 @@ use wasm_bindgen::JsValue;
 /// `serde_wasm_bindgen`. The serde representation of `Value` is externally
 /// tagged: `{"U64": 42}`, `{"F64": 3.14}`, etc.
 ///
-/// u64/i64 values are serialized as JS bigint to avoid precision loss above 2^53.
+/// u64/i64 and u128/i128 values are serialized as JS bigint to avoid precision
+/// loss above 2^53.
 pub fn map_to_js(
     map: BTreeMap<String, bitspec::value::Value>,
 ) -> Result<JsValue, JsValue> {
-    use serde::Serialize;
+    to_js(&map)
+}
+
+/// Like [`map_to_js`], for any serializable shape of values (e.g. a list of
+/// records, or a map of columns).
+pub fn to_js(value: &impl serde::Serialize) -> Result<JsValue, JsValue> {
     let serializer = serde_wasm_bindgen::Serializer::new()
         .serialize_large_number_types_as_bigints(true);
-    map.serialize(&serializer)
+    value
+        .serialize(&serializer)
         .map_err(|e| JsValue::from(crate::error::WasmError::from(e)))
 }
@@ -2,75 +2,140 @@
 //!
 //! Typed Rust errors (`CompileError`, `ReadError`, `WriteError`, `TransformError`)
 //! are converted to `WasmError` via `From` impls before being serialized across
-//! the WASM boundary as `{ code, message }`.
+//! the WASM boundary as `{ code, message }`, plus the field context when the error
+//! carries one.
 
-use bitspec::errors::{CompileError, ReadError, WriteError};
+use bitspec::errors::{CompileError, ErrorContext, ReadError, WriteError};
 use serde::Serialize;
 
-/// Flat error type serialized to JS as `{ code, message }`.
+/// Flat error type serialized to JS as `{ code, message }`. The context members are
+/// left out when unknown.
 #[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct WasmError {
     pub code: &'static str,
     pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub element: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fragment: Option<usize>,
+    /// `[start, end)` bit range that was requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bit_range: Option<[usize; 2]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available_bits: Option<usize>,
 }
 
 impl WasmError {
     pub fn new(code: &'static str, message: impl Into<String>) -> Self {
-        Self { code, message: message.into() }
+        Self {
+            code,
+            message: message.into(),
+            field: None,
+            element: None,
+            fragment: None,
+            bit_range: None,
+            available_bits: None,
+        }
+    }
+
+    fn with_context(mut self, context: Option<&ErrorContext>) -> Self {
+        if let Some(context) = context {
+            self.field = Some(context.field.clone()).filter(|field| !field.is_empty());
+            self.element = context.element;
+            self.fragment = context.fragment;
+            self.bit_range = context
+                .bit_range
+                .as_ref()
+                .map(|bits| [bits.start, bits.end]);
+            self.available_bits = context.available_bits;
+        }
+        self
     }
 }
 
 impl From<CompileError> for WasmError {
     fn from(e: CompileError) -> Self {
-        let code = match e {
+        let code = match e.root() {
             CompileError::InvalidArrayStride => "INVALID_ARRAY_STRIDE",
             CompileError::InvalidArrayCount => "INVALID_ARRAY_COUNT",
             CompileError::InvalidFieldSize => "INVALID_FIELD_SIZE",
             CompileError::InvalidFragment => "INVALID_FRAGMENT",
+            CompileError::InvalidByteOrder => "INVALID_BYTE_ORDER",
             CompileError::InvalidFieldKind => "INVALID_FIELD_KIND",
             CompileError::EmptyArrayElement => "EMPTY_ARRAY_ELEMENT",
             CompileError::InvalidFieldName => "INVALID_FIELD_NAME",
+            CompileError::UnknownField(_) => "UNKNOWN_FIELD",
+            CompileError::OverlappingFields { .. } => "OVERLAPPING_FIELDS",
+            CompileError::Context { .. } => unreachable!("root() unwraps context"),
         };
-        WasmError::new(code, e.to_string())
+        WasmError::new(code, e.to_string()).with_context(e.context())
     }
 }
 
 impl From<ReadError> for WasmError {
     fn from(e: ReadError) -> Self {
-        let code = match e {
+        let code = match e.root() {
             ReadError::OutOfBounds => "READ_OUT_OF_BOUNDS",
             ReadError::TooManyBitsRead => "TOO_MANY_BITS_READ",
             ReadError::PacketTooShort => "PACKET_TOO_SHORT",
+            ReadError::UnknownField(_) => "UNKNOWN_FIELD",
+            ReadError::InvalidCount(_) => "INVALID_COUNT",
+            ReadError::UnmatchedCase(_) => "UNMATCHED_CASE",
+            ReadError::NotScalar => "NOT_SCALAR",
+            ReadError::InvalidEncoding => "INVALID_ENCODING",
+            ReadError::ConstantMismatch { .. } => "CONSTANT_MISMATCH",
+            ReadError::ChecksumMismatch { .. } => "CHECKSUM_MISMATCH",
+            // Only raised when converting into derived Rust types.
+            ReadError::TypeMismatch { .. } => "INPUT_CONVERSION_ERROR",
+            ReadError::Context { .. } => unreachable!("root() unwraps context"),
         };
-        WasmError::new(code, e.to_string())
+        WasmError::new(code, e.to_string()).with_context(e.context())
     }
 }
 
 impl From<WriteError> for WasmError {
     fn from(e: WriteError) -> Self {
-        let code = match &e {
-            WriteError::OutOfBounds => "WRITE_OUT_OF_BOUNDS",
-            WriteError::InvalidValue => "INVALID_VALUE",
-            WriteError::MissingField(_) => "MISSING_FIELD",
-            WriteError::UnsupportedValue { .. } => "UNSUPPORTED_VALUE",
+        let (code, field) = match &e {
+            WriteError::Template(error) => return WasmError::from(error.clone()),
+            WriteError::OutOfBounds => ("WRITE_OUT_OF_BOUNDS", None),
+            WriteError::InvalidValue => ("INVALID_VALUE", None),
+            WriteError::MissingField(field) => ("MISSING_FIELD", Some(field)),
+            WriteError::UnmatchedCase(_) => ("UNMATCHED_CASE", None),
+            WriteError::CaseMismatch(field) => ("CASE_MISMATCH", Some(field)),
+            WriteError::UnknownField(field) => ("UNKNOWN_FIELD", Some(field)),
+            WriteError::DependentField(field) => ("DEPENDENT_FIELD", Some(field)),
+            WriteError::UnsupportedValue { field, .. } => ("UNSUPPORTED_VALUE", Some(field)),
+            WriteError::Transform { field, error } => (transform_error_code(error), Some(field)),
         };
-        WasmError::new(code, e.to_string())
+        WasmError {
+            field: field.cloned(),
+            ..WasmError::new(code, e.to_string())
+        }
     }
 }
 
 impl From<bitspec::transform::TransformError> for WasmError {
     fn from(e: bitspec::transform::TransformError) -> Self {
-        use bitspec::transform::TransformError;
-        let code = match e {
-            TransformError::InvalidBase => "INVALID_BASE",
-            TransformError::InvalidType => "INVALID_TYPE",
-            TransformError::InvalidEnumValue(_) => "INVALID_ENUM_VALUE",
-            TransformError::InvalidEncoding => "INVALID_ENCODING",
-            TransformError::InvalidByteValue => "INVALID_BYTE_VALUE",
-            TransformError::InvalidAsciiByteValue => "INVALID_ASCII_BYTE_VALUE",
-            TransformError::InvalidScaleOffset => "INVALID_SCALE_OFFSET",
-        };
-        WasmError::new(code, e.to_string())
+        WasmError::new(transform_error_code(&e), e.to_string())
+    }
+}
+
+/// Stable code for a `TransformError`, shared by direct and inverse-transform failures.
+fn transform_error_code(e: &bitspec::transform::TransformError) -> &'static str {
+    use bitspec::transform::TransformError;
+    match e {
+        TransformError::InvalidBase => "INVALID_BASE",
+        TransformError::InvalidType => "INVALID_TYPE",
+        TransformError::InvalidEnumValue(_) => "INVALID_ENUM_VALUE",
+        TransformError::InvalidEncoding => "INVALID_ENCODING",
+        TransformError::InvalidByteValue => "INVALID_BYTE_VALUE",
+        TransformError::InvalidAsciiByteValue => "INVALID_ASCII_BYTE_VALUE",
+        TransformError::InvalidScaleOffset => "INVALID_SCALE_OFFSET",
+        TransformError::InvalidEnumLabel(_) => "INVALID_ENUM_LABEL",
+        TransformError::NotRepresentable => "NOT_REPRESENTABLE",
     }
 }
 
@@ -102,6 +167,10 @@ mod tests {
         assert_eq!(WasmError::from(CompileError::InvalidArrayStride).code, "INVALID_ARRAY_STRIDE");
         assert_eq!(WasmError::from(CompileError::InvalidFieldSize).code, "INVALID_FIELD_SIZE");
         assert_eq!(WasmError::from(CompileError::InvalidFieldName).code, "INVALID_FIELD_NAME");
+        assert_eq!(WasmError::from(CompileError::InvalidByteOrder).code, "INVALID_BYTE_ORDER");
+        assert_eq!(WasmError::from(CompileError::UnknownField("n".into())).code, "UNKNOWN_FIELD");
+        let overlap = CompileError::OverlappingFields { a: "a".into(), b: "b".into(), bit: 3 };
+        assert_eq!(WasmError::from(overlap).code, "OVERLAPPING_FIELDS");
     }
 
     #[test]
@@ -109,6 +178,22 @@ mod tests {
         assert_eq!(WasmError::from(ReadError::OutOfBounds).code, "READ_OUT_OF_BOUNDS");
         assert_eq!(WasmError::from(ReadError::PacketTooShort).code, "PACKET_TOO_SHORT");
         assert_eq!(WasmError::from(ReadError::TooManyBitsRead).code, "TOO_MANY_BITS_READ");
+        assert_eq!(WasmError::from(ReadError::UnknownField("n".into())).code, "UNKNOWN_FIELD");
+        assert_eq!(WasmError::from(ReadError::InvalidCount("n".into())).code, "INVALID_COUNT");
+        assert_eq!(WasmError::from(ReadError::UnmatchedCase("t".into())).code, "UNMATCHED_CASE");
+        assert_eq!(WasmError::from(ReadError::NotScalar).code, "NOT_SCALAR");
+        assert_eq!(
+            WasmError::from(ReadError::InvalidEncoding).code,
+            "INVALID_ENCODING"
+        );
+        let mismatch = ReadError::ConstantMismatch {
+            field: "sync".into(),
+            expected: bitspec::value::Value::U64(0xEB90).into(),
+            found: bitspec::value::Value::U64(0).into(),
+        };
+        assert_eq!(WasmError::from(mismatch).code, "CONSTANT_MISMATCH");
+        let checksum = ReadError::ChecksumMismatch { field: "crc".into(), expected: 1, found: 2 };
+        assert_eq!(WasmError::from(checksum).code, "CHECKSUM_MISMATCH");
     }
 
     #[test]
@@ -116,6 +201,16 @@ mod tests {
         assert_eq!(WasmError::from(WriteError::OutOfBounds).code, "WRITE_OUT_OF_BOUNDS");
         assert_eq!(WasmError::from(WriteError::InvalidValue).code, "INVALID_VALUE");
         assert_eq!(WasmError::from(WriteError::MissingField("x".into())).code, "MISSING_FIELD");
+        assert_eq!(WasmError::from(WriteError::UnmatchedCase("t".into())).code, "UNMATCHED_CASE");
+        assert_eq!(WasmError::from(WriteError::CaseMismatch("x".into())).code, "CASE_MISMATCH");
+        assert_eq!(WasmError::from(WriteError::UnknownField("x".into())).code, "UNKNOWN_FIELD");
+        let template = WasmError::from(WriteError::Template(ReadError::PacketTooShort));
+        assert_eq!(template.code, "PACKET_TOO_SHORT");
+        let dependent = WasmError::from(WriteError::DependentField("x".into()));
+        assert_eq!(
+            (dependent.code, dependent.field.as_deref()),
+            ("DEPENDENT_FIELD", Some("x"))
+        );
         assert_eq!(
             WasmError::from(WriteError::UnsupportedValue {
                 field: "x".into(),
@@ -132,5 +227,52 @@ mod tests {
         assert_eq!(WasmError::from(TransformError::InvalidBase).code, "INVALID_BASE");
         assert_eq!(WasmError::from(TransformError::InvalidEnumValue(7)).code, "INVALID_ENUM_VALUE");
         assert_eq!(WasmError::from(TransformError::InvalidType).code, "INVALID_TYPE");
+        assert_eq!(
+            WasmError::from(TransformError::InvalidEnumLabel("x".into())).code,
+            "INVALID_ENUM_LABEL"
+        );
+    }
+
+    #[test]
+    fn inverse_transform_errors_use_transform_code() {
+        use bitspec::transform::TransformError;
+        let err = WasmError::from(WriteError::Transform {
+            field: "t".into(),
+            error: TransformError::NotRepresentable,
+        });
+        assert_eq!(err.code, "NOT_REPRESENTABLE");
+        assert!(err.message.contains("'t'"));
+        assert_eq!(err.field.as_deref(), Some("t"));
+    }
+
+    #[test]
+    fn context_is_copied_from_wrapped_errors() {
+        let err = WasmError::from(ReadError::Context {
+            context: ErrorContext {
+                field: "items[2].id".into(),
+                element: Some(2),
+                fragment: Some(0),
+                bit_range: Some(40..48),
+                available_bits: Some(44),
+            },
+            error: Box::new(ReadError::OutOfBounds),
+        });
+        assert_eq!(err.code, "READ_OUT_OF_BOUNDS");
+        assert_eq!(err.field.as_deref(), Some("items[2].id"));
+        assert_eq!(err.element, Some(2));
+        assert_eq!(err.fragment, Some(0));
+        assert_eq!(err.bit_range, Some([40, 48]));
+        assert_eq!(err.available_bits, Some(44));
+
+        let err = WasmError::from(CompileError::Context {
+            context: ErrorContext {
+                field: "a".into(),
+                ..ErrorContext::default()
+            },
+            error: Box::new(CompileError::InvalidFieldSize),
+        });
+        assert_eq!(err.code, "INVALID_FIELD_SIZE");
+        assert_eq!(err.field.as_deref(), Some("a"));
+        assert_eq!(err.bit_range, None);
     }
 }
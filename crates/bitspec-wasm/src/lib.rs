@@ -45,7 +45,9 @@
 mod convert;
 mod error;
 
-use bitspec::serde::SchemaDef;
+use std::collections::BTreeMap;
+
+use bitspec::{errors::ReadError, serde::SchemaDef, value::Value};
 use wasm_bindgen::prelude::*;
 
 /// Compiled schema that can be used from JavaScript to parse binary data.
@@ -73,8 +75,9 @@ impl WasmSchema {
     /// The `schema_json` string must deserialize into [`SchemaDef`], which
     /// in turn describes:
     ///
-    /// - **Fields**: their name, kind (scalar or fixed‑size array),
-    ///   signedness and assemble strategy.
+    /// - **Fields**: their name, kind (scalar, array, struct, switch, bytes,
+    ///   string, spacer, reserved bits or checksum), signedness and assemble
+    ///   strategy.
     /// - **Fragments**: the bit ranges that make up each field.
     /// - **Transforms** (optional): how to post‑process raw values using
     ///   `bitspec::transform` (base type, scale/offset, encodings, enums).
@@ -112,6 +115,33 @@ impl WasmSchema {
         convert::map_to_js(transformed)
     }
 
+    /// Parses a buffer of records laid back to back, such as a data logger dump,
+    /// in a single call.
+    ///
+    /// Returns an array with one object per record, each shaped like the result
+    /// of [`WasmSchema::parse`]. A trailing partial record throws
+    /// `PACKET_TOO_SHORT` (see [`bitspec::schema::Schema::parse_many`]).
+    pub fn parse_many(&self, data: &[u8]) -> Result<JsValue, JsValue> {
+        let records = self
+            .schema
+            .parse_many(data)
+            .map(|record| self.transformed(record))
+            .collect::<Result<Vec<_>, _>>()?;
+        convert::to_js(&records)
+    }
+
+    /// Like [`WasmSchema::parse_many`], but returns one array per top-level field
+    /// holding that field's value for every record.
+    pub fn parse_columns(&self, data: &[u8]) -> Result<JsValue, JsValue> {
+        let mut columns = BTreeMap::<String, Vec<Value>>::new();
+        for record in self.schema.parse_many(data) {
+            for (name, value) in self.transformed(record)? {
+                columns.entry(name).or_default().push(value);
+            }
+        }
+        convert::to_js(&columns)
+    }
+
     /// Serializes a JavaScript object into bytes according to this schema.
     ///
     /// `obj` is a JS object whose keys match field names and whose values are
@@ -124,4 +154,56 @@ impl WasmSchema {
             .serialize(&map)
             .map_err(|e| JsValue::from(error::WasmError::from(e)))
     }
+
+    /// Serializes a JavaScript object of transformed values into bytes.
+    ///
+    /// This is the inverse of [`WasmSchema::parse`]: each field's transform is
+    /// undone first (see [`bitspec::schema::Schema::serialize_transformed`]), so
+    /// scaled floats, enum labels and decoded strings are accepted.
+    pub fn serialize_transformed(&self, obj: JsValue) -> Result<Vec<u8>, JsValue> {
+        let map: std::collections::BTreeMap<String, bitspec::value::Value> =
+            serde_wasm_bindgen::from_value(obj)
+                .map_err(|e| JsValue::from(error::WasmError::from(e)))?;
+        self.schema
+            .serialize_transformed(&map)
+            .map_err(|e| JsValue::from(error::WasmError::from(e)))
+    }
+
+    /// Serializes a JavaScript object into bytes like [`WasmSchema::serialize`],
+    /// filling in the fields it leaves out.
+    ///
+    /// With `use_defaults`, a missing field takes the `default` of its definition.
+    /// With a `template` packet, missing fields and bits no field covers keep the
+    /// template's values; the template takes precedence over defaults (see
+    /// [`bitspec::schema::Schema::serialize_with_options`]).
+    pub fn serialize_with_options(
+        &self,
+        obj: JsValue,
+        use_defaults: bool,
+        template: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, JsValue> {
+        let map: std::collections::BTreeMap<String, bitspec::value::Value> =
+            serde_wasm_bindgen::from_value(obj)
+                .map_err(|e| JsValue::from(error::WasmError::from(e)))?;
+        let options = bitspec::schema::SerializeOptions {
+            use_defaults,
+            template: template.as_deref(),
+        };
+        self.schema
+            .serialize_with_options(&map, options)
+            .map_err(|e| JsValue::from(error::WasmError::from(e)))
+    }
+}
+
+impl WasmSchema {
+    /// Applies the schema's transforms to one parsed record.
+    fn transformed(
+        &self,
+        record: Result<BTreeMap<String, Value>, ReadError>,
+    ) -> Result<BTreeMap<String, Value>, JsValue> {
+        let record = record.map_err(|e| JsValue::from(error::WasmError::from(e)))?;
+        self.schema
+            .apply_transforms(record)
+            .map_err(|e| JsValue::from(error::WasmError::from(e)))
+    }
 }
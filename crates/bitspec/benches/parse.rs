@@ -1,5 +1,5 @@
 use bitspec::{
-    assembly::{Assemble, BitOrder},
+    assembly::{ArrayCount, Assemble, BitOrder},
     field::{ArraySpec, Field, FieldKind},
     fragment::Fragment,
     schema::Schema,
@@ -14,6 +14,9 @@ fn scalar_field(iter: usize) -> Field {
         assemble: Assemble::Concat(BitOrder::MsbFirst),
         fragments: vec![Fragment::new(iter * 16, 16)],
         transform: None,
+        condition: None,
+        default: None,
+        constant: None,
     }
 }
 
@@ -41,11 +44,19 @@ fn bench_parse_array(c: &mut Criterion) {
     for &n in &[10usize, 1000] {
         let field = Field {
             name: "arr".into(),
-            kind: FieldKind::Array(ArraySpec { count: n, stride_bits: 8, offset_bits: 0 }),
+            kind: FieldKind::Array(ArraySpec {
+                count: ArrayCount::Fixed(n),
+                stride_bits: 8,
+                offset_bits: 0,
+                element: None,
+            }),
             signed: false,
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(0, 8)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
         let schema = Schema::compile(&[field], None).unwrap();
         let data = packet(n * 8);
@@ -67,6 +78,9 @@ fn bench_parse_non_contiguous(c: &mut Criterion) {
             Fragment::new(32, 8),
         ],
         transform: None,
+        condition: None,
+        default: None,
+        constant: None,
     };
     let schema = Schema::compile(&[field], None).unwrap();
     let data = packet(40);
@@ -75,5 +89,28 @@ fn bench_parse_non_contiguous(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_parse_scalars, bench_parse_array, bench_parse_non_contiguous);
+fn bench_field_handles(c: &mut Criterion) {
+    let schema = scalar_schema(80);
+    let data = packet(80 * 16);
+    let handles: Vec<_> = ["f3", "f40", "f77"]
+        .iter()
+        .map(|name| schema.field_handle(name).unwrap())
+        .collect();
+    c.bench_function("field_handles_3_of_80", |b| {
+        b.iter(|| {
+            handles
+                .iter()
+                .map(|handle| handle.read_u64(&schema, &data).unwrap())
+                .sum::<u64>()
+        });
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_parse_scalars,
+    bench_parse_array,
+    bench_parse_non_contiguous,
+    bench_field_handles
+);
 criterion_main!(benches);
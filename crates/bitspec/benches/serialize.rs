@@ -16,6 +16,9 @@ fn scalar_schema(n: usize) -> (Schema, BTreeMap<String, Value>) {
         assemble: Assemble::Concat(BitOrder::MsbFirst),
         fragments: vec![Fragment::new(i * 16, 16)],
         transform: None,
+        condition: None,
+        default: None,
+        constant: None,
     }).collect();
     let schema = Schema::compile(&fields, None).unwrap();
     let obj: BTreeMap<String, Value> = (0..n)
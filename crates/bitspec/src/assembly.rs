@@ -5,6 +5,10 @@
 pub enum Assemble {
     /// Concatenate fragment bits in the given order to form the value.
     Concat(BitOrder),
+    /// Concatenate fragment bits most significant first, then read the bytes of the
+    /// result in the given order. The field's width must be a whole number of bytes,
+    /// or of 16-bit words for the byte-swapped orders.
+    Endian(Endian),
 }
 
 #[cfg(feature = "serde")]
@@ -13,10 +17,62 @@ impl From<crate::serde::AssembleDef> for Assemble {
         match value {
             crate::serde::AssembleDef::ConcatMsb => Assemble::Concat(BitOrder::MsbFirst),
             crate::serde::AssembleDef::ConcatLsb => Assemble::Concat(BitOrder::LsbFirst),
+            crate::serde::AssembleDef::BigEndian => Assemble::Endian(Endian::Big),
+            crate::serde::AssembleDef::LittleEndian => Assemble::Endian(Endian::Little),
+            crate::serde::AssembleDef::BigEndianByteSwap => Assemble::Endian(Endian::BigByteSwap),
+            crate::serde::AssembleDef::LittleEndianByteSwap => {
+                Assemble::Endian(Endian::LittleByteSwap)
+            }
         }
     }
 }
 
+/// Byte order of a multi-byte value, written for a 32-bit value `0xAABBCCDD`
+/// stored as the bytes `AA BB CC DD` (`ABCD`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    /// Most significant byte first (`ABCD`); the same as `Concat(MsbFirst)`.
+    Big,
+    /// Least significant byte first (`DCBA`), as on x86.
+    Little,
+    /// Big-endian 16-bit words with their bytes swapped (`BADC`), as the PDP-11
+    /// stores 32-bit values.
+    BigByteSwap,
+    /// Little-endian order of big-endian 16-bit words (`CDAB`), common for 32-bit
+    /// values in Modbus registers.
+    LittleByteSwap,
+}
+
+impl Endian {
+    /// Number of bits the width of a field in this order must be a multiple of.
+    pub fn unit_bits(self) -> usize {
+        match self {
+            Endian::Big | Endian::Little => 8,
+            Endian::BigByteSwap | Endian::LittleByteSwap => 16,
+        }
+    }
+
+    /// Converts between the big-endian value of `total_bits` bits read from the
+    /// fragments and the value in this byte order. Each order is its own inverse,
+    /// so the same call serves reading and writing.
+    pub(crate) fn reorder(self, value: u128, total_bits: usize) -> u128 {
+        const HIGH_BYTES: u128 = 0xFF00_FF00_FF00_FF00_FF00_FF00_FF00_FF00;
+        const LOW_BYTES: u128 = HIGH_BYTES >> 8;
+        // Bytes of the value, moved to the top of the word.
+        let top = value << (128 - total_bits);
+        let swapped = match self {
+            Endian::Big => top,
+            Endian::Little => top.swap_bytes() << (128 - total_bits),
+            Endian::BigByteSwap => ((top & HIGH_BYTES) >> 8) | ((top & LOW_BYTES) << 8),
+            Endian::LittleByteSwap => {
+                let words = top.swap_bytes() << (128 - total_bits);
+                ((words & HIGH_BYTES) >> 8) | ((words & LOW_BYTES) << 8)
+            }
+        };
+        swapped >> (128 - total_bits)
+    }
+}
+
 /// Bit order when reading a single fragment from the byte stream.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BitOrder {
@@ -43,8 +99,29 @@ impl Default for BitOrder {
 }
 
 /// Number of elements in an array field.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ArrayCount {
     /// Array has a fixed, known-at-compile-time number of elements.
     Fixed(usize),
+    /// Element count is the integer value of an earlier field plus `adjust`,
+    /// e.g. `adjust: -2` for a length byte that also counts a 2-element header.
+    /// `field` names a sibling or enclosing field; `a.b` reaches into a struct.
+    FromField {
+        /// Name of the field holding the count.
+        field: String,
+        /// Added to the referenced value to get the element count.
+        adjust: i64,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl From<crate::serde::ArrayCountDef> for ArrayCount {
+    fn from(value: crate::serde::ArrayCountDef) -> Self {
+        match value {
+            crate::serde::ArrayCountDef::Fixed(count) => ArrayCount::Fixed(count),
+            crate::serde::ArrayCountDef::FromField { field, adjust } => {
+                ArrayCount::FromField { field, adjust }
+            }
+        }
+    }
 }
@@ -110,12 +110,134 @@ pub fn write_bits_at(
     Ok(())
 }
 
+/// Reads `n` bits starting at `bit_pos` as an unsigned value (max 128 bits).
+///
+/// Reads of up to 64 bits go through [`read_bits_at`]; wider ones are split into
+/// a high and a low half.
+pub fn read_bits_u128_at(data: &[u8], bit_pos: usize, n: usize) -> Result<u128, ReadError> {
+    if n > 128 {
+        return Err(ReadError::TooManyBitsRead);
+    }
+    if n <= 64 {
+        return read_bits_at(data, bit_pos, n).map(u128::from);
+    }
+    let high_bits = n - 64;
+    let high = read_bits_at(data, bit_pos, high_bits)?;
+    let low = read_bits_at(data, bit_pos + high_bits, 64)?;
+    Ok((u128::from(high) << 64) | u128::from(low))
+}
+
+/// Writes the low `n` bits of `value` (max 128) into `data` starting at bit
+/// position `bit_pos`, MSB-first. The wide counterpart of [`write_bits_at`].
+pub fn write_bits_u128_at(
+    data: &mut [u8],
+    bit_pos: usize,
+    n: usize,
+    value: u128,
+) -> Result<(), crate::errors::WriteError> {
+    if n > 128 {
+        return Err(crate::errors::WriteError::OutOfBounds);
+    }
+    if n <= 64 {
+        return write_bits_at(data, bit_pos, n, value as u64);
+    }
+    let high_bits = n - 64;
+    let end = bit_pos
+        .checked_add(n)
+        .ok_or(crate::errors::WriteError::OutOfBounds)?;
+    if end > data.len() * 8 {
+        return Err(crate::errors::WriteError::OutOfBounds);
+    }
+    write_bits_at(data, bit_pos, high_bits, (value >> 64) as u64)?;
+    write_bits_at(data, bit_pos + high_bits, 64, value as u64)
+}
+
+/// Copies `n` bits starting at `bit_pos` into `n.div_ceil(8)` bytes, MSB-first.
+/// When `n` is not a multiple of 8, the spare bits are zero: the high bits of the
+/// first byte with `leading_pad`, otherwise the low bits of the last byte.
+pub fn read_bytes_at(
+    data: &[u8],
+    bit_pos: usize,
+    n: usize,
+    leading_pad: bool,
+) -> Result<Vec<u8>, ReadError> {
+    let end = bit_pos.checked_add(n).ok_or(ReadError::OutOfBounds)?;
+    if end > data.len() * 8 {
+        return Err(ReadError::OutOfBounds);
+    }
+    if bit_pos.is_multiple_of(8) && n.is_multiple_of(8) {
+        return Ok(data[bit_pos / 8..end / 8].to_vec());
+    }
+
+    let partial = n % 8;
+    let mut out = Vec::with_capacity(n.div_ceil(8));
+    let mut pos = bit_pos;
+    if leading_pad && partial != 0 {
+        out.push(read_bits_at(data, pos, partial)? as u8);
+        pos += partial;
+    }
+    while end - pos >= 8 {
+        out.push(read_bits_at(data, pos, 8)? as u8);
+        pos += 8;
+    }
+    if pos < end {
+        let len = end - pos;
+        out.push((read_bits_at(data, pos, len)? as u8) << (8 - len));
+    }
+    Ok(out)
+}
+
+/// Writes `bytes` as the `n`-bit range starting at `bit_pos`, the inverse of
+/// [`read_bytes_at`]; the spare bits of `bytes` are ignored. `bytes` must hold
+/// exactly `n.div_ceil(8)` bytes.
+pub fn write_bytes_at(
+    data: &mut [u8],
+    bit_pos: usize,
+    n: usize,
+    bytes: &[u8],
+    leading_pad: bool,
+) -> Result<(), crate::errors::WriteError> {
+    if bytes.len() != n.div_ceil(8) {
+        return Err(crate::errors::WriteError::InvalidValue);
+    }
+    let end = bit_pos
+        .checked_add(n)
+        .ok_or(crate::errors::WriteError::OutOfBounds)?;
+    if end > data.len() * 8 {
+        return Err(crate::errors::WriteError::OutOfBounds);
+    }
+    if bit_pos.is_multiple_of(8) && n.is_multiple_of(8) {
+        data[bit_pos / 8..end / 8].copy_from_slice(bytes);
+        return Ok(());
+    }
+
+    let partial = n % 8;
+    let mut pos = bit_pos;
+    let mut bytes = bytes.iter().copied();
+    if leading_pad && partial != 0 {
+        write_bits_at(data, pos, partial, u64::from(bytes.next().unwrap_or(0)))?;
+        pos += partial;
+    }
+    for byte in bytes {
+        let len = (end - pos).min(8);
+        write_bits_at(data, pos, len, u64::from(byte >> (8 - len)))?;
+        pos += len;
+    }
+    Ok(())
+}
+
 /// Sign-extends the low `bits` of `value` to a full `i64`.
 pub fn sign_extend(value: u64, bits: usize) -> i64 {
     let shift = 64 - bits;
     ((value << shift) as i64) >> shift
 }
 
+/// Sign-extends the low `bits` of `value` to a full `i128`.
+pub fn sign_extend_u128(value: u128, bits: usize) -> i128 {
+    let shift = 128 - bits;
+    ((value << shift) as i128) >> shift
+}
+
 /// Reverses the low `n` bits of `x` (LSB becomes MSB of the result).
 pub fn reverse_bits_n(mut x: u64, n: usize) -> u64 {
     let mut r = 0u64;
@@ -127,6 +249,15 @@ pub fn reverse_bits_n(mut x: u64, n: usize) -> u64 {
     r
 }
 
+/// Reverses the low `n` bits of `x`, for `n` up to 128.
+pub fn reverse_bits_u128_n(x: u128, n: usize) -> u128 {
+    if n == 0 {
+        0
+    } else {
+        x.reverse_bits() >> (128 - n)
+    }
+}
+
 /// Reverses the bit order within each byte of `data`. MSB becomes LSB and vice-versa.
 pub fn reverse_bits_in_bytes(data: &mut [u8]) {
     for byte in data.iter_mut() {
@@ -208,11 +339,65 @@ mod tests {
         assert_eq!(sign_extend(0b11111111, 8), -1);
     }
 
+    #[test]
+    fn test_read_and_write_bits_u128_at() {
+        let mut buf = vec![0u8; 13];
+        let value = 0x0_1234_5678_9ABC_DEFF_EDCB_A987u128;
+        write_bits_u128_at(&mut buf, 4, 100, value).unwrap();
+        assert_eq!(read_bits_u128_at(&buf, 4, 100).unwrap(), value);
+        assert_eq!(buf[0] >> 4, 0);
+        assert_eq!(
+            read_bits_u128_at(&buf, 0, 129).unwrap_err(),
+            ReadError::TooManyBitsRead
+        );
+        assert_eq!(
+            read_bits_u128_at(&buf, 8, 100).unwrap_err(),
+            ReadError::OutOfBounds
+        );
+    }
+
+    #[test]
+    fn test_read_and_write_bytes_at() {
+        let data = [0xAB, 0xCD, 0xEF];
+        assert_eq!(read_bytes_at(&data, 8, 16, false).unwrap(), [0xCD, 0xEF]);
+        // 12 bits from bit 4: 0xBCD.
+        assert_eq!(read_bytes_at(&data, 4, 12, false).unwrap(), [0xBC, 0xD0]);
+        assert_eq!(read_bytes_at(&data, 4, 12, true).unwrap(), [0x0B, 0xCD]);
+        assert_eq!(
+            read_bytes_at(&data, 4, 24, false).unwrap_err(),
+            ReadError::OutOfBounds
+        );
+
+        for leading_pad in [false, true] {
+            let bytes = read_bytes_at(&data, 4, 12, leading_pad).unwrap();
+            let mut buf = [0xAB, 0x00, 0xEF];
+            write_bytes_at(&mut buf, 4, 12, &bytes, leading_pad).unwrap();
+            assert_eq!(buf, data);
+        }
+        let mut buf = [0u8; 3];
+        assert_eq!(
+            write_bytes_at(&mut buf, 4, 12, &[0xBC], false).unwrap_err(),
+            crate::errors::WriteError::InvalidValue
+        );
+    }
+
+    #[test]
+    fn test_sign_extend_u128() {
+        assert_eq!(sign_extend_u128((1 << 80) - 1, 80), -1);
+        assert_eq!(sign_extend_u128(1 << 78, 80), 1 << 78);
+    }
+
     #[test]
     fn test_reverse_bits_n() {
         assert_eq!(reverse_bits_n(0b10101010, 8), 0b01010101);
     }
 
+    #[test]
+    fn test_reverse_bits_u128_n() {
+        assert_eq!(reverse_bits_u128_n(0b10101010, 8), 0b01010101);
+        assert_eq!(reverse_bits_u128_n(1, 100), 1 << 99);
+    }
+
     #[test]
     fn test_write_bits_at_aligned() {
         let mut buf = vec![0u8; 2];
@@ -0,0 +1,272 @@
+//! Checksum algorithms and the data they cover, for [`crate::field::FieldKind::Checksum`].
+//!
+//! Every algorithm works on bytes. Covered bits are gathered MSB-first into bytes,
+//! so a byte-aligned range is checksummed exactly as it appears in the packet; an
+//! unaligned tail is padded with zero bits.
+//!
+//! ## Example
+//!
+//! ```
+//! use bitspec::checksum::{ChecksumAlgorithm, Crc};
+//!
+//! let crc = ChecksumAlgorithm::Crc(Crc::CRC_16_CCITT_FALSE);
+//! assert_eq!(crc.compute(b"123456789"), 0x29B1);
+//! assert_eq!(ChecksumAlgorithm::Xor8.compute(&[0x01, 0x03]), 0x02);
+//! ```
+
+use std::ops::Range;
+
+use crate::{bits::reverse_bits_n, errors::ReadError};
+
+/// How a checksum value is computed from the covered bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    /// Cyclic redundancy check with the given parameters.
+    Crc(Crc),
+    /// Fletcher-16: two running sums of bytes modulo 255, the second in the high byte.
+    Fletcher16,
+    /// Fletcher-32: two running sums of big-endian 16-bit words modulo 65535, the
+    /// second in the high half. An odd trailing byte is padded with a zero byte.
+    Fletcher32,
+    /// Sum of all bytes modulo 256.
+    Sum8,
+    /// Exclusive or of all bytes.
+    Xor8,
+}
+
+impl ChecksumAlgorithm {
+    /// Number of bits in a checksum value.
+    pub fn width(&self) -> usize {
+        match self {
+            ChecksumAlgorithm::Crc(crc) => crc.width,
+            ChecksumAlgorithm::Fletcher16 => 16,
+            ChecksumAlgorithm::Fletcher32 => 32,
+            ChecksumAlgorithm::Sum8 | ChecksumAlgorithm::Xor8 => 8,
+        }
+    }
+
+    /// Computes the checksum of `data`.
+    pub fn compute(&self, data: &[u8]) -> u64 {
+        match self {
+            ChecksumAlgorithm::Crc(crc) => crc.compute(data),
+            ChecksumAlgorithm::Fletcher16 => {
+                let (mut low, mut high) = (0u64, 0u64);
+                for &byte in data {
+                    low = (low + u64::from(byte)) % 255;
+                    high = (high + low) % 255;
+                }
+                high << 8 | low
+            }
+            ChecksumAlgorithm::Fletcher32 => {
+                let (mut low, mut high) = (0u64, 0u64);
+                for word in data.chunks(2) {
+                    let word = u64::from(word[0]) << 8 | u64::from(*word.get(1).unwrap_or(&0));
+                    low = (low + word) % 65535;
+                    high = (high + low) % 65535;
+                }
+                high << 16 | low
+            }
+            ChecksumAlgorithm::Sum8 => data
+                .iter()
+                .fold(0u8, |acc, &byte| acc.wrapping_add(byte))
+                .into(),
+            ChecksumAlgorithm::Xor8 => data.iter().fold(0u8, |acc, &byte| acc ^ byte).into(),
+        }
+    }
+}
+
+/// Parameters of a CRC in the usual catalogue form (width, poly, init, refin,
+/// refout, xorout), so any catalogued CRC can be described.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crc {
+    /// Number of bits in the CRC, 1 to 64.
+    pub width: usize,
+    /// Generator polynomial without its top bit, in normal (MSB-first) form.
+    pub poly: u64,
+    /// Register value before the first byte.
+    pub init: u64,
+    /// Whether each input byte is processed LSB-first.
+    pub refin: bool,
+    /// Whether the final register is bit-reversed before `xorout` is applied.
+    pub refout: bool,
+    /// Value XORed into the result.
+    pub xorout: u64,
+}
+
+impl Crc {
+    /// CRC-8/SMBUS.
+    pub const CRC_8: Crc = Crc::new(8, 0x07, 0x00, false, false, 0x00);
+    /// CRC-16/CCITT-FALSE (also catalogued as CRC-16/IBM-3740).
+    pub const CRC_16_CCITT_FALSE: Crc = Crc::new(16, 0x1021, 0xFFFF, false, false, 0x0000);
+    /// CRC-16/KERMIT, the reflected CCITT CRC.
+    pub const CRC_16_KERMIT: Crc = Crc::new(16, 0x1021, 0x0000, true, true, 0x0000);
+    /// CRC-16/XMODEM.
+    pub const CRC_16_XMODEM: Crc = Crc::new(16, 0x1021, 0x0000, false, false, 0x0000);
+    /// CRC-16/MODBUS.
+    pub const CRC_16_MODBUS: Crc = Crc::new(16, 0x8005, 0xFFFF, true, true, 0x0000);
+    /// CRC-32 as used by Ethernet, zlib and PNG (CRC-32/ISO-HDLC).
+    pub const CRC_32: Crc = Crc::new(32, 0x04C1_1DB7, 0xFFFF_FFFF, true, true, 0xFFFF_FFFF);
+    /// CRC-32C (Castagnoli), as used by iSCSI and ext4.
+    pub const CRC_32C: Crc = Crc::new(32, 0x1EDC_6F41, 0xFFFF_FFFF, true, true, 0xFFFF_FFFF);
+
+    /// Creates CRC parameters.
+    pub const fn new(
+        width: usize,
+        poly: u64,
+        init: u64,
+        refin: bool,
+        refout: bool,
+        xorout: u64,
+    ) -> Self {
+        Crc {
+            width,
+            poly,
+            init,
+            refin,
+            refout,
+            xorout,
+        }
+    }
+
+    /// Whether the width is 1 to 64 and every parameter fits in it.
+    pub fn is_valid(&self) -> bool {
+        (1..=64).contains(&self.width)
+            && [self.poly, self.init, self.xorout]
+                .iter()
+                .all(|value| value & !self.mask() == 0)
+    }
+
+    /// Computes the CRC of `data`, bit by bit.
+    pub fn compute(&self, data: &[u8]) -> u64 {
+        let mask = self.mask();
+        let top = 1u64 << (self.width - 1);
+        let mut crc = self.init & mask;
+        for &byte in data {
+            let byte = if self.refin {
+                byte.reverse_bits()
+            } else {
+                byte
+            };
+            for i in (0..8).rev() {
+                let feedback = (crc & top != 0) != ((byte >> i) & 1 == 1);
+                crc = (crc << 1) & mask;
+                if feedback {
+                    crc ^= self.poly;
+                }
+            }
+        }
+        if self.refout {
+            crc = reverse_bits_n(crc, self.width);
+        }
+        (crc ^ self.xorout) & mask
+    }
+
+    fn mask(&self) -> u64 {
+        match self.width {
+            64.. => u64::MAX,
+            width => (1u64 << width) - 1,
+        }
+    }
+}
+
+/// Data a checksum is computed over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChecksumCoverage {
+    /// Bits relative to the start of the field list holding the checksum, i.e. the
+    /// record start for a top-level checksum, or the struct start inside a struct.
+    Bits(Range<usize>),
+    /// Bits of these sibling fields, in the order listed; each field spans from its
+    /// first to its last bit.
+    Fields(Vec<String>),
+}
+
+/// Gathers the bits of `ranges`, in order, into bytes MSB-first, padding the last
+/// byte with zero bits.
+pub(crate) fn gather_bits(data: &[u8], ranges: &[Range<usize>]) -> Result<Vec<u8>, ReadError> {
+    let total: usize = ranges.iter().map(|range| range.len()).sum();
+    let mut out = vec![0u8; total.div_ceil(8)];
+    let mut at = 0;
+    for range in ranges {
+        let mut pos = range.start;
+        while pos < range.end {
+            let len = (range.end - pos).min(64);
+            let bits = crate::bits::read_bits_at(data, pos, len)?;
+            crate::bits::write_bits_at(&mut out, at, len, bits)
+                .map_err(|_| ReadError::OutOfBounds)?;
+            pos += len;
+            at += len;
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(feature = "serde")]
+impl From<crate::serde::ChecksumAlgorithmDef> for ChecksumAlgorithm {
+    fn from(value: crate::serde::ChecksumAlgorithmDef) -> Self {
+        match value {
+            crate::serde::ChecksumAlgorithmDef::Crc {
+                width,
+                poly,
+                init,
+                refin,
+                refout,
+                xorout,
+            } => ChecksumAlgorithm::Crc(Crc::new(width, poly, init, refin, refout, xorout)),
+            crate::serde::ChecksumAlgorithmDef::Fletcher16 => ChecksumAlgorithm::Fletcher16,
+            crate::serde::ChecksumAlgorithmDef::Fletcher32 => ChecksumAlgorithm::Fletcher32,
+            crate::serde::ChecksumAlgorithmDef::Sum8 => ChecksumAlgorithm::Sum8,
+            crate::serde::ChecksumAlgorithmDef::Xor8 => ChecksumAlgorithm::Xor8,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<crate::serde::ChecksumCoverageDef> for ChecksumCoverage {
+    fn from(value: crate::serde::ChecksumCoverageDef) -> Self {
+        match value {
+            crate::serde::ChecksumCoverageDef::Bits(range) => ChecksumCoverage::Bits(range),
+            crate::serde::ChecksumCoverageDef::Fields(fields) => ChecksumCoverage::Fields(fields),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECK: &[u8] = b"123456789";
+
+    #[test]
+    fn test_catalogued_crcs_match_check_values() {
+        assert_eq!(Crc::CRC_8.compute(CHECK), 0xF4);
+        assert_eq!(Crc::CRC_16_CCITT_FALSE.compute(CHECK), 0x29B1);
+        assert_eq!(Crc::CRC_16_KERMIT.compute(CHECK), 0x2189);
+        assert_eq!(Crc::CRC_16_XMODEM.compute(CHECK), 0x31C3);
+        assert_eq!(Crc::CRC_16_MODBUS.compute(CHECK), 0x4B37);
+        assert_eq!(Crc::CRC_32.compute(CHECK), 0xCBF4_3926);
+        assert_eq!(Crc::CRC_32C.compute(CHECK), 0xE306_9283);
+
+        let crc_64_xz = Crc::new(64, 0x42F0_E1EB_A9EA_3693, u64::MAX, true, true, u64::MAX);
+        assert_eq!(crc_64_xz.compute(CHECK), 0x995D_C9BB_DF19_39FA);
+        let crc_5_usb = Crc::new(5, 0x05, 0x1F, true, true, 0x1F);
+        assert_eq!(crc_5_usb.compute(CHECK), 0x19);
+    }
+
+    #[test]
+    fn test_simple_checksums() {
+        assert_eq!(ChecksumAlgorithm::Fletcher16.compute(b"abcde"), 0xC8F0);
+        assert_eq!(ChecksumAlgorithm::Fletcher32.compute(b"abcde"), 0x4FF0_29C7);
+        assert_eq!(ChecksumAlgorithm::Sum8.compute(&[0xFF, 0x02]), 0x01);
+        assert_eq!(ChecksumAlgorithm::Xor8.compute(&[0xF0, 0x0F, 0x01]), 0xFE);
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_gather_bits_packs_ranges_msb_first() {
+        let data = [0xAB, 0xCD, 0xEF];
+        assert_eq!(gather_bits(&data, &[8..24]), Ok(vec![0xCD, 0xEF]));
+        assert_eq!(gather_bits(&data, &[4..8, 16..20]), Ok(vec![0xBE]));
+        assert_eq!(gather_bits(&data, &[0..4]), Ok(vec![0xA0]));
+        assert_eq!(gather_bits(&data, &[16..32]), Err(ReadError::OutOfBounds));
+    }
+}
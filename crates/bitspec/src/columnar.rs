@@ -0,0 +1,645 @@
+//! Columnar parse output: one typed column per field instead of one map per record.
+//!
+//! [`crate::schema::Schema::parse_table`] reads a buffer of back-to-back records
+//! into a [`Table`]. Each [`Column`] stores its values in a flat typed vector, with
+//! offsets for strings, bytes and arrays and a validity bitmap for absent values,
+//! laid out the way Apache Arrow expects. With the `arrow` feature,
+//! [`Table::into_record_batch`] turns the table into an Arrow `RecordBatch`.
+//!
+//! Use a [`TableBuilder`] to collect records yourself, e.g. after applying
+//! transforms.
+//!
+//! ## Example
+//!
+//! ```
+//! use bitspec::assembly::{Assemble, BitOrder};
+//! use bitspec::columnar::ColumnValues;
+//! use bitspec::field::{Field, FieldKind};
+//! use bitspec::fragment::Fragment;
+//! use bitspec::schema::Schema;
+//!
+//! let field = |name: &str, offset, signed| Field {
+//!     name: name.into(), kind: FieldKind::Scalar, signed,
+//!     assemble: Assemble::Concat(BitOrder::MsbFirst),
+//!     fragments: vec![Fragment::new(offset, 8)], transform: None, condition: None,
+//!     default: None,
+//!     constant: None,
+//! };
+//! let schema = Schema::compile(&[field("id", 0, false), field("temp", 8, true)], None).unwrap();
+//!
+//! let table = schema.parse_table(&[1, 0xFF, 2, 0x14]).unwrap();
+//! assert_eq!(table.len, 2);
+//! assert_eq!(table.column("id").unwrap().values, ColumnValues::U64(vec![1, 2]));
+//! assert_eq!(table.column("temp").unwrap().values, ColumnValues::I64(vec![-1, 20]));
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::{
+    compiled::{self, value_variant_name},
+    errors::ColumnError,
+    schema::Schema,
+    value::Value,
+};
+
+/// Typed values of one field across all records.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    /// Values in record order. Absent entries hold a placeholder: zero, an empty
+    /// string, or an empty list.
+    pub values: ColumnValues,
+    /// One bit per entry, least significant bit first, set when the entry is
+    /// present. `None` if every entry is present.
+    pub validity: Option<Vec<u8>>,
+    /// Number of entries.
+    pub len: usize,
+}
+
+/// Storage of a [`Column`], chosen by the first present value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnValues {
+    /// Every entry is absent, so the type is unknown.
+    Null,
+    /// Unsigned integers.
+    U64(Vec<u64>),
+    /// Signed integers.
+    I64(Vec<i64>),
+    /// Unsigned integers of fields wider than 64 bits.
+    U128(Vec<u128>),
+    /// Signed integers of fields wider than 64 bits.
+    I128(Vec<i128>),
+    /// 32-bit floats.
+    F32(Vec<f32>),
+    /// 64-bit floats.
+    F64(Vec<f64>),
+    /// Strings: entry `i` is `data[offsets[i]..offsets[i + 1]]`, UTF-8 encoded.
+    Utf8 {
+        /// `len + 1` offsets into `data`.
+        offsets: Vec<i64>,
+        /// Concatenated string bytes.
+        data: Vec<u8>,
+    },
+    /// Byte strings: entry `i` is `data[offsets[i]..offsets[i + 1]]`.
+    Binary {
+        /// `len + 1` offsets into `data`.
+        offsets: Vec<i64>,
+        /// Concatenated bytes.
+        data: Vec<u8>,
+    },
+    /// Arrays: entry `i` holds the elements `offsets[i]..offsets[i + 1]` of `values`.
+    List {
+        /// `len + 1` offsets into `values`.
+        offsets: Vec<i64>,
+        /// Elements of every array, concatenated.
+        values: Box<Column>,
+    },
+    /// Structs: one column per sub-field, each with one entry per struct.
+    Struct(Vec<(String, Column)>),
+}
+
+impl ColumnValues {
+    /// Empty storage for `value`, padded with `len` placeholders.
+    fn for_value(value: &Value, len: usize) -> Self {
+        match value {
+            Value::U64(_) => Self::U64(vec![0; len]),
+            Value::I64(_) => Self::I64(vec![0; len]),
+            Value::U128(_) => Self::U128(vec![0; len]),
+            Value::I128(_) => Self::I128(vec![0; len]),
+            Value::F32(_) => Self::F32(vec![0.0; len]),
+            Value::F64(_) => Self::F64(vec![0.0; len]),
+            Value::String(_) => Self::Utf8 {
+                offsets: vec![0; len + 1],
+                data: Vec::new(),
+            },
+            Value::Bytes(_) => Self::Binary {
+                offsets: vec![0; len + 1],
+                data: Vec::new(),
+            },
+            Value::Array(_) => Self::List {
+                offsets: vec![0; len + 1],
+                values: Box::new(Column::new()),
+            },
+            Value::Struct(_) => Self::Struct(Vec::new()),
+            Value::Absent => Self::Null,
+        }
+    }
+
+    /// Name of the [`Value`] variant this storage holds.
+    fn variant_name(&self) -> &'static str {
+        match self {
+            Self::Null => "Absent",
+            Self::U64(_) => "U64",
+            Self::I64(_) => "I64",
+            Self::U128(_) => "U128",
+            Self::I128(_) => "I128",
+            Self::F32(_) => "F32",
+            Self::F64(_) => "F64",
+            Self::Utf8 { .. } => "String",
+            Self::Binary { .. } => "Bytes",
+            Self::List { .. } => "Array",
+            Self::Struct(_) => "Struct",
+        }
+    }
+}
+
+impl Column {
+    fn new() -> Self {
+        Self {
+            values: ColumnValues::Null,
+            validity: None,
+            len: 0,
+        }
+    }
+
+    /// Whether entry `i` is present.
+    pub fn is_valid(&self, i: usize) -> bool {
+        i < self.len
+            && self
+                .validity
+                .as_ref()
+                .is_none_or(|bits| bits[i / 8] & (1 << (i % 8)) != 0)
+    }
+
+    /// Number of absent entries.
+    pub fn null_count(&self) -> usize {
+        (0..self.len).filter(|&i| !self.is_valid(i)).count()
+    }
+
+    /// Appends `value`, or a placeholder if it is [`Value::Absent`]. On a type
+    /// mismatch returns the expected and found variant names.
+    fn push(&mut self, value: Value) -> Result<(), (&'static str, &'static str)> {
+        if value == Value::Absent {
+            self.push_absent();
+            return Ok(());
+        }
+        if self.values == ColumnValues::Null {
+            self.values = ColumnValues::for_value(&value, self.len);
+        }
+        match (&mut self.values, value) {
+            (ColumnValues::U64(values), Value::U64(v)) => values.push(v),
+            (ColumnValues::I64(values), Value::I64(v)) => values.push(v),
+            (ColumnValues::U128(values), Value::U128(v)) => values.push(v),
+            (ColumnValues::I128(values), Value::I128(v)) => values.push(v),
+            (ColumnValues::F32(values), Value::F32(v)) => values.push(v),
+            (ColumnValues::F64(values), Value::F64(v)) => values.push(v),
+            (ColumnValues::Utf8 { offsets, data }, Value::String(v)) => {
+                data.extend_from_slice(v.as_bytes());
+                offsets.push(data.len() as i64);
+            }
+            (ColumnValues::Binary { offsets, data }, Value::Bytes(v)) => {
+                data.extend_from_slice(&v);
+                offsets.push(data.len() as i64);
+            }
+            (ColumnValues::List { offsets, values }, Value::Array(items)) => {
+                for item in items {
+                    values.push(item)?;
+                }
+                offsets.push(values.len as i64);
+            }
+            (ColumnValues::Struct(children), Value::Struct(mut map)) => {
+                for (name, child) in children.iter_mut() {
+                    child.push(map.remove(name).unwrap_or(Value::Absent))?;
+                }
+                // Sub-fields seen for the first time, e.g. from another switch case.
+                for (name, value) in map {
+                    let mut child = Column::new();
+                    for _ in 0..self.len {
+                        child.push_absent();
+                    }
+                    child.push(value)?;
+                    children.push((name, child));
+                }
+            }
+            (values, value) => return Err((values.variant_name(), value_variant_name(&value))),
+        }
+        self.push_validity(true);
+        Ok(())
+    }
+
+    fn push_absent(&mut self) {
+        match &mut self.values {
+            ColumnValues::Null => {}
+            ColumnValues::U64(values) => values.push(0),
+            ColumnValues::I64(values) => values.push(0),
+            ColumnValues::U128(values) => values.push(0),
+            ColumnValues::I128(values) => values.push(0),
+            ColumnValues::F32(values) => values.push(0.0),
+            ColumnValues::F64(values) => values.push(0.0),
+            ColumnValues::Utf8 { offsets, .. }
+            | ColumnValues::Binary { offsets, .. }
+            | ColumnValues::List { offsets, .. } => offsets.push(*offsets.last().unwrap_or(&0)),
+            ColumnValues::Struct(children) => {
+                for (_, child) in children {
+                    child.push_absent();
+                }
+            }
+        }
+        self.push_validity(false);
+    }
+
+    /// Records whether the entry just appended is present and counts it.
+    fn push_validity(&mut self, valid: bool) {
+        let i = self.len;
+        if !valid && self.validity.is_none() {
+            // Every earlier entry was present.
+            let mut bits = vec![0xFF; i / 8];
+            if !i.is_multiple_of(8) {
+                bits.push((1 << (i % 8)) - 1);
+            }
+            self.validity = Some(bits);
+        }
+        if let Some(bits) = &mut self.validity {
+            if i.is_multiple_of(8) {
+                bits.push(0);
+            }
+            if valid {
+                bits[i / 8] |= 1 << (i % 8);
+            }
+        }
+        self.len += 1;
+    }
+}
+
+/// Parsed records stored as one [`Column`] per top-level field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    /// Number of records.
+    pub len: usize,
+    /// Columns in schema order.
+    pub columns: Vec<(String, Column)>,
+}
+
+impl Table {
+    /// Column of the field called `name`, if any.
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns
+            .iter()
+            .find(|(column, _)| column == name)
+            .map(|(_, column)| column)
+    }
+}
+
+/// Collects records into a [`Table`], one column per top-level field of a schema.
+#[derive(Debug, Clone)]
+pub struct TableBuilder {
+    table: Table,
+}
+
+impl TableBuilder {
+    /// Creates an empty table with a column for every top-level field of `schema`
+    /// that produces a value.
+    pub fn new(schema: &Schema) -> Self {
+        let columns = schema
+            .fields
+            .iter()
+            .filter(|field| field.kind.has_value())
+            .map(|field| (field.name.clone(), Column::new()))
+            .collect();
+        Self {
+            table: Table { len: 0, columns },
+        }
+    }
+
+    /// Appends one record given as a map, e.g. after applying transforms. Fields
+    /// missing from `record` are stored as absent, and entries that are not schema
+    /// fields are ignored. After an error the columns may differ in length, so the
+    /// builder should be dropped.
+    pub fn push(&mut self, mut record: BTreeMap<String, Value>) -> Result<(), ColumnError> {
+        for (name, column) in &mut self.table.columns {
+            let value = record.remove(name.as_str()).unwrap_or(Value::Absent);
+            push_value(name, column, value)?;
+        }
+        self.table.len += 1;
+        Ok(())
+    }
+
+    /// Reads one record of `schema` starting at `base_bits` straight into the
+    /// columns, without a map in between. The schema's records must all have the
+    /// same layout, and `data` must hold the whole record.
+    pub(crate) fn push_static_record(
+        &mut self,
+        schema: &Schema,
+        data: &[u8],
+        base_bits: usize,
+    ) -> Result<(), ColumnError> {
+        // Columns follow the value fields in schema order, as does reading.
+        let mut columns = self.table.columns.iter_mut();
+        compiled::assemble_static_fields(&schema.fields, data, base_bits, |_, value| {
+            let (name, column) = columns.next().expect("one column per value field");
+            push_value(name, column, value)
+        })?;
+        self.table.len += 1;
+        Ok(())
+    }
+
+    /// Returns the collected table.
+    pub fn finish(self) -> Table {
+        self.table
+    }
+}
+
+/// Appends `value` to the column of the field `name`.
+fn push_value(name: &str, column: &mut Column, value: Value) -> Result<(), ColumnError> {
+    column
+        .push(value)
+        .map_err(|(expected, found)| ColumnError::TypeMismatch {
+            field: name.to_string(),
+            expected,
+            found,
+        })
+}
+
+#[cfg(feature = "arrow")]
+impl Column {
+    /// Converts the column into an Arrow array without copying its buffers.
+    pub fn into_array(self) -> Result<arrow_array::ArrayRef, arrow_schema::ArrowError> {
+        use std::sync::Arc;
+
+        use arrow_array::{
+            FixedSizeBinaryArray, Float32Array, Float64Array, Int64Array, LargeBinaryArray,
+            LargeListArray, LargeStringArray, NullArray, StructArray, UInt64Array,
+        };
+        use arrow_buffer::{BooleanBuffer, Buffer, NullBuffer, OffsetBuffer, ScalarBuffer};
+        use arrow_schema::{Field, Fields};
+
+        let len = self.len;
+        let nulls = self
+            .validity
+            .map(|bits| NullBuffer::new(BooleanBuffer::new(Buffer::from_vec(bits), 0, len)));
+        let offsets = |offsets: Vec<i64>| OffsetBuffer::new(ScalarBuffer::from(offsets));
+        let wide = |bytes: Vec<u8>, nulls| {
+            FixedSizeBinaryArray::try_new(16, Buffer::from_vec(bytes), nulls)
+        };
+        Ok(match self.values {
+            ColumnValues::Null => Arc::new(NullArray::new(len)),
+            ColumnValues::U64(values) => Arc::new(UInt64Array::try_new(values.into(), nulls)?),
+            ColumnValues::I64(values) => Arc::new(Int64Array::try_new(values.into(), nulls)?),
+            // Arrow has no 128-bit integer types, so both become 16 big-endian bytes.
+            // `Decimal128(38, 0)` would reject signed values of 39 digits.
+            ColumnValues::U128(values) => Arc::new(wide(
+                values.iter().flat_map(|v| v.to_be_bytes()).collect(),
+                nulls,
+            )?),
+            ColumnValues::I128(values) => Arc::new(wide(
+                values.iter().flat_map(|v| v.to_be_bytes()).collect(),
+                nulls,
+            )?),
+            ColumnValues::F32(values) => Arc::new(Float32Array::try_new(values.into(), nulls)?),
+            ColumnValues::F64(values) => Arc::new(Float64Array::try_new(values.into(), nulls)?),
+            ColumnValues::Utf8 { offsets: o, data } => Arc::new(LargeStringArray::try_new(
+                offsets(o),
+                Buffer::from_vec(data),
+                nulls,
+            )?),
+            ColumnValues::Binary { offsets: o, data } => Arc::new(LargeBinaryArray::try_new(
+                offsets(o),
+                Buffer::from_vec(data),
+                nulls,
+            )?),
+            ColumnValues::List { offsets: o, values } => {
+                let values = values.into_array()?;
+                let field = Field::new_list_field(values.data_type().clone(), true);
+                Arc::new(LargeListArray::try_new(
+                    Arc::new(field),
+                    offsets(o),
+                    values,
+                    nulls,
+                )?)
+            }
+            ColumnValues::Struct(children) if children.is_empty() => {
+                Arc::new(StructArray::new_empty_fields(len, nulls))
+            }
+            ColumnValues::Struct(children) => {
+                let (fields, arrays): (Vec<_>, Vec<_>) = children
+                    .into_iter()
+                    .map(|(name, child)| {
+                        let array = child.into_array()?;
+                        Ok((Field::new(name, array.data_type().clone(), true), array))
+                    })
+                    .collect::<Result<Vec<_>, arrow_schema::ArrowError>>()?
+                    .into_iter()
+                    .unzip();
+                Arc::new(StructArray::try_new(Fields::from(fields), arrays, nulls)?)
+            }
+        })
+    }
+}
+
+#[cfg(feature = "arrow")]
+impl Table {
+    /// Converts the table into an Arrow `RecordBatch` with one nullable column per
+    /// field. Strings, bytes and arrays use Arrow's 64-bit offset types
+    /// (`LargeUtf8`, `LargeBinary`, `LargeList`). 128-bit integers become
+    /// `FixedSizeBinary(16)`, big-endian and two's complement if signed.
+    pub fn into_record_batch(self) -> Result<arrow_array::RecordBatch, arrow_schema::ArrowError> {
+        use std::sync::Arc;
+
+        use arrow_array::{RecordBatch, RecordBatchOptions};
+        use arrow_schema::{Field, Schema as ArrowSchema};
+
+        let mut fields = Vec::with_capacity(self.columns.len());
+        let mut arrays = Vec::with_capacity(self.columns.len());
+        for (name, column) in self.columns {
+            let array = column.into_array()?;
+            fields.push(Field::new(name, array.data_type().clone(), true));
+            arrays.push(array);
+        }
+        RecordBatch::try_new_with_options(
+            Arc::new(ArrowSchema::new(fields)),
+            arrays,
+            &RecordBatchOptions::new().with_row_count(Some(self.len)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(entries: &[(&str, Value)]) -> BTreeMap<String, Value> {
+        entries
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect()
+    }
+
+    fn builder(names: &[&str]) -> TableBuilder {
+        TableBuilder {
+            table: Table {
+                len: 0,
+                columns: names
+                    .iter()
+                    .map(|name| (name.to_string(), Column::new()))
+                    .collect(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_absent_values_clear_validity_bits() {
+        let mut builder = builder(&["a"]);
+        for i in 0..10 {
+            let value = if i % 3 == 0 {
+                Value::Absent
+            } else {
+                Value::F64(i as f64)
+            };
+            builder.push(record(&[("a", value)])).unwrap();
+        }
+        let table = builder.finish();
+        let column = table.column("a").unwrap();
+
+        assert_eq!(column.len, 10);
+        assert_eq!(column.null_count(), 4);
+        assert_eq!(column.validity, Some(vec![0b1011_0110, 0b0000_0001]));
+        assert!(!column.is_valid(0) && column.is_valid(1) && !column.is_valid(9));
+        let ColumnValues::F64(values) = &column.values else {
+            panic!("expected an F64 column, got {:?}", column.values);
+        };
+        assert_eq!(values[..3], [0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_type_is_taken_from_first_present_value() {
+        let mut builder = builder(&["s"]);
+        builder.push(record(&[])).unwrap();
+        builder
+            .push(record(&[("s", Value::String("ab".into()))]))
+            .unwrap();
+        builder
+            .push(record(&[("s", Value::String("c".into()))]))
+            .unwrap();
+        let column = builder.finish().columns.remove(0).1;
+
+        assert_eq!(
+            column.values,
+            ColumnValues::Utf8 {
+                offsets: vec![0, 0, 2, 3],
+                data: b"abc".to_vec(),
+            }
+        );
+        assert_eq!(column.validity, Some(vec![0b110]));
+    }
+
+    #[test]
+    fn test_arrays_and_structs_nest() {
+        let mut builder = builder(&["items", "body"]);
+        let body = |entries: &[(&str, Value)]| Value::Struct(record(entries));
+        builder
+            .push(record(&[
+                ("items", Value::Array(vec![Value::U64(1), Value::U64(2)])),
+                ("body", body(&[("x", Value::U64(5))])),
+            ]))
+            .unwrap();
+        builder
+            .push(record(&[
+                ("items", Value::Array(vec![Value::U64(3)])),
+                ("body", body(&[("y", Value::I64(-1))])),
+            ]))
+            .unwrap();
+        let table = builder.finish();
+
+        let ColumnValues::List { offsets, values } = &table.column("items").unwrap().values else {
+            panic!("expected a list column");
+        };
+        assert_eq!(offsets, &[0, 2, 3]);
+        assert_eq!(values.values, ColumnValues::U64(vec![1, 2, 3]));
+
+        let ColumnValues::Struct(children) = &table.column("body").unwrap().values else {
+            panic!("expected a struct column");
+        };
+        assert_eq!(children[0].0, "x");
+        assert_eq!(children[0].1.values, ColumnValues::U64(vec![5, 0]));
+        assert_eq!(children[0].1.validity, Some(vec![0b01]));
+        assert_eq!(children[1].0, "y");
+        assert_eq!(children[1].1.values, ColumnValues::I64(vec![0, -1]));
+        assert_eq!(children[1].1.validity, Some(vec![0b10]));
+    }
+
+    #[test]
+    fn test_mismatched_value_is_rejected() {
+        let mut builder = builder(&["a"]);
+        builder.push(record(&[("a", Value::U64(1))])).unwrap();
+        let err = builder
+            .push(record(&[("a", Value::String("x".into()))]))
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            ColumnError::TypeMismatch {
+                field: "a".to_string(),
+                expected: "U64",
+                found: "String",
+            }
+        );
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_into_record_batch() {
+        use arrow_array::{Array, LargeListArray, UInt64Array};
+
+        let mut builder = builder(&["id", "items"]);
+        builder
+            .push(record(&[
+                ("id", Value::U64(7)),
+                ("items", Value::Array(vec![Value::I64(-1), Value::I64(2)])),
+            ]))
+            .unwrap();
+        builder
+            .push(record(&[("items", Value::Array(vec![]))]))
+            .unwrap();
+        let batch = builder.finish().into_record_batch().unwrap();
+
+        assert_eq!(batch.num_rows(), 2);
+        let ids = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<UInt64Array>()
+            .unwrap();
+        assert_eq!(ids.value(0), 7);
+        assert!(ids.is_null(1));
+        let items = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<LargeListArray>()
+            .unwrap();
+        assert_eq!(items.value_length(0), 2);
+        assert_eq!(items.value_length(1), 0);
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_wide_integers_convert_to_arrow() {
+        use arrow_array::{Array, FixedSizeBinaryArray};
+
+        let mut builder = builder(&["uuid", "time"]);
+        builder
+            .push(record(&[
+                ("uuid", Value::U128(u128::MAX - 1)),
+                ("time", Value::I128(i128::MIN)),
+            ]))
+            .unwrap();
+        builder.push(record(&[])).unwrap();
+        builder
+            .push(record(&[("time", Value::I128(i128::MAX))]))
+            .unwrap();
+        let batch = builder.finish().into_record_batch().unwrap();
+
+        let uuids = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<FixedSizeBinaryArray>()
+            .unwrap();
+        assert_eq!(uuids.value(0), (u128::MAX - 1).to_be_bytes());
+        assert!(uuids.is_null(1));
+        let times = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<FixedSizeBinaryArray>()
+            .unwrap();
+        assert_eq!(times.value(0), i128::MIN.to_be_bytes());
+        assert!(times.is_null(1));
+        assert_eq!(times.value(2), i128::MAX.to_be_bytes());
+    }
+}
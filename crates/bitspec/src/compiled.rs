@@ -1,59 +1,1009 @@
 //! Compiled (executable) representation of fields and fragments for fast parsing.
 
+use std::{collections::BTreeMap, ops::Range};
+
 use crate::{
-    assembly::{ArrayCount, Assemble, BitOrder},
-    bits::{self, reverse_bits_n, sign_extend},
-    errors::{CompileError, ReadError, WriteError},
-    field::FieldKind,
+    assembly::{ArrayCount, Assemble, BitOrder, Endian},
+    bits::{self, reverse_bits_u128_n, sign_extend, sign_extend_u128},
+    checksum::{ChecksumAlgorithm, ChecksumCoverage},
+    errors::{CompileError, ErrorContext, ReadError, WriteError},
+    field::{BytesPadding, FieldKind, Layout, StringFraming},
+    transform::Encoding,
     value::Value,
 };
 
-/// Compiled field: either a scalar or an array.
+/// Compiled field: a scalar, an array, or a struct of sub-fields.
 #[derive(Debug, Clone)]
 pub enum CompiledFieldKind {
     /// A single value assembled from one or more fragments.
     Scalar(CompiledScalar),
     /// A repeated sequence of elements with configurable stride.
     Array(CompiledArray),
+    /// A group of named sub-fields sharing a common base offset.
+    Struct(CompiledStruct),
+    /// One of several sub-field lists, chosen by the value of another field.
+    Switch(CompiledSwitch),
+    /// Raw bit range of `len_bits` starting at `offset_bits`, read into a
+    /// [`Value::Bytes`].
+    Bytes {
+        /// Start of the range relative to the field's base.
+        offset_bits: usize,
+        /// Length of the range.
+        len_bits: usize,
+        /// Where the spare bits of a partial byte go.
+        padding: BytesPadding,
+    },
+    /// Text framed by a length prefix, a terminator, or a fixed size.
+    String(CompiledString),
+    /// Unnamed gap of `len_bits` starting at `offset_bits` (sequential layout only).
+    Skip {
+        /// Start of the gap relative to the field's base.
+        offset_bits: usize,
+        /// Length of the gap.
+        len_bits: usize,
+    },
+    /// Advances the cursor to the next multiple of this many bits, counted from the
+    /// start of the enclosing field list (sequential layout only).
+    Align(usize),
+    /// Reserved bits that produce no value; `fill` is written on serialize.
+    Reserved {
+        /// Where the bits are.
+        scalar: CompiledScalar,
+        /// Raw value written on serialize.
+        fill: u64,
+        /// Whether parse checks the bits against `fill`.
+        check: bool,
+    },
+    /// Checksum over other bits, computed on serialize and verified on parse.
+    Checksum {
+        /// Where the checksum is stored.
+        scalar: CompiledScalar,
+        /// How the value is computed.
+        algorithm: ChecksumAlgorithm,
+        /// Bits or sibling fields it covers.
+        covers: ChecksumCoverage,
+        /// Whether parse fails when the stored value differs.
+        check: bool,
+    },
 }
 
-/// A field after compilation: name and either scalar or array layout.
+impl CompiledFieldKind {
+    /// Reads a value of this kind from `data`, with offsets relative to `base_offset`.
+    /// Field references (such as array counts) are resolved in `scope`.
+    pub fn assemble_at(
+        &self,
+        data: &[u8],
+        base_offset: usize,
+        scope: &Scope<'_>,
+    ) -> Result<Value, ReadError> {
+        match self {
+            CompiledFieldKind::Scalar(scalar) => scalar.assemble_at(data, base_offset),
+            CompiledFieldKind::Array(array) => array.assemble_at(data, base_offset, scope),
+            CompiledFieldKind::Struct(group) => group.assemble_at(data, base_offset, scope),
+            CompiledFieldKind::Switch(switch) => switch.assemble_at(data, base_offset, scope),
+            CompiledFieldKind::Bytes {
+                offset_bits,
+                len_bits,
+                padding,
+            } => {
+                let start = base_offset + offset_bits;
+                bits::read_bytes_at(data, start, *len_bits, *padding == BytesPadding::Leading)
+                    .map(Value::Bytes)
+                    .map_err(|e| {
+                        e.with_context(ErrorContext {
+                            bit_range: Some(start..start + len_bits),
+                            available_bits: Some(data.len() * 8),
+                            ..ErrorContext::default()
+                        })
+                    })
+            }
+            CompiledFieldKind::String(string) => string.assemble_at(data, base_offset),
+            // Checked reserved bits are read for the caller to compare with the fill.
+            CompiledFieldKind::Reserved {
+                scalar,
+                check: true,
+                ..
+            } => scalar
+                .read_wide_bits_at(data, base_offset)
+                .map(|bits| scalar.raw_value(bits)),
+            CompiledFieldKind::Checksum { scalar, .. } => {
+                scalar.read_bits_at(data, base_offset).map(Value::U64)
+            }
+            // Spacers carry no data and are left out of parse output.
+            CompiledFieldKind::Skip { .. }
+            | CompiledFieldKind::Align(_)
+            | CompiledFieldKind::Reserved { .. } => Ok(Value::Absent),
+        }
+    }
+
+    /// Writes `value` into `buf`, with offsets relative to `base_offset`.
+    /// Field references (such as array counts) are resolved in `scope`.
+    pub fn disassemble_at(
+        &self,
+        value: &Value,
+        buf: &mut [u8],
+        base_offset: usize,
+        scope: &Scope<'_>,
+    ) -> Result<(), WriteError> {
+        match self {
+            CompiledFieldKind::Scalar(scalar) => scalar.disassemble_at(value, buf, base_offset),
+            CompiledFieldKind::Array(array) => array.disassemble_at(value, buf, base_offset, scope),
+            CompiledFieldKind::Struct(group) => {
+                group.disassemble_at(value, buf, base_offset, scope)
+            }
+            CompiledFieldKind::Switch(switch) => {
+                switch.disassemble_at(value, buf, base_offset, scope)
+            }
+            CompiledFieldKind::Bytes {
+                offset_bits,
+                len_bits,
+                padding,
+            } => {
+                let leading_pad = *padding == BytesPadding::Leading;
+                let start = base_offset + offset_bits;
+                match value {
+                    Value::Bytes(bytes) => {
+                        bits::write_bytes_at(buf, start, *len_bits, bytes, leading_pad)
+                    }
+                    // Byte values as an inverted `Base::Bytes` transform returns them.
+                    Value::Array(items) => {
+                        let bytes = items
+                            .iter()
+                            .map(|item| match item {
+                                Value::U64(v) => u8::try_from(*v).ok(),
+                                Value::I64(v) => u8::try_from(*v).ok(),
+                                _ => None,
+                            })
+                            .collect::<Option<Vec<u8>>>()
+                            .ok_or(WriteError::InvalidValue)?;
+                        bits::write_bytes_at(buf, start, *len_bits, &bytes, leading_pad)
+                    }
+                    Value::Struct(_) | Value::Absent => Err(WriteError::InvalidValue),
+                    _ => Err(WriteError::UnsupportedValue {
+                        field: String::new(),
+                        variant: value_variant_name(value),
+                    }),
+                }
+            }
+            CompiledFieldKind::String(string) => string.disassemble_at(value, buf, base_offset),
+            // Reserved bits are written with their fill whatever the value.
+            CompiledFieldKind::Reserved { scalar, fill, .. } => {
+                scalar.disassemble_at(&Value::U64(*fill), buf, base_offset)
+            }
+            CompiledFieldKind::Checksum { scalar, .. } => {
+                scalar.disassemble_at(value, buf, base_offset)
+            }
+            CompiledFieldKind::Skip { .. } | CompiledFieldKind::Align(_) => Ok(()),
+        }
+    }
+
+    /// True if this kind produces a value in parse output (spacers and reserved
+    /// bits do not).
+    pub fn has_value(&self) -> bool {
+        !matches!(
+            self,
+            CompiledFieldKind::Skip { .. }
+                | CompiledFieldKind::Align(_)
+                | CompiledFieldKind::Reserved { .. }
+        )
+    }
+
+    /// One past the last bit touched by this kind, relative to its base offset.
+    /// Arrays whose count comes from another field contribute nothing, so this is
+    /// the minimum size when such arrays are present.
+    pub fn end_bits(&self) -> usize {
+        match self {
+            CompiledFieldKind::Scalar(scalar)
+            | CompiledFieldKind::Reserved { scalar, .. }
+            | CompiledFieldKind::Checksum { scalar, .. } => scalar
+                .fragments
+                .iter()
+                .map(|frag| frag.offset_bits + frag.len_bits)
+                .max()
+                .unwrap_or(0),
+            CompiledFieldKind::Array(array) => match array.count {
+                ArrayCount::Fixed(count) => {
+                    array.offset_bits + array.element.end_bits() + array.stride_bits * (count - 1)
+                }
+                ArrayCount::FromField { .. } => 0,
+            },
+            CompiledFieldKind::Struct(group) => {
+                group.offset_bits + fields_end_bits(&group.fields, CompiledField::min_end_bits)
+            }
+            CompiledFieldKind::Switch(switch) => {
+                switch.offset_bits + switch.case_end_bits().min().unwrap_or(0)
+            }
+            CompiledFieldKind::Bytes {
+                offset_bits,
+                len_bits,
+                ..
+            }
+            | CompiledFieldKind::Skip {
+                offset_bits,
+                len_bits,
+            } => offset_bits + len_bits,
+            CompiledFieldKind::String(string) => string.offset_bits + string.min_bits(),
+            CompiledFieldKind::Align(_) => 0,
+        }
+    }
+
+    /// First bit touched by this kind, relative to its base offset. Fields placed
+    /// after a variable-length field count from the base, so for a struct or switch
+    /// holding them this is a lower bound.
+    pub fn start_bits(&self) -> usize {
+        match self {
+            CompiledFieldKind::Scalar(scalar)
+            | CompiledFieldKind::Reserved { scalar, .. }
+            | CompiledFieldKind::Checksum { scalar, .. } => scalar
+                .fragments
+                .iter()
+                .map(|frag| frag.offset_bits)
+                .min()
+                .unwrap_or(0),
+            CompiledFieldKind::Array(array) => array.offset_bits + array.element.start_bits(),
+            CompiledFieldKind::Struct(group) => {
+                group.offset_bits + fields_start_bits(&group.fields)
+            }
+            CompiledFieldKind::Switch(switch) => {
+                let cases = switch.cases.values().chain(&switch.default);
+                switch.offset_bits
+                    + cases
+                        .map(|fields| fields_start_bits(fields))
+                        .min()
+                        .unwrap_or(0)
+            }
+            CompiledFieldKind::Bytes { offset_bits, .. }
+            | CompiledFieldKind::Skip { offset_bits, .. } => *offset_bits,
+            CompiledFieldKind::String(string) => string.offset_bits,
+            CompiledFieldKind::Align(_) => 0,
+        }
+    }
+
+    /// One past the last bit that writing `value` with this kind touches. Unlike
+    /// [`CompiledFieldKind::end_bits`] this accounts for the actual array lengths,
+    /// present conditional fields and switch cases, resolving references in `scope`.
+    pub fn value_end_bits(&self, value: &Value, scope: &Scope<'_>) -> usize {
+        match (self, value) {
+            (CompiledFieldKind::Array(array), Value::Array(values)) => values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    array.offset_bits
+                        + i * array.stride_bits
+                        + array.element.value_end_bits(v, scope)
+                })
+                .max()
+                .unwrap_or(array.offset_bits),
+            (CompiledFieldKind::Struct(group), Value::Struct(map)) => {
+                group.offset_bits + values_end_bits(&group.fields, map, scope)
+            }
+            (CompiledFieldKind::Switch(switch), Value::Struct(map)) => match switch.select(scope) {
+                Ok(Some(fields)) => switch.offset_bits + values_end_bits(fields, map, scope),
+                _ => self.end_bits(),
+            },
+            (CompiledFieldKind::String(string), Value::String(text)) => {
+                string.offset_bits + string.text_bits(text).unwrap_or_else(|| string.min_bits())
+            }
+            // Spacers take their space even though they carry no value.
+            (CompiledFieldKind::Skip { .. } | CompiledFieldKind::Reserved { .. }, _) => {
+                self.end_bits()
+            }
+            (_, Value::Absent) => 0,
+            _ => self.end_bits(),
+        }
+    }
+
+    /// True if the size of this kind is known at compile time.
+    pub(crate) fn is_static(&self) -> bool {
+        match self {
+            CompiledFieldKind::Scalar(_)
+            | CompiledFieldKind::Bytes { .. }
+            | CompiledFieldKind::Skip { .. }
+            | CompiledFieldKind::Reserved { .. }
+            | CompiledFieldKind::Checksum { .. } => true,
+            CompiledFieldKind::Array(array) => {
+                matches!(array.count, ArrayCount::Fixed(_)) && array.element.is_static()
+            }
+            CompiledFieldKind::Struct(group) => {
+                group.fields.iter().all(|field| field.condition.is_none() && field.kind.is_static())
+            }
+            CompiledFieldKind::String(string) => {
+                matches!(string.framing, StringFraming::Fixed { .. })
+            }
+            CompiledFieldKind::Switch(_) | CompiledFieldKind::Align(_) => false,
+        }
+    }
+
+    /// Moves this kind `bits` further from its base.
+    fn shift(&mut self, bits: usize) {
+        match self {
+            CompiledFieldKind::Scalar(scalar)
+            | CompiledFieldKind::Reserved { scalar, .. }
+            | CompiledFieldKind::Checksum { scalar, .. } => {
+                for fragment in &mut scalar.fragments {
+                    fragment.offset_bits += bits;
+                }
+            }
+            CompiledFieldKind::Array(array) => array.offset_bits += bits,
+            CompiledFieldKind::Struct(group) => group.offset_bits += bits,
+            CompiledFieldKind::Switch(switch) => switch.offset_bits += bits,
+            CompiledFieldKind::String(string) => string.offset_bits += bits,
+            CompiledFieldKind::Bytes { offset_bits, .. }
+            | CompiledFieldKind::Skip { offset_bits, .. } => *offset_bits += bits,
+            CompiledFieldKind::Align(_) => {}
+        }
+    }
+
+    /// Bits occupied by one value of this kind, used to validate array strides.
+    fn size_bits(&self) -> usize {
+        match self {
+            CompiledFieldKind::Scalar(scalar) => scalar.total_bits,
+            // Every case has to fit, not just the smallest.
+            CompiledFieldKind::Switch(switch) => {
+                switch.offset_bits + switch.case_end_bits().max().unwrap_or(0)
+            }
+            _ => self.end_bits(),
+        }
+    }
+}
+
+/// Where a compiled field starts relative to the base of its field list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// Offsets are relative to the base; in sequential layouts the static cursor
+    /// position has already been added to them.
+    Fixed,
+    /// Offsets are relative to the end of the previous field, which is only known
+    /// at run time (sequential layout after a variable-length field).
+    Cursor,
+}
+
+/// A field after compilation: name, layout, and optional transform.
 #[derive(Debug, Clone)]
 pub struct CompiledField {
     /// Field name used as the key in parsed output maps.
     pub name: String,
-    /// Scalar or array layout for this field.
+    /// Scalar, array, or struct layout for this field.
     pub kind: CompiledFieldKind,
+    /// Transform applied by [`crate::schema::Schema::apply_transforms`], if any.
+    pub transform: Option<crate::transform::Transform>,
+    /// Whether the field's offsets are fixed or follow the running cursor.
+    pub placement: Placement,
+    /// Presence condition; when it does not hold the field is read as
+    /// [`Value::Absent`] and not written.
+    pub condition: Option<crate::field::Condition>,
+    /// Raw value written in place of a missing entry when serializing with
+    /// defaults.
+    pub default: Option<Value>,
+    /// Value the field must hold; checked on parse and written when missing on
+    /// serialize.
+    pub constant: Option<Value>,
+}
+
+impl CompiledField {
+    /// End bit of the field when it is present with its minimum size; conditional
+    /// fields may be absent and contribute nothing.
+    pub fn min_end_bits(&self) -> usize {
+        match self.condition {
+            Some(_) => 0,
+            None => self.kind.end_bits(),
+        }
+    }
+
+    /// Checks a parsed value against the field's constant, or checked reserved
+    /// bits against their fill.
+    pub(crate) fn check_constant(&self, value: &Value) -> Result<(), ReadError> {
+        let fill;
+        let expected = match (&self.kind, &self.constant) {
+            (
+                CompiledFieldKind::Reserved {
+                    scalar,
+                    fill: bits,
+                    check: true,
+                },
+                _,
+            ) => {
+                fill = scalar.raw_value(u128::from(*bits));
+                &fill
+            }
+            (_, Some(constant)) => constant,
+            _ => return Ok(()),
+        };
+        if expected == value {
+            Ok(())
+        } else {
+            Err(ReadError::ConstantMismatch {
+                field: self.name.clone(),
+                expected: expected.clone().into(),
+                found: value.clone().into(),
+            })
+        }
+    }
+
+    /// Recomputes a checked checksum over `data` and compares it with the stored
+    /// `value`. Other fields pass.
+    pub(crate) fn verify_checksum(
+        &self,
+        value: &Value,
+        data: &[u8],
+        base_offset: usize,
+        spans: &[(&str, Range<usize>)],
+    ) -> Result<(), ReadError> {
+        let (
+            CompiledFieldKind::Checksum {
+                algorithm,
+                covers,
+                check: true,
+                ..
+            },
+            Value::U64(found),
+        ) = (&self.kind, value)
+        else {
+            return Ok(());
+        };
+        let covered =
+            crate::checksum::gather_bits(data, &covered_ranges(covers, base_offset, spans))?;
+        let expected = algorithm.compute(&covered);
+        if expected == *found {
+            Ok(())
+        } else {
+            Err(ReadError::ChecksumMismatch {
+                field: self.name.clone(),
+                expected,
+                found: *found,
+            })
+        }
+    }
+
+    /// Computes a checksum over `buf` and writes it into the field's bits.
+    pub(crate) fn write_checksum(
+        &self,
+        buf: &mut [u8],
+        start: usize,
+        base_offset: usize,
+        spans: &[(&str, Range<usize>)],
+    ) -> Result<(), WriteError> {
+        let CompiledFieldKind::Checksum {
+            scalar,
+            algorithm,
+            covers,
+            ..
+        } = &self.kind
+        else {
+            return Ok(());
+        };
+        let covered =
+            crate::checksum::gather_bits(buf, &covered_ranges(covers, base_offset, spans))
+                .map_err(|_| WriteError::OutOfBounds)?;
+        scalar.disassemble_at(&Value::U64(algorithm.compute(&covered)), buf, start)
+    }
+}
+
+/// Bit ranges of the data a checksum covers. `spans` holds the bits of every field
+/// of the list read or written so far; fields without a span (absent ones) add
+/// nothing.
+fn covered_ranges(
+    covers: &ChecksumCoverage,
+    base_offset: usize,
+    spans: &[(&str, Range<usize>)],
+) -> Vec<Range<usize>> {
+    match covers {
+        ChecksumCoverage::Bits(bits) => {
+            std::iter::once(base_offset + bits.start..base_offset + bits.end).collect()
+        }
+        ChecksumCoverage::Fields(names) => names
+            .iter()
+            .filter_map(|name| {
+                spans
+                    .iter()
+                    .find(|(field, _)| field == name)
+                    .map(|(_, bits)| bits.clone())
+            })
+            .collect(),
+    }
 }
 
 impl TryFrom<&crate::field::Field> for CompiledField {
     type Error = CompileError;
 
     fn try_from(value: &crate::field::Field) -> Result<Self, Self::Error> {
-        let compiled_scalar: CompiledScalar = value.try_into()?;
-        match &value.kind {
-            FieldKind::Scalar => Ok(CompiledField {
-                name: value.name.clone(),
-                kind: CompiledFieldKind::Scalar(compiled_scalar),
-            }),
-            FieldKind::Array(spec) => {
-                if spec.stride_bits < compiled_scalar.total_bits {
-                    return Err(CompileError::InvalidArrayStride);
-                } else if spec.count == 0 {
-                    return Err(CompileError::InvalidArrayCount);
-                } else if value.fragments.len() == 0 {
-                    return Err(CompileError::EmptyArrayElement);
+        Ok(CompiledField {
+            name: value.name.clone(),
+            kind: compile_kind(value, &value.kind, Layout::Absolute)
+                .map_err(|e| e.in_field(&value.name))?,
+            transform: value.transform.clone(),
+            placement: Placement::Fixed,
+            condition: value.condition.clone(),
+            default: value.default.clone(),
+            constant: value.constant.clone(),
+        })
+    }
+}
+
+/// Compiles a field list. With [`Layout::Sequential`], every field is placed after
+/// the previous one: while the cursor is known at compile time it is folded into
+/// the field's offsets, and after a variable-length field the remaining fields
+/// are marked [`Placement::Cursor`]. A conditional field may take no space, so it
+/// and the fields after it also follow the cursor.
+pub(crate) fn compile_fields(
+    fields: &[crate::field::Field],
+    layout: Layout,
+) -> Result<Vec<CompiledField>, CompileError> {
+    let mut compiled = Vec::with_capacity(fields.len());
+    let mut cursor: Option<usize> = Some(0);
+
+    for field in fields {
+        let mut kind =
+            compile_kind(field, &field.kind, layout).map_err(|e| e.in_field(&field.name))?;
+        let mut placement = Placement::Fixed;
+
+        if layout == Layout::Sequential {
+            match cursor {
+                Some(_) if field.condition.is_some() => {
+                    placement = Placement::Cursor;
+                    cursor = None;
+                }
+                Some(at) => {
+                    if let CompiledFieldKind::Align(align_bits) = kind {
+                        let aligned = at.next_multiple_of(align_bits);
+                        kind = CompiledFieldKind::Skip {
+                            offset_bits: at,
+                            len_bits: aligned - at,
+                        };
+                    } else {
+                        kind.shift(at);
+                    }
+                    cursor = kind.is_static().then(|| kind.end_bits());
                 }
+                None => placement = Placement::Cursor,
+            }
+        }
 
-                Ok(CompiledField {
-                    name: value.name.clone(),
-                    kind: CompiledFieldKind::Array(CompiledArray {
-                        element: compiled_scalar,
-                        count: ArrayCount::Fixed(spec.count),
-                        stride_bits: spec.stride_bits,
-                        offset_bits: spec.offset_bits,
-                    }),
-                })
+        compiled.push(CompiledField {
+            name: field.name.clone(),
+            kind,
+            transform: field.transform.clone(),
+            placement,
+            condition: field.condition.clone(),
+            default: field.default.clone(),
+            constant: field.constant.clone(),
+        });
+    }
+
+    for field in &compiled {
+        if let CompiledFieldKind::Checksum {
+            covers: ChecksumCoverage::Fields(names),
+            ..
+        } = &field.kind
+        {
+            check_covered_fields(&field.name, names, &compiled)
+                .map_err(|e| e.in_field(&field.name))?;
+        }
+    }
+
+    Ok(compiled)
+}
+
+/// Checks that every field a checksum covers is another value-producing field of
+/// the same list.
+fn check_covered_fields(
+    checksum: &str,
+    names: &[String],
+    fields: &[CompiledField],
+) -> Result<(), CompileError> {
+    for name in names {
+        if name == checksum {
+            return Err(CompileError::InvalidFieldKind);
+        } else if !fields
+            .iter()
+            .any(|field| field.name == *name && field.kind.has_value())
+        {
+            return Err(CompileError::UnknownField(name.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// Compiles `kind` using the fragments, signedness, and assembly of `field`.
+fn compile_kind(
+    field: &crate::field::Field,
+    kind: &FieldKind,
+    layout: Layout,
+) -> Result<CompiledFieldKind, CompileError> {
+    match kind {
+        FieldKind::Scalar => Ok(CompiledFieldKind::Scalar(field.try_into()?)),
+        FieldKind::Array(spec) => {
+            let element = match &spec.element {
+                Some(element) => compile_kind(field, element, layout)?,
+                None => compile_kind(field, &FieldKind::Scalar, layout)?,
+            };
+
+            if !element.has_value() || matches!(element, CompiledFieldKind::Checksum { .. }) {
+                return Err(CompileError::InvalidFieldKind);
+            } else if spec.stride_bits < element.size_bits() {
+                return Err(CompileError::InvalidArrayStride);
+            } else if spec.count == ArrayCount::Fixed(0) {
+                return Err(CompileError::InvalidArrayCount);
+            } else if element.end_bits() == 0 {
+                return Err(CompileError::EmptyArrayElement);
+            }
+
+            Ok(CompiledFieldKind::Array(CompiledArray {
+                element: Box::new(element),
+                count: spec.count.clone(),
+                stride_bits: spec.stride_bits,
+                offset_bits: spec.offset_bits,
+            }))
+        }
+        FieldKind::Struct(fields) => Ok(CompiledFieldKind::Struct(CompiledStruct {
+            offset_bits: 0,
+            fields: compile_fields(fields, layout)?,
+        })),
+        FieldKind::Switch { cases, default, .. } if cases.is_empty() && default.is_none() => {
+            Err(CompileError::InvalidFieldKind)
+        }
+        FieldKind::Switch { on, cases, default } => Ok(CompiledFieldKind::Switch(CompiledSwitch {
+            offset_bits: 0,
+            on: on.clone(),
+            cases: cases
+                .iter()
+                .map(|(key, fields)| Ok((*key, compile_fields(fields, layout)?)))
+                .collect::<Result<_, CompileError>>()?,
+            default: default
+                .as_ref()
+                .map(|fields| compile_fields(fields, layout))
+                .transpose()?,
+        })),
+        FieldKind::Bytes { len_bits: 0, .. } => Err(CompileError::InvalidFieldSize),
+        FieldKind::Bytes {
+            offset_bits,
+            len_bits,
+            padding,
+        } => Ok(CompiledFieldKind::Bytes {
+            offset_bits: *offset_bits,
+            len_bits: *len_bits,
+            padding: *padding,
+        }),
+        FieldKind::String {
+            framing: StringFraming::LengthPrefixed { prefix_bits },
+            ..
+        } if !(1..=64).contains(prefix_bits) => Err(CompileError::InvalidFieldSize),
+        FieldKind::String {
+            framing: StringFraming::Fixed { len_bytes: 0, .. },
+            ..
+        } => Err(CompileError::InvalidFieldSize),
+        FieldKind::String {
+            offset_bits,
+            framing,
+            encoding,
+        } => Ok(CompiledFieldKind::String(CompiledString {
+            offset_bits: *offset_bits,
+            framing: *framing,
+            encoding: encoding.clone(),
+        })),
+        FieldKind::Skip(_) | FieldKind::Align(_) if layout != Layout::Sequential => {
+            Err(CompileError::InvalidFieldKind)
+        }
+        FieldKind::Skip(len_bits) => Ok(CompiledFieldKind::Skip {
+            offset_bits: 0,
+            len_bits: *len_bits,
+        }),
+        FieldKind::Reserved { fill, check } => {
+            let scalar: CompiledScalar = field.try_into()?;
+            if scalar.total_bits < 64 && fill >> scalar.total_bits != 0 {
+                // The fill would not fit in the reserved bits.
+                return Err(CompileError::InvalidFieldKind);
+            }
+            Ok(CompiledFieldKind::Reserved {
+                scalar,
+                fill: *fill,
+                check: *check,
+            })
+        }
+        FieldKind::Checksum {
+            algorithm,
+            covers,
+            check,
+        } => {
+            let scalar: CompiledScalar = field.try_into()?;
+            // Checksums are computed as `u64`.
+            if scalar.total_bits < algorithm.width() || scalar.total_bits > 64 {
+                return Err(CompileError::InvalidFieldSize);
+            }
+            let valid = match (algorithm, covers) {
+                (ChecksumAlgorithm::Crc(crc), _) if !crc.is_valid() => false,
+                (_, ChecksumCoverage::Bits(bits)) => !bits.is_empty(),
+                (_, ChecksumCoverage::Fields(names)) => !names.is_empty(),
+            };
+            if !valid {
+                return Err(CompileError::InvalidFieldKind);
+            }
+            Ok(CompiledFieldKind::Checksum {
+                scalar,
+                algorithm: algorithm.clone(),
+                covers: covers.clone(),
+                check: *check,
+            })
+        }
+        FieldKind::Align(0) => Err(CompileError::InvalidFieldSize),
+        FieldKind::Align(align_bits) => Ok(CompiledFieldKind::Align(*align_bits)),
+    }
+}
+
+/// Runs `visit` on each field of a list with the bit offset it starts at, and
+/// returns one past the last bit used. `visit` returns the end of the field
+/// relative to that start. Fixed fields start at `base_offset`; cursor fields
+/// start where the previous field ended.
+fn walk_fields<'a, E>(
+    fields: &'a [CompiledField],
+    base_offset: usize,
+    mut visit: impl FnMut(&'a CompiledField, usize) -> Result<usize, E>,
+) -> Result<usize, E> {
+    let mut cursor = base_offset;
+    let mut end = base_offset;
+    for field in fields {
+        let start = match field.placement {
+            Placement::Fixed => base_offset,
+            Placement::Cursor => cursor,
+        };
+        cursor = match &field.kind {
+            CompiledFieldKind::Align(align_bits) => {
+                base_offset + (start - base_offset).next_multiple_of(*align_bits)
+            }
+            _ => start + visit(field, start)?,
+        };
+        end = end.max(cursor);
+    }
+    Ok(end)
+}
+
+/// End bit of a field list laid out from bit 0, given the end bit of each field.
+pub(crate) fn fields_end_bits(
+    fields: &[CompiledField],
+    end_bits: impl Fn(&CompiledField) -> usize,
+) -> usize {
+    let Ok(end) =
+        walk_fields::<std::convert::Infallible>(fields, 0, |field, _| Ok(end_bits(field)));
+    end
+}
+
+/// First bit of a field list laid out from bit 0; a field following the cursor
+/// counts as starting at 0.
+fn fields_start_bits(fields: &[CompiledField]) -> usize {
+    fields
+        .iter()
+        .map(|field| match field.placement {
+            Placement::Fixed => field.kind.start_bits(),
+            Placement::Cursor => 0,
+        })
+        .min()
+        .unwrap_or(0)
+}
+
+/// End bit of a field list when writing the values in `map`. Conditional fields
+/// that are not present count as empty.
+pub(crate) fn values_end_bits(
+    fields: &[CompiledField],
+    map: &BTreeMap<String, Value>,
+    scope: &Scope<'_>,
+) -> usize {
+    let scope = scope.child(map);
+    fields_end_bits(fields, |field| {
+        if !is_present(field, &scope) {
+            return 0;
+        }
+        match map.get(&field.name) {
+            Some(v) => field.kind.value_end_bits(v, &scope),
+            None => field.kind.end_bits(),
+        }
+    })
+}
+
+/// Whether a conditional field is present, judged on the values visible in `scope`.
+pub(crate) fn is_present(field: &CompiledField, scope: &Scope<'_>) -> bool {
+    match &field.condition {
+        Some(condition) => scope
+            .lookup(condition.field())
+            .is_some_and(|value| condition.is_met(value)),
+        None => true,
+    }
+}
+
+/// Reads a field list starting at `base_offset` into a map of values. Earlier
+/// fields are visible to later ones through `scope`, and fields with a constant
+/// must hold it. Checked checksums are verified once the whole list is read.
+pub(crate) fn assemble_fields(
+    fields: &[CompiledField],
+    data: &[u8],
+    base_offset: usize,
+    scope: &Scope<'_>,
+) -> Result<BTreeMap<String, Value>, ReadError> {
+    let mut map = BTreeMap::new();
+    let mut spans = Vec::new();
+    walk_fields(fields, base_offset, |field, start| {
+        let value = if is_present(field, &scope.child(&map)) {
+            let value = field
+                .kind
+                .assemble_at(data, start, &scope.child(&map))
+                .map_err(|e| e.in_field(&field.name))?;
+            field
+                .check_constant(&value)
+                .map_err(|e| e.in_field(&field.name))?;
+            value
+        } else {
+            Value::Absent
+        };
+        let end = field.kind.value_end_bits(&value, &scope.child(&map));
+        if field.kind.has_value() {
+            if value != Value::Absent {
+                spans.push((
+                    field.name.as_str(),
+                    start + field.kind.start_bits()..start + end,
+                ));
+            }
+            map.insert(field.name.clone(), value);
+        }
+        Ok(end)
+    })?;
+    for field in fields {
+        if let Some(value) = map.get(&field.name) {
+            field
+                .verify_checksum(value, data, base_offset, &spans)
+                .map_err(|e| e.in_field(&field.name))?;
+        }
+    }
+    Ok(map)
+}
+
+/// Reads a field list like [`assemble_fields`], but hands each value to `emit` in
+/// field order instead of collecting a map. Only for lists where no field refers
+/// to another: no conditions and only static kinds.
+pub(crate) fn assemble_static_fields<'a, E: From<ReadError>>(
+    fields: &'a [CompiledField],
+    data: &[u8],
+    base_offset: usize,
+    mut emit: impl FnMut(&'a CompiledField, Value) -> Result<(), E>,
+) -> Result<(), E> {
+    let scope = Scope::default();
+    let mut spans = Vec::new();
+    let mut checksums = Vec::new();
+    walk_fields(fields, base_offset, |field, start| -> Result<usize, E> {
+        let value = field
+            .kind
+            .assemble_at(data, start, &scope)
+            .and_then(|value| field.check_constant(&value).map(|()| value))
+            .map_err(|e| e.in_field(&field.name))?;
+        let end = field.kind.value_end_bits(&value, &scope);
+        if field.kind.has_value() {
+            spans.push((
+                field.name.as_str(),
+                start + field.kind.start_bits()..start + end,
+            ));
+            if matches!(field.kind, CompiledFieldKind::Checksum { check: true, .. }) {
+                checksums.push((field, value.clone()));
+            }
+            emit(field, value)?;
+        }
+        Ok(end)
+    })?;
+    for (field, value) in checksums {
+        field
+            .verify_checksum(&value, data, base_offset, &spans)
+            .map_err(|e| e.in_field(&field.name))?;
+    }
+    Ok(())
+}
+
+/// Writes the values of `map` for a field list starting at `base_offset`.
+/// A missing value is replaced by the field's constant, if it has one, and is
+/// otherwise reported as [`WriteError::MissingField`]. Errors are
+/// tagged with the field name. Conditional fields whose condition does not hold
+/// are skipped. Checksums need no value: they are computed in order once every
+/// other field of the list is written.
+pub(crate) fn disassemble_fields(
+    fields: &[CompiledField],
+    map: &BTreeMap<String, Value>,
+    buf: &mut [u8],
+    base_offset: usize,
+    scope: &Scope<'_>,
+) -> Result<(), WriteError> {
+    let scope = scope.child(map);
+    let mut spans = Vec::new();
+    let mut checksums = Vec::new();
+    walk_fields(fields, base_offset, |field, start| {
+        if !is_present(field, &scope) {
+            return Ok(0);
+        } else if !field.kind.has_value() {
+            field
+                .kind
+                .disassemble_at(&Value::Absent, buf, start, &scope)
+                .map_err(|e| attach_field_name(e, &field.name))?;
+            return Ok(field.kind.end_bits());
+        }
+        let end = if let CompiledFieldKind::Checksum { .. } = field.kind {
+            checksums.push((field, start));
+            field.kind.end_bits()
+        } else {
+            let value = map
+                .get(&field.name)
+                .filter(|value| !matches!(value, Value::Absent))
+                .or(field.constant.as_ref())
+                .ok_or_else(|| WriteError::MissingField(field.name.clone()))?;
+            field
+                .kind
+                .disassemble_at(value, buf, start, &scope)
+                .map_err(|e| attach_field_name(e, &field.name))?;
+            field.kind.value_end_bits(value, &scope)
+        };
+        spans.push((
+            field.name.as_str(),
+            start + field.kind.start_bits()..start + end,
+        ));
+        Ok(end)
+    })?;
+    for (field, start) in checksums {
+        field
+            .write_checksum(buf, start, base_offset, &spans)
+            .map_err(|e| attach_field_name(e, &field.name))?;
+    }
+    Ok(())
+}
+
+/// Adds the [`CompiledField::default`] of every field missing from `map`, then
+/// does the same inside struct values and the elements of arrays. A missing
+/// struct without a default is filled from the defaults of its sub-fields. Switch
+/// cases are left as they are.
+pub(crate) fn fill_defaults(fields: &[CompiledField], map: &mut BTreeMap<String, Value>) {
+    for field in fields.iter().filter(|field| field.kind.has_value()) {
+        let (mut value, missing) = match map.remove(&field.name) {
+            Some(value) if value != Value::Absent => (value, false),
+            _ => match (&field.default, &field.kind) {
+                (Some(default), _) => (default.clone(), false),
+                (None, CompiledFieldKind::Struct(_)) => (Value::Struct(BTreeMap::new()), true),
+                (None, _) => continue,
+            },
+        };
+        fill_kind_defaults(&field.kind, &mut value);
+        if !(missing && value == Value::Struct(BTreeMap::new())) {
+            map.insert(field.name.clone(), value);
+        }
+    }
+}
+
+fn fill_kind_defaults(kind: &CompiledFieldKind, value: &mut Value) {
+    match (kind, value) {
+        (CompiledFieldKind::Struct(group), Value::Struct(map)) => fill_defaults(&group.fields, map),
+        (CompiledFieldKind::Array(array), Value::Array(items)) => {
+            for item in items {
+                fill_kind_defaults(&array.element, item);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Writes the entries of `values` over `base`. Struct values are merged entry by
+/// entry; any other value, including a switch body, replaces the one in `base`.
+pub(crate) fn overlay_values(
+    fields: &[CompiledField],
+    base: &mut BTreeMap<String, Value>,
+    values: &BTreeMap<String, Value>,
+) {
+    for (name, value) in values {
+        let group = fields
+            .iter()
+            .find(|field| field.name == *name)
+            .and_then(|field| match &field.kind {
+                CompiledFieldKind::Struct(group) => Some(group),
+                _ => None,
+            });
+        match (group, base.get_mut(name), value) {
+            (_, _, Value::Absent) => {}
+            (Some(group), Some(Value::Struct(inner)), Value::Struct(entries)) => {
+                overlay_values(&group.fields, inner, entries)
+            }
+            _ => {
+                base.insert(name.clone(), value.clone());
             }
         }
     }
@@ -62,8 +1012,8 @@ impl TryFrom<&crate::field::Field> for CompiledField {
 /// Compiled array: element layout, count, stride, and start offset.
 #[derive(Debug, Clone)]
 pub struct CompiledArray {
-    /// Compiled layout of a single array element.
-    pub element: CompiledScalar,
+    /// Compiled layout of a single array element (a scalar or a struct).
+    pub element: Box<CompiledFieldKind>,
     /// Number of elements.
     pub count: ArrayCount,
     /// Bits between the start of consecutive elements.
@@ -77,39 +1027,99 @@ pub struct CompiledArray {
 
 impl CompiledArray {
     /// Assembles the array from `data` into a [Value::Array].
+    /// Counts taken from other fields are unresolved here; use [`CompiledArray::assemble_at`].
     pub fn assemble(&self, data: &[u8]) -> Result<Value, ReadError> {
-        let count = match self.count {
-            ArrayCount::Fixed(count) => count,
-        };
+        self.assemble_at(data, 0, &Scope::default())
+    }
+
+    /// Assembles the array from `data`, with its offset relative to `base_offset`.
+    /// A count taken from another field is looked up in `scope`.
+    pub fn assemble_at(
+        &self,
+        data: &[u8],
+        base_offset: usize,
+        scope: &Scope<'_>,
+    ) -> Result<Value, ReadError> {
+        let count = self.resolve_count(scope)?;
+        self.check_fits(count, data, base_offset)?;
 
         let mut values = Vec::<Value>::with_capacity(count);
         for i in 0..count {
-            let offset = self.offset_bits + i * self.stride_bits;
-            values.push(self.element.assemble_at(data, offset)?);
+            let offset = base_offset + self.offset_bits + i * self.stride_bits;
+            let value = self
+                .element
+                .assemble_at(data, offset, scope)
+                .map_err(|e| e.in_element(i))?;
+            values.push(value);
         }
 
         Ok(Value::Array(values))
     }
+
+    /// Rejects a count read from the data that the data cannot hold, before
+    /// anything is allocated for it.
+    pub(crate) fn check_fits(
+        &self,
+        count: usize,
+        data: &[u8],
+        base_offset: usize,
+    ) -> Result<(), ReadError> {
+        if count > 0 && matches!(self.count, ArrayCount::FromField { .. }) {
+            let start = base_offset + self.offset_bits;
+            let end = (count - 1)
+                .saturating_mul(self.stride_bits)
+                .saturating_add(start + self.element.end_bits());
+            if end > data.len().saturating_mul(8) {
+                return Err(ReadError::PacketTooShort.with_context(ErrorContext {
+                    bit_range: Some(start..end),
+                    available_bits: Some(data.len() * 8),
+                    ..ErrorContext::default()
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of elements, reading the referenced field from `scope` when needed.
+    pub fn resolve_count(&self, scope: &Scope<'_>) -> Result<usize, ReadError> {
+        match &self.count {
+            ArrayCount::Fixed(count) => Ok(*count),
+            ArrayCount::FromField { field, adjust } => {
+                let count = match scope.lookup(field) {
+                    Some(Value::U64(v)) => i128::from(*v),
+                    Some(Value::I64(v)) => i128::from(*v),
+                    Some(_) => return Err(ReadError::InvalidCount(field.clone())),
+                    None => return Err(ReadError::UnknownField(field.clone())),
+                };
+                usize::try_from(count + i128::from(*adjust))
+                    .map_err(|_| ReadError::InvalidCount(field.clone()))
+            }
+        }
+    }
 }
 
 impl CompiledArray {
-    /// Writes the array `value` into `buf`, placing each element at its strided bit offset.
+    /// Writes the array `value` into `buf`, placing each element at its strided bit
+    /// offset relative to `base_offset`. The number of values must match the count,
+    /// including a count read from another field in `scope`.
     pub fn disassemble_at(
         &self,
         value: &Value,
         buf: &mut [u8],
+        base_offset: usize,
+        scope: &Scope<'_>,
     ) -> Result<(), WriteError> {
         match value {
             Value::Array(values) => {
-                let count = match self.count {
-                    ArrayCount::Fixed(c) => c,
-                };
+                let count = self
+                    .resolve_count(scope)
+                    .map_err(|_| WriteError::InvalidValue)?;
                 if values.len() != count {
                     return Err(WriteError::InvalidValue);
                 }
                 for (i, v) in values.iter().enumerate() {
-                    let elem_offset = self.offset_bits + i * self.stride_bits;
-                    self.element.disassemble_at(v, buf, elem_offset)?;
+                    let elem_offset = base_offset + self.offset_bits + i * self.stride_bits;
+                    self.element.disassemble_at(v, buf, elem_offset, scope)?;
                 }
                 Ok(())
             }
@@ -118,15 +1128,349 @@ impl CompiledArray {
     }
 }
 
+/// Compiled struct: sub-fields whose offsets are relative to the struct's start.
+#[derive(Debug, Clone)]
+pub struct CompiledStruct {
+    /// Start of the struct relative to its base (non-zero in sequential layouts).
+    pub offset_bits: usize,
+    /// Compiled sub-fields in definition order.
+    pub fields: Vec<CompiledField>,
+}
+
+impl CompiledStruct {
+    /// Assembles every sub-field at `base_offset` into a [Value::Struct].
+    /// Sub-fields see earlier sub-fields first, then the enclosing `scope`.
+    pub fn assemble_at(
+        &self,
+        data: &[u8],
+        base_offset: usize,
+        scope: &Scope<'_>,
+    ) -> Result<Value, ReadError> {
+        let map = assemble_fields(&self.fields, data, base_offset + self.offset_bits, scope)?;
+        Ok(Value::Struct(map))
+    }
+
+    /// Writes a [Value::Struct] into `buf`, every sub-field relative to `base_offset`.
+    pub fn disassemble_at(
+        &self,
+        value: &Value,
+        buf: &mut [u8],
+        base_offset: usize,
+        scope: &Scope<'_>,
+    ) -> Result<(), WriteError> {
+        let Value::Struct(map) = value else {
+            return Err(WriteError::InvalidValue);
+        };
+        disassemble_fields(
+            &self.fields,
+            map,
+            buf,
+            base_offset + self.offset_bits,
+            scope,
+        )
+    }
+}
+
+/// Compiled switch: sub-field lists keyed by the value of a discriminator field.
+#[derive(Debug, Clone)]
+pub struct CompiledSwitch {
+    /// Start of the case fields relative to the switch's base (non-zero in sequential layouts).
+    pub offset_bits: usize,
+    /// Name of the discriminator field, resolved like an array count reference.
+    pub on: String,
+    /// Compiled sub-fields of each case, keyed by discriminator value.
+    pub cases: BTreeMap<u64, Vec<CompiledField>>,
+    /// Sub-fields used when no case matches, if any.
+    pub default: Option<Vec<CompiledField>>,
+}
+
+impl CompiledSwitch {
+    /// Sub-fields of the case selected by the discriminator in `scope`, or `None`
+    /// when no case matches and there is no default.
+    pub fn select(&self, scope: &Scope<'_>) -> Result<Option<&[CompiledField]>, ReadError> {
+        let key = match scope.lookup(&self.on) {
+            Some(Value::U64(v)) => Some(*v),
+            Some(Value::I64(v)) => u64::try_from(*v).ok(),
+            Some(Value::U128(v)) => u64::try_from(*v).ok(),
+            Some(Value::I128(v)) => u64::try_from(*v).ok(),
+            Some(_) => None,
+            None => return Err(ReadError::UnknownField(self.on.clone())),
+        };
+        let fields = key
+            .and_then(|key| self.cases.get(&key))
+            .or(self.default.as_ref());
+        Ok(fields.map(Vec::as_slice))
+    }
+
+    /// Assembles the selected case at `base_offset` into a [Value::Struct].
+    pub fn assemble_at(
+        &self,
+        data: &[u8],
+        base_offset: usize,
+        scope: &Scope<'_>,
+    ) -> Result<Value, ReadError> {
+        let fields = self
+            .select(scope)?
+            .ok_or_else(|| ReadError::UnmatchedCase(self.on.clone()))?;
+        let map = assemble_fields(fields, data, base_offset + self.offset_bits, scope)?;
+        Ok(Value::Struct(map))
+    }
+
+    /// Writes a [Value::Struct] using the case selected by the discriminator in
+    /// `scope`. Every entry of the struct must be a field of that case.
+    pub fn disassemble_at(
+        &self,
+        value: &Value,
+        buf: &mut [u8],
+        base_offset: usize,
+        scope: &Scope<'_>,
+    ) -> Result<(), WriteError> {
+        let Value::Struct(map) = value else {
+            return Err(WriteError::InvalidValue);
+        };
+        let Ok(Some(fields)) = self.select(scope) else {
+            return Err(WriteError::UnmatchedCase(self.on.clone()));
+        };
+        if let Some(name) = map.keys().find(|name| {
+            !fields
+                .iter()
+                .any(|field| field.kind.has_value() && &field.name == *name)
+        }) {
+            return Err(WriteError::CaseMismatch(name.clone()));
+        }
+        disassemble_fields(fields, map, buf, base_offset + self.offset_bits, scope)
+    }
+
+    /// Minimum end bit of each case (and the default), relative to the case start.
+    fn case_end_bits(&self) -> impl Iterator<Item = usize> + '_ {
+        self.cases
+            .values()
+            .chain(&self.default)
+            .map(|fields| fields_end_bits(fields, CompiledField::min_end_bits))
+    }
+}
+
+/// Compiled string: where it starts, how its end is found, and its encoding.
+#[derive(Debug, Clone)]
+pub struct CompiledString {
+    /// Start of the string (or its length prefix) relative to the field's base.
+    pub offset_bits: usize,
+    /// How the end of the string is found.
+    pub framing: StringFraming,
+    /// How the bytes map to characters.
+    pub encoding: Encoding,
+}
+
+impl CompiledString {
+    /// Reads the string at `base_offset` into a [Value::String]. Trailing padding
+    /// of a fixed-size string is stripped.
+    pub fn assemble_at(&self, data: &[u8], base_offset: usize) -> Result<Value, ReadError> {
+        let start = base_offset + self.offset_bits;
+        let context = |bits: Range<usize>| ErrorContext {
+            bit_range: Some(bits),
+            available_bits: Some(data.len() * 8),
+            ..ErrorContext::default()
+        };
+        let (bytes_start, len) = match self.framing {
+            StringFraming::LengthPrefixed { prefix_bits } => {
+                let len = bits::read_bits_at(data, start, prefix_bits)
+                    .map_err(|e| e.with_context(context(start..start + prefix_bits)))?;
+                (
+                    start + prefix_bits,
+                    usize::try_from(len).unwrap_or(usize::MAX),
+                )
+            }
+            StringFraming::NullTerminated => {
+                let unit_bits = self.encoding.unit_bytes() * 8;
+                let mut len = 0;
+                loop {
+                    let at = start + len * 8;
+                    match bits::read_bits_at(data, at, unit_bits) {
+                        Ok(0) => break,
+                        Ok(_) => len += self.encoding.unit_bytes(),
+                        Err(e) => return Err(e.with_context(context(start..at + unit_bits))),
+                    }
+                }
+                (start, len)
+            }
+            StringFraming::Fixed { len_bytes, .. } => (start, len_bytes),
+        };
+
+        let len_bits = len.saturating_mul(8);
+        let bits = bytes_start..bytes_start.saturating_add(len_bits);
+        let mut bytes = bits::read_bytes_at(data, bytes_start, len_bits, false)
+            .map_err(|e| e.with_context(context(bits.clone())))?;
+        if let StringFraming::Fixed { pad, .. } = self.framing {
+            let unit = self.encoding.unit_bytes();
+            while bytes.len() >= unit && bytes[bytes.len() - unit..].iter().all(|b| *b == pad) {
+                bytes.truncate(bytes.len() - unit);
+            }
+        }
+        self.encoding
+            .decode(bytes)
+            .map(Value::String)
+            .ok_or_else(|| ReadError::InvalidEncoding.with_context(context(bits)))
+    }
+
+    /// Writes a [Value::String] at `base_offset`: the length prefix and the bytes,
+    /// the bytes and a terminator, or the bytes padded to the fixed size. Fails with
+    /// [`WriteError::InvalidValue`] if the text cannot be encoded or framed, e.g. it
+    /// is longer than a fixed size or its length prefix allows.
+    pub fn disassemble_at(
+        &self,
+        value: &Value,
+        buf: &mut [u8],
+        base_offset: usize,
+    ) -> Result<(), WriteError> {
+        let text = match value {
+            Value::String(text) => text,
+            Value::Struct(_) | Value::Absent => return Err(WriteError::InvalidValue),
+            _ => {
+                return Err(WriteError::UnsupportedValue {
+                    field: String::new(),
+                    variant: value_variant_name(value),
+                });
+            }
+        };
+        let mut bytes = self.encoding.encode(text).ok_or(WriteError::InvalidValue)?;
+        let mut start = base_offset + self.offset_bits;
+        match self.framing {
+            StringFraming::LengthPrefixed { prefix_bits } => {
+                let len = bytes.len() as u64;
+                if prefix_bits < 64 && len >> prefix_bits != 0 {
+                    return Err(WriteError::InvalidValue);
+                }
+                bits::write_bits_at(buf, start, prefix_bits, len)?;
+                start += prefix_bits;
+            }
+            StringFraming::NullTerminated => {
+                let unit = self.encoding.unit_bytes();
+                if bytes.chunks(unit).any(|c| c.iter().all(|b| *b == 0)) {
+                    return Err(WriteError::InvalidValue);
+                }
+                bytes.resize(bytes.len() + unit, 0);
+            }
+            StringFraming::Fixed { len_bytes, pad } => {
+                if bytes.len() > len_bytes {
+                    return Err(WriteError::InvalidValue);
+                }
+                bytes.resize(len_bytes, pad);
+            }
+        }
+        bits::write_bytes_at(buf, start, bytes.len() * 8, &bytes, false)
+    }
+
+    /// Bits taken by the empty string: the prefix, the terminator (one zero code
+    /// unit), or the fixed size.
+    fn min_bits(&self) -> usize {
+        match self.framing {
+            StringFraming::LengthPrefixed { prefix_bits } => prefix_bits,
+            StringFraming::NullTerminated => self.encoding.unit_bytes() * 8,
+            StringFraming::Fixed { len_bytes, .. } => len_bytes * 8,
+        }
+    }
+
+    /// Bits taken by `text` once encoded and framed, or `None` if the encoding
+    /// cannot represent it.
+    fn text_bits(&self, text: &str) -> Option<usize> {
+        let len = self.encoding.encode(text)?.len();
+        Some(match self.framing {
+            StringFraming::LengthPrefixed { prefix_bits } => prefix_bits + len * 8,
+            StringFraming::NullTerminated => (len + self.encoding.unit_bytes()) * 8,
+            StringFraming::Fixed { len_bytes, .. } => len_bytes * 8,
+        })
+    }
+}
+
+/// Values visible to a field whose layout depends on other fields, such as an
+/// array with [`ArrayCount::FromField`]. Lookups try the innermost level first
+/// and then each enclosing level.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Scope<'a> {
+    values: Option<&'a BTreeMap<String, Value>>,
+    parent: Option<&'a Scope<'a>>,
+}
+
+impl<'a> Scope<'a> {
+    /// Creates a top-level scope over `values`.
+    pub fn new(values: &'a BTreeMap<String, Value>) -> Self {
+        Scope {
+            values: Some(values),
+            parent: None,
+        }
+    }
+
+    /// Creates a nested scope over `values` that falls back to `self`.
+    pub fn child(&'a self, values: &'a BTreeMap<String, Value>) -> Scope<'a> {
+        Scope {
+            values: Some(values),
+            parent: Some(self),
+        }
+    }
+
+    /// Looks up `path`; `a.b` resolves `a` through the scope chain and then reads
+    /// sub-field `b` of the struct value.
+    pub fn lookup(&self, path: &str) -> Option<&'a Value> {
+        let mut parts = path.split('.');
+        let mut value = self.find(parts.next()?)?;
+        for part in parts {
+            match value {
+                Value::Struct(map) => value = map.get(part)?,
+                _ => return None,
+            }
+        }
+        Some(value)
+    }
+
+    fn find(&self, name: &str) -> Option<&'a Value> {
+        self.values
+            .and_then(|values| values.get(name))
+            .or_else(|| self.parent.and_then(|parent| parent.find(name)))
+    }
+}
+
+/// Fills in (or prefixes, for nested fields) the field name carried by `err`.
+pub(crate) fn attach_field_name(err: WriteError, field: &str) -> WriteError {
+    let qualify = |inner: String| {
+        if inner.is_empty() {
+            field.to_string()
+        } else {
+            format!("{field}.{inner}")
+        }
+    };
+    match err {
+        WriteError::UnsupportedValue {
+            field: inner,
+            variant,
+        } => WriteError::UnsupportedValue {
+            field: qualify(inner),
+            variant,
+        },
+        WriteError::MissingField(inner) => WriteError::MissingField(qualify(inner)),
+        WriteError::CaseMismatch(inner) => WriteError::CaseMismatch(qualify(inner)),
+        WriteError::Transform {
+            field: inner,
+            error,
+        } => WriteError::Transform {
+            field: qualify(inner),
+            error,
+        },
+        other => other,
+    }
+}
+
 /// Compiled scalar: total size, signedness, and list of fragments with shifts.
 #[derive(Debug, Clone)]
 pub struct CompiledScalar {
-    /// Whether the assembled value should be sign-extended to an `i64`.
+    /// Whether the assembled value should be sign-extended to an `i64`, or an
+    /// `i128` for fields wider than 64 bits.
     pub signed: bool,
-    /// Total bit width (sum of fragment lengths, 1–64).
+    /// Total bit width (sum of fragment lengths, 1–128).
     pub total_bits: usize,
     /// Fragments with precomputed shift for assembly.
     pub fragments: Vec<CompiledFragment>,
+    /// Byte order applied to the concatenated fragments, if any.
+    pub endian: Option<Endian>,
 }
 
 impl TryFrom<&crate::field::Field> for CompiledScalar {
@@ -138,19 +1482,39 @@ impl TryFrom<&crate::field::Field> for CompiledScalar {
             .iter()
             .fold(0, |acc, fragment| acc + fragment.len_bits);
 
-        if total_bits == 0 || total_bits > 64 {
+        if total_bits == 0 || total_bits > 128 {
             return Err(CompileError::InvalidFieldSize);
         }
 
         let mut fragments = Vec::with_capacity(value.fragments.len());
 
+        // Names the failing fragment; the field name is added by the caller.
+        let compile_fragment = |index: usize, fragment: &crate::fragment::Fragment| {
+            CompiledFragment::try_from(fragment).map_err(|error| CompileError::Context {
+                context: ErrorContext {
+                    fragment: Some(index),
+                    bit_range: Some(fragment.offset_bits..fragment.offset_bits + fragment.len_bits),
+                    ..ErrorContext::default()
+                },
+                error: Box::new(error),
+            })
+        };
+
+        let endian = match value.assemble {
+            Assemble::Endian(endian) if !total_bits.is_multiple_of(endian.unit_bits()) => {
+                return Err(CompileError::InvalidByteOrder);
+            }
+            Assemble::Endian(endian) => Some(endian),
+            Assemble::Concat(_) => None,
+        };
+
         match value.assemble {
-            Assemble::Concat(BitOrder::MsbFirst) => {
+            Assemble::Concat(BitOrder::MsbFirst) | Assemble::Endian(_) => {
                 let mut remaining = total_bits;
-                for fragment in &value.fragments {
+                for (index, fragment) in value.fragments.iter().enumerate() {
                     remaining -= fragment.len_bits;
 
-                    let mut compiled_fragment = CompiledFragment::try_from(fragment)?;
+                    let mut compiled_fragment = compile_fragment(index, fragment)?;
                     compiled_fragment.shift = remaining;
 
                     fragments.push(compiled_fragment);
@@ -158,8 +1522,8 @@ impl TryFrom<&crate::field::Field> for CompiledScalar {
             }
             Assemble::Concat(BitOrder::LsbFirst) => {
                 let mut shift = 0;
-                for fragment in &value.fragments {
-                    let mut compiled_fragment = CompiledFragment::try_from(fragment)?;
+                for (index, fragment) in value.fragments.iter().enumerate() {
+                    let mut compiled_fragment = compile_fragment(index, fragment)?;
                     compiled_fragment.shift = shift;
 
                     fragments.push(compiled_fragment);
@@ -173,6 +1537,7 @@ impl TryFrom<&crate::field::Field> for CompiledScalar {
             signed: value.signed,
             total_bits,
             fragments,
+            endian,
         })
     }
 }
@@ -183,25 +1548,65 @@ impl CompiledScalar {
         self.assemble_at(data, 0)
     }
 
-    /// Assembles the scalar from `data` starting at `offset_bits`.
+    /// Assembles the scalar from `data` starting at `offset_bits`. Fields of up to
+    /// 64 bits become [`Value::U64`] or [`Value::I64`], wider ones [`Value::U128`]
+    /// or [`Value::I128`].
     pub fn assemble_at(&self, data: &[u8], offset_bits: usize) -> Result<Value, ReadError> {
-        let mut value = 0u64;
+        let value = self.read_wide_bits_at(data, offset_bits)?;
+        match (self.signed, self.total_bits <= 64) {
+            (true, true) => Ok(Value::I64(sign_extend(value as u64, self.total_bits))),
+            (true, false) => Ok(Value::I128(sign_extend_u128(value, self.total_bits))),
+            (false, _) => Ok(self.raw_value(value)),
+        }
+    }
 
-        for fragment in &self.fragments {
+    /// Reads the raw bits of the scalar from `data` starting at `offset_bits`,
+    /// zero-extended regardless of signedness. Fails with
+    /// [`ReadError::TooManyBitsRead`] for fields wider than 64 bits; use
+    /// [`Self::read_wide_bits_at`] for those.
+    pub fn read_bits_at(&self, data: &[u8], offset_bits: usize) -> Result<u64, ReadError> {
+        if self.total_bits > 64 {
+            return Err(ReadError::TooManyBitsRead);
+        }
+        self.read_wide_bits_at(data, offset_bits)
+            .map(|value| value as u64)
+    }
+
+    /// Like [`Self::read_bits_at`], for fields of any width.
+    pub fn read_wide_bits_at(&self, data: &[u8], offset_bits: usize) -> Result<u128, ReadError> {
+        let mut value = 0u128;
+
+        for (index, fragment) in self.fragments.iter().enumerate() {
+            let start = fragment.offset_bits + offset_bits;
             let mut part =
-                bits::read_bits_at(data, fragment.offset_bits + offset_bits, fragment.len_bits)?;
+                bits::read_bits_u128_at(data, start, fragment.len_bits).map_err(|e| {
+                    e.with_context(ErrorContext {
+                        fragment: Some(index),
+                        bit_range: Some(start..start + fragment.len_bits),
+                        available_bits: Some(data.len() * 8),
+                        ..ErrorContext::default()
+                    })
+                })?;
 
             if fragment.bit_order == BitOrder::LsbFirst {
-                part = reverse_bits_n(part, fragment.len_bits);
+                part = reverse_bits_u128_n(part, fragment.len_bits);
             }
 
             value |= part << fragment.shift;
         }
+        if let Some(endian) = self.endian {
+            value = endian.reorder(value, self.total_bits);
+        }
+        Ok(value)
+    }
 
-        if self.signed {
-            Ok(Value::I64(sign_extend(value, self.total_bits)))
+    /// Raw bits as an unsigned value: [`Value::U64`] for fields of up to 64 bits,
+    /// [`Value::U128`] for wider ones.
+    pub(crate) fn raw_value(&self, bits: u128) -> Value {
+        if self.total_bits <= 64 {
+            Value::U64(bits as u64)
         } else {
-            Ok(Value::U64(value))
+            Value::U128(bits)
         }
     }
 }
@@ -217,9 +1622,13 @@ impl CompiledScalar {
         base_offset: usize,
     ) -> Result<(), WriteError> {
         let value = match value {
-            Value::I64(v) => *v as u64,
-            Value::U64(v) => *v,
-            Value::Array(_) => return Err(WriteError::InvalidValue),
+            Value::I64(v) => *v as u128,
+            Value::U64(v) => u128::from(*v),
+            Value::I128(v) => *v as u128,
+            Value::U128(v) => *v,
+            Value::Array(_) | Value::Struct(_) | Value::Absent => {
+                return Err(WriteError::InvalidValue);
+            }
             Value::F32(_) | Value::F64(_) | Value::Bytes(_) | Value::String(_) => {
                 return Err(WriteError::UnsupportedValue {
                     field: String::new(),
@@ -228,20 +1637,20 @@ impl CompiledScalar {
             }
         };
 
+        let value = match self.endian {
+            Some(endian) => endian.reorder(value & width_mask(self.total_bits), self.total_bits),
+            None => value,
+        };
+
         for fragment in &self.fragments {
             let mut part = value >> fragment.shift;
-            let mask = if fragment.len_bits == 64 {
-                u64::MAX
-            } else {
-                (1u64 << fragment.len_bits) - 1
-            };
-            part &= mask;
+            part &= width_mask(fragment.len_bits);
 
             if fragment.bit_order == BitOrder::LsbFirst {
-                part = reverse_bits_n(part, fragment.len_bits);
+                part = reverse_bits_u128_n(part, fragment.len_bits);
             }
 
-            crate::bits::write_bits_at(
+            crate::bits::write_bits_u128_at(
                 buf,
                 base_offset + fragment.offset_bits,
                 fragment.len_bits,
@@ -252,15 +1661,28 @@ impl CompiledScalar {
     }
 }
 
-fn value_variant_name(v: &Value) -> &'static str {
+/// Mask of the low `bits` bits.
+fn width_mask(bits: usize) -> u128 {
+    if bits == 128 {
+        u128::MAX
+    } else {
+        (1u128 << bits) - 1
+    }
+}
+
+pub(crate) fn value_variant_name(v: &Value) -> &'static str {
     match v {
         Value::U64(_) => "U64",
         Value::I64(_) => "I64",
+        Value::U128(_) => "U128",
+        Value::I128(_) => "I128",
         Value::F32(_) => "F32",
         Value::F64(_) => "F64",
         Value::Bytes(_) => "Bytes",
         Value::String(_) => "String",
         Value::Array(_) => "Array",
+        Value::Struct(_) => "Struct",
+        Value::Absent => "Absent",
     }
 }
 
@@ -311,6 +1733,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(0, 2)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let value_field = Field {
@@ -320,6 +1745,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(2, 11)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let crc_field = Field {
@@ -329,6 +1757,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(13, 3)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let compiled_id_field = CompiledScalar::try_from(&id_field).unwrap();
@@ -355,6 +1786,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(0, 8), Fragment::new(16, 8)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let second_value_field = Field {
@@ -364,6 +1798,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(8, 8), Fragment::new(24, 8)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let compiled_first_value_field = CompiledScalar::try_from(&first_value_field).unwrap();
@@ -387,6 +1824,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::LsbFirst),
             fragments: vec![Fragment::new(4, 4), Fragment::new(12, 4)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let compiled_value_field = CompiledScalar::try_from(&value_field).unwrap();
@@ -403,6 +1843,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(0, 3), Fragment::new(5, 5)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let compiled = CompiledScalar::try_from(&field).unwrap();
@@ -421,6 +1864,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::LsbFirst),
             fragments: vec![Fragment::new(0, 3), Fragment::new(5, 5)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let compiled = CompiledScalar::try_from(&field).unwrap();
@@ -428,4 +1874,147 @@ mod tests {
         assert_eq!(compiled.fragments[0].shift, 0);
         assert_eq!(compiled.fragments[1].shift, 3);
     }
+
+    fn endian_field(endian: Endian, signed: bool, fragments: Vec<Fragment>) -> Field {
+        Field {
+            name: "x".to_string(),
+            kind: FieldKind::Scalar,
+            signed,
+            assemble: Assemble::Endian(endian),
+            fragments,
+            transform: None,
+            condition: None,
+            default: None,
+            constant: None,
+        }
+    }
+
+    #[test]
+    fn test_assemble_endian_orders() {
+        let data = [0xAA, 0xBB, 0xCC, 0xDD];
+        for (endian, expected) in [
+            (Endian::Big, 0xAABBCCDD),
+            (Endian::Little, 0xDDCCBBAA),
+            (Endian::BigByteSwap, 0xBBAADDCC),
+            (Endian::LittleByteSwap, 0xCCDDAABB),
+        ] {
+            let scalar =
+                CompiledScalar::try_from(&endian_field(endian, false, vec![Fragment::new(0, 32)]))
+                    .unwrap();
+            assert_eq!(scalar.assemble(&data).unwrap(), Value::U64(expected));
+
+            let mut buf = [0u8; 4];
+            scalar
+                .disassemble_at(&Value::U64(expected), &mut buf, 0)
+                .unwrap();
+            assert_eq!(buf, data, "{endian:?}");
+        }
+    }
+
+    #[test]
+    fn test_assemble_little_endian_signed_across_fragments() {
+        // A little-endian i16 split over two fragments, the sign in the second byte.
+        let field = endian_field(
+            Endian::Little,
+            true,
+            vec![Fragment::new(0, 8), Fragment::new(16, 8)],
+        );
+        let scalar = CompiledScalar::try_from(&field).unwrap();
+        let data = [0xFE, 0x00, 0xFF];
+        assert_eq!(scalar.assemble(&data).unwrap(), Value::I64(-2));
+
+        let mut buf = [0u8; 3];
+        scalar.disassemble_at(&Value::I64(-2), &mut buf, 0).unwrap();
+        assert_eq!(buf, data);
+    }
+
+    #[test]
+    fn test_endian_needs_whole_bytes_or_words() {
+        let err = CompiledScalar::try_from(&endian_field(
+            Endian::Little,
+            false,
+            vec![Fragment::new(0, 12)],
+        ))
+        .unwrap_err();
+        assert_eq!(err, CompileError::InvalidByteOrder);
+
+        let err = CompiledScalar::try_from(&endian_field(
+            Endian::BigByteSwap,
+            false,
+            vec![Fragment::new(0, 24)],
+        ))
+        .unwrap_err();
+        assert_eq!(err, CompileError::InvalidByteOrder);
+        assert!(
+            CompiledScalar::try_from(&endian_field(
+                Endian::Little,
+                false,
+                vec![Fragment::new(4, 64)],
+            ))
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_assemble_fields_wider_than_64_bits() {
+        // A 128-bit UUID, read and written back whole.
+        let data: Vec<u8> = (0..16).collect();
+        let uuid = endian_field(Endian::Big, false, vec![Fragment::new(0, 128)]);
+        let scalar = CompiledScalar::try_from(&uuid).unwrap();
+        let expected = u128::from_be_bytes(data.clone().try_into().unwrap());
+        assert_eq!(scalar.assemble(&data).unwrap(), Value::U128(expected));
+        assert_eq!(
+            scalar.read_bits_at(&data, 0).unwrap_err(),
+            ReadError::TooManyBitsRead
+        );
+        let mut buf = [0u8; 16];
+        scalar
+            .disassemble_at(&Value::U128(expected), &mut buf, 0)
+            .unwrap();
+        assert_eq!(buf.as_slice(), data.as_slice());
+
+        // A signed little-endian 80-bit value split over a 64-bit and a 16-bit fragment.
+        let field = endian_field(
+            Endian::Little,
+            true,
+            vec![Fragment::new(0, 64), Fragment::new(72, 16)],
+        );
+        let scalar = CompiledScalar::try_from(&field).unwrap();
+        let data = [
+            0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0xFF, 0xFF,
+        ];
+        assert_eq!(scalar.assemble(&data).unwrap(), Value::I128(-2));
+        let mut buf = [0u8; 11];
+        scalar
+            .disassemble_at(&Value::I128(-2), &mut buf, 0)
+            .unwrap();
+        assert_eq!(buf, data);
+
+        // Small values of other integer variants are accepted too.
+        scalar.disassemble_at(&Value::I64(-2), &mut buf, 0).unwrap();
+        assert_eq!(buf, data);
+
+        let too_wide = endian_field(Endian::Big, false, vec![Fragment::new(0, 136)]);
+        assert_eq!(
+            CompiledScalar::try_from(&too_wide).unwrap_err(),
+            CompileError::InvalidFieldSize
+        );
+    }
+
+    #[test]
+    fn test_assemble_wide_lsb_fragment() {
+        let field = Field {
+            assemble: Assemble::Concat(BitOrder::LsbFirst),
+            fragments: vec![Fragment::new_with_bit_order(0, 96, BitOrder::LsbFirst)],
+            ..endian_field(Endian::Big, false, Vec::new())
+        };
+        let scalar = CompiledScalar::try_from(&field).unwrap();
+        let mut data = [0u8; 12];
+        data[0] = 0x80;
+        assert_eq!(scalar.assemble(&data).unwrap(), Value::U128(1));
+
+        let mut buf = [0u8; 12];
+        scalar.disassemble_at(&Value::U128(1), &mut buf, 0).unwrap();
+        assert_eq!(buf, data);
+    }
 }
@@ -0,0 +1,424 @@
+//! Bit coverage of compiled fields: overlap checks and uncovered-bit listing.
+//!
+//! Only positions known at compile time are compared. Fields placed after a
+//! variable-length field, and the elements of arrays whose count comes from another
+//! field, are checked on their own for overlaps within themselves.
+
+use std::ops::Range;
+
+use crate::{
+    assembly::ArrayCount,
+    compiled::{CompiledField, CompiledFieldKind, Placement},
+    errors::CompileError,
+};
+
+/// Bit range claimed by one field, element, or spacer.
+#[derive(Debug)]
+struct Span {
+    /// Bits of the first element of every array the span repeats in.
+    bits: Range<usize>,
+    /// Path of the value, e.g. `header.flags` or `items[].x`; empty for spacers.
+    name: String,
+    /// Spacers count as covered but never overlap anything.
+    spacer: bool,
+    /// Switch cases this span belongs to, as `(switch, case)` pairs. Spans from
+    /// different cases of the same switch never overlap each other.
+    cases: Vec<(usize, usize)>,
+    /// Arrays the span repeats in, outermost first.
+    repeats: Vec<Repeat>,
+}
+
+/// One array a span repeats in, once per element.
+#[derive(Debug, Clone)]
+struct Repeat {
+    /// Tells the elements of one array apart from those of another.
+    array: usize,
+    count: usize,
+    stride: usize,
+    /// Byte offset in the span's name where the element index goes.
+    index_at: usize,
+}
+
+impl Span {
+    fn new(bits: Range<usize>, name: &str, cases: &[(usize, usize)]) -> Self {
+        Span {
+            bits,
+            name: name.to_string(),
+            spacer: false,
+            cases: cases.to_vec(),
+            repeats: Vec::new(),
+        }
+    }
+
+    /// End of the last element's bits.
+    fn end(&self) -> usize {
+        self.bits.end + extent(&self.repeats)
+    }
+
+    /// Bit ranges of every element, one after another.
+    fn ranges(&self) -> Vec<Range<usize>> {
+        self.repeats
+            .iter()
+            .fold(vec![self.bits.clone()], |ranges, repeat| {
+                (0..repeat.count)
+                    .flat_map(|i| {
+                        let shift = i * repeat.stride;
+                        ranges.iter().map(move |r| r.start + shift..r.end + shift)
+                    })
+                    .collect()
+            })
+    }
+}
+
+/// Distance from the first to the last element of nested repeats.
+fn extent(repeats: &[Repeat]) -> usize {
+    repeats
+        .iter()
+        .map(|repeat| (repeat.count - 1) * repeat.stride)
+        .sum()
+}
+
+/// A span narrowed down to single elements of its outermost arrays.
+#[derive(Debug, Clone)]
+struct Placed<'a> {
+    span: &'a Span,
+    shift: usize,
+    /// Element index within each of the outermost repeats narrowed so far.
+    indices: Vec<usize>,
+}
+
+impl<'a> Placed<'a> {
+    fn whole(span: &'a Span) -> Self {
+        Placed {
+            span,
+            shift: 0,
+            indices: Vec::new(),
+        }
+    }
+
+    /// The next array not yet narrowed to one element.
+    fn open(&self) -> Option<&'a Repeat> {
+        self.span.repeats.get(self.indices.len())
+    }
+
+    fn start(&self) -> usize {
+        self.span.bits.start + self.shift
+    }
+
+    fn end(&self) -> usize {
+        self.span.bits.end + self.shift + extent(&self.span.repeats[self.indices.len()..])
+    }
+
+    fn element(&self, i: usize) -> Self {
+        let repeat = self.open().expect("span has no open repeat");
+        let mut indices = self.indices.clone();
+        indices.push(i);
+        Placed {
+            span: self.span,
+            shift: self.shift + i * repeat.stride,
+            indices,
+        }
+    }
+
+    /// Whether both lie in the same element of every array they share.
+    fn same_elements(&self, other: &Placed) -> bool {
+        self.span
+            .repeats
+            .iter()
+            .zip(&self.indices)
+            .all(|(repeat, i)| {
+                other
+                    .span
+                    .repeats
+                    .iter()
+                    .zip(&other.indices)
+                    .all(|(other_repeat, j)| other_repeat.array != repeat.array || i == j)
+            })
+    }
+
+    /// Whether two fully narrowed spans that share bits count as overlapping.
+    fn conflicts_with(&self, other: &Placed) -> bool {
+        let exclusive_cases = self.span.cases.iter().any(|(switch, case)| {
+            other
+                .span
+                .cases
+                .iter()
+                .any(|(other_switch, other_case)| switch == other_switch && case != other_case)
+        });
+        // Within one element, a span is itself and switch cases exclude each other.
+        let apart =
+            self.same_elements(other) && (std::ptr::eq(self.span, other.span) || exclusive_cases);
+        !(self.span.spacer || other.span.spacer || apart)
+    }
+
+    /// The name with the element indices filled in, e.g. `items[2].x`.
+    fn name(&self) -> String {
+        let mut name = self.span.name.clone();
+        for (repeat, i) in self.span.repeats.iter().zip(&self.indices).rev() {
+            if repeat.index_at <= name.len() {
+                name.insert_str(repeat.index_at, &i.to_string());
+            }
+        }
+        name
+    }
+}
+
+/// Range of element indices `i` for which an element placed at `first + i * stride`
+/// can share bits with `other`, given by the first and last candidate.
+fn candidates(first: Range<usize>, other: Range<usize>, stride: usize) -> (isize, isize) {
+    if stride == 0 {
+        return (-1, 1);
+    }
+    let stride = stride as isize;
+    (
+        (other.start as isize - first.end as isize).div_euclid(stride) + 1,
+        (other.end as isize - first.start as isize - 1).div_euclid(stride),
+    )
+}
+
+/// Finds a pair of elements of `a` and `b` that claim the same bit, without walking
+/// every element of a uniformly strided array.
+fn clash<'a>(a: &Placed<'a>, b: &Placed<'a>) -> Option<(Placed<'a>, Placed<'a>)> {
+    if a.start() >= b.end() || b.start() >= a.end() {
+        return None;
+    }
+    match (a.open(), b.open()) {
+        (None, None) => a.conflicts_with(b).then(|| (a.clone(), b.clone())),
+        // Elements of two arrays with the same stride line up the same way all
+        // along, so only the distance between their indices matters.
+        (Some(a_repeat), Some(b_repeat)) if a_repeat.stride == b_repeat.stride => {
+            let (a0, b0) = (a.element(0), b.element(0));
+            let (lo, hi) = candidates(a0.start()..a0.end(), b0.start()..b0.end(), a_repeat.stride);
+            (lo.max(1 - b_repeat.count as isize)..=hi.min(a_repeat.count as isize - 1)).find_map(
+                |distance| {
+                    let j = (-distance).max(0) as usize;
+                    let i = (j as isize + distance) as usize;
+                    clash(&a.element(i), &b.element(j))
+                },
+            )
+        }
+        (Some(repeat), _) => {
+            let a0 = a.element(0);
+            let (lo, hi) = candidates(a0.start()..a0.end(), b.start()..b.end(), repeat.stride);
+            let hi = if repeat.stride == 0 { 0 } else { hi };
+            (lo.max(0)..=hi.min(repeat.count as isize - 1))
+                .find_map(|i| clash(&a.element(i as usize), b))
+        }
+        (None, Some(_)) => clash(b, a).map(|(b, a)| (a, b)),
+    }
+}
+
+#[derive(Debug, Default)]
+struct Coverage {
+    /// Spans at positions known at compile time.
+    spans: Vec<Span>,
+    /// Spans of fields whose position is only known at run time, one list per field,
+    /// relative to the start of that field.
+    detached: Vec<Vec<Span>>,
+    next_switch: usize,
+    next_array: usize,
+}
+
+impl Coverage {
+    fn of(fields: &[CompiledField]) -> Self {
+        let mut coverage = Coverage::default();
+        coverage.add_fields(fields, 0, "", &[]);
+        coverage
+    }
+
+    fn add_fields(
+        &mut self,
+        fields: &[CompiledField],
+        base: usize,
+        prefix: &str,
+        cases: &[(usize, usize)],
+    ) {
+        for field in fields {
+            let named =
+                field.kind.has_value() || matches!(field.kind, CompiledFieldKind::Reserved { .. });
+            let name = match (prefix, named) {
+                (_, false) => String::new(),
+                ("", true) => field.name.clone(),
+                (prefix, true) => format!("{prefix}.{}", field.name),
+            };
+            match field.placement {
+                Placement::Fixed => self.add_kind(&field.kind, base, &name, cases),
+                Placement::Cursor => {
+                    self.detach(|inner| inner.add_kind(&field.kind, 0, &name, &[]))
+                }
+            }
+        }
+    }
+
+    fn add_kind(
+        &mut self,
+        kind: &CompiledFieldKind,
+        base: usize,
+        name: &str,
+        cases: &[(usize, usize)],
+    ) {
+        match kind {
+            CompiledFieldKind::Scalar(scalar)
+            | CompiledFieldKind::Reserved { scalar, .. }
+            | CompiledFieldKind::Checksum { scalar, .. } => {
+                for fragment in &scalar.fragments {
+                    let start = base + fragment.offset_bits;
+                    self.spans
+                        .push(Span::new(start..start + fragment.len_bits, name, cases));
+                }
+            }
+            CompiledFieldKind::Array(array) => {
+                // The element is laid out once and repeated, so the cost does not
+                // grow with the count.
+                let add_elements = |coverage: &mut Coverage, base: usize, count: usize| {
+                    if count == 0 {
+                        return;
+                    }
+                    let repeat = Repeat {
+                        array: coverage.next_array,
+                        count,
+                        stride: array.stride_bits,
+                        index_at: name.len() + 1,
+                    };
+                    let mut element = Coverage {
+                        next_switch: coverage.next_switch,
+                        next_array: coverage.next_array + 1,
+                        ..Coverage::default()
+                    };
+                    element.add_kind(
+                        &array.element,
+                        base + array.offset_bits,
+                        &format!("{name}[]"),
+                        cases,
+                    );
+                    coverage.next_switch = element.next_switch;
+                    coverage.next_array = element.next_array;
+                    for mut span in element.spans {
+                        span.repeats.insert(0, repeat.clone());
+                        coverage.spans.push(span);
+                    }
+                    coverage.detached.append(&mut element.detached);
+                };
+                match array.count {
+                    ArrayCount::Fixed(count) => add_elements(self, base, count),
+                    // Two elements are enough to catch elements running into each other.
+                    ArrayCount::FromField { .. } => {
+                        self.detach(|inner| add_elements(inner, 0, 2));
+                    }
+                }
+            }
+            CompiledFieldKind::Struct(group) => {
+                self.add_fields(&group.fields, base + group.offset_bits, name, cases);
+            }
+            CompiledFieldKind::Switch(switch) => {
+                let id = self.next_switch;
+                self.next_switch += 1;
+                for (i, fields) in switch.cases.values().chain(&switch.default).enumerate() {
+                    let mut case_path = cases.to_vec();
+                    case_path.push((id, i));
+                    self.add_fields(fields, base + switch.offset_bits, name, &case_path);
+                }
+            }
+            CompiledFieldKind::Bytes {
+                offset_bits,
+                len_bits,
+                ..
+            } => self.spans.push(Span::new(
+                base + offset_bits..base + offset_bits + len_bits,
+                name,
+                cases,
+            )),
+            // A variable-length string counts with its minimum size.
+            CompiledFieldKind::String(_) => self.spans.push(Span::new(
+                base + kind.start_bits()..base + kind.end_bits(),
+                name,
+                cases,
+            )),
+            CompiledFieldKind::Skip {
+                offset_bits,
+                len_bits,
+            } => self.spans.push(Span {
+                spacer: true,
+                ..Span::new(base + offset_bits..base + offset_bits + len_bits, "", cases)
+            }),
+            CompiledFieldKind::Align(_) => {}
+        }
+    }
+
+    /// Collects spans whose position is only known relative to their own start.
+    fn detach(&mut self, add: impl FnOnce(&mut Coverage)) {
+        let mut inner = Coverage {
+            next_switch: self.next_switch,
+            next_array: self.next_array,
+            ..Coverage::default()
+        };
+        add(&mut inner);
+        self.next_switch = inner.next_switch;
+        self.next_array = inner.next_array;
+        self.detached.push(inner.spans);
+        self.detached.append(&mut inner.detached);
+    }
+}
+
+/// Fails with [`CompileError::OverlappingFields`] if two fields (or two fragments of
+/// one field, or two array elements) claim the same bit.
+pub(crate) fn check_overlaps(fields: &[CompiledField]) -> Result<(), CompileError> {
+    let Coverage {
+        spans, detached, ..
+    } = Coverage::of(fields);
+    std::iter::once(spans)
+        .chain(detached)
+        .try_for_each(first_overlap)
+}
+
+fn first_overlap(mut spans: Vec<Span>) -> Result<(), CompileError> {
+    spans.sort_by_key(|span| (span.bits.start, span.end()));
+    for (i, span) in spans.iter().enumerate() {
+        // Elements of one array may run into each other.
+        let within = if span.repeats.is_empty() {
+            None
+        } else {
+            clash(&Placed::whole(span), &Placed::whole(span))
+        };
+        let found = within.or_else(|| {
+            spans[i + 1..]
+                .iter()
+                .take_while(|other| other.bits.start < span.end())
+                .find_map(|other| clash(&Placed::whole(span), &Placed::whole(other)))
+        });
+        if let Some((a, b)) = found {
+            let (a, b) = if (b.start(), &b.indices) < (a.start(), &a.indices) {
+                (b, a)
+            } else {
+                (a, b)
+            };
+            return Err(CompileError::OverlappingFields {
+                a: a.name(),
+                b: b.name(),
+                bit: b.start(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Bit ranges up to the end of the statically placed fields that no field or
+/// spacer covers.
+pub(crate) fn uncovered_bits(fields: &[CompiledField]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<_> = Coverage::of(fields)
+        .spans
+        .iter()
+        .flat_map(Span::ranges)
+        .collect();
+    ranges.sort_by_key(|range| range.start);
+
+    let mut gaps = Vec::new();
+    let mut covered = 0;
+    for range in ranges {
+        if range.start > covered {
+            gaps.push(covered..range.start);
+        }
+        covered = covered.max(range.end);
+    }
+    gaps
+}
@@ -0,0 +1,521 @@
+//! Serde deserializer that reads a record straight from the compiled fields, for
+//! [`crate::schema::Schema::parse_into`].
+//!
+//! Fields are read one at a time as the target type asks for them: a field list
+//! is a map, an array a sequence, and a scalar an integer. Only the values later
+//! fields may refer to (array counts, conditions, switch discriminators, and
+//! checksums) are kept. A field with a transform or a constant is read whole, so
+//! the transform can run or the constant be checked, and handed over as a
+//! [`Value`].
+
+use std::{collections::BTreeMap, ops::Range};
+
+use serde::de::{
+    self, Deserialize, DeserializeSeed, IgnoredAny, IntoDeserializer, MapAccess, SeqAccess,
+    Visitor,
+    value::{MapDeserializer, SeqDeserializer},
+};
+
+use crate::{
+    compiled::{CompiledArray, CompiledField, CompiledFieldKind, Placement, Scope, is_present},
+    errors::{DecodeError, ReadError},
+    value::Value,
+};
+
+/// Deserializes `T` from `fields` laid out from the start of `data`.
+pub(crate) fn from_fields<'de, T: Deserialize<'de>>(
+    fields: &[CompiledField],
+    data: &[u8],
+) -> Result<T, DecodeError> {
+    let scope = Scope::default();
+    let mut access = FieldsAccess::new(fields, data, 0, &scope);
+    T::deserialize(Record(&mut access))
+}
+
+/// The top-level field list, read as a map.
+struct Record<'r, 'a, 's>(&'r mut FieldsAccess<'a, 's>);
+
+impl<'de> de::Deserializer<'de> for Record<'_, '_, '_> {
+    type Error = DecodeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        let value = visitor.visit_map(&mut *self.0)?;
+        self.0.finish()?;
+        Ok(value)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// What reading one field left behind for the rest of its field list.
+#[derive(Default)]
+struct Read {
+    /// Value later fields can refer to: a scalar, or a struct of such values.
+    record: Option<Value>,
+    /// One past the last bit read, relative to the field's base.
+    end: usize,
+}
+
+/// Reads a field list as a map with one entry per present field that has a value.
+struct FieldsAccess<'a, 's> {
+    fields: &'a [CompiledField],
+    data: &'a [u8],
+    base_offset: usize,
+    parent: &'s Scope<'s>,
+    /// Values of the fields read so far that later fields may refer to.
+    record: BTreeMap<String, Value>,
+    /// Bits of every field read so far, for checksums covering named fields.
+    spans: Vec<(&'a str, Range<usize>)>,
+    next: usize,
+    cursor: usize,
+    end: usize,
+    /// Field whose key was returned but whose value has not been read yet.
+    pending: Option<(&'a CompiledField, usize)>,
+    done: bool,
+}
+
+impl<'a, 's> FieldsAccess<'a, 's> {
+    /// Starts reading `fields` at `base_offset`; references not found among the
+    /// fields are resolved in `parent`.
+    fn new(
+        fields: &'a [CompiledField],
+        data: &'a [u8],
+        base_offset: usize,
+        parent: &'s Scope<'s>,
+    ) -> Self {
+        FieldsAccess {
+            fields,
+            data,
+            base_offset,
+            parent,
+            record: BTreeMap::new(),
+            spans: Vec::new(),
+            next: 0,
+            cursor: base_offset,
+            end: base_offset,
+            pending: None,
+            done: false,
+        }
+    }
+
+    /// Reads the fields the target type did not ask for, so that constants and
+    /// checksums are still checked and the end of the list is known.
+    fn finish(&mut self) -> Result<(), DecodeError> {
+        if self.pending.is_some() {
+            self.next_value::<IgnoredAny>()?;
+        }
+        while self.next_key::<IgnoredAny>()?.is_some() {
+            self.next_value::<IgnoredAny>()?;
+        }
+        Ok(())
+    }
+
+    fn advance(&mut self, start: usize, end: usize) {
+        self.cursor = start + end;
+        self.end = self.end.max(self.cursor);
+    }
+
+    /// Moves to the next field that produces a value and returns it with the bit
+    /// it starts at. Spacers, reserved bits, and absent fields are passed over.
+    fn next_field(&mut self) -> Result<Option<(&'a CompiledField, usize)>, DecodeError> {
+        while let Some(field) = self.fields.get(self.next) {
+            self.next += 1;
+            let start = match field.placement {
+                Placement::Fixed => self.base_offset,
+                Placement::Cursor => self.cursor,
+            };
+            if let CompiledFieldKind::Align(align_bits) = field.kind {
+                self.cursor =
+                    self.base_offset + (start - self.base_offset).next_multiple_of(align_bits);
+                self.end = self.end.max(self.cursor);
+                continue;
+            }
+            let scope = self.parent.child(&self.record);
+            let end = if !is_present(field, &scope) {
+                field.kind.value_end_bits(&Value::Absent, &scope)
+            } else if !field.kind.has_value() {
+                let value = field
+                    .kind
+                    .assemble_at(self.data, start, &scope)
+                    .and_then(|value| field.check_constant(&value).map(|()| value))
+                    .map_err(|e| e.in_field(&field.name))?;
+                field.kind.value_end_bits(&value, &scope)
+            } else {
+                return Ok(Some((field, start)));
+            };
+            self.advance(start, end);
+        }
+        Ok(None)
+    }
+
+    /// Verifies the checksums of the list once every field is read.
+    fn verify_checksums(&self) -> Result<(), ReadError> {
+        for field in self.fields {
+            if let Some(value) = self.record.get(&field.name) {
+                field
+                    .verify_checksum(value, self.data, self.base_offset, &self.spans)
+                    .map_err(|e| e.in_field(&field.name))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'de> MapAccess<'de> for FieldsAccess<'_, '_> {
+    type Error = DecodeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DecodeError> {
+        if self.done {
+            return Ok(None);
+        }
+        match self.next_field()? {
+            Some((field, start)) => {
+                self.pending = Some((field, start));
+                seed.deserialize(field.name.as_str().into_deserializer())
+                    .map(Some)
+            }
+            None => {
+                self.done = true;
+                self.verify_checksums()?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, DecodeError> {
+        let (field, start) = self
+            .pending
+            .take()
+            .expect("next_value_seed is called after next_key_seed");
+        let mut read = Read::default();
+        let value = {
+            let scope = self.parent.child(&self.record);
+            read_field(field, self.data, start, &scope, &mut read, seed)
+                .map_err(|e| e.in_field(&field.name))?
+        };
+        if let Some(record) = read.record {
+            self.record.insert(field.name.clone(), record);
+        }
+        self.spans.push((
+            field.name.as_str(),
+            start + field.kind.start_bits()..start + read.end,
+        ));
+        self.advance(start, read.end);
+        Ok(value)
+    }
+}
+
+/// Reads one present field into `seed`.
+fn read_field<'de, V: DeserializeSeed<'de>>(
+    field: &CompiledField,
+    data: &[u8],
+    start: usize,
+    scope: &Scope<'_>,
+    read: &mut Read,
+    seed: V,
+) -> Result<V::Value, DecodeError> {
+    #[cfg(feature = "transform")]
+    let whole = field.transform.is_some() || field.constant.is_some();
+    #[cfg(not(feature = "transform"))]
+    let whole = field.constant.is_some();
+    if !whole {
+        return seed.deserialize(KindDeserializer {
+            kind: &field.kind,
+            data,
+            start,
+            scope,
+            read,
+        });
+    }
+
+    let raw = field.kind.assemble_at(data, start, scope)?;
+    field.check_constant(&raw)?;
+    read.end = field.kind.value_end_bits(&raw, scope);
+    read.record = Some(raw.clone());
+    #[cfg(feature = "transform")]
+    let raw = crate::schema::apply_field_transforms(field, raw).map_err(|error| {
+        DecodeError::Transform {
+            field: String::new(),
+            error,
+        }
+    })?;
+    seed.deserialize(ValueDeserializer(raw))
+}
+
+/// Deserializer for one field or array element, read from `data` at `start`.
+struct KindDeserializer<'a, 's> {
+    kind: &'a CompiledFieldKind,
+    data: &'a [u8],
+    start: usize,
+    scope: &'s Scope<'s>,
+    read: &'s mut Read,
+}
+
+impl KindDeserializer<'_, '_> {
+    /// Reads the value of a scalar, bytes or string kind; other kinds give `None`.
+    fn read_scalar(&mut self) -> Result<Option<Value>, ReadError> {
+        match self.kind {
+            CompiledFieldKind::Scalar(_)
+            | CompiledFieldKind::Bytes { .. }
+            | CompiledFieldKind::String(_)
+            | CompiledFieldKind::Checksum { .. } => {
+                let value = self.kind.assemble_at(self.data, self.start, self.scope)?;
+                self.read.end = self.kind.value_end_bits(&value, self.scope);
+                self.read.record = Some(value.clone());
+                Ok(Some(value))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Reads a struct body or switch case laid out from `base_offset`.
+    fn visit_fields<'de, V: Visitor<'de>>(
+        self,
+        fields: &[CompiledField],
+        base_offset: usize,
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        let mut access = FieldsAccess::new(fields, self.data, base_offset, self.scope);
+        let value = visitor.visit_map(&mut access)?;
+        access.finish()?;
+        self.read.end = access.end - self.start;
+        self.read.record = Some(Value::Struct(access.record));
+        Ok(value)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for KindDeserializer<'_, '_> {
+    type Error = DecodeError;
+
+    fn deserialize_any<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, DecodeError> {
+        if let Some(value) = self.read_scalar()? {
+            return ValueDeserializer(value).deserialize_any(visitor);
+        }
+        match self.kind {
+            CompiledFieldKind::Array(array) => {
+                let count = array.resolve_count(self.scope)?;
+                array.check_fits(count, self.data, self.start)?;
+                let mut access = ArrayAccess {
+                    array,
+                    data: self.data,
+                    start: self.start,
+                    scope: self.scope,
+                    index: 0,
+                    count,
+                    end: 0,
+                };
+                let value = visitor.visit_seq(&mut access)?;
+                if access.index < count {
+                    return Err(de::Error::invalid_length(count, &"every array element"));
+                }
+                self.read.end = access.end;
+                Ok(value)
+            }
+            CompiledFieldKind::Struct(group) => {
+                let base_offset = self.start + group.offset_bits;
+                self.visit_fields(&group.fields, base_offset, visitor)
+            }
+            CompiledFieldKind::Switch(switch) => {
+                let fields = switch
+                    .select(self.scope)?
+                    .ok_or_else(|| ReadError::UnmatchedCase(switch.on.clone()))?;
+                let base_offset = self.start + switch.offset_bits;
+                self.visit_fields(fields, base_offset, visitor)
+            }
+            // Kinds without a value never become map entries.
+            _ => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, DecodeError> {
+        match self.read_scalar()? {
+            Some(value) => ValueDeserializer(value).deserialize_bool(visitor),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, DecodeError> {
+        match self.read_scalar()? {
+            Some(value) => ValueDeserializer(value).deserialize_seq(visitor),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Reads the elements of an array as a sequence.
+struct ArrayAccess<'a, 's> {
+    array: &'a CompiledArray,
+    data: &'a [u8],
+    start: usize,
+    scope: &'s Scope<'s>,
+    index: usize,
+    count: usize,
+    /// One past the last bit read, relative to the array's base.
+    end: usize,
+}
+
+impl<'de> SeqAccess<'de> for ArrayAccess<'_, '_> {
+    type Error = DecodeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DecodeError> {
+        if self.index == self.count {
+            return Ok(None);
+        }
+        let i = self.index;
+        let element_start = self.array.offset_bits + i * self.array.stride_bits;
+        let mut read = Read::default();
+        let value = seed
+            .deserialize(KindDeserializer {
+                kind: &self.array.element,
+                data: self.data,
+                start: self.start + element_start,
+                scope: self.scope,
+                read: &mut read,
+            })
+            .map_err(|e| e.in_element(i))?;
+        self.end = self.end.max(element_start + read.end);
+        self.index += 1;
+        Ok(Some(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.count - self.index)
+    }
+}
+
+/// Deserializer over a [`Value`] that has already been read, such as the output of
+/// a transform.
+struct ValueDeserializer(Value);
+
+impl<'de> IntoDeserializer<'de, DecodeError> for ValueDeserializer {
+    type Deserializer = ValueDeserializer;
+
+    fn into_deserializer(self) -> ValueDeserializer {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = DecodeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        match self.0 {
+            Value::U64(v) => visitor.visit_u64(v),
+            Value::I64(v) => visitor.visit_i64(v),
+            Value::U128(v) => visitor.visit_u128(v),
+            Value::I128(v) => visitor.visit_i128(v),
+            Value::F32(v) => visitor.visit_f32(v),
+            Value::F64(v) => visitor.visit_f64(v),
+            Value::Bytes(v) => visitor.visit_byte_buf(v),
+            Value::String(v) => visitor.visit_string(v),
+            Value::Array(items) => {
+                let mut seq = SeqDeserializer::new(items.into_iter().map(ValueDeserializer));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Struct(map) => {
+                let entries = map
+                    .into_iter()
+                    .filter(|(_, v)| *v != Value::Absent)
+                    .map(|(k, v)| (k, ValueDeserializer(v)));
+                let mut map = MapDeserializer::new(entries);
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            Value::Absent => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        match self.0 {
+            Value::U64(0) => visitor.visit_bool(false),
+            Value::U64(1) => visitor.visit_bool(true),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        match self.0 {
+            Value::Absent => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    /// Byte buffers from the `Bytes` base also read as sequences, e.g. into `Vec<u8>`.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        match self.0 {
+            Value::Bytes(bytes) => {
+                let mut seq = SeqDeserializer::<_, DecodeError>::new(bytes.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    /// Strings, such as enum-map labels, read as unit variants.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        match self.0 {
+            Value::String(label) => visitor.visit_enum(label.into_deserializer()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct tuple tuple_struct map struct identifier ignored_any
+    }
+}
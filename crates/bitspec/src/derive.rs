@@ -0,0 +1,348 @@
+//! Typed access to schemas described by Rust structs.
+//!
+//! [`BitSpec`] ties a struct to its field list, compiled schema, and the value map
+//! used by [`crate::schema::Schema::parse`] and [`crate::schema::Schema::serialize`].
+//! It is normally implemented with `#[derive(BitSpec)]` (the `derive` feature); the
+//! attributes are described in the `bitspec-derive` crate.
+//!
+//! ## Example
+//!
+//! ```
+//! use bitspec::derive::BitSpec;
+//! # use bitspec_derive::BitSpec;
+//!
+//! #[derive(BitSpec, Debug, PartialEq)]
+//! struct Header {
+//!     #[bits(offset = 0, len = 4)]
+//!     version: u8,
+//!     #[bits(offset = 4, len = 12, signed)]
+//!     temperature: i16,
+//!     #[bits(len = 1)]
+//!     flag: bool,
+//! }
+//!
+//! let header = Header::from_bytes(&[0x1F, 0xFE, 0x80]).unwrap();
+//! assert_eq!(header.temperature, -2);
+//! assert_eq!(header.to_bytes().unwrap(), vec![0x1F, 0xFE, 0x80]);
+//! ```
+//!
+//! Fields whose bits overlap are a compile error:
+//!
+//! ```compile_fail
+//! # use bitspec_derive::BitSpec;
+//! #[derive(BitSpec)]
+//! struct Overlapping {
+//!     #[bits(offset = 0)]
+//!     a: u8,
+//!     #[bits(offset = 4)]
+//!     b: u8, // error: field `b` overlaps `a` at bit 4
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::{
+    checksum::ChecksumCoverage,
+    compiled::value_variant_name,
+    errors::{ReadError, WriteError},
+    field::{Field, FieldKind},
+    schema::Schema,
+    value::Value,
+};
+
+/// A struct with a bit layout, convertible from and to bytes.
+pub trait BitSpec: Sized {
+    /// The fields describing the layout, with absolute offsets.
+    fn fields() -> Vec<Field>;
+
+    /// The compiled schema for [`BitSpec::fields`], compiled once.
+    ///
+    /// The derive rejects overlapping fields at compile time when their offsets and
+    /// lengths follow from the attributes alone. Layouts that depend on the size of
+    /// a nested struct are checked here instead, and derived implementations panic
+    /// on first use if they do not compile.
+    fn schema() -> &'static Schema;
+
+    /// Builds the struct from parsed values. Fails with [`ReadError::UnknownField`]
+    /// if a field is missing, and with [`ReadError::TypeMismatch`] if it holds a
+    /// value that does not fit its type.
+    fn from_values(values: &BTreeMap<String, Value>) -> Result<Self, ReadError>;
+
+    /// Converts the struct into values for serialize.
+    fn to_values(&self) -> BTreeMap<String, Value>;
+
+    /// Parses `data` into the struct.
+    fn from_bytes(data: &[u8]) -> Result<Self, ReadError> {
+        Self::from_values(&Self::schema().parse(data)?)
+    }
+
+    /// Serializes the struct into bytes.
+    fn to_bytes(&self) -> Result<Vec<u8>, WriteError> {
+        Self::schema().serialize(&self.to_values())
+    }
+}
+
+/// A Rust type a single field value converts from and to.
+pub trait BitValue: Sized {
+    /// Converts a parsed value, or returns `None` if it does not fit.
+    fn from_value(value: &Value) -> Option<Self>;
+
+    /// Converts into a value for serialize.
+    fn to_value(&self) -> Value;
+}
+
+macro_rules! impl_bit_value {
+    ($variant:ident($wide:ty): $($ty:ty),*) => {$(
+        impl BitValue for $ty {
+            fn from_value(value: &Value) -> Option<Self> {
+                match *value {
+                    Value::U64(v) => v.try_into().ok(),
+                    Value::I64(v) => v.try_into().ok(),
+                    Value::U128(v) => v.try_into().ok(),
+                    Value::I128(v) => v.try_into().ok(),
+                    _ => None,
+                }
+            }
+
+            fn to_value(&self) -> Value {
+                Value::$variant(<$wide>::from(*self))
+            }
+        }
+    )*};
+}
+
+impl_bit_value!(U64(u64): u8, u16, u32, u64);
+impl_bit_value!(I64(i64): i8, i16, i32, i64);
+impl_bit_value!(U128(u128): u128);
+impl_bit_value!(I128(i128): i128);
+
+impl BitValue for bool {
+    fn from_value(value: &Value) -> Option<Self> {
+        match *value {
+            Value::U64(0) => Some(false),
+            Value::U64(1) => Some(true),
+            _ => None,
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::U64(u64::from(*self))
+    }
+}
+
+impl<T: BitValue, const N: usize> BitValue for [T; N] {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Array(items) => items
+                .iter()
+                .map(T::from_value)
+                .collect::<Option<Vec<_>>>()?
+                .try_into()
+                .ok(),
+            _ => None,
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Array(self.iter().map(T::to_value).collect())
+    }
+}
+
+/// Looks up `name` in `values` and converts it, for derived [`BitSpec::from_values`].
+pub fn take_field<T: BitValue>(
+    values: &BTreeMap<String, Value>,
+    name: &str,
+) -> Result<T, ReadError> {
+    let value = values
+        .get(name)
+        .ok_or_else(|| ReadError::UnknownField(name.to_string()))?;
+    T::from_value(value).ok_or_else(|| ReadError::TypeMismatch {
+        field: name.to_string(),
+        found: value_variant_name(value),
+    })
+}
+
+/// Moves every offset in `fields` by `bits`, so a struct laid out from bit 0 can be
+/// placed at `bits` in an absolute layout.
+pub fn shift_fields(mut fields: Vec<Field>, bits: usize) -> Vec<Field> {
+    for field in &mut fields {
+        // Array fragments are relative to each element, so only the array moves.
+        if !matches!(field.kind, FieldKind::Array(_)) {
+            for fragment in &mut field.fragments {
+                fragment.offset_bits += bits;
+            }
+        }
+        shift_kind(&mut field.kind, bits);
+    }
+    fields
+}
+
+fn shift_kind(kind: &mut FieldKind, bits: usize) {
+    match kind {
+        FieldKind::Array(spec) => spec.offset_bits += bits,
+        FieldKind::Bytes { offset_bits, .. } | FieldKind::String { offset_bits, .. } => {
+            *offset_bits += bits
+        }
+        FieldKind::Struct(fields) => *fields = shift_fields(std::mem::take(fields), bits),
+        FieldKind::Switch { cases, default, .. } => {
+            for fields in cases.values_mut() {
+                *fields = shift_fields(std::mem::take(fields), bits);
+            }
+            if let Some(fields) = default {
+                *fields = shift_fields(std::mem::take(fields), bits);
+            }
+        }
+        FieldKind::Checksum {
+            covers: ChecksumCoverage::Bits(range),
+            ..
+        } => *range = range.start + bits..range.end + bits,
+        _ => {}
+    }
+}
+
+/// One past the last bit any of `fields` occupies, for fixed-size fields. Used to
+/// place a field after a nested struct or struct array.
+pub fn fields_end_bits(fields: &[Field]) -> usize {
+    fields.iter().map(field_end_bits).max().unwrap_or(0)
+}
+
+fn field_end_bits(field: &Field) -> usize {
+    let fragments_end = field
+        .fragments
+        .iter()
+        .map(|fragment| fragment.offset_bits + fragment.len_bits)
+        .max()
+        .unwrap_or(0);
+    match &field.kind {
+        FieldKind::Array(spec) => {
+            let count = match spec.count {
+                crate::assembly::ArrayCount::Fixed(count) => count,
+                _ => return spec.offset_bits,
+            };
+            let element_end = match spec.element.as_deref() {
+                Some(FieldKind::Struct(fields)) => fields_end_bits(fields),
+                _ => fragments_end,
+            };
+            match count {
+                0 => spec.offset_bits,
+                count => spec.offset_bits + (count - 1) * spec.stride_bits + element_end,
+            }
+        }
+        FieldKind::Struct(fields) => fields_end_bits(fields),
+        FieldKind::Bytes {
+            offset_bits,
+            len_bits,
+            ..
+        } => offset_bits + len_bits,
+        FieldKind::String {
+            offset_bits,
+            framing: crate::field::StringFraming::Fixed { len_bytes, .. },
+            ..
+        } => offset_bits + len_bytes * 8,
+        FieldKind::String { offset_bits, .. } => *offset_bits,
+        _ => fragments_end,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assembly::{Assemble, BitOrder},
+        fragment::Fragment,
+    };
+
+    fn scalar(name: &str, offset: usize, len: usize) -> Field {
+        Field {
+            name: name.to_string(),
+            kind: FieldKind::Scalar,
+            signed: false,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments: vec![Fragment::new(offset, len)],
+            transform: None,
+            condition: None,
+            default: None,
+            constant: None,
+        }
+    }
+
+    #[test]
+    fn test_bit_values_round_trip_and_reject_misfits() {
+        assert_eq!(u8::from_value(&Value::U64(200)), Some(200));
+        assert_eq!(u8::from_value(&Value::U64(300)), None);
+        assert_eq!(i16::from_value(&Value::I64(-2)), Some(-2));
+        assert_eq!((-2i16).to_value(), Value::I64(-2));
+        assert_eq!(bool::from_value(&Value::U64(2)), None);
+        assert_eq!(
+            <[u8; 2]>::from_value(&Value::Array(vec![Value::U64(1), Value::U64(2)])),
+            Some([1, 2])
+        );
+        assert_eq!(
+            <[u8; 3]>::from_value(&Value::Array(vec![Value::U64(1)])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_take_field_tells_missing_from_misfit_values() {
+        let values = BTreeMap::from([
+            ("small".to_string(), Value::U64(7)),
+            ("big".to_string(), Value::U64(300)),
+        ]);
+        assert_eq!(take_field::<u8>(&values, "small"), Ok(7));
+        assert_eq!(
+            take_field::<u8>(&values, "big"),
+            Err(ReadError::TypeMismatch {
+                field: "big".to_string(),
+                found: "U64",
+            })
+        );
+        assert_eq!(
+            take_field::<u8>(&values, "gone"),
+            Err(ReadError::UnknownField("gone".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_shift_fields_and_end_bits() {
+        let inner = vec![scalar("a", 0, 4), scalar("b", 4, 12)];
+        assert_eq!(fields_end_bits(&inner), 16);
+
+        let shifted = shift_fields(inner, 8);
+        assert_eq!(shifted[1].fragments[0].offset_bits, 12);
+        assert_eq!(fields_end_bits(&shifted), 24);
+
+        let items = Field {
+            kind: FieldKind::Array(crate::field::ArraySpec {
+                count: crate::assembly::ArrayCount::Fixed(2),
+                stride_bits: 8,
+                offset_bits: 0,
+                element: None,
+            }),
+            ..scalar("items", 0, 8)
+        };
+        let payload = Field {
+            kind: FieldKind::Bytes {
+                offset_bits: 16,
+                len_bits: 16,
+                padding: crate::field::BytesPadding::Trailing,
+            },
+            fragments: vec![],
+            ..scalar("payload", 0, 0)
+        };
+        let shifted = shift_fields(vec![items, payload], 8);
+        assert_eq!(shifted[0].fragments[0].offset_bits, 0);
+        assert!(matches!(
+            &shifted[0].kind,
+            FieldKind::Array(spec) if spec.offset_bits == 8
+        ));
+        assert!(matches!(
+            shifted[1].kind,
+            FieldKind::Bytes {
+                offset_bits: 24,
+                ..
+            }
+        ));
+        assert_eq!(fields_end_bits(&shifted), 40);
+    }
+}
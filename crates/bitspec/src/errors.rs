@@ -1,6 +1,60 @@
 //! Error types for schema compilation and bit reading/writing.
 
-use std::fmt;
+use std::{fmt, ops::Range};
+
+/// Where in a schema an error occurred. Attached to errors through the
+/// `Context` variants of [`CompileError`] and [`ReadError`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ErrorContext {
+    /// Path of the field, e.g. `header.flags` or `items[3].id`; empty if unknown.
+    pub field: String,
+    /// Index of the innermost array element on the path, if any.
+    pub element: Option<usize>,
+    /// Index of the fragment within the field, if the error concerns one fragment.
+    pub fragment: Option<usize>,
+    /// Bits that were requested, relative to the start of the input.
+    pub bit_range: Option<Range<usize>>,
+    /// Number of bits the input holds.
+    pub available_bits: Option<usize>,
+}
+
+impl ErrorContext {
+    /// Prepends a path segment: a field name, or `[index]` for an array element.
+    fn prefix(&mut self, segment: &str) {
+        self.field = prefix_path(segment, &self.field);
+    }
+}
+
+/// Prepends a path segment to `path`, joining field names with `.`.
+fn prefix_path(segment: &str, path: &str) -> String {
+    if path.is_empty() {
+        segment.to_string()
+    } else if path.starts_with('[') {
+        format!("{segment}{path}")
+    } else {
+        format!("{segment}.{path}")
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if !self.field.is_empty() {
+            parts.push(format!("field '{}'", self.field));
+        }
+        if let Some(fragment) = self.fragment {
+            parts.push(format!("fragment {fragment}"));
+        }
+        match (&self.bit_range, self.available_bits) {
+            (Some(range), Some(available)) => {
+                parts.push(format!("bits {range:?} of {available} available"))
+            }
+            (Some(range), None) => parts.push(format!("bits {range:?}")),
+            (None, _) => {}
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
 
 /// Errors produced when compiling a [`crate::field::Field`] into a [`crate::compiled::CompiledField`].
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -9,16 +63,80 @@ pub enum CompileError {
     InvalidArrayStride,
     /// Array count is zero.
     InvalidArrayCount,
-    /// Scalar field total size is 0 or greater than 64 bits.
+    /// Scalar field total size is 0 or greater than 128 bits.
     InvalidFieldSize,
     /// Fragment has zero length or is otherwise invalid.
     InvalidFragment,
+    /// A field assembled with [`crate::assembly::Assemble::Endian`] is not a whole
+    /// number of bytes, or of 16-bit words for the byte-swapped orders.
+    InvalidByteOrder,
     /// Field kind is not supported.
     InvalidFieldKind,
     /// Array element has no fragments.
     EmptyArrayElement,
     /// Field name is invalid (e.g. empty or duplicate).
     InvalidFieldName,
+    /// A field refers to another field (e.g. [`crate::assembly::ArrayCount::FromField`])
+    /// that is not defined earlier at the same or an enclosing level.
+    UnknownField(String),
+    /// Two fields, two fragments of one field, or two array elements claim the same
+    /// bit. Allowed with [`crate::schema::CompileOptions::allow_overlap`].
+    OverlappingFields {
+        /// Path of the first field (e.g. `header.flags` or `items[2]`).
+        a: String,
+        /// Path of the second field; equal to `a` when a field overlaps itself.
+        b: String,
+        /// First bit claimed by both, relative to the payload start (or to the field
+        /// start for fields placed after a variable-length field).
+        bit: usize,
+    },
+    /// Another error, with the field (and fragment) it occurred in.
+    Context {
+        /// Where the error occurred.
+        context: ErrorContext,
+        /// The underlying error.
+        error: Box<CompileError>,
+    },
+}
+
+impl CompileError {
+    /// The underlying error, without any [`ErrorContext`].
+    pub fn root(&self) -> &CompileError {
+        match self {
+            Self::Context { error, .. } => error.root(),
+            other => other,
+        }
+    }
+
+    /// Where the error occurred, if known.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Self::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Records that the error occurred inside the field named `segment`. Unnamed
+    /// fields (spacers) add nothing.
+    pub(crate) fn in_field(self, segment: &str) -> Self {
+        if segment.is_empty() {
+            return self;
+        }
+        match self {
+            Self::Context { mut context, error } => {
+                context.prefix(segment);
+                Self::Context { context, error }
+            }
+            Self::OverlappingFields { .. } => self,
+            error => Self::Context {
+                context: ErrorContext {
+                    field: segment.to_string(),
+                    ..ErrorContext::default()
+                },
+                error: Box::new(error),
+            },
+        }
+    }
 }
 
 impl fmt::Display for CompileError {
@@ -26,11 +144,19 @@ impl fmt::Display for CompileError {
         match self {
             Self::InvalidArrayStride => write!(f, "array stride is smaller than element size"),
             Self::InvalidArrayCount => write!(f, "array count is zero"),
-            Self::InvalidFieldSize => write!(f, "field total size must be 1..=64 bits"),
+            Self::InvalidFieldSize => write!(f, "field total size must be 1..=128 bits"),
             Self::InvalidFragment => write!(f, "fragment is invalid (zero length or malformed)"),
+            Self::InvalidByteOrder => write!(f, "byte order needs whole bytes (words if swapped)"),
             Self::InvalidFieldKind => write!(f, "unsupported field kind"),
             Self::EmptyArrayElement => write!(f, "array element has no fragments"),
             Self::InvalidFieldName => write!(f, "field name is empty or duplicated"),
+            Self::UnknownField(name) => {
+                write!(f, "referenced field '{name}' is not defined before its use")
+            }
+            Self::OverlappingFields { a, b, bit } => {
+                write!(f, "fields '{a}' and '{b}' overlap at bit {bit}")
+            }
+            Self::Context { context, error } => write!(f, "{context}: {error}"),
         }
     }
 }
@@ -42,18 +168,165 @@ impl std::error::Error for CompileError {}
 pub enum ReadError {
     /// Requested bit range is beyond the end of the data.
     OutOfBounds,
-    /// More than 64 bits were requested in a single read.
+    /// More bits were requested in a single read than the result holds: over 64
+    /// for a `u64` read (e.g. [`crate::schema::FieldHandle::read_u64`] on a wide
+    /// field), over 128 for a `u128` read.
     TooManyBitsRead,
-    /// Input data is shorter than the schema's total bit length.
+    /// Input data ends before all fields could be read.
     PacketTooShort,
+    /// A referenced field (e.g. an array count) has not been parsed.
+    UnknownField(String),
+    /// A referenced count field does not hold a non-negative integer after adjustment.
+    InvalidCount(String),
+    /// The discriminator of a switch selects no case and the switch has no default.
+    UnmatchedCase(String),
+    /// A scalar read (e.g. [`crate::schema::FieldHandle::read_u64`]) was requested
+    /// from a field that is not a scalar.
+    NotScalar,
+    /// The bytes of a string field are not valid in its encoding.
+    InvalidEncoding,
+    /// A parsed value does not fit the Rust type it is converted into, e.g. by
+    /// [`crate::derive::BitSpec::from_values`].
+    TypeMismatch {
+        /// Name of the field.
+        field: String,
+        /// Name of the [`crate::value::Value`] variant that did not fit.
+        found: &'static str,
+    },
+    /// A field with a constant holds a different value.
+    ConstantMismatch {
+        /// Name of the field.
+        field: String,
+        /// The field's constant.
+        expected: crate::value::ExactValue,
+        /// Value read from the data.
+        found: crate::value::ExactValue,
+    },
+    /// A checksum field holds a different value than the one computed over the
+    /// data it covers.
+    ChecksumMismatch {
+        /// Name of the field.
+        field: String,
+        /// Checksum computed from the data.
+        expected: u64,
+        /// Value stored in the field.
+        found: u64,
+    },
+    /// Another error, with the field, element, fragment, and bit range it occurred at.
+    Context {
+        /// Where the error occurred.
+        context: ErrorContext,
+        /// The underlying error.
+        error: Box<ReadError>,
+    },
+}
+
+impl ReadError {
+    /// The underlying error, without any [`ErrorContext`].
+    pub fn root(&self) -> &ReadError {
+        match self {
+            Self::Context { error, .. } => error.root(),
+            other => other,
+        }
+    }
+
+    /// Where the error occurred, if known.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Self::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Wraps the error with `context`; used where the error is first raised.
+    pub(crate) fn with_context(self, context: ErrorContext) -> Self {
+        Self::Context {
+            context,
+            error: Box::new(self),
+        }
+    }
+
+    /// Records that the error occurred inside the field named `segment`. Unnamed
+    /// fields (spacers) add nothing.
+    pub(crate) fn in_field(self, segment: &str) -> Self {
+        if segment.is_empty() {
+            return self;
+        }
+        match self {
+            Self::Context { mut context, error } => {
+                context.prefix(segment);
+                Self::Context { context, error }
+            }
+            error => error.with_context(ErrorContext {
+                field: segment.to_string(),
+                ..ErrorContext::default()
+            }),
+        }
+    }
+
+    /// Records that the error occurred inside array element `index`.
+    pub(crate) fn in_element(self, index: usize) -> Self {
+        let segment = format!("[{index}]");
+        match self.in_field(&segment) {
+            Self::Context { mut context, error } => {
+                context.element.get_or_insert(index);
+                Self::Context { context, error }
+            }
+            other => other,
+        }
+    }
+
+    /// Replaces an [`ReadError::OutOfBounds`] root with [`ReadError::PacketTooShort`],
+    /// keeping the context.
+    pub(crate) fn out_of_bounds_as_too_short(self) -> Self {
+        match self {
+            Self::OutOfBounds => Self::PacketTooShort,
+            Self::Context { context, error } => Self::Context {
+                context,
+                error: Box::new(error.out_of_bounds_as_too_short()),
+            },
+            other => other,
+        }
+    }
 }
 
 impl fmt::Display for ReadError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::OutOfBounds => write!(f, "bit range is beyond the end of the data"),
-            Self::TooManyBitsRead => write!(f, "requested more than 64 bits in one read"),
-            Self::PacketTooShort => write!(f, "input data is shorter than the schema's total bit length"),
+            Self::TooManyBitsRead => {
+                write!(f, "requested more bits in one read than the result holds")
+            }
+            Self::PacketTooShort => write!(f, "input data ends before all fields could be read"),
+            Self::UnknownField(name) => write!(f, "referenced field '{name}' has no parsed value"),
+            Self::InvalidCount(name) => {
+                write!(f, "field '{name}' does not hold a valid element count")
+            }
+            Self::UnmatchedCase(name) => {
+                write!(f, "no switch case matches the value of field '{name}'")
+            }
+            Self::NotScalar => write!(f, "field is not a scalar"),
+            Self::InvalidEncoding => write!(f, "string bytes are not valid in its encoding"),
+            Self::TypeMismatch { field, found } => {
+                write!(
+                    f,
+                    "field '{field}' holds a Value::{found} that does not fit its type"
+                )
+            }
+            Self::ConstantMismatch {
+                expected, found, ..
+            } => write!(
+                f,
+                "found {:?} instead of the constant {:?}",
+                found.0, expected.0
+            ),
+            Self::ChecksumMismatch {
+                expected, found, ..
+            } => write!(
+                f,
+                "stored checksum {found:#x} does not match computed {expected:#x}"
+            ),
+            Self::Context { context, error } => write!(f, "{context}: {error}"),
         }
     }
 }
@@ -63,33 +336,313 @@ impl std::error::Error for ReadError {}
 /// Errors produced when writing values back to bytes (e.g. during [`crate::schema::Schema::serialize`]).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum WriteError {
-    /// Buffer is too short to write the value, or requested width exceeds 64 bits.
+    /// Buffer is too short to write the value, or the requested width exceeds what
+    /// the write holds (64 bits for a `u64`, 128 for a `u128`).
     OutOfBounds,
     /// Value cannot be written to this field (e.g. array length mismatch, or Array variant for a scalar field).
     InvalidValue,
     /// Required field missing from the input object.
     MissingField(String),
-    /// The provided value variant (e.g. F32/F64/Bytes/String) is not supported for serialization.
+    /// The discriminator of a switch selects no case and the switch has no default.
+    UnmatchedCase(String),
+    /// A switch value has an entry that is not a field of the case selected by the
+    /// discriminator.
+    CaseMismatch(String),
+    /// No field has the path given to [`crate::schema::Schema::write_field`].
+    UnknownField(String),
+    /// The field's position or size depends on other fields, so it cannot be
+    /// written in place.
+    DependentField(String),
+    /// The template given in [`crate::schema::SerializeOptions`] could not be
+    /// parsed.
+    Template(ReadError),
+    /// The value variant cannot be written to this kind of field, e.g. `F32`/`F64`
+    /// anywhere, or `Bytes`/`String` outside byte and string fields.
     UnsupportedValue {
         /// Name of the field that received the unsupported value.
         field: String,
         /// Name of the [`crate::value::Value`] variant that was rejected.
         variant: &'static str,
     },
+    /// A transformed value could not be inverted back into a raw value
+    /// (see [`crate::schema::Schema::serialize_transformed`]). Only produced with
+    /// the `transform` feature, but always present so that enabling the feature
+    /// elsewhere in a build does not break exhaustive matches.
+    Transform {
+        /// Name of the field whose transform failed to invert.
+        field: String,
+        /// The underlying transform error.
+        error: crate::transform::TransformError,
+    },
 }
 
 impl fmt::Display for WriteError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::OutOfBounds => write!(f, "buffer is too short to write the value"),
+            Self::OutOfBounds => write!(
+                f,
+                "buffer is too short or the width too large to write the value"
+            ),
             Self::InvalidValue => write!(f, "value cannot be written to this field"),
             Self::MissingField(name) => write!(f, "missing field '{name}' in object"),
+            Self::UnmatchedCase(name) => {
+                write!(f, "no switch case matches the value of field '{name}'")
+            }
+            Self::CaseMismatch(name) => write!(
+                f,
+                "field '{name}' is not part of the switch case selected by its discriminator"
+            ),
+            Self::UnknownField(path) => write!(f, "no field at path '{path}'"),
+            Self::DependentField(path) => write!(
+                f,
+                "field '{path}' depends on other fields and cannot be written in place"
+            ),
+            Self::Template(error) => write!(f, "template could not be parsed: {error}"),
             Self::UnsupportedValue { field, variant } => write!(
                 f,
-                "field '{field}' received Value::{variant}; serialize accepts only U64, I64, and Array"
+                "field '{field}' received Value::{variant}, which its kind cannot hold"
             ),
+            Self::Transform { field, error } => {
+                write!(f, "field '{field}' could not be inverted: {error}")
+            }
         }
     }
 }
 
 impl std::error::Error for WriteError {}
+
+/// Errors produced by [`crate::frame::FrameDecoder`] and [`crate::frame::Frames`].
+#[derive(Debug)]
+pub enum FrameError {
+    /// The buffered frame could not be parsed.
+    Read(ReadError),
+    /// The length field holds a value that is negative or too large after adjustment.
+    InvalidLength(String),
+    /// No complete frame was found within the configured maximum frame size.
+    TooLong(usize),
+    /// The input ended with this many bytes that do not form a complete frame.
+    Truncated(usize),
+    /// Reading from the underlying source failed.
+    Io(std::io::Error),
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read(error) => write!(f, "frame could not be parsed: {error}"),
+            Self::InvalidLength(name) => {
+                write!(
+                    f,
+                    "length field '{name}' does not hold a valid frame length"
+                )
+            }
+            Self::TooLong(max) => write!(f, "no complete frame within {max} bytes"),
+            Self::Truncated(len) => write!(f, "input ends with {len} bytes of incomplete frame"),
+            Self::Io(error) => write!(f, "reading frames failed: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Read(error) => Some(error),
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ReadError> for FrameError {
+    fn from(error: ReadError) -> Self {
+        Self::Read(error)
+    }
+}
+
+impl From<std::io::Error> for FrameError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Errors produced when collecting records into a [`crate::columnar::Table`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColumnError {
+    /// A record could not be parsed.
+    Read(ReadError),
+    /// A value has a different type than the earlier values of its column.
+    TypeMismatch {
+        /// Name of the top-level field the column belongs to.
+        field: String,
+        /// [`crate::value::Value`] variant the column holds.
+        expected: &'static str,
+        /// [`crate::value::Value`] variant that was rejected.
+        found: &'static str,
+    },
+}
+
+impl fmt::Display for ColumnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read(error) => write!(f, "record could not be parsed: {error}"),
+            Self::TypeMismatch {
+                field,
+                expected,
+                found,
+            } => write!(
+                f,
+                "column '{field}' holds Value::{expected} but received Value::{found}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ColumnError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Read(error) => Some(error),
+            Self::TypeMismatch { .. } => None,
+        }
+    }
+}
+
+impl From<ReadError> for ColumnError {
+    fn from(error: ReadError) -> Self {
+        Self::Read(error)
+    }
+}
+
+/// Errors produced by [`crate::schema::Schema::parse_into`].
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The data could not be parsed.
+    Read(ReadError),
+    /// A parsed value could not be transformed. Only produced with the `transform`
+    /// feature, like [`WriteError::Transform`].
+    Transform {
+        /// Path of the field whose transform failed.
+        field: String,
+        /// The underlying transform error.
+        error: crate::transform::TransformError,
+    },
+    /// A value does not fit the type it is deserialized into.
+    Type {
+        /// Path of the field, e.g. `header.flags` or `items[3]`; empty if unknown.
+        field: String,
+        /// What went wrong, as reported by the target type.
+        message: String,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl DecodeError {
+    /// Records that the error occurred inside the field named `segment`.
+    pub(crate) fn in_field(self, segment: &str) -> Self {
+        match self {
+            Self::Read(error) => Self::Read(error.in_field(segment)),
+            Self::Transform { field, error } => Self::Transform {
+                field: prefix_path(segment, &field),
+                error,
+            },
+            Self::Type { field, message } => Self::Type {
+                field: prefix_path(segment, &field),
+                message,
+            },
+        }
+    }
+
+    /// Records that the error occurred inside array element `index`.
+    pub(crate) fn in_element(self, index: usize) -> Self {
+        match self {
+            Self::Read(error) => Self::Read(error.in_element(index)),
+            other => other.in_field(&format!("[{index}]")),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read(error) => write!(f, "record could not be parsed: {error}"),
+            Self::Transform { field, error } => {
+                write!(f, "field '{field}' could not be transformed: {error}")
+            }
+            Self::Type { field, message } if field.is_empty() => write!(f, "{message}"),
+            Self::Type { field, message } => write!(f, "field '{field}': {message}"),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Read(error) => Some(error),
+            Self::Transform { error, .. } => Some(error),
+            Self::Type { .. } => None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ReadError> for DecodeError {
+    fn from(error: ReadError) -> Self {
+        Self::Read(error)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ::serde::de::Error for DecodeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Type {
+            field: String::new(),
+            message: msg.to_string(),
+        }
+    }
+}
+
+/// Errors produced by [`crate::schema::Schema::serialize_from`].
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    /// The values could not be written.
+    Write(WriteError),
+    /// The input has a shape no field can hold, such as an enum with data.
+    Type(String),
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Write(error) => write!(f, "values could not be written: {error}"),
+            Self::Type(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::error::Error for EncodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Write(error) => Some(error),
+            Self::Type(_) => None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<WriteError> for EncodeError {
+    fn from(error: WriteError) -> Self {
+        Self::Write(error)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ::serde::ser::Error for EncodeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Type(msg.to_string())
+    }
+}
@@ -14,15 +14,18 @@
 //!     assemble: Assemble::Concat(BitOrder::MsbFirst),
 //!     fragments: vec![Fragment::new(0, 16)],
 //!     transform: None,
+//!     condition: None,
+//!     default: None,
+//!     constant: None,
 //! };
 //! ```
 
-/// A single named field in a schema: either a scalar or an array of scalars.
+/// A single named field in a schema: a scalar, an array, a struct of sub-fields, or a switch.
 #[derive(Debug, Clone)]
 pub struct Field {
     /// Name used in the parsed result map.
     pub name: String,
-    /// Whether this is a scalar or an array, and array parameters.
+    /// Whether this is a scalar, an array, a struct, or a switch, and its parameters.
     pub kind: FieldKind,
     /// If true, the assembled value is interpreted as signed and sign-extended.
     pub signed: bool,
@@ -34,6 +37,19 @@ pub struct Field {
     /// When the `transform` feature is disabled, this field exists but cannot
     /// be applied (the `apply_transforms` method is gated).
     pub transform: Option<crate::transform::Transform>,
+    /// If set, the field is only present when the condition on an earlier field
+    /// holds; otherwise parse emits [`crate::value::Value::Absent`] and serialize
+    /// skips it.
+    pub condition: Option<Condition>,
+    /// Raw value written by [`crate::schema::Schema::serialize_with_options`] when
+    /// the input has no entry for this field and
+    /// [`crate::schema::SerializeOptions::use_defaults`] is set.
+    pub default: Option<crate::value::Value>,
+    /// Value the field must hold, such as a sync word or a fixed version number.
+    /// Parse fails with [`crate::errors::ReadError::ConstantMismatch`] when the
+    /// bits differ, and serialize writes it when the input has no entry for the
+    /// field.
+    pub constant: Option<crate::value::Value>,
 }
 
 #[cfg(feature = "serde")]
@@ -48,17 +64,93 @@ impl From<crate::serde::FieldDef> for Field {
             transform: value
                 .transform
                 .map(|def| crate::transform::Transform::try_from(def).expect("valid transform def")),
+            condition: value.condition.map(Into::into),
+            default: value.default,
+            constant: value.constant,
         }
     }
 }
 
-/// Distinguishes scalar fields from fixed-length array fields.
+/// Distinguishes scalar fields from array, struct, and switch fields.
 #[derive(Debug, Clone)]
 pub enum FieldKind {
     /// Single value assembled from one or more fragments.
     Scalar,
-    /// Repeated element with fixed count and stride.
+    /// Repeated element with a fixed stride; the count is fixed or taken from another field.
     Array(ArraySpec),
+    /// Group of named sub-fields parsed into a [`crate::value::Value::Struct`].
+    /// Sub-field fragment offsets are relative to the struct's base (bit 0 at top
+    /// level, or the element start inside an array). The field's own `fragments`,
+    /// `signed` and `assemble` are not used.
+    Struct(Vec<Field>),
+    /// Sub-fields chosen by the value of another field, parsed into a
+    /// [`crate::value::Value::Struct`] holding the fields of the selected case.
+    /// Offsets are relative to the switch's base, as for [`FieldKind::Struct`].
+    Switch {
+        /// Name of the discriminator field, resolved like
+        /// [`crate::assembly::ArrayCount::FromField`].
+        on: String,
+        /// Sub-fields for each discriminator value.
+        cases: std::collections::BTreeMap<u64, Vec<Field>>,
+        /// Sub-fields used when no case matches; without it, parsing fails with
+        /// [`crate::errors::ReadError::UnmatchedCase`].
+        default: Option<Vec<Field>>,
+    },
+    /// Bit range of any length read into a [`crate::value::Value::Bytes`] as a
+    /// whole, such as an opaque payload or a hash, instead of an array of 8-bit
+    /// elements. The range may start and end anywhere. The field's `fragments`,
+    /// `signed` and `assemble` are not used.
+    Bytes {
+        /// Bit offset where the range starts.
+        offset_bits: usize,
+        /// Length of the range in bits.
+        len_bits: usize,
+        /// Where the spare bits go when `len_bits` is not a multiple of 8.
+        padding: BytesPadding,
+    },
+    /// Text read into a [`crate::value::Value::String`] and written from one. The
+    /// bytes start at `offset_bits`, which need not be byte-aligned, and their
+    /// extent is given by `framing`. Only [`StringFraming::Fixed`] has a size known
+    /// at compile time. The field's `fragments`, `signed` and `assemble` are not used.
+    String {
+        /// Bit offset where the string (or its length prefix) starts.
+        offset_bits: usize,
+        /// How the end of the string is found.
+        framing: StringFraming,
+        /// How the bytes map to characters.
+        encoding: crate::transform::Encoding,
+    },
+    /// Unnamed gap of this many bits; produces no value. Only valid in a
+    /// [`Layout::Sequential`] field list.
+    Skip(usize),
+    /// Moves the cursor to the next multiple of this many bits, counted from the
+    /// start of the field list; produces no value. Only valid in a
+    /// [`Layout::Sequential`] field list.
+    Align(usize),
+    /// Reserved bits at the field's fragments, at most 64 like a scalar's; produces
+    /// no value and is not needed on serialize, which writes `fill`. Unlike
+    /// [`FieldKind::Skip`] it works in any layout and counts in overlap checks.
+    Reserved {
+        /// Bits to write, assembled like a scalar's value; usually 0.
+        fill: u64,
+        /// Whether parse fails with [`crate::errors::ReadError::ConstantMismatch`]
+        /// when the bits differ from `fill`, e.g. for must-be-zero bits.
+        check: bool,
+    },
+    /// Checksum stored at the field's fragments, at most 64 bits like a scalar's
+    /// and at least as wide as the algorithm's result. Parse output holds the
+    /// stored value; serialize computes it once every other field of the list is
+    /// written, so the input needs no entry for it.
+    Checksum {
+        /// How the value is computed.
+        algorithm: crate::checksum::ChecksumAlgorithm,
+        /// Bits or sibling fields the checksum is computed over.
+        covers: crate::checksum::ChecksumCoverage,
+        /// Whether parse fails with [`crate::errors::ReadError::ChecksumMismatch`]
+        /// when the stored value differs from the computed one. Without it, parse
+        /// returns the stored value unchecked.
+        check: bool,
+    },
 }
 
 #[cfg(feature = "serde")]
@@ -70,22 +162,243 @@ impl From<crate::serde::FieldKindDef> for FieldKind {
                 count,
                 stride_bits,
                 offset_bits,
+                element,
             } => FieldKind::Array(ArraySpec {
-                count,
+                count: count.into(),
                 stride_bits,
                 offset_bits,
+                element: element.map(|element| Box::new((*element).into())),
             }),
+            crate::serde::FieldKindDef::Struct { fields } => {
+                FieldKind::Struct(fields.into_iter().map(Into::into).collect())
+            }
+            crate::serde::FieldKindDef::Switch { on, cases, default } => FieldKind::Switch {
+                on,
+                cases: cases
+                    .into_iter()
+                    .map(|(key, fields)| (key, fields.into_iter().map(Into::into).collect()))
+                    .collect(),
+                default: default.map(|fields| fields.into_iter().map(Into::into).collect()),
+            },
+            crate::serde::FieldKindDef::Bytes {
+                offset_bits,
+                len_bits,
+                padding,
+            } => FieldKind::Bytes {
+                offset_bits,
+                len_bits,
+                padding: padding.into(),
+            },
+            crate::serde::FieldKindDef::String {
+                offset_bits,
+                framing,
+                encoding,
+            } => FieldKind::String {
+                offset_bits,
+                framing: framing.into(),
+                encoding: encoding.into(),
+            },
+            crate::serde::FieldKindDef::Skip { bits } => FieldKind::Skip(bits),
+            crate::serde::FieldKindDef::Align { bits } => FieldKind::Align(bits),
+            crate::serde::FieldKindDef::Reserved { fill, check } => {
+                FieldKind::Reserved { fill, check }
+            }
+            crate::serde::FieldKindDef::Checksum {
+                algorithm,
+                covers,
+                check,
+            } => FieldKind::Checksum {
+                algorithm: algorithm.into(),
+                covers: covers.into(),
+                check,
+            },
         }
     }
 }
 
-/// Parameters for an array field: count, stride, and start offset in bits.
+/// Parameters for an array field: count, stride, start offset in bits, and element kind.
 #[derive(Debug, Clone)]
 pub struct ArraySpec {
-    /// Number of elements.
-    pub count: usize,
+    /// Number of elements: fixed, or read from an earlier field.
+    pub count: crate::assembly::ArrayCount,
     /// Distance in bits between the start of consecutive elements.
     pub stride_bits: usize,
     /// Bit offset where the first element starts.
     pub offset_bits: usize,
+    /// Kind of each element. `None` means a scalar built from the field's fragments;
+    /// `Some(FieldKind::Struct(..))` makes every element a struct whose sub-field
+    /// offsets are relative to the element start.
+    pub element: Option<Box<FieldKind>>,
+}
+
+/// How the extent of a [`FieldKind::String`] is found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringFraming {
+    /// An unsigned byte count `prefix_bits` wide (1 to 64, MSB-first), followed
+    /// by that many bytes.
+    LengthPrefixed {
+        /// Width of the length prefix.
+        prefix_bits: usize,
+    },
+    /// Bytes up to a zero byte, which ends the string and is not part of it. For
+    /// UTF-16 the terminator is a zero code unit, two bytes.
+    NullTerminated,
+    /// Exactly `len_bytes` bytes. Parse strips trailing `pad` bytes, and serialize
+    /// pads shorter strings with them.
+    Fixed {
+        /// Size of the string in bytes.
+        len_bytes: usize,
+        /// Byte filling the unused tail, usually 0 or `b' '`.
+        pad: u8,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl From<crate::serde::StringFramingDef> for StringFraming {
+    fn from(value: crate::serde::StringFramingDef) -> Self {
+        match value {
+            crate::serde::StringFramingDef::LengthPrefixed { prefix_bits } => {
+                StringFraming::LengthPrefixed { prefix_bits }
+            }
+            crate::serde::StringFramingDef::NullTerminated => StringFraming::NullTerminated,
+            crate::serde::StringFramingDef::Fixed { len_bytes, pad } => {
+                StringFraming::Fixed { len_bytes, pad }
+            }
+        }
+    }
+}
+
+/// How the bits of a [`FieldKind::Bytes`] range sit in its bytes when the length
+/// is not a multiple of 8. The spare bits read as zero and are ignored on
+/// serialize.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BytesPadding {
+    /// The range starts at the high bit of the first byte; the low bits of the
+    /// last byte are spare.
+    #[default]
+    Trailing,
+    /// The range ends at the low bit of the last byte, like an integer; the high
+    /// bits of the first byte are spare.
+    Leading,
+}
+
+#[cfg(feature = "serde")]
+impl From<crate::serde::BytesPaddingDef> for BytesPadding {
+    fn from(value: crate::serde::BytesPaddingDef) -> Self {
+        match value {
+            crate::serde::BytesPaddingDef::Trailing => BytesPadding::Trailing,
+            crate::serde::BytesPaddingDef::Leading => BytesPadding::Leading,
+        }
+    }
+}
+
+/// How the fragment offsets of a field list are interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// Every offset is absolute: relative to the payload start, or to the struct or
+    /// element start for nested fields.
+    #[default]
+    Absolute,
+    /// Fields follow one another from a running bit cursor: each field's offsets
+    /// (fragments, `ArraySpec::offset_bits`, or the `offset_bits` of
+    /// [`FieldKind::Bytes`] and [`FieldKind::String`]) are relative to where the previous
+    /// field ended. [`FieldKind::Skip`] and [`FieldKind::Align`] move the cursor.
+    /// Nested structs use the same layout.
+    Sequential,
+}
+
+#[cfg(feature = "serde")]
+impl From<crate::serde::LayoutDef> for Layout {
+    fn from(value: crate::serde::LayoutDef) -> Self {
+        match value {
+            crate::serde::LayoutDef::Absolute => Layout::Absolute,
+            crate::serde::LayoutDef::Sequential => Layout::Sequential,
+        }
+    }
+}
+
+/// Test on the integer value of an earlier field that decides whether a field is present.
+///
+/// `field` names a sibling or enclosing field, like [`crate::assembly::ArrayCount::FromField`].
+/// The condition does not hold when the referenced value is absent or not an integer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// Holds when the value equals `value`.
+    Equals {
+        /// Name of the field to test.
+        field: String,
+        /// Expected value.
+        value: i64,
+    },
+    /// Holds when `value & mask == expected` (two's complement bits for signed values).
+    Mask {
+        /// Name of the field to test.
+        field: String,
+        /// Bits to compare.
+        mask: u64,
+        /// Expected value of the masked bits.
+        expected: u64,
+    },
+    /// Holds when `min <= value <= max`.
+    Range {
+        /// Name of the field to test.
+        field: String,
+        /// Inclusive lower bound.
+        min: i64,
+        /// Inclusive upper bound.
+        max: i64,
+    },
+}
+
+impl Condition {
+    /// Name of the field the condition tests.
+    pub fn field(&self) -> &str {
+        match self {
+            Condition::Equals { field, .. }
+            | Condition::Mask { field, .. }
+            | Condition::Range { field, .. } => field,
+        }
+    }
+
+    /// Evaluates the condition against the referenced field's value.
+    pub fn is_met(&self, value: &crate::value::Value) -> bool {
+        let v = match value {
+            crate::value::Value::U64(v) => i128::from(*v),
+            crate::value::Value::I64(v) => i128::from(*v),
+            crate::value::Value::I128(v) => *v,
+            crate::value::Value::U128(v) => match i128::try_from(*v) {
+                Ok(v) => v,
+                Err(_) => return false,
+            },
+            _ => return false,
+        };
+        match self {
+            Condition::Equals { value, .. } => v == i128::from(*value),
+            Condition::Mask { mask, expected, .. } => (v as u64) & mask == *expected,
+            Condition::Range { min, max, .. } => (i128::from(*min)..=i128::from(*max)).contains(&v),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<crate::serde::ConditionDef> for Condition {
+    fn from(value: crate::serde::ConditionDef) -> Self {
+        match value {
+            crate::serde::ConditionDef::Equals { field, value } => {
+                Condition::Equals { field, value }
+            }
+            crate::serde::ConditionDef::Mask {
+                field,
+                mask,
+                expected,
+            } => Condition::Mask {
+                field,
+                mask,
+                expected,
+            },
+            crate::serde::ConditionDef::Range { field, min, max } => {
+                Condition::Range { field, min, max }
+            }
+        }
+    }
 }
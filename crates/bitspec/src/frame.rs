@@ -0,0 +1,484 @@
+//! Frame decoding for byte streams that arrive in arbitrary chunks, such as serial
+//! ports or TCP sockets.
+//!
+//! A [`FrameDecoder`] buffers pushed bytes and hands out one parsed frame at a
+//! time once enough bytes are available. It can hunt for a sync word, take the
+//! frame length from a header field, and drops input to resynchronise after a
+//! frame that fails to parse. [`Frames`] drives a decoder from a [`std::io::Read`].
+//!
+//! ## Example
+//!
+//! ```
+//! use bitspec::assembly::{Assemble, BitOrder};
+//! use bitspec::field::{Field, FieldKind};
+//! use bitspec::frame::{FrameDecoder, FrameOptions};
+//! use bitspec::fragment::Fragment;
+//! use bitspec::schema::Schema;
+//! use bitspec::value::Value;
+//!
+//! let byte = |name: &str, offset| Field {
+//!     name: name.into(), kind: FieldKind::Scalar, signed: false,
+//!     assemble: Assemble::Concat(BitOrder::MsbFirst),
+//!     fragments: vec![Fragment::new(offset, 8)], transform: None, condition: None,
+//!     default: None,
+//!     constant: None,
+//! };
+//! let schema = Schema::compile(&[byte("magic", 0), byte("value", 8)], None).unwrap();
+//! let options = FrameOptions { sync: Some(vec![0xAA]), ..FrameOptions::default() };
+//! let mut decoder = FrameDecoder::with_options(schema, options).unwrap();
+//!
+//! decoder.push(&[0x00, 0x13, 0xAA]); // garbage, then the start of a frame
+//! assert_eq!(decoder.decode().unwrap(), None);
+//! decoder.push(&[0x07]);
+//! let frame = decoder.decode().unwrap().unwrap();
+//! assert_eq!(frame.get("value"), Some(&Value::U64(7)));
+//! assert_eq!(decoder.discarded(), 2);
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    io::{ErrorKind, Read},
+};
+
+use crate::{
+    compiled::{CompiledFieldKind, CompiledScalar, Placement},
+    errors::{CompileError, FrameError, ReadError},
+    schema::Schema,
+    value::Value,
+};
+
+/// A parsed frame and its length in bytes.
+type Decoded = (BTreeMap<String, Value>, usize);
+
+/// Options for [`FrameDecoder::with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct FrameOptions {
+    /// Bytes every frame starts with. Input before them is discarded, and after a
+    /// bad frame the decoder hunts for the next occurrence.
+    pub sync: Option<Vec<u8>>,
+    /// Field holding the length of each frame. Without it a frame ends where the
+    /// schema's fields end.
+    pub length: Option<LengthField>,
+    /// Largest frame, in bytes, the decoder waits for before treating the buffered
+    /// input as garbage.
+    pub max_frame_bytes: Option<usize>,
+}
+
+/// A top-level scalar field holding the length of the frame in bytes.
+#[derive(Debug, Clone)]
+pub struct LengthField {
+    /// Name of the field.
+    pub field: String,
+    /// Added to the field value to get the frame length, e.g. the header size when
+    /// the length only counts the payload.
+    pub adjust: i64,
+}
+
+/// Length field resolved against the compiled schema.
+#[derive(Debug, Clone)]
+struct CompiledLength {
+    field: String,
+    adjust: i64,
+    scalar: CompiledScalar,
+    end_bits: usize,
+}
+
+impl CompiledLength {
+    fn new(schema: &Schema, length: LengthField) -> Result<Self, CompileError> {
+        let field = schema
+            .fields
+            .iter()
+            .find(|field| field.name == length.field && field.kind.has_value())
+            .ok_or_else(|| CompileError::UnknownField(length.field.clone()))?;
+        match (&field.kind, field.placement, &field.condition) {
+            (CompiledFieldKind::Scalar(scalar), Placement::Fixed, None) => Ok(Self {
+                scalar: scalar.clone(),
+                end_bits: field.kind.end_bits(),
+                field: length.field,
+                adjust: length.adjust,
+            }),
+            _ => Err(CompileError::InvalidFieldKind.in_field(&length.field)),
+        }
+    }
+
+    /// Frame length in bytes, or `None` if `data` does not hold the field yet.
+    fn frame_bytes(&self, data: &[u8]) -> Result<Option<usize>, FrameError> {
+        if data.len() * 8 < self.end_bits {
+            return Ok(None);
+        }
+        let len = match self.scalar.assemble(data)? {
+            Value::U64(v) => i128::from(v),
+            Value::I64(v) => i128::from(v),
+            _ => return Err(FrameError::InvalidLength(self.field.clone())),
+        };
+        usize::try_from(len + i128::from(self.adjust))
+            .map(Some)
+            .map_err(|_| FrameError::InvalidLength(self.field.clone()))
+    }
+}
+
+/// Buffers bytes from a stream and parses them into frames with a [`Schema`].
+///
+/// Push bytes with [`FrameDecoder::push`] and call [`FrameDecoder::decode`] until it
+/// returns `Ok(None)`. When a frame fails to parse, `decode` returns the error and
+/// drops the first byte of that frame, so the next call resumes hunting right after
+/// it.
+#[derive(Debug, Clone)]
+pub struct FrameDecoder {
+    schema: Schema,
+    sync: Option<Vec<u8>>,
+    length: Option<CompiledLength>,
+    max_frame_bytes: Option<usize>,
+    buffer: Vec<u8>,
+    discarded: usize,
+}
+
+impl FrameDecoder {
+    /// Creates a decoder whose frames end where the schema's fields end.
+    pub fn new(schema: Schema) -> Self {
+        Self {
+            schema,
+            sync: None,
+            length: None,
+            max_frame_bytes: None,
+            buffer: Vec::new(),
+            discarded: 0,
+        }
+    }
+
+    /// Creates a decoder with a sync word, length field, or size limit. Fails if the
+    /// length field is not a top-level, unconditional scalar at a fixed position.
+    pub fn with_options(schema: Schema, options: FrameOptions) -> Result<Self, CompileError> {
+        let length = options
+            .length
+            .map(|length| CompiledLength::new(&schema, length))
+            .transpose()?;
+        Ok(Self {
+            sync: options.sync.filter(|sync| !sync.is_empty()),
+            length,
+            max_frame_bytes: options.max_frame_bytes,
+            ..Self::new(schema)
+        })
+    }
+
+    /// The schema frames are parsed with.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Appends bytes received from the stream.
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Bytes buffered but not yet consumed by a frame.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Total number of bytes dropped while hunting for a sync word or
+    /// resynchronising after a bad frame.
+    pub fn discarded(&self) -> usize {
+        self.discarded
+    }
+
+    /// Parses the next frame from the buffered bytes. Returns `Ok(None)` if more
+    /// bytes are needed.
+    pub fn decode(&mut self) -> Result<Option<BTreeMap<String, Value>>, FrameError> {
+        if !self.hunt() {
+            return Ok(None);
+        }
+        match self.next_frame() {
+            Ok(Some((frame, len))) => {
+                self.buffer.drain(..len);
+                Ok(Some(frame))
+            }
+            Ok(None) => Ok(None),
+            Err(e) => {
+                self.discard(1);
+                Err(e)
+            }
+        }
+    }
+
+    /// Wraps `reader` in an iterator over its frames.
+    pub fn frames<R: Read>(self, reader: R) -> Frames<R> {
+        Frames {
+            decoder: self,
+            reader,
+            done: false,
+        }
+    }
+
+    /// Drops input before the next sync word. Returns whether a frame may start at
+    /// the front of the buffer.
+    fn hunt(&mut self) -> bool {
+        let Some(sync) = &self.sync else {
+            return !self.buffer.is_empty();
+        };
+        match self
+            .buffer
+            .windows(sync.len())
+            .position(|window| window == sync.as_slice())
+        {
+            Some(start) => {
+                self.discard(start);
+                true
+            }
+            None => {
+                // Keep a possible partial sync word at the end.
+                let keep = sync.len() - 1;
+                self.discard(self.buffer.len().saturating_sub(keep));
+                false
+            }
+        }
+    }
+
+    /// Parses the frame at the front of the buffer and returns it with its length
+    /// in bytes, or `None` if it is not complete yet.
+    fn next_frame(&self) -> Result<Option<Decoded>, FrameError> {
+        if let Some(length) = &self.length {
+            let Some(len) = length.frame_bytes(&self.buffer)? else {
+                return Ok(None);
+            };
+            if self.max_frame_bytes.is_some_and(|max| len > max) {
+                return Err(FrameError::InvalidLength(length.field.clone()));
+            }
+            if self.buffer.len() < len {
+                return Ok(None);
+            }
+            let frame = self.schema.parse(&self.buffer[..len])?;
+            // Always make progress, even on a zero-length frame.
+            return Ok(Some((frame, len.max(1))));
+        }
+
+        match self.schema.parse_record(&self.buffer, 0) {
+            // Trailing alignment after variable-length fields is not read.
+            Ok((_, len)) if self.buffer.len() < len => Ok(None),
+            Ok(decoded) => Ok(Some(decoded)),
+            Err(e) if *e.root() == ReadError::PacketTooShort => match self.max_frame_bytes {
+                Some(max) if self.buffer.len() >= max => Err(FrameError::TooLong(max)),
+                _ => Ok(None),
+            },
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn discard(&mut self, len: usize) {
+        self.buffer.drain(..len);
+        self.discarded += len;
+    }
+
+    /// Called at the end of the input: drops a trailing partial sync word and
+    /// reports any incomplete frame left in the buffer.
+    fn finish(&mut self) -> Option<FrameError> {
+        if self.sync.is_some() && !self.hunt() {
+            self.discard(self.buffer.len());
+        }
+        let len = self.buffer.len();
+        self.buffer.clear();
+        (len > 0).then_some(FrameError::Truncated(len))
+    }
+}
+
+/// Iterator over the frames read from a [`Read`] source, created with
+/// [`FrameDecoder::frames`].
+///
+/// Parse errors are yielded and decoding continues after them. The iterator ends
+/// at the end of the input, after yielding [`FrameError::Truncated`] if an
+/// incomplete frame is left, or after the first I/O error.
+#[derive(Debug)]
+pub struct Frames<R> {
+    decoder: FrameDecoder,
+    reader: R,
+    done: bool,
+}
+
+impl<R> Frames<R> {
+    /// The decoder, e.g. to read [`FrameDecoder::discarded`].
+    pub fn decoder(&self) -> &FrameDecoder {
+        &self.decoder
+    }
+}
+
+impl<R: Read> Iterator for Frames<R> {
+    type Item = Result<BTreeMap<String, Value>, FrameError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = [0u8; 4096];
+        while !self.done {
+            match self.decoder.decode() {
+                Ok(Some(frame)) => return Some(Ok(frame)),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            match self.reader.read(&mut chunk) {
+                Ok(0) => {
+                    self.done = true;
+                    return self.decoder.finish().map(Err);
+                }
+                Ok(n) => self.decoder.push(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assembly::{ArrayCount, Assemble, BitOrder},
+        field::{ArraySpec, Field, FieldKind},
+        fragment::Fragment,
+    };
+
+    fn byte(name: &str, offset_bits: usize) -> Field {
+        Field {
+            name: name.to_string(),
+            kind: FieldKind::Scalar,
+            signed: false,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments: vec![Fragment::new(offset_bits, 8)],
+            transform: None,
+            condition: None,
+            default: None,
+            constant: None,
+        }
+    }
+
+    /// `magic`, `len`, then `len` one-byte `items`.
+    fn counted_schema() -> Schema {
+        let items = Field {
+            kind: FieldKind::Array(ArraySpec {
+                count: ArrayCount::FromField {
+                    field: "len".to_string(),
+                    adjust: 0,
+                },
+                stride_bits: 8,
+                offset_bits: 16,
+                element: None,
+            }),
+            ..byte("items", 0)
+        };
+        Schema::compile(&[byte("magic", 0), byte("len", 8), items], None).unwrap()
+    }
+
+    fn items(frame: &BTreeMap<String, Value>) -> &Value {
+        frame.get("items").unwrap()
+    }
+
+    fn u64s(values: &[u64]) -> Value {
+        Value::Array(values.iter().copied().map(Value::U64).collect())
+    }
+
+    #[test]
+    fn test_decode_waits_for_complete_frame() {
+        let mut decoder = FrameDecoder::new(counted_schema());
+        for byte in [0xAA, 2, 5] {
+            decoder.push(&[byte]);
+            assert_eq!(decoder.decode().unwrap(), None);
+        }
+        decoder.push(&[6, 0xAA, 0]);
+        assert_eq!(items(&decoder.decode().unwrap().unwrap()), &u64s(&[5, 6]));
+        assert_eq!(items(&decoder.decode().unwrap().unwrap()), &u64s(&[]));
+        assert_eq!(decoder.decode().unwrap(), None);
+        assert!(decoder.buffered().is_empty());
+    }
+
+    #[test]
+    fn test_decode_hunts_for_sync_word_across_chunks() {
+        let options = FrameOptions {
+            sync: Some(vec![0xAA, 0x55]),
+            ..FrameOptions::default()
+        };
+        let schema = Schema::compile(&[byte("a", 0), byte("b", 8), byte("c", 16)], None).unwrap();
+        let mut decoder = FrameDecoder::with_options(schema, options).unwrap();
+
+        decoder.push(&[1, 2, 3, 0xAA]);
+        assert_eq!(decoder.decode().unwrap(), None);
+        assert_eq!(decoder.buffered(), &[0xAA]);
+        decoder.push(&[0x55, 9]);
+        let frame = decoder.decode().unwrap().unwrap();
+        assert_eq!(frame.get("c"), Some(&Value::U64(9)));
+        assert_eq!(decoder.discarded(), 3);
+    }
+
+    #[test]
+    fn test_decode_resynchronises_after_bad_frame() {
+        let options = FrameOptions {
+            sync: Some(vec![0xAA]),
+            max_frame_bytes: Some(8),
+            ..FrameOptions::default()
+        };
+        let mut decoder = FrameDecoder::with_options(counted_schema(), options).unwrap();
+
+        // A corrupt length makes the first frame run past the size limit.
+        decoder.push(&[0xAA, 200, 1, 2, 3, 4, 5, 0xAA, 1, 7]);
+        assert!(matches!(decoder.decode(), Err(FrameError::TooLong(8))));
+        assert_eq!(items(&decoder.decode().unwrap().unwrap()), &u64s(&[7]));
+        assert_eq!(decoder.discarded(), 7);
+    }
+
+    #[test]
+    fn test_decode_uses_length_field() {
+        let options = FrameOptions {
+            length: Some(LengthField {
+                field: "len".to_string(),
+                adjust: 1,
+            }),
+            max_frame_bytes: Some(16),
+            ..FrameOptions::default()
+        };
+        // The frame carries bytes the schema does not describe after `value`.
+        let schema = Schema::compile(&[byte("len", 0), byte("value", 8)], None).unwrap();
+        let mut decoder = FrameDecoder::with_options(schema, options).unwrap();
+
+        decoder.push(&[3, 1, 0xFF]);
+        assert_eq!(decoder.decode().unwrap(), None);
+        decoder.push(&[0xFF, 1, 2]);
+        assert_eq!(
+            decoder.decode().unwrap().unwrap().get("value"),
+            Some(&Value::U64(1))
+        );
+        assert_eq!(
+            decoder.decode().unwrap().unwrap().get("value"),
+            Some(&Value::U64(2))
+        );
+
+        decoder.push(&[100]);
+        assert!(matches!(decoder.decode(), Err(FrameError::InvalidLength(name)) if name == "len"));
+        assert!(decoder.buffered().is_empty());
+    }
+
+    #[test]
+    fn test_with_options_rejects_unusable_length_field() {
+        let length = |field: &str| FrameOptions {
+            length: Some(LengthField {
+                field: field.to_string(),
+                adjust: 0,
+            }),
+            ..FrameOptions::default()
+        };
+        let err = FrameDecoder::with_options(counted_schema(), length("missing")).unwrap_err();
+        assert_eq!(err, CompileError::UnknownField("missing".to_string()));
+        let err = FrameDecoder::with_options(counted_schema(), length("items")).unwrap_err();
+        assert_eq!(err.root(), &CompileError::InvalidFieldKind);
+    }
+
+    #[test]
+    fn test_frames_reads_until_end_of_input() {
+        let input: &[u8] = &[0xAA, 1, 3, 0xAA, 0, 0xAA, 2, 9];
+        let mut frames = FrameDecoder::new(counted_schema()).frames(input);
+
+        assert_eq!(items(&frames.next().unwrap().unwrap()), &u64s(&[3]));
+        assert_eq!(items(&frames.next().unwrap().unwrap()), &u64s(&[]));
+        assert!(matches!(frames.next(), Some(Err(FrameError::Truncated(3)))));
+        assert!(frames.next().is_none());
+    }
+}
@@ -23,6 +23,9 @@
 //!         assemble: Assemble::Concat(BitOrder::MsbFirst),
 //!         fragments: vec![Fragment::new(0, 8)],
 //!         transform: None,
+//!         condition: None,
+//!         default: None,
+//!         constant: None,
 //!     },
 //! ];
 //! let schema = Schema::compile(&fields, None).unwrap();
@@ -32,11 +35,20 @@
 
 pub mod assembly;
 pub mod bits;
+pub mod checksum;
+pub mod columnar;
 pub mod compiled;
+mod coverage;
+#[cfg(feature = "serde")]
+mod de;
+pub mod derive;
 pub mod errors;
 pub mod field;
 pub mod fragment;
+pub mod frame;
 pub mod schema;
+#[cfg(feature = "serde")]
+mod ser;
 pub mod value;
 
 #[cfg(feature = "serde")]
@@ -44,4 +56,8 @@ pub mod serde;
 
 pub mod transform;
 
+pub use derive::BitSpec;
 pub use value::Value;
+
+#[cfg(feature = "derive")]
+pub use bitspec_derive::BitSpec;
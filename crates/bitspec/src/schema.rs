@@ -14,10 +14,10 @@
 //! let fields = vec![
 //!     Field { name: "a".into(), kind: FieldKind::Scalar, signed: false,
 //!             assemble: Assemble::Concat(BitOrder::MsbFirst),
-//!             fragments: vec![Fragment::new(0, 4)], transform: None },
+//!             fragments: vec![Fragment::new(0, 4)], transform: None, condition: None, default: None, constant: None },
 //!     Field { name: "b".into(), kind: FieldKind::Scalar, signed: false,
 //!             assemble: Assemble::Concat(BitOrder::MsbFirst),
-//!             fragments: vec![Fragment::new(4, 4)], transform: None },
+//!             fragments: vec![Fragment::new(4, 4)], transform: None, condition: None, default: None, constant: None },
 //! ];
 //! let schema = Schema::compile(&fields, None).unwrap();
 //!
@@ -36,9 +36,10 @@ use std::collections::BTreeMap;
 
 use crate::{
     assembly::{ArrayCount, BitOrder},
-    compiled::{CompiledField, CompiledFieldKind},
-    errors::{CompileError, ReadError, WriteError},
-    field::Field,
+    compiled::{self, CompiledField, CompiledFieldKind, Placement, Scope},
+    coverage,
+    errors::{ColumnError, CompileError, ReadError, WriteError},
+    field::{Field, FieldKind, Layout},
     value::Value,
 };
 
@@ -47,6 +48,9 @@ use crate::{
 pub struct WriteConfig {
     /// Bit order applied to each byte of the output buffer after writing.
     pub bit_order: BitOrder,
+    /// Rounding used by [`Schema::serialize_transformed`] when scaled values are
+    /// turned back into integers.
+    pub rounding: crate::transform::Rounding,
 }
 
 #[cfg(feature = "serde")]
@@ -54,6 +58,7 @@ impl From<crate::serde::WriteConfigDef> for WriteConfig {
     fn from(value: crate::serde::WriteConfigDef) -> Self {
         WriteConfig {
             bit_order: value.bit_order.into(),
+            rounding: value.rounding.into(),
         }
     }
 }
@@ -62,11 +67,35 @@ impl Default for WriteConfig {
     fn default() -> Self {
         WriteConfig {
             bit_order: BitOrder::MsbFirst,
+            rounding: Default::default(),
         }
     }
 }
 
-/// A compiled schema: list of [`CompiledField`]s and total bit length.
+/// Options for [`Schema::serialize_with_options`]: where values missing from the
+/// input come from.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SerializeOptions<'a> {
+    /// Write each field's [`Field::default`] when the input has no entry for it,
+    /// instead of failing with [`WriteError::MissingField`].
+    pub use_defaults: bool,
+    /// Existing packet to start from, as returned by [`Schema::serialize`]. Fields
+    /// missing from the input keep the template's values, and bits that no field
+    /// covers keep the template's bits. Takes precedence over defaults.
+    pub template: Option<&'a [u8]>,
+}
+
+/// Options for [`Schema::compile_with_options`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompileOptions {
+    /// How fragment offsets are interpreted.
+    pub layout: Layout,
+    /// Accept fields whose bit ranges overlap (deliberate aliasing) instead of
+    /// failing with [`CompileError::OverlappingFields`].
+    pub allow_overlap: bool,
+}
+
+/// A compiled schema: list of [`CompiledField`]s and minimum bit length.
 /// Use [`Schema::compile`] to build from [`Field`]s, then [`Schema::parse`] to parse bytes.
 #[derive(Debug, Clone)]
 pub struct Schema {
@@ -76,7 +105,7 @@ pub struct Schema {
     /// Optional write configuration (bit order for serialize).
     pub write_config: Option<WriteConfig>,
     #[cfg_attr(not(feature = "transform"), allow(dead_code))]
-    transforms: std::collections::HashMap<String, crate::transform::Transform>,
+    index: std::collections::HashMap<String, usize>,
 }
 
 #[cfg(feature = "serde")]
@@ -86,56 +115,69 @@ impl TryFrom<crate::serde::SchemaDef> for Schema {
     fn try_from(value: crate::serde::SchemaDef) -> Result<Self, Self::Error> {
         let fields: Vec<Field> = value.fields.into_iter().map(Into::into).collect();
         let write_config = value.write_config.map(Into::into);
-        return Self::compile(&fields, write_config);
+        let options = CompileOptions {
+            layout: value.layout.into(),
+            allow_overlap: value.allow_overlap,
+        };
+        return Self::compile_with_options(&fields, options, write_config);
     }
 }
 
 impl Schema {
-    /// Compiles a slice of [`Field`]s into a schema. Fails if any field is invalid.
+    /// Compiles a slice of [`Field`]s into a schema. Fails if any field is invalid,
+    /// refers to a field that is not defined before it, or overlaps another field.
     pub fn compile(
         fields: &[Field],
         write_config: Option<WriteConfig>,
     ) -> Result<Self, CompileError> {
-        let mut compiled_fields: Vec<CompiledField> = Vec::with_capacity(fields.len());
-        let mut total_bits = 0;
-        let mut transforms = std::collections::HashMap::new();
-
-        for field in fields {
-            let compiled_field: CompiledField = field.try_into()?;
-
-            match &compiled_field.kind {
-                CompiledFieldKind::Scalar(scalar) => {
-                    for frag in &scalar.fragments {
-                        let end = frag.offset_bits + frag.len_bits;
-                        total_bits = total_bits.max(end);
-                    }
-                }
-                CompiledFieldKind::Array(array) => {
-                    let ArrayCount::Fixed(count) = array.count;
-                    let end = array.offset_bits
-                        + array.element.total_bits
-                        + array.stride_bits * (count - 1);
-                    total_bits = total_bits.max(end);
-                }
-            }
+        Self::compile_with_layout(fields, Layout::Absolute, write_config)
+    }
 
-            if let Some(transform) = &field.transform {
-                transforms.insert(field.name.clone(), transform.clone());
-            }
+    /// Like [`Schema::compile`], but interprets offsets according to `layout`.
+    /// With [`Layout::Sequential`] each field starts where the previous one ended.
+    pub fn compile_with_layout(
+        fields: &[Field],
+        layout: Layout,
+        write_config: Option<WriteConfig>,
+    ) -> Result<Self, CompileError> {
+        let options = CompileOptions {
+            layout,
+            ..CompileOptions::default()
+        };
+        Self::compile_with_options(fields, options, write_config)
+    }
+
+    /// Like [`Schema::compile`], with the layout and overlap check set by `options`.
+    pub fn compile_with_options(
+        fields: &[Field],
+        options: CompileOptions,
+        write_config: Option<WriteConfig>,
+    ) -> Result<Self, CompileError> {
+        check_references(fields, &[])?;
 
-            compiled_fields.push(compiled_field);
+        let compiled_fields = compiled::compile_fields(fields, options.layout)?;
+        if !options.allow_overlap {
+            coverage::check_overlaps(&compiled_fields)?;
         }
+        let total_bits = compiled::fields_end_bits(&compiled_fields, CompiledField::min_end_bits);
+        let index = compiled_fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| field.kind.has_value())
+            .map(|(i, field)| (field.name.clone(), i))
+            .collect();
 
         Ok(Self {
             fields: compiled_fields,
             total_bits,
             write_config,
-            transforms,
+            index,
         })
     }
 
     /// Applies each field's [`crate::transform::Transform`] to the values in `obj`,
-    /// returning a new map with transformed values.
+    /// returning a new map with transformed values. Struct values are walked so that
+    /// sub-field transforms are applied too.
     #[cfg(feature = "transform")]
     pub fn apply_transforms(
         &self,
@@ -143,8 +185,8 @@ impl Schema {
     ) -> Result<std::collections::BTreeMap<String, crate::value::Value>, crate::transform::TransformError> {
         let mut map = std::collections::BTreeMap::new();
         for (name, value) in obj {
-            let transformed = match self.transforms.get(&name) {
-                Some(transform) => transform.apply(value)?,
+            let transformed = match self.index.get(&name) {
+                Some(&i) => apply_field_transforms(&self.fields[i], value)?,
                 None => value,
             };
             map.insert(name, transformed);
@@ -152,26 +194,359 @@ impl Schema {
         Ok(map)
     }
 
+    /// Inverts each field's [`crate::transform::Transform`] on the values in `obj`,
+    /// returning a map of raw values that [`Schema::serialize`] accepts.
+    ///
+    /// This is the inverse of [`Schema::apply_transforms`]. Scaled values are rounded
+    /// with the [`WriteConfig::rounding`] mode, and byte arrays shorter than the
+    /// field's element count (or a [`crate::field::FieldKind::Bytes`] field's
+    /// length) are zero-padded.
+    #[cfg(feature = "transform")]
+    pub fn invert_transforms(
+        &self,
+        obj: std::collections::BTreeMap<String, crate::value::Value>,
+    ) -> Result<
+        std::collections::BTreeMap<String, crate::value::Value>,
+        crate::transform::TransformError,
+    > {
+        let mut map = std::collections::BTreeMap::new();
+        for (name, value) in obj {
+            let raw = self.invert_field(&name, value)?;
+            map.insert(name, raw);
+        }
+        Ok(map)
+    }
+
+    /// Serializes a map of transformed values (as produced by [`Schema::apply_transforms`])
+    /// by inverting each field's transform and then calling [`Schema::serialize`].
+    ///
+    /// Transform failures are reported as [`WriteError::Transform`] with the field name.
+    #[cfg(feature = "transform")]
+    pub fn serialize_transformed(
+        &self,
+        obj: &std::collections::BTreeMap<String, crate::value::Value>,
+    ) -> Result<Vec<u8>, WriteError> {
+        let mut raw = std::collections::BTreeMap::new();
+        for (name, value) in obj {
+            let inverted =
+                self.invert_field(name, value.clone())
+                    .map_err(|error| WriteError::Transform {
+                        field: name.clone(),
+                        error,
+                    })?;
+            raw.insert(name.clone(), inverted);
+        }
+        self.serialize(&raw)
+    }
+
+    /// Inverts the transforms of the field `name` and of its nested sub-fields.
+    #[cfg(feature = "transform")]
+    fn invert_field(
+        &self,
+        name: &str,
+        value: crate::value::Value,
+    ) -> Result<crate::value::Value, crate::transform::TransformError> {
+        let Some(&i) = self.index.get(name) else {
+            return Ok(value);
+        };
+        let rounding = self
+            .write_config
+            .as_ref()
+            .map(|config| config.rounding)
+            .unwrap_or_default();
+        invert_field_transforms(&self.fields[i], value, rounding)
+    }
+
     /// Parses `data` according to this schema. Returns a map of field names to [Value]s. Fails if `data` is too short.
+    ///
+    /// Arrays whose count comes from another field make the packet length dynamic,
+    /// so [`ReadError::PacketTooShort`] is also reported when a read runs past the end.
+    /// Errors carry an [`crate::errors::ErrorContext`] naming the field that failed.
     pub fn parse(&self, data: &[u8]) -> Result<BTreeMap<String, Value>, ReadError> {
-        if data.len() * 8 < self.total_bits {
-            return Err(ReadError::PacketTooShort);
+        self.parse_record(data, 0).map(|(map, _)| map)
+    }
+
+    /// Parses `data` as records laid back to back, each starting at the byte after
+    /// the previous one ends. Every record is read in place from `data`, without
+    /// slicing it.
+    ///
+    /// The iterator ends after the last record, or after the first error: a trailing
+    /// partial record yields [`ReadError::PacketTooShort`]. When every record has
+    /// the same length, the length of `data` is checked once up front rather than
+    /// for each record.
+    pub fn parse_many<'a>(
+        &'a self,
+        data: &'a [u8],
+    ) -> impl Iterator<Item = Result<BTreeMap<String, Value>, ReadError>> + 'a {
+        let record_bytes = self.static_record_bytes();
+        let whole_bytes = record_bytes.map_or(0, |len| data.len() / len * len);
+        let mut offset = 0;
+        let mut failed = false;
+        std::iter::from_fn(move || {
+            if failed || offset >= data.len() {
+                return None;
+            }
+            let record = match record_bytes {
+                Some(len) if offset < whole_bytes => {
+                    compiled::assemble_fields(&self.fields, data, offset * 8, &Scope::default())
+                        .map(|map| (map, len))
+                }
+                _ => self.parse_record(data, offset * 8),
+            };
+            match &record {
+                Ok((_, len)) => offset += len,
+                Err(_) => failed = true,
+            }
+            Some(record.map(|(map, _)| map))
+        })
+    }
+
+    /// Like [`Schema::parse_many`], but returns one column per top-level field,
+    /// holding that field's value for every record in order.
+    pub fn parse_columns(&self, data: &[u8]) -> Result<BTreeMap<String, Vec<Value>>, ReadError> {
+        let mut columns: BTreeMap<String, Vec<Value>> = self
+            .fields
+            .iter()
+            .filter(|field| field.kind.has_value())
+            .map(|field| (field.name.clone(), Vec::new()))
+            .collect();
+        for record in self.parse_many(data) {
+            for (name, value) in record? {
+                columns.entry(name).or_default().push(value);
+            }
+        }
+        Ok(columns)
+    }
+
+    /// Like [`Schema::parse_columns`], but stores each column as a flat typed vector
+    /// with a validity bitmap (see [`crate::columnar`]).
+    ///
+    /// When every record has the same length, values go straight from `data` into
+    /// the columns. Otherwise each record is read into a map first, since its
+    /// fields may depend on each other.
+    pub fn parse_table(&self, data: &[u8]) -> Result<crate::columnar::Table, ColumnError> {
+        let mut builder = crate::columnar::TableBuilder::new(self);
+        let Some(len) = self.static_record_bytes() else {
+            for record in self.parse_many(data) {
+                builder.push(record?)?;
+            }
+            return Ok(builder.finish());
+        };
+        let whole_bytes = data.len() / len * len;
+        for offset in (0..whole_bytes).step_by(len) {
+            builder.push_static_record(self, data, offset * 8)?;
+        }
+        if whole_bytes < data.len() {
+            // A trailing partial record fails the same way as in `parse_many`.
+            let (record, _) = self.parse_record(data, whole_bytes * 8)?;
+            builder.push(record)?;
+        }
+        Ok(builder.finish())
+    }
+
+    /// Parses one record starting at `base_bits` and returns it with its length in
+    /// bytes.
+    pub(crate) fn parse_record(
+        &self,
+        data: &[u8],
+        base_bits: usize,
+    ) -> Result<(BTreeMap<String, Value>, usize), ReadError> {
+        let map = compiled::assemble_fields(&self.fields, data, base_bits, &Scope::default())
+            .map_err(ReadError::out_of_bounds_as_too_short)?;
+        self.check_len(data, base_bits)?;
+        // Records always advance, even when every field is absent.
+        let len = self.encoded_bits(&map).div_ceil(8).max(1);
+        Ok((map, len))
+    }
+
+    /// Length in bytes of every record, if no field's presence, position or size
+    /// depends on the data.
+    fn static_record_bytes(&self) -> Option<usize> {
+        self.fields
+            .iter()
+            .all(|field| field.condition.is_none() && field.kind.is_static())
+            .then(|| self.total_bits.div_ceil(8).max(1))
+    }
+
+    /// Trailing spacers are not read, so checks the length once more after parsing.
+    fn check_len(&self, data: &[u8], base_bits: usize) -> Result<(), ReadError> {
+        let available_bits = data.len() * 8;
+        let end_bits = base_bits + self.total_bits;
+        if available_bits < end_bits {
+            return Err(
+                ReadError::PacketTooShort.with_context(crate::errors::ErrorContext {
+                    bit_range: Some(base_bits..end_bits),
+                    available_bits: Some(available_bits),
+                    ..Default::default()
+                }),
+            );
         }
+        Ok(())
+    }
+
+    /// Parses `data` straight into `T`, which sees the record as a map from field
+    /// names to values, like the output of [`Schema::parse`]. Fields are read as
+    /// `T` asks for them, without building that map first: integers, arrays as
+    /// sequences, and structs and switch cases as nested maps. Absent conditional
+    /// fields are left out, so they suit `Option` members.
+    ///
+    /// With the `transform` feature, transformed fields are handed over as their
+    /// transformed value, so a scaled field reads into `f64`, an enum-mapped field
+    /// into a `String` or a unit-variant enum, and a `Bytes` field into `Vec<u8>`.
+    ///
+    /// Fails with [`crate::errors::DecodeError::Read`] where [`Schema::parse`] would
+    /// fail, and with [`crate::errors::DecodeError::Type`] if a value does not fit
+    /// its member of `T`.
+    #[cfg(feature = "serde")]
+    pub fn parse_into<T: serde::de::DeserializeOwned>(
+        &self,
+        data: &[u8],
+    ) -> Result<T, crate::errors::DecodeError> {
+        use crate::errors::DecodeError;
+
+        let value = crate::de::from_fields(&self.fields, data).map_err(|e| match e {
+            DecodeError::Read(e) => DecodeError::Read(e.out_of_bounds_as_too_short()),
+            other => other,
+        })?;
+        self.check_len(data, 0)?;
+        Ok(value)
+    }
+
+    /// Bit ranges that no field or spacer covers, up to the end of the fields whose
+    /// position is known at compile time. Useful to spot unmapped bits while
+    /// reverse-engineering a format.
+    pub fn uncovered_bits(&self) -> Vec<std::ops::Range<usize>> {
+        coverage::uncovered_bits(&self.fields)
+    }
 
-        let mut map: BTreeMap<String, Value> = BTreeMap::new();
+    /// Number of bits `obj` takes up when encoded: the fixed length of the schema, or
+    /// more if variable-length fields need it.
+    pub(crate) fn encoded_bits(&self, obj: &BTreeMap<String, Value>) -> usize {
+        self.total_bits.max(compiled::values_end_bits(
+            &self.fields,
+            obj,
+            &Scope::default(),
+        ))
+    }
 
-        for field in &self.fields {
-            match &field.kind {
-                CompiledFieldKind::Scalar(scalar) => {
-                    map.insert(field.name.clone(), scalar.assemble(data)?);
+    /// Looks up the field at `path` (a top-level name, or a dotted path into struct
+    /// fields such as `engine.rpm`) and returns a handle that reads it straight from
+    /// the data, without building a map or checking the length of the whole record.
+    ///
+    /// Fails with [`CompileError::UnknownField`] if there is no such field, and with
+    /// [`CompileError::InvalidFieldKind`] if its position or size depends on other
+    /// fields: conditional fields, fields after a variable-length field in a
+    /// sequential layout, arrays counted by another field, and switches.
+    pub fn field_handle(&self, path: &str) -> Result<FieldHandle, CompileError> {
+        let unknown = || CompileError::UnknownField(path.to_string());
+        let mut fields = &self.fields;
+        let mut indices = Vec::new();
+        let mut base_bits = 0;
+        let mut segments = path.split('.').peekable();
+        while let Some(segment) = segments.next() {
+            let (index, field) = fields
+                .iter()
+                .enumerate()
+                .find(|(_, field)| field.name == segment && field.kind.has_value())
+                .ok_or_else(unknown)?;
+            if field.condition.is_some() || field.placement != Placement::Fixed {
+                return Err(CompileError::InvalidFieldKind.in_field(path));
+            }
+            indices.push(index);
+            match (&field.kind, segments.peek()) {
+                (CompiledFieldKind::Struct(group), Some(_)) => {
+                    base_bits += group.offset_bits;
+                    fields = &group.fields;
                 }
-                CompiledFieldKind::Array(array) => {
-                    map.insert(field.name.clone(), array.assemble(data)?);
+                (_, Some(_)) => return Err(unknown()),
+                (kind, None) if !kind.is_static() => {
+                    return Err(CompileError::InvalidFieldKind.in_field(path));
                 }
+                (_, None) => {}
             }
         }
+        Ok(FieldHandle {
+            path: path.to_string(),
+            indices,
+            base_bits,
+        })
+    }
 
-        Ok(map)
+    /// Writes `value` over the bits of the field at `path` in `buf`, an existing
+    /// packet, leaving every other bit as it was. `path` and the fields allowed are
+    /// as for [`Schema::field_handle`]; offsets are read as [`Schema::parse`] reads
+    /// them, without applying [`WriteConfig`].
+    pub fn write_field(&self, buf: &mut [u8], path: &str, value: &Value) -> Result<(), WriteError> {
+        self.writable_handle(path)?.write(self, buf, value)
+    }
+
+    /// Writes every entry of `values` into `buf` like [`Schema::write_field`]. Keys
+    /// are field paths. Either every entry is written or, on error, `buf` is left
+    /// unchanged.
+    pub fn patch(
+        &self,
+        buf: &mut [u8],
+        values: &BTreeMap<String, Value>,
+    ) -> Result<(), WriteError> {
+        let handles = values
+            .iter()
+            .map(|(path, value)| Ok((self.writable_handle(path)?, value)))
+            .collect::<Result<Vec<_>, WriteError>>()?;
+        let mut patched = buf.to_vec();
+        for (handle, value) in handles {
+            handle.write(self, &mut patched, value)?;
+        }
+        buf.copy_from_slice(&patched);
+        Ok(())
+    }
+
+    fn writable_handle(&self, path: &str) -> Result<FieldHandle, WriteError> {
+        self.field_handle(path).map_err(|e| match e.root() {
+            CompileError::UnknownField(_) => WriteError::UnknownField(path.to_string()),
+            _ => WriteError::DependentField(path.to_string()),
+        })
+    }
+
+    /// Serializes `value`, a struct or map with one entry per field, like the input
+    /// of [`Schema::serialize`]. Integers and `bool` are written as they are,
+    /// sequences as arrays, and nested structs as struct values; `None` members
+    /// are left out, so absent conditional fields can be `Option`s.
+    ///
+    /// Like [`Schema::parse_into`], this works straight over the compiled fields:
+    /// each field is written as soon as its value arrives, and only the values
+    /// later fields depend on (array counts, conditions, and switch
+    /// discriminators) are kept. Members listed in another order than the schema
+    /// are held until the fields ahead of them are written. Missing fields with a
+    /// constant are filled with it, and checksums are computed at the end.
+    ///
+    /// With the `transform` feature, transforms are inverted as in
+    /// [`Schema::serialize_transformed`], so floats, enum labels (or unit-variant
+    /// enums), and strings are written back as raw values.
+    ///
+    /// Fails with [`crate::errors::EncodeError::Write`] where serialize would fail,
+    /// and with [`crate::errors::EncodeError::Type`] if `value` has a shape no field
+    /// holds, such as an enum variant with data.
+    #[cfg(feature = "serde")]
+    pub fn serialize_from<T: serde::Serialize + ?Sized>(
+        &self,
+        value: &T,
+    ) -> Result<Vec<u8>, crate::errors::EncodeError> {
+        let mut buf = crate::ser::to_fields(
+            &self.fields,
+            value,
+            self.total_bits,
+            #[cfg(feature = "transform")]
+            self.write_config
+                .as_ref()
+                .map(|config| config.rounding)
+                .unwrap_or_default(),
+        )?;
+        if let Some(config) = &self.write_config {
+            if config.bit_order == crate::assembly::BitOrder::LsbFirst {
+                crate::bits::reverse_bits_in_bytes(&mut buf);
+            }
+        }
+        Ok(buf)
     }
 
     /// Serializes `obj` into bytes according to this schema, respecting [`WriteConfig`].
@@ -179,27 +554,52 @@ impl Schema {
         &self,
         obj: &std::collections::BTreeMap<String, crate::value::Value>,
     ) -> Result<Vec<u8>, WriteError> {
-        let total_bytes = (self.total_bits + 7) / 8;
-        let mut buf = vec![0u8; total_bytes];
-
-        for field in &self.fields {
-            let value = obj
-                .get(&field.name)
-                .ok_or_else(|| WriteError::MissingField(field.name.clone()))?;
+        self.serialize_onto(obj, &[])
+    }
 
-            match &field.kind {
-                CompiledFieldKind::Scalar(scalar) => {
-                    scalar
-                        .disassemble_at(value, &mut buf, 0)
-                        .map_err(|e| attach_field_name(e, &field.name))?;
-                }
-                CompiledFieldKind::Array(array) => {
-                    array
-                        .disassemble_at(value, &mut buf)
-                        .map_err(|e| attach_field_name(e, &field.name))?;
-                }
-            }
+    /// Like [`Schema::serialize`], but fills fields missing from `obj` from their
+    /// defaults or from a template packet, as set in `options`. Entries in `obj`
+    /// always win; inside a struct value, missing sub-fields are filled as well.
+    ///
+    /// Fails with [`WriteError::Template`] if the template cannot be parsed, and
+    /// with [`WriteError::MissingField`] if a field is still missing.
+    pub fn serialize_with_options(
+        &self,
+        obj: &BTreeMap<String, Value>,
+        options: SerializeOptions<'_>,
+    ) -> Result<Vec<u8>, WriteError> {
+        let mut template = options.template.map(<[u8]>::to_vec).unwrap_or_default();
+        let lsb_first = self.write_config.as_ref().map(|config| config.bit_order)
+            == Some(crate::assembly::BitOrder::LsbFirst);
+        if lsb_first {
+            crate::bits::reverse_bits_in_bytes(&mut template);
         }
+        let mut values = std::borrow::Cow::Borrowed(obj);
+        if options.template.is_some() {
+            let mut base = self.parse(&template).map_err(WriteError::Template)?;
+            compiled::overlay_values(&self.fields, &mut base, obj);
+            values = std::borrow::Cow::Owned(base);
+        }
+        if options.use_defaults {
+            compiled::fill_defaults(&self.fields, values.to_mut());
+        }
+        self.serialize_onto(&values, &template)
+    }
+
+    /// Serializes `obj` over a copy of `template`, zero-extended or cut to the
+    /// encoded length. `template` is in the order of the bits before [`WriteConfig`]
+    /// is applied.
+    fn serialize_onto(
+        &self,
+        obj: &BTreeMap<String, Value>,
+        template: &[u8],
+    ) -> Result<Vec<u8>, WriteError> {
+        let total_bytes = self.encoded_bits(obj).div_ceil(8);
+        let mut buf = vec![0u8; total_bytes];
+        let kept = template.len().min(total_bytes);
+        buf[..kept].copy_from_slice(&template[..kept]);
+
+        compiled::disassemble_fields(&self.fields, obj, &mut buf, 0, &Scope::default())?;
 
         if let Some(config) = &self.write_config {
             if config.bit_order == crate::assembly::BitOrder::LsbFirst {
@@ -211,22 +611,281 @@ impl Schema {
     }
 }
 
-fn attach_field_name(err: WriteError, field: &str) -> WriteError {
-    match err {
-        WriteError::UnsupportedValue { variant, .. } => WriteError::UnsupportedValue {
-            field: field.to_string(),
-            variant,
+/// Precomputed location of one field, created with [`Schema::field_handle`], for
+/// reading that field alone in a hot loop. Reads neither allocate nor check the
+/// length of the whole record. A handle is only meaningful for the schema that
+/// created it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldHandle {
+    path: String,
+    /// Index of the field at each level of `path`.
+    indices: Vec<usize>,
+    /// Start of the field list holding the field.
+    base_bits: usize,
+}
+
+impl FieldHandle {
+    /// Path the handle was created for.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Reads the field's value from `data`, as [`Schema::parse`] would.
+    pub fn read(&self, schema: &Schema, data: &[u8]) -> Result<Value, ReadError> {
+        self.kind(schema)?
+            .assemble_at(data, self.base_bits, &Scope::default())
+            .map_err(|e| self.error(e))
+    }
+
+    /// Reads the raw bits of a scalar field, zero-extended. Fails with
+    /// [`ReadError::NotScalar`] for other fields, and with
+    /// [`ReadError::TooManyBitsRead`] for fields wider than 64 bits.
+    pub fn read_u64(&self, schema: &Schema, data: &[u8]) -> Result<u64, ReadError> {
+        self.scalar(schema)?
+            .read_bits_at(data, self.base_bits)
+            .map_err(|e| self.error(e))
+    }
+
+    /// Reads a scalar field as `i64`: sign-extended if the field is signed,
+    /// otherwise its raw bits reinterpreted. Fails like [`Self::read_u64`].
+    pub fn read_i64(&self, schema: &Schema, data: &[u8]) -> Result<i64, ReadError> {
+        let scalar = self.scalar(schema)?;
+        let bits = scalar
+            .read_bits_at(data, self.base_bits)
+            .map_err(|e| self.error(e))?;
+        if scalar.signed {
+            Ok(crate::bits::sign_extend(bits, scalar.total_bits))
+        } else {
+            Ok(bits as i64)
+        }
+    }
+
+    /// Writes `value` over the field's bits in `buf`, leaving every other bit as it
+    /// was.
+    pub fn write(&self, schema: &Schema, buf: &mut [u8], value: &Value) -> Result<(), WriteError> {
+        self.kind(schema)
+            .map_err(|_| WriteError::UnknownField(self.path.clone()))?
+            .disassemble_at(value, buf, self.base_bits, &Scope::default())
+            .map_err(|e| compiled::attach_field_name(e, &self.path))
+    }
+
+    fn kind<'s>(&self, schema: &'s Schema) -> Result<&'s CompiledFieldKind, ReadError> {
+        let unknown = || ReadError::UnknownField(self.path.clone());
+        let (last, outer) = self.indices.split_last().ok_or_else(unknown)?;
+        let mut fields = &schema.fields;
+        for &index in outer {
+            match fields.get(index).map(|field| &field.kind) {
+                Some(CompiledFieldKind::Struct(group)) => fields = &group.fields,
+                _ => return Err(unknown()),
+            }
+        }
+        fields
+            .get(*last)
+            .map(|field| &field.kind)
+            .ok_or_else(unknown)
+    }
+
+    fn scalar<'s>(&self, schema: &'s Schema) -> Result<&'s compiled::CompiledScalar, ReadError> {
+        match self.kind(schema)? {
+            CompiledFieldKind::Scalar(scalar) => Ok(scalar),
+            _ => Err(ReadError::NotScalar.in_field(&self.path)),
+        }
+    }
+
+    fn error(&self, error: ReadError) -> ReadError {
+        error.out_of_bounds_as_too_short().in_field(&self.path)
+    }
+}
+
+/// Checks that every field reference (e.g. [`ArrayCount::FromField`] or a
+/// [`crate::field::Condition`]) names a field defined earlier at the same level or
+/// at an enclosing level.
+fn check_references(fields: &[Field], outer: &[&str]) -> Result<(), CompileError> {
+    let mut visible = outer.to_vec();
+    for field in fields {
+        if let Some(condition) = &field.condition {
+            check_reference(condition.field(), &visible).map_err(|e| e.in_field(&field.name))?;
+        }
+        check_kind_references(&field.kind, &visible).map_err(|e| e.in_field(&field.name))?;
+        visible.push(&field.name);
+    }
+    Ok(())
+}
+
+/// Checks that the first segment of a dotted field path is visible.
+fn check_reference(path: &str, visible: &[&str]) -> Result<(), CompileError> {
+    let head = path.split('.').next().unwrap_or_default();
+    if visible.contains(&head) {
+        Ok(())
+    } else {
+        Err(CompileError::UnknownField(path.to_string()))
+    }
+}
+
+fn check_kind_references(kind: &FieldKind, visible: &[&str]) -> Result<(), CompileError> {
+    match kind {
+        FieldKind::Scalar => Ok(()),
+        FieldKind::Array(spec) => {
+            if let ArrayCount::FromField { field, .. } = &spec.count {
+                check_reference(field, visible)?;
+            }
+            match &spec.element {
+                Some(element) => check_kind_references(element, visible),
+                None => Ok(()),
+            }
+        }
+        FieldKind::Struct(fields) => check_references(fields, visible),
+        FieldKind::Switch { on, cases, default } => {
+            check_reference(on, visible)?;
+            cases
+                .values()
+                .chain(default)
+                .try_for_each(|fields| check_references(fields, visible))
+        }
+        FieldKind::Bytes { .. }
+        | FieldKind::String { .. }
+        | FieldKind::Skip(_)
+        | FieldKind::Align(_)
+        | FieldKind::Reserved { .. }
+        | FieldKind::Checksum { .. } => Ok(()),
+    }
+}
+
+/// Applies the transforms of nested sub-fields, then the field's own transform.
+/// Absent values pass through unchanged.
+#[cfg(feature = "transform")]
+pub(crate) fn apply_field_transforms(
+    field: &CompiledField,
+    value: Value,
+) -> Result<Value, crate::transform::TransformError> {
+    if let Value::Absent = value {
+        return Ok(value);
+    }
+    let value = map_nested(&field.kind, value, &apply_field_transforms)?;
+    match &field.transform {
+        Some(transform) => transform.apply(value),
+        None => Ok(value),
+    }
+}
+
+/// Inverts the field's own transform, then the transforms of nested sub-fields.
+/// Byte arrays shorter than a fixed array count or a bytes field are zero-padded;
+/// absent values pass through unchanged.
+#[cfg(feature = "transform")]
+pub(crate) fn invert_field_transforms(
+    field: &CompiledField,
+    value: Value,
+    rounding: crate::transform::Rounding,
+) -> Result<Value, crate::transform::TransformError> {
+    if let Value::Absent = value {
+        return Ok(value);
+    }
+    let mut raw = match &field.transform {
+        Some(transform) => transform.invert(value, rounding)?,
+        None => value,
+    };
+
+    let is_bytes = field
+        .transform
+        .as_ref()
+        .is_some_and(|transform| transform.base == crate::transform::Base::Bytes);
+    let byte_count = match &field.kind {
+        CompiledFieldKind::Array(array) => match array.count {
+            ArrayCount::Fixed(count) => Some(count),
+            ArrayCount::FromField { .. } => None,
         },
-        other => other,
+        CompiledFieldKind::Bytes { len_bits, .. } => Some(len_bits.div_ceil(8)),
+        _ => None,
+    };
+    if let (true, Some(count), Value::Array(values)) = (is_bytes, byte_count, &mut raw)
+        && values.len() < count
+    {
+        values.resize(count, Value::U64(0));
+    }
+
+    map_nested(&field.kind, raw, &|field: &CompiledField, value: Value| {
+        invert_field_transforms(field, value, rounding)
+    })
+}
+
+/// Runs `f` on every sub-field value of a struct or switch (or an array of them);
+/// other values pass through.
+#[cfg(feature = "transform")]
+fn map_nested<F>(
+    kind: &CompiledFieldKind,
+    value: Value,
+    f: &F,
+) -> Result<Value, crate::transform::TransformError>
+where
+    F: Fn(&CompiledField, Value) -> Result<Value, crate::transform::TransformError>,
+{
+    match (kind, value) {
+        (CompiledFieldKind::Struct(group), Value::Struct(map)) => map_fields(&group.fields, map, f),
+        // The discriminator is not at hand here, so the case is the one whose
+        // fields cover every entry of the value.
+        (CompiledFieldKind::Switch(switch), Value::Struct(map)) => {
+            let case = switch.cases.values().chain(&switch.default).find(|fields| {
+                map.keys()
+                    .all(|name| find_value_field(fields, name).is_some())
+            });
+            match case {
+                Some(fields) => map_fields(fields, map, f),
+                None => Ok(Value::Struct(map)),
+            }
+        }
+        (CompiledFieldKind::Array(array), Value::Array(values))
+            if !matches!(*array.element, CompiledFieldKind::Scalar(_)) =>
+        {
+            let values = values
+                .into_iter()
+                .map(|value| map_nested(&array.element, value, f))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Value::Array(values))
+        }
+        (_, value) => Ok(value),
     }
 }
 
+/// Runs `f` on every entry of `map` that names one of `fields`.
+#[cfg(feature = "transform")]
+fn map_fields<F>(
+    fields: &[CompiledField],
+    map: BTreeMap<String, Value>,
+    f: &F,
+) -> Result<Value, crate::transform::TransformError>
+where
+    F: Fn(&CompiledField, Value) -> Result<Value, crate::transform::TransformError>,
+{
+    let mut out = BTreeMap::new();
+    for (name, value) in map {
+        let value = match find_value_field(fields, &name) {
+            Some(field) => f(field, value)?,
+            None => value,
+        };
+        out.insert(name, value);
+    }
+    Ok(Value::Struct(out))
+}
+
+/// The field of `fields` that produces the value named `name`, skipping spacers.
+#[cfg(feature = "transform")]
+fn find_value_field<'a>(fields: &'a [CompiledField], name: &str) -> Option<&'a CompiledField> {
+    fields
+        .iter()
+        .find(|field| field.name == name && field.kind.has_value())
+}
+
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "serde")]
+    use crate::errors::{DecodeError, EncodeError};
     use crate::{
         assembly::{Assemble, BitOrder},
-        field::{ArraySpec, Field, FieldKind},
+        checksum::{ChecksumAlgorithm, ChecksumCoverage, Crc},
+        field::{ArraySpec, BytesPadding, Condition, Field, FieldKind, StringFraming},
         fragment::Fragment,
+        transform::Encoding,
+        value::ExactValue,
     };
 
     use super::*;
@@ -248,6 +907,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(0, 1)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
         let schema = Schema::compile(&vec![field], None).unwrap();
         let data = vec![0x01, 0x02, 0x03, 0x04];
@@ -267,6 +929,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(0, 8)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
         let field2 = Field {
             name: "test2".to_string(),
@@ -275,6 +940,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(8, 16)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
         let schema = Schema::compile(&vec![field1, field2], None).unwrap();
         let data = vec![0x01, 0x00, 0x01, 0x04];
@@ -293,14 +961,18 @@ mod tests {
         let field = Field {
             name: "test".to_string(),
             kind: FieldKind::Array(ArraySpec {
-                count: 4,
+                count: ArrayCount::Fixed(4),
                 stride_bits: 8,
                 offset_bits: 0,
+                element: None,
             }),
             signed: false,
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(0, 8)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let schema = Schema::compile(&vec![field], None).unwrap();
@@ -329,6 +1001,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(0, 16)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let temperature_field = Field {
@@ -338,19 +1013,26 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(16, 8)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let values_field = Field {
             name: "values".to_string(),
             kind: FieldKind::Array(ArraySpec {
-                count: 5,
+                count: ArrayCount::Fixed(5),
                 stride_bits: 8,
                 offset_bits: 24,
+                element: None,
             }),
             signed: false,
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(0, 8)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let schema =
@@ -386,6 +1068,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(0, 8)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let schema = Schema::compile(&[field], None).unwrap();
@@ -405,6 +1090,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(0, 4)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let b = Field {
@@ -414,6 +1102,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(4, 4)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let schema = Schema::compile(&[a, b], None).unwrap();
@@ -436,6 +1127,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(4, 2), Fragment::new(0, 2)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let schema = Schema::compile(&[field], None).unwrap();
@@ -456,14 +1150,18 @@ mod tests {
         let field = Field {
             name: "arr".to_string(),
             kind: FieldKind::Array(ArraySpec {
-                count: 3,
+                count: ArrayCount::Fixed(3),
                 stride_bits: 8,
                 offset_bits: 0, // irrelevant for serialize
+                element: None,
             }),
             signed: false,
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(0, 8)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let schema = Schema::compile(&[field], None).unwrap();
@@ -486,6 +1184,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(4, 4)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
         let schema = Schema::compile(&[field], None).unwrap();
 
@@ -503,6 +1204,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(4, 4)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
         let schema = Schema::compile(&[field], None).unwrap();
 
@@ -521,10 +1225,16 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(0, 8)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
         let schema = Schema::compile(
             &[field],
-            Some(WriteConfig { bit_order: BitOrder::LsbFirst }),
+            Some(WriteConfig {
+                bit_order: BitOrder::LsbFirst,
+                ..Default::default()
+            }),
         )
         .unwrap();
         let obj = BTreeMap::from([("x".to_string(), crate::value::Value::U64(0b1010_0011))]);
@@ -533,6 +1243,111 @@ mod tests {
         assert_eq!(bytes, vec![0b1100_0101]);
     }
 
+    #[cfg(feature = "transform")]
+    #[test]
+    fn test_serialize_transformed_roundtrip() {
+        use crate::transform::{Base, Encoding, Transform};
+        use std::collections::HashMap;
+
+        let mut temperature = Transform::new(Base::Int);
+        temperature.set_scale(0.5).set_offset(-20.0);
+        let mut mode = Transform::new(Base::Int);
+        mode.set_enum_map(HashMap::from([
+            (0, "idle".to_string()),
+            (1, "run".to_string()),
+        ]));
+        let mut label = Transform::new(Base::Bytes);
+        label
+            .set_encoding(Encoding::Ascii)
+            .set_zero_terminated(true);
+
+        let fields = [
+            Field {
+                name: "temperature".to_string(),
+                kind: FieldKind::Scalar,
+                signed: false,
+                assemble: Assemble::Concat(BitOrder::MsbFirst),
+                fragments: vec![Fragment::new(0, 8)],
+                transform: Some(temperature),
+                condition: None,
+                default: None,
+                constant: None,
+            },
+            Field {
+                name: "mode".to_string(),
+                kind: FieldKind::Scalar,
+                signed: false,
+                assemble: Assemble::Concat(BitOrder::MsbFirst),
+                fragments: vec![Fragment::new(8, 8)],
+                transform: Some(mode),
+                condition: None,
+                default: None,
+                constant: None,
+            },
+            Field {
+                name: "label".to_string(),
+                kind: FieldKind::Array(ArraySpec {
+                    count: ArrayCount::Fixed(4),
+                    stride_bits: 8,
+                    offset_bits: 16,
+                    element: None,
+                }),
+                signed: false,
+                assemble: Assemble::Concat(BitOrder::MsbFirst),
+                fragments: vec![Fragment::new(0, 8)],
+                transform: Some(label),
+                condition: None,
+                default: None,
+                constant: None,
+            },
+        ];
+        let schema = Schema::compile(&fields, None).unwrap();
+
+        let obj = BTreeMap::from([
+            ("temperature".to_string(), Value::F64(21.5)),
+            ("mode".to_string(), Value::String("run".to_string())),
+            ("label".to_string(), Value::String("ab".to_string())),
+        ]);
+        let bytes = schema.serialize_transformed(&obj).unwrap();
+        assert_eq!(bytes, vec![83, 1, b'a', b'b', 0, 0]);
+
+        let parsed = schema
+            .apply_transforms(schema.parse(&bytes).unwrap())
+            .unwrap();
+        assert_eq!(parsed, obj);
+    }
+
+    #[cfg(feature = "transform")]
+    #[test]
+    fn test_serialize_transformed_reports_field() {
+        use crate::transform::{Base, Transform, TransformError};
+        use std::collections::HashMap;
+
+        let mut mode = Transform::new(Base::Int);
+        mode.set_enum_map(HashMap::from([(0, "idle".to_string())]));
+        let field = Field {
+            name: "mode".to_string(),
+            kind: FieldKind::Scalar,
+            signed: false,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments: vec![Fragment::new(0, 8)],
+            transform: Some(mode),
+            condition: None,
+            default: None,
+            constant: None,
+        };
+        let schema = Schema::compile(&[field], None).unwrap();
+
+        let obj = BTreeMap::from([("mode".to_string(), Value::String("busy".to_string()))]);
+        assert_eq!(
+            schema.serialize_transformed(&obj),
+            Err(WriteError::Transform {
+                field: "mode".to_string(),
+                error: TransformError::InvalidEnumLabel("busy".to_string()),
+            })
+        );
+    }
+
     #[test]
     fn test_serialize_parse_roundtrip_dense() {
         let field = Field {
@@ -542,6 +1357,9 @@ mod tests {
             assemble: Assemble::Concat(BitOrder::MsbFirst),
             fragments: vec![Fragment::new(0, 8)],
             transform: None,
+            condition: None,
+            default: None,
+            constant: None,
         };
 
         let schema = Schema::compile(&[field], None).unwrap();
@@ -553,4 +1371,2044 @@ mod tests {
 
         assert_eq!(parsed.get("x"), Some(&Value::U64(42)));
     }
+
+    fn sensor_block() -> Field {
+        let sub = |name: &str, offset: usize, len: usize, signed: bool| Field {
+            name: name.to_string(),
+            kind: FieldKind::Scalar,
+            signed,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments: vec![Fragment::new(offset, len)],
+            transform: None,
+            condition: None,
+            default: None,
+            constant: None,
+        };
+        Field {
+            name: "sensors".to_string(),
+            kind: FieldKind::Array(ArraySpec {
+                count: ArrayCount::Fixed(2),
+                stride_bits: 24,
+                offset_bits: 8,
+                element: Some(Box::new(FieldKind::Struct(vec![
+                    sub("id", 0, 8, false),
+                    sub("temp", 8, 16, true),
+                ]))),
+            }),
+            signed: false,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments: vec![],
+            transform: None,
+            condition: None,
+            default: None,
+            constant: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_serialize_struct_array() {
+        let count = Field {
+            name: "count".to_string(),
+            kind: FieldKind::Scalar,
+            signed: false,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments: vec![Fragment::new(0, 8)],
+            transform: None,
+            condition: None,
+            default: None,
+            constant: None,
+        };
+        let schema = Schema::compile(&[count, sensor_block()], None).unwrap();
+        let data = [2, 1, 0x00, 0x15, 2, 0xFF, 0xFE];
+
+        let parsed = schema.parse(&data).unwrap();
+        let sensor = |id: u64, temp: i64| {
+            Value::Struct(BTreeMap::from([
+                ("id".to_string(), Value::U64(id)),
+                ("temp".to_string(), Value::I64(temp)),
+            ]))
+        };
+        assert_eq!(
+            parsed,
+            BTreeMap::from([
+                ("count".to_string(), Value::U64(2)),
+                (
+                    "sensors".to_string(),
+                    Value::Array(vec![sensor(1, 21), sensor(2, -2)])
+                ),
+            ])
+        );
+
+        assert_eq!(schema.serialize(&parsed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_serialize_struct_reports_nested_missing_field() {
+        let schema = Schema::compile(&[sensor_block()], None).unwrap();
+        let element = Value::Struct(BTreeMap::from([("id".to_string(), Value::U64(1))]));
+        let obj = BTreeMap::from([(
+            "sensors".to_string(),
+            Value::Array(vec![element.clone(), element]),
+        )]);
+
+        assert_eq!(
+            schema.serialize(&obj),
+            Err(WriteError::MissingField("sensors.temp".to_string()))
+        );
+    }
+
+    #[cfg(feature = "transform")]
+    #[test]
+    fn test_transforms_apply_to_struct_sub_fields() {
+        use crate::transform::{Base, Transform};
+
+        let scaled = Field {
+            name: "scaled".to_string(),
+            kind: FieldKind::Scalar,
+            signed: false,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments: vec![Fragment::new(0, 8)],
+            transform: Some(Transform {
+                base: Base::Int,
+                scale: Some(0.5),
+                offset: None,
+                encoding: None,
+                zero_terminated: None,
+                trim: None,
+                enum_map: None,
+            }),
+            condition: None,
+            default: None,
+            constant: None,
+        };
+        let header = Field {
+            name: "header".to_string(),
+            kind: FieldKind::Struct(vec![scaled]),
+            signed: false,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments: vec![],
+            transform: None,
+            condition: None,
+            default: None,
+            constant: None,
+        };
+        let schema = Schema::compile(&[header], None).unwrap();
+
+        let parsed = schema.parse(&[10]).unwrap();
+        let transformed = schema.apply_transforms(parsed.clone()).unwrap();
+        assert_eq!(
+            transformed.get("header"),
+            Some(&Value::Struct(BTreeMap::from([(
+                "scaled".to_string(),
+                Value::F64(5.0)
+            )])))
+        );
+
+        assert_eq!(schema.invert_transforms(transformed).unwrap(), parsed);
+    }
+
+    fn counted_items(adjust: i64) -> [Field; 2] {
+        let len = Field {
+            name: "len".to_string(),
+            kind: FieldKind::Scalar,
+            signed: false,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments: vec![Fragment::new(0, 8)],
+            transform: None,
+            condition: None,
+            default: None,
+            constant: None,
+        };
+        let items = Field {
+            name: "items".to_string(),
+            kind: FieldKind::Array(ArraySpec {
+                count: ArrayCount::FromField {
+                    field: "len".to_string(),
+                    adjust,
+                },
+                stride_bits: 8,
+                offset_bits: 8,
+                element: None,
+            }),
+            signed: false,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments: vec![Fragment::new(0, 8)],
+            transform: None,
+            condition: None,
+            default: None,
+            constant: None,
+        };
+        [len, items]
+    }
+
+    #[test]
+    fn test_parse_serialize_count_from_field() {
+        let schema = Schema::compile(&counted_items(-1), None).unwrap();
+        let data = [3, 0xAA, 0xBB];
+
+        let parsed = schema.parse(&data).unwrap();
+        assert_eq!(
+            parsed.get("items"),
+            Some(&Value::Array(vec![Value::U64(0xAA), Value::U64(0xBB)]))
+        );
+
+        // The output buffer grows to fit the actual number of elements.
+        assert_eq!(schema.serialize(&parsed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_parse_count_from_field_checks_length() {
+        let schema = Schema::compile(&counted_items(0), None).unwrap();
+
+        assert_eq!(
+            schema.parse(&[0]).unwrap().get("items"),
+            Some(&Value::Array(vec![]))
+        );
+        assert_eq!(
+            schema.parse(&[200, 1, 2]).unwrap_err().root(),
+            &ReadError::PacketTooShort
+        );
+        assert_eq!(
+            schema.parse(&[]).unwrap_err().root(),
+            &ReadError::PacketTooShort
+        );
+    }
+
+    #[test]
+    fn test_parse_count_from_field_rejects_negative_count() {
+        let schema = Schema::compile(&counted_items(-2), None).unwrap();
+
+        assert_eq!(
+            schema.parse(&[1, 0]).unwrap_err().root(),
+            &ReadError::InvalidCount("len".to_string())
+        );
+    }
+
+    #[test]
+    fn test_serialize_count_from_field_mismatch() {
+        let schema = Schema::compile(&counted_items(0), None).unwrap();
+        let obj = BTreeMap::from([
+            ("len".to_string(), Value::U64(3)),
+            ("items".to_string(), Value::Array(vec![Value::U64(1)])),
+        ]);
+
+        assert_eq!(schema.serialize(&obj), Err(WriteError::InvalidValue));
+    }
+
+    #[test]
+    fn test_compile_rejects_forward_count_reference() {
+        let [len, items] = counted_items(0);
+
+        assert_eq!(
+            Schema::compile(&[items, len], None).unwrap_err().root(),
+            &CompileError::UnknownField("len".to_string())
+        );
+    }
+
+    #[test]
+    fn test_count_from_enclosing_struct_field() {
+        let [len, items] = counted_items(0);
+        let record = Field {
+            name: "record".to_string(),
+            kind: FieldKind::Struct(vec![items]),
+            signed: false,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments: vec![],
+            transform: None,
+            condition: None,
+            default: None,
+            constant: None,
+        };
+        let schema = Schema::compile(&[len, record], None).unwrap();
+
+        let parsed = schema.parse(&[1, 7]).unwrap();
+        assert_eq!(
+            parsed.get("record"),
+            Some(&Value::Struct(BTreeMap::from([(
+                "items".to_string(),
+                Value::Array(vec![Value::U64(7)])
+            )])))
+        );
+    }
+
+    fn seq_field(name: &str, kind: FieldKind, len_bits: usize) -> Field {
+        Field {
+            name: name.to_string(),
+            kind,
+            signed: false,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments: vec![Fragment::new(0, len_bits)],
+            transform: None,
+            condition: None,
+            default: None,
+            constant: None,
+        }
+    }
+
+    #[test]
+    fn test_sequential_layout_resolves_static_offsets() {
+        let fields = [
+            seq_field("a", FieldKind::Scalar, 4),
+            seq_field("b", FieldKind::Scalar, 3),
+            seq_field("", FieldKind::Align(8), 0),
+            seq_field("", FieldKind::Skip(8), 0),
+            seq_field("c", FieldKind::Scalar, 8),
+        ];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+
+        match &schema.fields[4].kind {
+            CompiledFieldKind::Scalar(scalar) => assert_eq!(scalar.fragments[0].offset_bits, 16),
+            other => panic!("unexpected kind {other:?}"),
+        }
+        assert!(
+            schema
+                .fields
+                .iter()
+                .all(|field| field.placement == compiled::Placement::Fixed)
+        );
+
+        let data = [0b1010_0110, 0xFF, 0x42];
+        let parsed = schema.parse(&data).unwrap();
+        assert_eq!(
+            parsed,
+            BTreeMap::from([
+                ("a".to_string(), Value::U64(0b1010)),
+                ("b".to_string(), Value::U64(0b011)),
+                ("c".to_string(), Value::U64(0x42)),
+            ])
+        );
+        assert_eq!(schema.serialize(&parsed).unwrap(), [0b1010_0110, 0, 0x42]);
+    }
+
+    #[test]
+    fn test_sequential_layout_after_variable_length_field() {
+        let [len, mut items] = counted_items(0);
+        if let FieldKind::Array(spec) = &mut items.kind {
+            spec.offset_bits = 0;
+        }
+        let fields = [
+            len,
+            items,
+            seq_field("", FieldKind::Align(16), 0),
+            seq_field("crc", FieldKind::Scalar, 8),
+        ];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+        assert_eq!(schema.fields[3].placement, compiled::Placement::Cursor);
+
+        let parsed = schema.parse(&[2, 0xA1, 0xA2, 0, 0x5C]).unwrap();
+        assert_eq!(parsed.get("crc"), Some(&Value::U64(0x5C)));
+        assert_eq!(schema.serialize(&parsed).unwrap(), [2, 0xA1, 0xA2, 0, 0x5C]);
+
+        let parsed = schema.parse(&[1, 0xA1, 0x5C]).unwrap();
+        assert_eq!(parsed.get("crc"), Some(&Value::U64(0x5C)));
+        assert_eq!(
+            schema.parse(&[3, 0xA1, 0xA2, 0xA3]).unwrap_err().root(),
+            &ReadError::PacketTooShort
+        );
+    }
+
+    #[test]
+    fn test_sequential_layout_after_empty_counted_array() {
+        let [len, mut items] = counted_items(0);
+        if let FieldKind::Array(spec) = &mut items.kind {
+            spec.offset_bits = 0;
+        }
+        let empty = BTreeMap::from([
+            ("len".to_string(), Value::U64(0)),
+            ("items".to_string(), Value::Array(vec![])),
+            ("tail".to_string(), Value::U64(7)),
+        ]);
+
+        let fields = [
+            len.clone(),
+            items.clone(),
+            seq_field("tail", FieldKind::Scalar, 8),
+        ];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+        assert_eq!(schema.parse(&[0, 7]).unwrap(), empty);
+        assert_eq!(schema.serialize(&empty).unwrap(), [0, 7]);
+        let tails: Vec<_> = schema
+            .parse_many(&[0, 7, 0, 9])
+            .map(|record| record.unwrap()["tail"].clone())
+            .collect();
+        assert_eq!(tails, [Value::U64(7), Value::U64(9)]);
+
+        let fields = [
+            len,
+            items,
+            seq_field("", FieldKind::Align(32), 0),
+            seq_field("tail", FieldKind::Scalar, 8),
+        ];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+        assert_eq!(schema.parse(&[0, 0, 0, 0, 7]).unwrap(), empty);
+        assert_eq!(schema.serialize(&empty).unwrap(), [0, 0, 0, 0, 7]);
+        assert_eq!(schema.parse_many(&[0, 0, 0, 0, 7]).count(), 1);
+    }
+
+    #[test]
+    fn test_sequential_layout_nested_struct() {
+        let header = Field {
+            name: "header".to_string(),
+            kind: FieldKind::Struct(vec![
+                seq_field("version", FieldKind::Scalar, 4),
+                seq_field("flags", FieldKind::Scalar, 4),
+            ]),
+            signed: false,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments: vec![],
+            transform: None,
+            condition: None,
+            default: None,
+            constant: None,
+        };
+        let fields = [header, seq_field("payload", FieldKind::Scalar, 8)];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+
+        let parsed = schema.parse(&[0x21, 0x99]).unwrap();
+        assert_eq!(
+            parsed.get("header"),
+            Some(&Value::Struct(BTreeMap::from([
+                ("flags".to_string(), Value::U64(1)),
+                ("version".to_string(), Value::U64(2)),
+            ])))
+        );
+        assert_eq!(parsed.get("payload"), Some(&Value::U64(0x99)));
+    }
+
+    #[test]
+    fn test_spacers_require_sequential_layout() {
+        let fields = [seq_field("", FieldKind::Skip(8), 0)];
+        assert_eq!(
+            Schema::compile(&fields, None).unwrap_err(),
+            CompileError::InvalidFieldKind
+        );
+    }
+
+    fn flagged_extension() -> [Field; 3] {
+        let flags = seq_field("flags", FieldKind::Scalar, 8);
+        let mut ext = seq_field("ext", FieldKind::Scalar, 8);
+        ext.condition = Some(Condition::Mask {
+            field: "flags".to_string(),
+            mask: 0x01,
+            expected: 0x01,
+        });
+        [flags, ext, seq_field("tail", FieldKind::Scalar, 8)]
+    }
+
+    #[test]
+    fn test_conditional_field_in_sequential_layout() {
+        let schema =
+            Schema::compile_with_layout(&flagged_extension(), Layout::Sequential, None).unwrap();
+        assert_eq!(schema.fields[1].placement, compiled::Placement::Cursor);
+        assert_eq!(schema.fields[2].placement, compiled::Placement::Cursor);
+
+        let parsed = schema.parse(&[0x01, 0xEE, 0x77]).unwrap();
+        assert_eq!(parsed.get("ext"), Some(&Value::U64(0xEE)));
+        assert_eq!(parsed.get("tail"), Some(&Value::U64(0x77)));
+        assert_eq!(schema.serialize(&parsed).unwrap(), [0x01, 0xEE, 0x77]);
+
+        let parsed = schema.parse(&[0x00, 0x77]).unwrap();
+        assert_eq!(parsed.get("ext"), Some(&Value::Absent));
+        assert_eq!(parsed.get("tail"), Some(&Value::U64(0x77)));
+        assert_eq!(schema.serialize(&parsed).unwrap(), [0x00, 0x77]);
+    }
+
+    #[test]
+    fn test_serialize_skips_absent_conditional_field() {
+        let schema =
+            Schema::compile_with_layout(&flagged_extension(), Layout::Sequential, None).unwrap();
+
+        // No entry for "ext": not a MissingField while the flag is clear.
+        let obj = BTreeMap::from([
+            ("flags".to_string(), Value::U64(0)),
+            ("tail".to_string(), Value::U64(0x77)),
+        ]);
+        assert_eq!(schema.serialize(&obj).unwrap(), [0x00, 0x77]);
+
+        // A value given for an absent field is ignored.
+        let mut with_ext = obj.clone();
+        with_ext.insert("ext".to_string(), Value::U64(0xEE));
+        assert_eq!(schema.serialize(&with_ext).unwrap(), [0x00, 0x77]);
+
+        let mut flagged = obj;
+        flagged.insert("flags".to_string(), Value::U64(1));
+        assert_eq!(
+            schema.serialize(&flagged),
+            Err(WriteError::MissingField("ext".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_conditional_field_in_absolute_layout() {
+        let [flags, mut ext, _] = flagged_extension();
+        ext.fragments = vec![Fragment::new(8, 8)];
+        ext.condition = Some(Condition::Range {
+            field: "flags".to_string(),
+            min: 2,
+            max: 4,
+        });
+        let schema = Schema::compile(&[flags, ext], None).unwrap();
+
+        assert_eq!(
+            schema.parse(&[3, 0xEE]).unwrap().get("ext"),
+            Some(&Value::U64(0xEE))
+        );
+        assert_eq!(schema.parse(&[5]).unwrap().get("ext"), Some(&Value::Absent));
+    }
+
+    #[test]
+    fn test_compile_rejects_forward_condition_reference() {
+        let [flags, ext, tail] = flagged_extension();
+
+        assert_eq!(
+            Schema::compile_with_layout(&[ext, flags, tail], Layout::Sequential, None)
+                .unwrap_err()
+                .root(),
+            &CompileError::UnknownField("flags".to_string())
+        );
+    }
+
+    fn message_family(default: Option<Vec<Field>>) -> [Field; 3] {
+        let body = Field {
+            name: "body".to_string(),
+            kind: FieldKind::Switch {
+                on: "msg_type".to_string(),
+                cases: BTreeMap::from([
+                    (1, vec![seq_field("temp", FieldKind::Scalar, 16)]),
+                    (
+                        2,
+                        vec![
+                            seq_field("x", FieldKind::Scalar, 8),
+                            seq_field("y", FieldKind::Scalar, 8),
+                            seq_field("z", FieldKind::Scalar, 8),
+                        ],
+                    ),
+                ]),
+                default,
+            },
+            signed: false,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments: vec![],
+            transform: None,
+            condition: None,
+            default: None,
+            constant: None,
+        };
+        [
+            seq_field("msg_type", FieldKind::Scalar, 8),
+            body,
+            seq_field("crc", FieldKind::Scalar, 8),
+        ]
+    }
+
+    #[test]
+    fn test_switch_selects_case_by_discriminator() {
+        let schema =
+            Schema::compile_with_layout(&message_family(None), Layout::Sequential, None).unwrap();
+        assert_eq!(schema.fields[2].placement, compiled::Placement::Cursor);
+
+        let parsed = schema.parse(&[1, 0x01, 0x02, 0xCC]).unwrap();
+        assert_eq!(
+            parsed.get("body"),
+            Some(&Value::Struct(BTreeMap::from([(
+                "temp".to_string(),
+                Value::U64(0x0102)
+            )])))
+        );
+        assert_eq!(parsed.get("crc"), Some(&Value::U64(0xCC)));
+        assert_eq!(schema.serialize(&parsed).unwrap(), [1, 0x01, 0x02, 0xCC]);
+
+        let parsed = schema.parse(&[2, 7, 8, 9, 0xCC]).unwrap();
+        assert_eq!(
+            parsed.get("body"),
+            Some(&Value::Struct(BTreeMap::from([
+                ("x".to_string(), Value::U64(7)),
+                ("y".to_string(), Value::U64(8)),
+                ("z".to_string(), Value::U64(9)),
+            ])))
+        );
+        assert_eq!(schema.serialize(&parsed).unwrap(), [2, 7, 8, 9, 0xCC]);
+    }
+
+    #[test]
+    fn test_switch_without_matching_case() {
+        let schema =
+            Schema::compile_with_layout(&message_family(None), Layout::Sequential, None).unwrap();
+        assert_eq!(
+            schema.parse(&[9, 0, 0, 0]).unwrap_err().root(),
+            &ReadError::UnmatchedCase("msg_type".to_string())
+        );
+
+        let fallback = vec![seq_field("raw", FieldKind::Scalar, 8)];
+        let schema =
+            Schema::compile_with_layout(&message_family(Some(fallback)), Layout::Sequential, None)
+                .unwrap();
+        let parsed = schema.parse(&[9, 0xAB, 0xCC]).unwrap();
+        assert_eq!(
+            parsed.get("body"),
+            Some(&Value::Struct(BTreeMap::from([(
+                "raw".to_string(),
+                Value::U64(0xAB)
+            )])))
+        );
+    }
+
+    #[test]
+    fn test_serialize_switch_checks_case() {
+        let schema =
+            Schema::compile_with_layout(&message_family(None), Layout::Sequential, None).unwrap();
+        let obj = |msg_type| {
+            BTreeMap::from([
+                ("msg_type".to_string(), Value::U64(msg_type)),
+                (
+                    "body".to_string(),
+                    Value::Struct(BTreeMap::from([("temp".to_string(), Value::U64(5))])),
+                ),
+                ("crc".to_string(), Value::U64(0)),
+            ])
+        };
+
+        assert_eq!(schema.serialize(&obj(1)).unwrap(), [1, 0, 5, 0]);
+        assert_eq!(
+            schema.serialize(&obj(2)),
+            Err(WriteError::CaseMismatch("body.temp".to_string()))
+        );
+        assert_eq!(
+            schema.serialize(&obj(3)),
+            Err(WriteError::UnmatchedCase("msg_type".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_compile_rejects_forward_switch_reference() {
+        let [msg_type, body, crc] = message_family(None);
+
+        assert_eq!(
+            Schema::compile_with_layout(&[body, msg_type, crc], Layout::Sequential, None)
+                .unwrap_err()
+                .root(),
+            &CompileError::UnknownField("msg_type".to_string())
+        );
+    }
+
+    fn scalar_at(name: &str, fragments: Vec<Fragment>) -> Field {
+        Field {
+            name: name.to_string(),
+            kind: FieldKind::Scalar,
+            signed: false,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments,
+            transform: None,
+            condition: None,
+            default: None,
+            constant: None,
+        }
+    }
+
+    #[test]
+    fn test_compile_rejects_overlapping_fields() {
+        let fields = [
+            scalar_at("a", vec![Fragment::new(0, 8)]),
+            scalar_at("b", vec![Fragment::new(6, 4)]),
+        ];
+        assert_eq!(
+            Schema::compile(&fields, None).unwrap_err(),
+            CompileError::OverlappingFields {
+                a: "a".to_string(),
+                b: "b".to_string(),
+                bit: 6,
+            }
+        );
+
+        let options = CompileOptions {
+            allow_overlap: true,
+            ..CompileOptions::default()
+        };
+        let schema = Schema::compile_with_options(&fields, options, None).unwrap();
+        assert_eq!(
+            schema.parse(&[0xFF, 0xC0]).unwrap().get("b"),
+            Some(&Value::U64(0xF))
+        );
+    }
+
+    #[test]
+    fn test_compile_rejects_overlapping_fragments_of_one_field() {
+        let fields = [scalar_at(
+            "a",
+            vec![Fragment::new(0, 8), Fragment::new(4, 8)],
+        )];
+        assert_eq!(
+            Schema::compile(&fields, None).unwrap_err(),
+            CompileError::OverlappingFields {
+                a: "a".to_string(),
+                b: "a".to_string(),
+                bit: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn test_compile_rejects_overlapping_array_elements() {
+        // Each element spans bits 0..4 and 8..12 of its window, so element 1
+        // (starting at bit 8) runs into the second fragment of element 0.
+        let mut items = scalar_at("items", vec![Fragment::new(0, 4), Fragment::new(8, 4)]);
+        items.kind = FieldKind::Array(ArraySpec {
+            count: ArrayCount::Fixed(2),
+            stride_bits: 8,
+            offset_bits: 0,
+            element: None,
+        });
+        assert_eq!(
+            Schema::compile(&[items.clone()], None).unwrap_err(),
+            CompileError::OverlappingFields {
+                a: "items[0]".to_string(),
+                b: "items[1]".to_string(),
+                bit: 8,
+            }
+        );
+
+        let len = scalar_at("len", vec![Fragment::new(0, 8)]);
+        if let FieldKind::Array(spec) = &mut items.kind {
+            spec.count = ArrayCount::FromField {
+                field: "len".to_string(),
+                adjust: 0,
+            };
+            spec.offset_bits = 8;
+        }
+        assert!(matches!(
+            Schema::compile(&[len, items], None),
+            Err(CompileError::OverlappingFields { .. })
+        ));
+    }
+
+    #[test]
+    fn test_overlap_checks_do_not_walk_array_elements() {
+        let array = |name: &str, offset_bits| {
+            let mut field = scalar_at(name, vec![Fragment::new(0, 8)]);
+            field.kind = FieldKind::Array(ArraySpec {
+                count: ArrayCount::Fixed(1 << 40),
+                stride_bits: 16,
+                offset_bits,
+                element: None,
+            });
+            field
+        };
+        // Interleaved arrays share no bits, however long they are.
+        assert!(Schema::compile(&[array("even", 0), array("odd", 8)], None).is_ok());
+
+        let fields = [
+            array("even", 0),
+            scalar_at("tail", vec![Fragment::new(84, 8)]),
+        ];
+        assert_eq!(
+            Schema::compile(&fields, None).unwrap_err(),
+            CompileError::OverlappingFields {
+                a: "even[5]".to_string(),
+                b: "tail".to_string(),
+                bit: 84,
+            }
+        );
+    }
+
+    #[test]
+    fn test_switch_cases_may_share_bits() {
+        // Case 1 ("temp") and case 2 ("x", "y", "z") both start right after "msg_type".
+        assert!(
+            Schema::compile_with_layout(&message_family(None), Layout::Sequential, None).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_uncovered_bits() {
+        let fields = [
+            scalar_at("a", vec![Fragment::new(0, 4)]),
+            scalar_at("b", vec![Fragment::new(8, 4), Fragment::new(16, 8)]),
+        ];
+        let schema = Schema::compile(&fields, None).unwrap();
+        assert_eq!(schema.uncovered_bits(), vec![4..8, 12..16]);
+
+        let fields = [
+            seq_field("a", FieldKind::Scalar, 3),
+            seq_field("", FieldKind::Align(8), 0),
+            seq_field("b", FieldKind::Scalar, 8),
+        ];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+        assert!(schema.uncovered_bits().is_empty());
+    }
+
+    #[test]
+    fn test_parse_error_names_field_element_and_bits() {
+        let schema = Schema::compile(&[sensor_block()], None).unwrap();
+
+        let err = schema.parse(&[0; 5]).unwrap_err();
+        assert_eq!(
+            err,
+            ReadError::Context {
+                context: crate::errors::ErrorContext {
+                    field: "sensors[1].temp".to_string(),
+                    element: Some(1),
+                    fragment: Some(0),
+                    bit_range: Some(40..56),
+                    available_bits: Some(40),
+                },
+                error: Box::new(ReadError::PacketTooShort),
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "field 'sensors[1].temp', fragment 0, bits 40..56 of 40 available: \
+             input data ends before all fields could be read"
+        );
+    }
+
+    #[test]
+    fn test_compile_error_names_field_and_fragment() {
+        let fields = [scalar_at(
+            "a",
+            vec![Fragment::new(0, 4), Fragment::new(4, 0)],
+        )];
+        let err = Schema::compile(&fields, None).unwrap_err();
+
+        assert_eq!(err.root(), &CompileError::InvalidFragment);
+        let context = err.context().unwrap();
+        assert_eq!(context.field, "a");
+        assert_eq!(context.fragment, Some(1));
+        assert_eq!(context.bit_range, Some(4..4));
+    }
+
+    #[test]
+    fn test_parse_many_reads_records_back_to_back() {
+        let fields = [
+            scalar_at("a", vec![Fragment::new(0, 4)]),
+            scalar_at("b", vec![Fragment::new(4, 12)]),
+        ];
+        let schema = Schema::compile(&fields, None).unwrap();
+
+        let records: Vec<_> = schema.parse_many(&[0x10, 0x02, 0x20, 0x03, 0x30]).collect();
+        assert_eq!(records.len(), 3);
+        let record = records[1].as_ref().unwrap();
+        assert_eq!(record.get("a"), Some(&Value::U64(2)));
+        assert_eq!(record.get("b"), Some(&Value::U64(3)));
+        assert_eq!(
+            records[2].as_ref().unwrap_err().root(),
+            &ReadError::PacketTooShort
+        );
+        let context = records[2].as_ref().unwrap_err().context().unwrap();
+        assert_eq!(context.field, "b");
+        assert_eq!(context.bit_range, Some(36..48));
+    }
+
+    #[test]
+    fn test_parse_many_checks_static_records_once() {
+        let fields = [
+            seq_field("a", FieldKind::Scalar, 8),
+            seq_field("", FieldKind::Skip(8), 0),
+        ];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+        assert_eq!(schema.static_record_bytes(), Some(2));
+
+        let records: Vec<_> = schema.parse_many(&[1, 0xFF, 2, 0xFF, 3]).collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1].as_ref().unwrap()["a"], Value::U64(2));
+        // The last record's value fits, but its trailing spacer does not.
+        let err = records[2].as_ref().unwrap_err();
+        assert_eq!(err.root(), &ReadError::PacketTooShort);
+        assert_eq!(err.context().unwrap().bit_range, Some(32..48));
+
+        let mut conditional = seq_field("b", FieldKind::Scalar, 8);
+        conditional.condition = Some(Condition::Equals {
+            field: "a".to_string(),
+            value: 1,
+        });
+        let schema = Schema::compile_with_layout(
+            &[seq_field("a", FieldKind::Scalar, 8), conditional],
+            Layout::Sequential,
+            None,
+        )
+        .unwrap();
+        assert_eq!(schema.static_record_bytes(), None);
+    }
+
+    #[test]
+    fn test_parse_table_reads_static_records_into_columns() {
+        let temp = Field {
+            signed: true,
+            ..seq_field("temp", FieldKind::Scalar, 8)
+        };
+        let fields = [
+            seq_field("id", FieldKind::Scalar, 8),
+            seq_field("", FieldKind::Skip(8), 0),
+            temp,
+        ];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+        let data = [1, 0xFF, 0xFE, 2, 0xFF, 0x14];
+
+        let table = schema.parse_table(&data).unwrap();
+        let mut builder = crate::columnar::TableBuilder::new(&schema);
+        for record in schema.parse_many(&data) {
+            builder.push(record.unwrap()).unwrap();
+        }
+        assert_eq!(table, builder.finish());
+        assert_eq!(
+            table.column("temp").unwrap().values,
+            crate::columnar::ColumnValues::I64(vec![-2, 20])
+        );
+
+        // A trailing partial record fails as it does in `parse_many`.
+        let partial = schema.parse_many(&data[..5]).last().unwrap().unwrap_err();
+        assert_eq!(
+            schema.parse_table(&data[..5]).unwrap_err(),
+            ColumnError::Read(partial)
+        );
+    }
+
+    #[test]
+    fn test_parse_many_advances_by_record_length() {
+        let fields = [
+            scalar_at("len", vec![Fragment::new(0, 8)]),
+            Field {
+                kind: FieldKind::Array(ArraySpec {
+                    count: ArrayCount::FromField {
+                        field: "len".to_string(),
+                        adjust: 0,
+                    },
+                    stride_bits: 8,
+                    offset_bits: 8,
+                    element: None,
+                }),
+                ..scalar_at("items", vec![Fragment::new(0, 8)])
+            },
+        ];
+        let schema = Schema::compile(&fields, None).unwrap();
+
+        let columns = schema.parse_columns(&[2, 7, 8, 0, 1, 9]).unwrap();
+        assert_eq!(
+            columns["len"],
+            vec![Value::U64(2), Value::U64(0), Value::U64(1)]
+        );
+        assert_eq!(
+            columns["items"],
+            vec![
+                Value::Array(vec![Value::U64(7), Value::U64(8)]),
+                Value::Array(vec![]),
+                Value::Array(vec![Value::U64(9)]),
+            ]
+        );
+        assert_eq!(
+            schema.parse_columns(&[2, 7]).unwrap_err().root(),
+            &ReadError::PacketTooShort
+        );
+    }
+
+    #[test]
+    fn test_field_handle_reads_single_fields() {
+        let header = Field {
+            kind: FieldKind::Struct(vec![
+                seq_field("version", FieldKind::Scalar, 4),
+                seq_field("flags", FieldKind::Scalar, 4),
+            ]),
+            fragments: vec![],
+            ..seq_field("header", FieldKind::Scalar, 0)
+        };
+        let temp = Field {
+            signed: true,
+            ..seq_field("temp", FieldKind::Scalar, 8)
+        };
+        let fields = [header, seq_field("payload", FieldKind::Scalar, 8), temp];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+        let data = [0x21, 0x99, 0xFE];
+
+        let flags = schema.field_handle("header.flags").unwrap();
+        assert_eq!(flags.read(&schema, &data), Ok(Value::U64(1)));
+        let temp = schema.field_handle("temp").unwrap();
+        assert_eq!(temp.read_i64(&schema, &data), Ok(-2));
+        assert_eq!(temp.read_u64(&schema, &data), Ok(0xFE));
+        let header = schema.field_handle("header").unwrap();
+        assert_eq!(
+            header.read(&schema, &data),
+            Ok(schema.parse(&data).unwrap()["header"].clone())
+        );
+        assert_eq!(
+            header.read_u64(&schema, &data).unwrap_err().root(),
+            &ReadError::NotScalar
+        );
+
+        // Only the bytes of the field itself need to be present.
+        let payload = schema.field_handle("payload").unwrap();
+        assert_eq!(payload.read_u64(&schema, &data[..2]), Ok(0x99));
+        let err = temp.read(&schema, &data[..2]).unwrap_err();
+        assert_eq!(err.root(), &ReadError::PacketTooShort);
+        assert_eq!(err.context().unwrap().field, "temp");
+    }
+
+    #[test]
+    fn test_field_handle_rejects_unknown_and_dependent_fields() {
+        let fields = [
+            scalar_at("len", vec![Fragment::new(0, 8)]),
+            Field {
+                kind: FieldKind::Array(ArraySpec {
+                    count: ArrayCount::FromField {
+                        field: "len".to_string(),
+                        adjust: 0,
+                    },
+                    stride_bits: 8,
+                    offset_bits: 8,
+                    element: None,
+                }),
+                ..scalar_at("items", vec![Fragment::new(0, 8)])
+            },
+        ];
+        let schema = Schema::compile(&fields, None).unwrap();
+
+        assert_eq!(
+            schema.field_handle("missing"),
+            Err(CompileError::UnknownField("missing".to_string()))
+        );
+        assert_eq!(
+            schema.field_handle("len.x"),
+            Err(CompileError::UnknownField("len.x".to_string()))
+        );
+        let err = schema.field_handle("items").unwrap_err();
+        assert_eq!(err.root(), &CompileError::InvalidFieldKind);
+        assert_eq!(err.context().unwrap().field, "items");
+    }
+
+    #[test]
+    fn test_write_field_keeps_uncovered_bits() {
+        let fields = [
+            scalar_at("version", vec![Fragment::new(0, 4)]),
+            scalar_at("id", vec![Fragment::new(8, 4), Fragment::new(16, 4)]),
+        ];
+        let schema = Schema::compile(&fields, None).unwrap();
+        let mut packet = [0x3A, 0x5C, 0xDE];
+
+        schema
+            .write_field(&mut packet, "id", &Value::U64(0x81))
+            .unwrap();
+        assert_eq!(packet, [0x3A, 0x8C, 0x1E]);
+        schema
+            .write_field(&mut packet, "version", &Value::U64(7))
+            .unwrap();
+        assert_eq!(packet, [0x7A, 0x8C, 0x1E]);
+    }
+
+    #[test]
+    fn test_patch_writes_nested_fields_or_nothing() {
+        let header = Field {
+            kind: FieldKind::Struct(vec![
+                seq_field("version", FieldKind::Scalar, 4),
+                seq_field("flags", FieldKind::Scalar, 4),
+            ]),
+            fragments: vec![],
+            ..seq_field("header", FieldKind::Scalar, 0)
+        };
+        let fields = [header, seq_field("payload", FieldKind::Scalar, 8)];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+        let mut packet = [0x21, 0x99];
+
+        let patch = BTreeMap::from([
+            ("header.flags".to_string(), Value::U64(0xF)),
+            ("payload".to_string(), Value::U64(0x42)),
+        ]);
+        schema.patch(&mut packet, &patch).unwrap();
+        assert_eq!(packet, [0x2F, 0x42]);
+
+        let patch = BTreeMap::from([
+            ("header.version".to_string(), Value::U64(3)),
+            ("payload".to_string(), Value::String("x".to_string())),
+        ]);
+        let err = schema.patch(&mut packet, &patch).unwrap_err();
+        assert!(matches!(err, WriteError::UnsupportedValue { field, .. } if field == "payload"));
+        assert_eq!(packet, [0x2F, 0x42]);
+    }
+
+    #[test]
+    fn test_write_field_rejects_unknown_and_dependent_fields() {
+        let fields = [
+            scalar_at("len", vec![Fragment::new(0, 8)]),
+            Field {
+                kind: FieldKind::Array(ArraySpec {
+                    count: ArrayCount::FromField {
+                        field: "len".to_string(),
+                        adjust: 0,
+                    },
+                    stride_bits: 8,
+                    offset_bits: 8,
+                    element: None,
+                }),
+                ..scalar_at("items", vec![Fragment::new(0, 8)])
+            },
+        ];
+        let schema = Schema::compile(&fields, None).unwrap();
+        let mut packet = [1, 2];
+
+        assert_eq!(
+            schema.write_field(&mut packet, "missing", &Value::U64(0)),
+            Err(WriteError::UnknownField("missing".to_string()))
+        );
+        assert_eq!(
+            schema.write_field(&mut packet, "items", &Value::Array(vec![])),
+            Err(WriteError::DependentField("items".to_string()))
+        );
+        assert_eq!(
+            schema.write_field(&mut [], "len", &Value::U64(0)),
+            Err(WriteError::OutOfBounds)
+        );
+        assert_eq!(packet, [1, 2]);
+    }
+
+    #[test]
+    fn test_serialize_with_defaults_fills_missing_fields() {
+        let header = Field {
+            kind: FieldKind::Struct(vec![
+                Field {
+                    default: Some(Value::U64(2)),
+                    ..seq_field("version", FieldKind::Scalar, 4)
+                },
+                seq_field("flags", FieldKind::Scalar, 4),
+            ]),
+            fragments: vec![],
+            ..seq_field("header", FieldKind::Scalar, 0)
+        };
+        let fields = [
+            header,
+            Field {
+                default: Some(Value::U64(0xAA)),
+                ..seq_field("cmd", FieldKind::Scalar, 8)
+            },
+        ];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+        let options = SerializeOptions {
+            use_defaults: true,
+            ..SerializeOptions::default()
+        };
+        let header = |flags| Value::Struct(BTreeMap::from([("flags".to_string(), flags)]));
+
+        let obj = BTreeMap::from([("header".to_string(), header(Value::U64(1)))]);
+        assert_eq!(
+            schema.serialize_with_options(&obj, options),
+            Ok(vec![0x21, 0xAA])
+        );
+        assert_eq!(
+            schema.serialize(&obj),
+            Err(WriteError::MissingField("header.version".to_string()))
+        );
+
+        let obj = BTreeMap::from([("cmd".to_string(), Value::U64(3))]);
+        assert_eq!(
+            schema.serialize_with_options(&obj, options),
+            Err(WriteError::MissingField("header.flags".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_serialize_with_template_keeps_missing_values_and_bits() {
+        let fields = [
+            scalar_at("seq", vec![Fragment::new(0, 4)]),
+            Field {
+                default: Some(Value::U64(1)),
+                ..scalar_at("cmd", vec![Fragment::new(8, 8)])
+            },
+        ];
+        let schema = Schema::compile(&fields, None).unwrap();
+        let obj = BTreeMap::from([("seq".to_string(), Value::U64(9))]);
+        let options = SerializeOptions {
+            use_defaults: true,
+            template: Some(&[0x1F, 0x42]),
+        };
+
+        assert_eq!(
+            schema.serialize_with_options(&obj, options),
+            Ok(vec![0x9F, 0x42])
+        );
+        let options = SerializeOptions {
+            template: Some(&[0x1F]),
+            ..options
+        };
+        assert!(matches!(
+            schema.serialize_with_options(&obj, options),
+            Err(WriteError::Template(_))
+        ));
+    }
+
+    #[test]
+    fn test_constant_fields_are_checked_and_written() {
+        let sync = Field {
+            constant: Some(Value::U64(0xEB90)),
+            ..seq_field("sync", FieldKind::Scalar, 16)
+        };
+        let fields = [sync, seq_field("len", FieldKind::Scalar, 8)];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+
+        let obj = BTreeMap::from([("len".to_string(), Value::U64(3))]);
+        let bytes = schema.serialize(&obj).unwrap();
+        assert_eq!(bytes, [0xEB, 0x90, 0x03]);
+        assert_eq!(schema.parse(&bytes).unwrap()["sync"], Value::U64(0xEB90));
+
+        let err = schema.parse(&[0xEB, 0x91, 0x03]).unwrap_err();
+        assert_eq!(
+            err.root(),
+            &ReadError::ConstantMismatch {
+                field: "sync".to_string(),
+                expected: ExactValue(Value::U64(0xEB90)),
+                found: ExactValue(Value::U64(0xEB91)),
+            }
+        );
+        assert_eq!(err.context().unwrap().field, "sync");
+    }
+
+    #[test]
+    fn test_constants_inside_structs_and_arrays() {
+        let sync = Field {
+            constant: Some(Value::U64(0xEB90)),
+            ..seq_field("sync", FieldKind::Scalar, 16)
+        };
+        let header = Field {
+            name: "header".to_string(),
+            kind: FieldKind::Struct(vec![sync, seq_field("len", FieldKind::Scalar, 8)]),
+            signed: false,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments: vec![],
+            transform: None,
+            condition: None,
+            default: None,
+            constant: None,
+        };
+        let magic = Field {
+            constant: Some(Value::Array(vec![Value::U64(0xCA), Value::U64(0xFE)])),
+            ..seq_field(
+                "magic",
+                FieldKind::Array(ArraySpec {
+                    count: ArrayCount::Fixed(2),
+                    stride_bits: 8,
+                    offset_bits: 0,
+                    element: None,
+                }),
+                8,
+            )
+        };
+        let schema =
+            Schema::compile_with_layout(&[header, magic], Layout::Sequential, None).unwrap();
+
+        // Both constants are written when the input leaves them out.
+        let obj = BTreeMap::from([(
+            "header".to_string(),
+            Value::Struct(BTreeMap::from([("len".to_string(), Value::U64(3))])),
+        )]);
+        let bytes = schema.serialize(&obj).unwrap();
+        assert_eq!(bytes, [0xEB, 0x90, 0x03, 0xCA, 0xFE]);
+        #[cfg(feature = "serde")]
+        {
+            #[derive(serde::Serialize)]
+            struct Header {
+                len: u8,
+            }
+            #[derive(serde::Serialize)]
+            struct Framed {
+                header: Header,
+            }
+            let framed = Framed {
+                header: Header { len: 3 },
+            };
+            assert_eq!(schema.serialize_from(&framed).unwrap(), bytes);
+        }
+        let parsed = schema.parse(&bytes).unwrap();
+        assert_eq!(
+            parsed["header"],
+            Value::Struct(BTreeMap::from([
+                ("sync".to_string(), Value::U64(0xEB90)),
+                ("len".to_string(), Value::U64(3)),
+            ]))
+        );
+
+        let err = schema.parse(&[0xEB, 0x91, 0x03, 0xCA, 0xFE]).unwrap_err();
+        assert_eq!(
+            err.root(),
+            &ReadError::ConstantMismatch {
+                field: "sync".to_string(),
+                expected: ExactValue(Value::U64(0xEB90)),
+                found: ExactValue(Value::U64(0xEB91)),
+            }
+        );
+        assert_eq!(err.context().unwrap().field, "header.sync");
+
+        let err = schema.parse(&[0xEB, 0x90, 0x03, 0xCA, 0xFF]).unwrap_err();
+        assert_eq!(
+            err.root(),
+            &ReadError::ConstantMismatch {
+                field: "magic".to_string(),
+                expected: ExactValue(Value::Array(vec![Value::U64(0xCA), Value::U64(0xFE)])),
+                found: ExactValue(Value::Array(vec![Value::U64(0xCA), Value::U64(0xFF)])),
+            }
+        );
+        assert_eq!(err.context().unwrap().field, "magic");
+    }
+
+    #[test]
+    fn test_reserved_bits_are_filled_and_left_out_of_output() {
+        let fields = [
+            scalar_at("a", vec![Fragment::new(0, 4)]),
+            Field {
+                kind: FieldKind::Reserved {
+                    fill: 0b1010,
+                    check: false,
+                },
+                ..scalar_at("reserved", vec![Fragment::new(4, 4)])
+            },
+            scalar_at("b", vec![Fragment::new(8, 8)]),
+            Field {
+                kind: FieldKind::Reserved {
+                    fill: 0,
+                    check: true,
+                },
+                ..scalar_at("must_be_zero", vec![Fragment::new(16, 8)])
+            },
+        ];
+        let schema = Schema::compile(&fields, None).unwrap();
+
+        let obj = BTreeMap::from([
+            ("a".to_string(), Value::U64(3)),
+            ("b".to_string(), Value::U64(0x42)),
+        ]);
+        let bytes = schema.serialize(&obj).unwrap();
+        assert_eq!(bytes, [0x3A, 0x42, 0x00]);
+        assert_eq!(schema.parse(&[0x3F, 0x42, 0x00]), Ok(obj));
+        assert_eq!(
+            schema.parse(&[0x3A, 0x42]).unwrap_err().root(),
+            &ReadError::PacketTooShort
+        );
+        assert_eq!(
+            schema.parse(&[0x3A, 0x42, 0x01]).unwrap_err().root(),
+            &ReadError::ConstantMismatch {
+                field: "must_be_zero".to_string(),
+                expected: ExactValue(Value::U64(0)),
+                found: ExactValue(Value::U64(1)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_reserved_bits_count_in_overlap_checks() {
+        let reserved = |fill| Field {
+            kind: FieldKind::Reserved { fill, check: false },
+            ..scalar_at("reserved", vec![Fragment::new(4, 4)])
+        };
+        let fields = [scalar_at("a", vec![Fragment::new(0, 6)]), reserved(0)];
+        assert_eq!(
+            Schema::compile(&fields, None).unwrap_err(),
+            CompileError::OverlappingFields {
+                a: "a".to_string(),
+                b: "reserved".to_string(),
+                bit: 4,
+            }
+        );
+
+        let err = Schema::compile(&[reserved(0x10)], None).unwrap_err();
+        assert_eq!(err.root(), &CompileError::InvalidFieldKind);
+    }
+
+    #[test]
+    fn test_spacers_after_variable_length_field_take_their_size() {
+        let [len, mut items] = counted_items(0);
+        if let FieldKind::Array(spec) = &mut items.kind {
+            spec.offset_bits = 0;
+        }
+        let fields = [
+            len,
+            items,
+            seq_field("", FieldKind::Skip(8), 0),
+            Field {
+                kind: FieldKind::Reserved {
+                    fill: 0xF,
+                    check: false,
+                },
+                ..seq_field("reserved", FieldKind::Scalar, 4)
+            },
+            seq_field("crc", FieldKind::Scalar, 4),
+        ];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+
+        let parsed = schema.parse(&[1, 0xA1, 0xEE, 0xF5]).unwrap();
+        assert_eq!(parsed.get("crc"), Some(&Value::U64(5)));
+        assert_eq!(schema.serialize(&parsed).unwrap(), [1, 0xA1, 0, 0xF5]);
+    }
+
+    fn bytes_field(
+        name: &str,
+        offset_bits: usize,
+        len_bits: usize,
+        padding: BytesPadding,
+    ) -> Field {
+        Field {
+            kind: FieldKind::Bytes {
+                offset_bits,
+                len_bits,
+                padding,
+            },
+            ..seq_field(name, FieldKind::Scalar, 0)
+        }
+    }
+
+    #[test]
+    fn test_bytes_fields_read_unaligned_ranges() {
+        let fields = [
+            bytes_field("key", 4, 12, BytesPadding::Trailing),
+            bytes_field("tail", 16, 12, BytesPadding::Leading),
+            bytes_field("hash", 32, 16, BytesPadding::Trailing),
+        ];
+        let schema = Schema::compile(&fields, None).unwrap();
+
+        let data = [0x0B, 0xCD, 0xEF, 0x10, 0x12, 0x34];
+        let parsed = schema.parse(&data).unwrap();
+        assert_eq!(
+            parsed,
+            BTreeMap::from([
+                ("key".to_string(), Value::Bytes(vec![0xBC, 0xD0])),
+                ("tail".to_string(), Value::Bytes(vec![0x0E, 0xF1])),
+                ("hash".to_string(), Value::Bytes(vec![0x12, 0x34])),
+            ])
+        );
+        assert_eq!(schema.serialize(&parsed).unwrap(), data);
+
+        let mut obj = parsed;
+        obj.insert("hash".to_string(), Value::Bytes(vec![0x12]));
+        assert_eq!(
+            schema.serialize(&obj).unwrap_err(),
+            WriteError::InvalidValue
+        );
+        obj.insert("hash".to_string(), Value::U64(0x1234));
+        assert!(matches!(
+            schema.serialize(&obj).unwrap_err(),
+            WriteError::UnsupportedValue { field, .. } if field == "hash"
+        ));
+
+        let err = Schema::compile(&[bytes_field("empty", 0, 0, BytesPadding::Trailing)], None)
+            .unwrap_err();
+        assert_eq!(err.root(), &CompileError::InvalidFieldSize);
+    }
+
+    #[test]
+    fn test_bytes_field_after_variable_length_field() {
+        let [len, mut items] = counted_items(0);
+        if let FieldKind::Array(spec) = &mut items.kind {
+            spec.offset_bits = 0;
+        }
+        let fields = [
+            len,
+            items,
+            bytes_field("payload", 4, 20, BytesPadding::Leading),
+        ];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+        assert_eq!(schema.fields[2].placement, compiled::Placement::Cursor);
+
+        let data = [1, 0xA1, 0x0A, 0xBC, 0xDE];
+        let parsed = schema.parse(&data).unwrap();
+        assert_eq!(parsed["payload"], Value::Bytes(vec![0x0A, 0xBC, 0xDE]));
+        assert_eq!(schema.serialize(&parsed).unwrap(), data);
+    }
+
+    #[cfg(feature = "transform")]
+    #[test]
+    fn test_bytes_field_decodes_text() {
+        use crate::transform::{Base, Encoding, Transform};
+
+        let mut label = Transform::new(Base::Bytes);
+        label
+            .set_encoding(Encoding::Ascii)
+            .set_zero_terminated(true);
+        let fields = [Field {
+            transform: Some(label),
+            ..bytes_field("label", 0, 32, BytesPadding::Trailing)
+        }];
+        let schema = Schema::compile(&fields, None).unwrap();
+
+        let obj = BTreeMap::from([("label".to_string(), Value::String("ab".to_string()))]);
+        let bytes = schema.serialize_transformed(&obj).unwrap();
+        assert_eq!(bytes, vec![b'a', b'b', 0, 0]);
+        let parsed = schema
+            .apply_transforms(schema.parse(&bytes).unwrap())
+            .unwrap();
+        assert_eq!(parsed, obj);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_parse_into_reads_bytes_fields() {
+        #[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+        struct Keyed {
+            key: Vec<u8>,
+            tail: Vec<u8>,
+        }
+
+        let fields = [
+            bytes_field("key", 4, 12, BytesPadding::Trailing),
+            bytes_field("tail", 16, 12, BytesPadding::Leading),
+        ];
+        let schema = Schema::compile(&fields, None).unwrap();
+        let data = [0x0B, 0xCD, 0xEF, 0x10];
+        let keyed: Keyed = schema.parse_into(&data).unwrap();
+        assert_eq!(
+            keyed,
+            Keyed {
+                key: vec![0xBC, 0xD0],
+                tail: vec![0x0E, 0xF1],
+            }
+        );
+        assert_eq!(schema.serialize_from(&keyed).unwrap(), data);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_parse_into_reads_string_fields() {
+        #[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+        struct Named {
+            name: String,
+            tail: u8,
+        }
+
+        let fields = [
+            string_field("name", StringFraming::NullTerminated, Encoding::Utf8),
+            seq_field("tail", FieldKind::Scalar, 8),
+        ];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+        let named: Named = schema.parse_into(b"abc\0\x05").unwrap();
+        assert_eq!(
+            named,
+            Named {
+                name: "abc".to_string(),
+                tail: 5,
+            }
+        );
+        assert_eq!(schema.serialize_from(&named).unwrap(), b"abc\0\x05");
+    }
+
+    fn string_field(name: &str, framing: StringFraming, encoding: Encoding) -> Field {
+        Field {
+            kind: FieldKind::String {
+                offset_bits: 0,
+                framing,
+                encoding,
+            },
+            ..seq_field(name, FieldKind::Scalar, 0)
+        }
+    }
+
+    #[test]
+    fn test_string_fields_parse_and_serialize_each_framing() {
+        let fields = [
+            string_field(
+                "name",
+                StringFraming::LengthPrefixed { prefix_bits: 8 },
+                Encoding::Utf8,
+            ),
+            string_field("label", StringFraming::NullTerminated, Encoding::Ascii),
+            string_field(
+                "code",
+                StringFraming::Fixed {
+                    len_bytes: 4,
+                    pad: b' ',
+                },
+                Encoding::Ascii,
+            ),
+            seq_field("tail", FieldKind::Scalar, 8),
+        ];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+        assert_eq!(schema.fields[1].placement, compiled::Placement::Cursor);
+
+        let data = b"\x03h\xC3\xA9ok\0AB  \x7F";
+        let parsed = schema.parse(data).unwrap();
+        assert_eq!(
+            parsed,
+            BTreeMap::from([
+                ("name".to_string(), Value::String("hé".to_string())),
+                ("label".to_string(), Value::String("ok".to_string())),
+                ("code".to_string(), Value::String("AB".to_string())),
+                ("tail".to_string(), Value::U64(0x7F)),
+            ])
+        );
+        assert_eq!(schema.serialize(&parsed).unwrap(), data);
+
+        let mut obj = parsed;
+        obj.insert("code".to_string(), Value::String("ABCDE".to_string()));
+        assert_eq!(
+            schema.serialize(&obj).unwrap_err(),
+            WriteError::InvalidValue
+        );
+        obj.insert("code".to_string(), Value::String("é".to_string()));
+        assert_eq!(
+            schema.serialize(&obj).unwrap_err(),
+            WriteError::InvalidValue
+        );
+        obj.insert("code".to_string(), Value::U64(1));
+        assert!(matches!(
+            schema.serialize(&obj).unwrap_err(),
+            WriteError::UnsupportedValue { field, .. } if field == "code"
+        ));
+    }
+
+    #[test]
+    fn test_utf16_string_fields_frame_by_code_unit() {
+        let fields = [
+            string_field("name", StringFraming::NullTerminated, Encoding::Utf16Le),
+            string_field(
+                "code",
+                StringFraming::Fixed {
+                    len_bytes: 6,
+                    pad: 0,
+                },
+                Encoding::Utf16Be,
+            ),
+            seq_field("tail", FieldKind::Scalar, 8),
+        ];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+
+        // "Ā" is 0x0100, so its low byte is zero without ending the string.
+        let data = b"A\0\0\x01\0\0\0Z\0\0\0\0\x7F";
+        let parsed = schema.parse(data).unwrap();
+        assert_eq!(
+            parsed,
+            BTreeMap::from([
+                ("name".to_string(), Value::String("A\u{100}".to_string())),
+                ("code".to_string(), Value::String("Z".to_string())),
+                ("tail".to_string(), Value::U64(0x7F)),
+            ])
+        );
+        assert_eq!(schema.serialize(&parsed).unwrap(), data);
+    }
+
+    #[test]
+    fn test_string_fields_reject_bad_data_and_framing() {
+        let schema = Schema::compile(
+            &[string_field(
+                "label",
+                StringFraming::NullTerminated,
+                Encoding::Utf8,
+            )],
+            None,
+        )
+        .unwrap();
+        let err = schema.parse(b"abc").unwrap_err();
+        assert_eq!(err.root(), &ReadError::PacketTooShort);
+        assert_eq!(err.context().unwrap().field, "label");
+        let err = schema.parse(b"\xFF\0").unwrap_err();
+        assert_eq!(err.root(), &ReadError::InvalidEncoding);
+
+        let short = [string_field(
+            "name",
+            StringFraming::LengthPrefixed { prefix_bits: 2 },
+            Encoding::Utf8,
+        )];
+        let schema = Schema::compile(&short, None).unwrap();
+        let obj = BTreeMap::from([("name".to_string(), Value::String("abcd".to_string()))]);
+        assert_eq!(
+            schema.serialize(&obj).unwrap_err(),
+            WriteError::InvalidValue
+        );
+        assert_eq!(
+            schema.parse(&[0b1100_0000, b'a']).unwrap_err().root(),
+            &ReadError::PacketTooShort
+        );
+
+        let framings = [
+            StringFraming::LengthPrefixed { prefix_bits: 0 },
+            StringFraming::LengthPrefixed { prefix_bits: 65 },
+            StringFraming::Fixed {
+                len_bytes: 0,
+                pad: 0,
+            },
+        ];
+        for framing in framings {
+            let err =
+                Schema::compile(&[string_field("s", framing, Encoding::Utf8)], None).unwrap_err();
+            assert_eq!(err.root(), &CompileError::InvalidFieldSize);
+        }
+    }
+
+    fn checksum_field(
+        name: &str,
+        algorithm: ChecksumAlgorithm,
+        covers: ChecksumCoverage,
+        fragments: Vec<Fragment>,
+    ) -> Field {
+        Field {
+            kind: FieldKind::Checksum {
+                algorithm,
+                covers,
+                check: true,
+            },
+            ..scalar_at(name, fragments)
+        }
+    }
+
+    #[test]
+    fn test_checksum_is_computed_on_serialize_and_verified_on_parse() {
+        let fields = [
+            scalar_at("id", vec![Fragment::new(0, 8)]),
+            scalar_at("value", vec![Fragment::new(8, 32)]),
+            checksum_field(
+                "crc",
+                ChecksumAlgorithm::Crc(Crc::CRC_16_CCITT_FALSE),
+                ChecksumCoverage::Bits(0..40),
+                vec![Fragment::new(40, 16)],
+            ),
+        ];
+        let schema = Schema::compile(&fields, None).unwrap();
+
+        let obj = BTreeMap::from([
+            ("id".to_string(), Value::U64(u64::from(b'1'))),
+            ("value".to_string(), Value::U64(0x3233_3435)),
+        ]);
+        let bytes = schema.serialize(&obj).unwrap();
+        assert_eq!(bytes, [b'1', b'2', b'3', b'4', b'5', 0x45, 0x60]);
+        assert_eq!(
+            schema.parse(&bytes).unwrap().get("crc"),
+            Some(&Value::U64(0x4560))
+        );
+
+        let mut corrupted = bytes.clone();
+        corrupted[2] ^= 0x01;
+        let err = schema.parse(&corrupted).unwrap_err();
+        assert!(matches!(
+            err.root(),
+            ReadError::ChecksumMismatch { field, found: 0x4560, .. } if field == "crc"
+        ));
+        assert_eq!(err.context().unwrap().field, "crc");
+        assert_eq!(
+            schema.parse_table(&corrupted).unwrap_err(),
+            ColumnError::Read(err)
+        );
+
+        let mut unchecked = fields;
+        if let FieldKind::Checksum { check, .. } = &mut unchecked[2].kind {
+            *check = false;
+        }
+        let schema = Schema::compile(&unchecked, None).unwrap();
+        assert_eq!(
+            schema.parse(&corrupted).unwrap().get("crc"),
+            Some(&Value::U64(0x4560))
+        );
+    }
+
+    #[test]
+    fn test_checksum_covers_fields_after_variable_length_field() {
+        let [_, mut items] = counted_items(0);
+        if let FieldKind::Array(spec) = &mut items.kind {
+            spec.offset_bits = 0;
+        }
+        let fields = [
+            checksum_field(
+                "sum",
+                ChecksumAlgorithm::Sum8,
+                ChecksumCoverage::Fields(vec!["len".to_string(), "items".to_string()]),
+                vec![Fragment::new(0, 8)],
+            ),
+            seq_field("len", FieldKind::Scalar, 8),
+            items,
+            seq_field("trailer", FieldKind::Scalar, 8),
+        ];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+
+        let obj = BTreeMap::from([
+            ("len".to_string(), Value::U64(2)),
+            (
+                "items".to_string(),
+                Value::Array(vec![Value::U64(0x10), Value::U64(0xF5)]),
+            ),
+            ("trailer".to_string(), Value::U64(0xEE)),
+        ]);
+        let bytes = schema.serialize(&obj).unwrap();
+        assert_eq!(bytes, [0x07, 2, 0x10, 0xF5, 0xEE]);
+
+        let mut parsed = schema.parse(&bytes).unwrap();
+        assert_eq!(parsed.remove("sum"), Some(Value::U64(0x07)));
+        assert_eq!(parsed, obj);
+        assert!(schema.parse(&[0x07, 2, 0x10, 0xF6, 0xEE]).is_err());
+    }
+
+    #[test]
+    fn test_compile_rejects_invalid_checksums() {
+        let data = scalar_at("data", vec![Fragment::new(0, 8)]);
+        let checksum = |algorithm, covers, len_bits| {
+            checksum_field("crc", algorithm, covers, vec![Fragment::new(8, len_bits)])
+        };
+
+        let narrow = checksum(
+            ChecksumAlgorithm::Crc(Crc::CRC_32),
+            ChecksumCoverage::Bits(0..8),
+            16,
+        );
+        let err = Schema::compile(&[data.clone(), narrow], None).unwrap_err();
+        assert_eq!(err.root(), &CompileError::InvalidFieldSize);
+
+        let unknown = checksum(
+            ChecksumAlgorithm::Xor8,
+            ChecksumCoverage::Fields(vec!["payload".to_string()]),
+            8,
+        );
+        let err = Schema::compile(&[data.clone(), unknown], None).unwrap_err();
+        assert_eq!(
+            err.root(),
+            &CompileError::UnknownField("payload".to_string())
+        );
+        assert_eq!(err.context().unwrap().field, "crc");
+
+        let bad_crc = Crc::new(8, 0x107, 0, false, false, 0);
+        let invalid = checksum(
+            ChecksumAlgorithm::Crc(bad_crc),
+            ChecksumCoverage::Bits(0..8),
+            8,
+        );
+        let err = Schema::compile(&[data, invalid], None).unwrap_err();
+        assert_eq!(err.root(), &CompileError::InvalidFieldKind);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_parse_into_reads_counted_arrays_and_checksums() {
+        #[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+        struct Items {
+            len: u8,
+            items: Vec<u8>,
+        }
+
+        let schema = Schema::compile(&counted_items(0), None).unwrap();
+        let items: Items = schema.parse_into(&[2, 0xAA, 0xBB]).unwrap();
+        assert_eq!(
+            items,
+            Items {
+                len: 2,
+                items: vec![0xAA, 0xBB],
+            }
+        );
+        assert_eq!(schema.serialize_from(&items).unwrap(), [2, 0xAA, 0xBB]);
+
+        let err = schema.parse_into::<Items>(&[3, 0xAA]).unwrap_err();
+        assert!(matches!(err, DecodeError::Read(e) if e.root() == &ReadError::PacketTooShort));
+
+        // Fields the target leaves out are still read, so checksums are verified.
+        #[derive(Debug, serde::Deserialize)]
+        struct Id {
+            #[allow(dead_code)]
+            id: u8,
+        }
+        let fields = [
+            scalar_at("id", vec![Fragment::new(0, 8)]),
+            checksum_field(
+                "check",
+                ChecksumAlgorithm::Xor8,
+                ChecksumCoverage::Bits(0..8),
+                vec![Fragment::new(8, 8)],
+            ),
+        ];
+        let schema = Schema::compile(&fields, None).unwrap();
+        assert!(schema.parse_into::<Id>(&[0x12, 0x12]).is_ok());
+        let err = schema.parse_into::<Id>(&[0x12, 0x13]).unwrap_err();
+        assert!(matches!(
+            err,
+            DecodeError::Read(e) if matches!(e.root(), ReadError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_parse_into_maps_conditional_fields_and_switches() {
+        #[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+        struct Flagged {
+            flags: u8,
+            ext: Option<u8>,
+            tail: u8,
+        }
+
+        let schema =
+            Schema::compile_with_layout(&flagged_extension(), Layout::Sequential, None).unwrap();
+        for (bytes, ext) in [
+            (vec![0x01, 0xEE, 0x77], Some(0xEE)),
+            (vec![0x00, 0x77], None),
+        ] {
+            let flagged: Flagged = schema.parse_into(&bytes).unwrap();
+            assert_eq!(flagged.ext, ext);
+            assert_eq!(flagged.tail, 0x77);
+            assert_eq!(schema.serialize_from(&flagged).unwrap(), bytes);
+        }
+
+        #[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+        struct Message {
+            msg_type: u8,
+            body: BTreeMap<String, u16>,
+            crc: u8,
+        }
+
+        let schema =
+            Schema::compile_with_layout(&message_family(None), Layout::Sequential, None).unwrap();
+        let bytes = [2, 1, 2, 3, 0x99];
+        let message: Message = schema.parse_into(&bytes).unwrap();
+        assert_eq!(
+            message.body,
+            BTreeMap::from([
+                ("x".to_string(), 1),
+                ("y".to_string(), 2),
+                ("z".to_string(), 3),
+            ])
+        );
+        assert_eq!(message.crc, 0x99);
+        assert_eq!(schema.serialize_from(&message).unwrap(), bytes);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_from_writes_members_in_schema_order() {
+        // `items` arrives before the count it depends on, so it is held until then.
+        #[derive(serde::Serialize)]
+        struct Reversed {
+            items: Vec<u8>,
+            len: u8,
+        }
+
+        let schema = Schema::compile(&counted_items(0), None).unwrap();
+        let reversed = Reversed {
+            items: vec![0xAA, 0xBB],
+            len: 2,
+        };
+        assert_eq!(schema.serialize_from(&reversed).unwrap(), [2, 0xAA, 0xBB]);
+        let reversed = Reversed {
+            items: vec![0xAA, 0xBB, 0xCC],
+            len: 2,
+        };
+        assert!(matches!(
+            schema.serialize_from(&reversed),
+            Err(EncodeError::Write(WriteError::InvalidValue))
+        ));
+
+        // The switch case is picked by `msg_type`, written from the same value.
+        #[derive(serde::Serialize)]
+        struct Point {
+            x: u8,
+            y: u8,
+            z: u8,
+        }
+        #[derive(serde::Serialize)]
+        struct Message {
+            crc: u8,
+            body: Point,
+            msg_type: u8,
+        }
+
+        let schema =
+            Schema::compile_with_layout(&message_family(None), Layout::Sequential, None).unwrap();
+        let message = Message {
+            crc: 0x99,
+            body: Point { x: 1, y: 2, z: 3 },
+            msg_type: 2,
+        };
+        assert_eq!(schema.serialize_from(&message).unwrap(), [2, 1, 2, 3, 0x99]);
+
+        // A missing field with a constant is filled with it; others are an error.
+        #[derive(serde::Serialize)]
+        struct Len {
+            len: u8,
+        }
+
+        let sync = Field {
+            constant: Some(Value::U64(0xEB90)),
+            ..seq_field("sync", FieldKind::Scalar, 16)
+        };
+        let fields = [sync, seq_field("len", FieldKind::Scalar, 8)];
+        let schema = Schema::compile_with_layout(&fields, Layout::Sequential, None).unwrap();
+        assert_eq!(
+            schema.serialize_from(&Len { len: 3 }).unwrap(),
+            [0xEB, 0x90, 0x03]
+        );
+        let schema =
+            Schema::compile_with_layout(&message_family(None), Layout::Sequential, None).unwrap();
+        assert!(matches!(
+            schema.serialize_from(&Len { len: 3 }),
+            Err(EncodeError::Write(WriteError::MissingField(name))) if name == "msg_type"
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_parse_into_reports_values_that_do_not_fit() {
+        #[derive(Debug, serde::Deserialize)]
+        struct Narrow {
+            #[allow(dead_code)]
+            wide: u8,
+        }
+
+        let schema =
+            Schema::compile(&[scalar_at("wide", vec![Fragment::new(0, 16)])], None).unwrap();
+        match schema.parse_into::<Narrow>(&[0x01, 0x2C]).unwrap_err() {
+            DecodeError::Type { field, message } => {
+                assert_eq!(field, "wide");
+                assert!(message.contains("300"), "{message}");
+            }
+            other => panic!("expected a type error, got {other:?}"),
+        }
+    }
+
+    #[cfg(all(feature = "serde", feature = "transform"))]
+    #[test]
+    fn test_parse_into_uses_transformed_values() {
+        use crate::transform::{Base, Encoding, Transform};
+        use std::collections::HashMap;
+
+        #[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Mode {
+            Idle,
+            Run,
+        }
+
+        #[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+        struct Reading {
+            temperature: f64,
+            mode: Mode,
+            label: String,
+        }
+
+        let mut temperature = Transform::new(Base::Int);
+        temperature.set_scale(0.5).set_offset(-20.0);
+        let mut mode = Transform::new(Base::Int);
+        mode.set_enum_map(HashMap::from([
+            (0, "idle".to_string()),
+            (1, "run".to_string()),
+        ]));
+        let mut label = Transform::new(Base::Bytes);
+        label
+            .set_encoding(Encoding::Ascii)
+            .set_zero_terminated(true);
+        let fields = [
+            Field {
+                transform: Some(temperature),
+                ..scalar_at("temperature", vec![Fragment::new(0, 8)])
+            },
+            Field {
+                transform: Some(mode),
+                ..scalar_at("mode", vec![Fragment::new(8, 8)])
+            },
+            Field {
+                kind: FieldKind::Array(ArraySpec {
+                    count: ArrayCount::Fixed(4),
+                    stride_bits: 8,
+                    offset_bits: 16,
+                    element: None,
+                }),
+                transform: Some(label),
+                ..scalar_at("label", vec![Fragment::new(0, 8)])
+            },
+        ];
+        let schema = Schema::compile(&fields, None).unwrap();
+
+        let bytes = [83, 1, b'a', b'b', 0, 0];
+        let reading: Reading = schema.parse_into(&bytes).unwrap();
+        assert_eq!(
+            reading,
+            Reading {
+                temperature: 21.5,
+                mode: Mode::Run,
+                label: "ab".to_string(),
+            }
+        );
+        assert_eq!(schema.serialize_from(&reading).unwrap(), bytes);
+    }
 }
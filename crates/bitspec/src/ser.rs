@@ -0,0 +1,1190 @@
+//! Serde serializer that writes a Rust value straight over the compiled fields, for
+//! [`crate::schema::Schema::serialize_from`].
+//!
+//! Each field is written as soon as its value arrives: a field list takes a struct
+//! or map, an array a sequence, and a scalar an integer. Only the values later
+//! fields may refer to (array counts, conditions, and switch discriminators) are
+//! kept, as scalars or structs of scalars. A field whose value arrives before the
+//! fields ahead of it are written, e.g. because the type lists its members in
+//! another order than the schema, is held as a [`Value`] until its turn. A field
+//! with a transform, and any value whose shape does not match its field, is turned
+//! into a [`Value`] and written whole.
+//!
+//! Integers become [`Value::U64`] or [`Value::I64`], or [`Value::U128`] or
+//! [`Value::I128`] when they do not fit in 64 bits. `bool` becomes 0 or 1, floats
+//! and strings stay as they are for transforms to invert, sequences become arrays,
+//! and structs and maps become structs. `None` and unit leave the entry out.
+
+use std::{collections::BTreeMap, ops::Range};
+
+use serde::ser::{self, Serialize};
+
+use crate::{
+    compiled::{
+        CompiledArray, CompiledField, CompiledFieldKind, Placement, Scope, attach_field_name,
+        is_present,
+    },
+    errors::{EncodeError, WriteError},
+    value::Value,
+};
+
+/// Serializes `value`, a struct or map of field values, into `fields` laid out from
+/// bit 0. The buffer is as long as the last bit written, or `min_bits` if longer.
+pub(crate) fn to_fields<T: Serialize + ?Sized>(
+    fields: &[CompiledField],
+    value: &T,
+    min_bits: usize,
+    #[cfg(feature = "transform")] rounding: crate::transform::Rounding,
+) -> Result<Vec<u8>, EncodeError> {
+    let mut sink = Sink {
+        buf: vec![0; min_bits.div_ceil(8)],
+        #[cfg(feature = "transform")]
+        rounding,
+    };
+    let scope = Scope::default();
+    value.serialize(RecordSerializer {
+        fields,
+        scope: &scope,
+        sink: &mut sink,
+    })?;
+    Ok(sink.buf)
+}
+
+/// The buffer being written, shared by every level of the record.
+struct Sink {
+    buf: Vec<u8>,
+    #[cfg(feature = "transform")]
+    rounding: crate::transform::Rounding,
+}
+
+impl Sink {
+    /// Zero-extends the buffer to hold `end_bits`.
+    fn reserve(&mut self, end_bits: usize) {
+        let len = end_bits.div_ceil(8);
+        if self.buf.len() < len {
+            self.buf.resize(len, 0);
+        }
+    }
+
+    /// Writes `value` with `kind` at `start` and returns the end of what it wrote,
+    /// relative to `start`.
+    fn put(
+        &mut self,
+        kind: &CompiledFieldKind,
+        value: &Value,
+        start: usize,
+        scope: &Scope<'_>,
+    ) -> Result<usize, WriteError> {
+        let end = kind.value_end_bits(value, scope);
+        self.reserve(start + end);
+        kind.disassemble_at(value, &mut self.buf, start, scope)?;
+        Ok(end)
+    }
+}
+
+/// What writing one field left behind for the rest of its field list.
+struct Written {
+    /// One past the last bit written, relative to the field's base.
+    end: usize,
+    /// Value later fields can refer to: a scalar, or a struct of such values.
+    record: Option<Value>,
+}
+
+fn in_field(error: EncodeError, name: &str) -> EncodeError {
+    match error {
+        EncodeError::Write(error) => EncodeError::Write(attach_field_name(error, name)),
+        other => other,
+    }
+}
+
+fn unsupported(what: &str) -> EncodeError {
+    EncodeError::Type(format!("{what} cannot be written to a field"))
+}
+
+fn variant_with_data(name: &str, variant: &str) -> EncodeError {
+    unsupported(&format!("enum variant {name}::{variant} with data"))
+}
+
+fn not_a_record() -> EncodeError {
+    EncodeError::Type("expected a struct or map of field values".to_string())
+}
+
+/// The top level of the record, which must be a struct or map.
+struct RecordSerializer<'a, 'w, 's> {
+    fields: &'a [CompiledField],
+    scope: &'s Scope<'s>,
+    sink: &'w mut Sink,
+}
+
+macro_rules! reject_as_record {
+    ($($method:ident($ty:ty)),* $(,)?) => {$(
+        fn $method(self, _v: $ty) -> Result<(), EncodeError> {
+            Err(not_a_record())
+        }
+    )*};
+}
+
+impl<'a, 'w, 's> ser::Serializer for RecordSerializer<'a, 'w, 's> {
+    type Ok = ();
+    type Error = EncodeError;
+    type SerializeSeq = ser::Impossible<(), EncodeError>;
+    type SerializeTuple = ser::Impossible<(), EncodeError>;
+    type SerializeTupleStruct = ser::Impossible<(), EncodeError>;
+    type SerializeTupleVariant = ser::Impossible<(), EncodeError>;
+    type SerializeMap = FieldsWriter<'a, 'w, 's>;
+    type SerializeStruct = FieldsWriter<'a, 'w, 's>;
+    type SerializeStructVariant = ser::Impossible<(), EncodeError>;
+
+    reject_as_record!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_unit_struct(&'static str),
+    );
+
+    fn serialize_none(self) -> Result<(), EncodeError> {
+        Err(not_a_record())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), EncodeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), EncodeError> {
+        Err(not_a_record())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), EncodeError> {
+        Err(not_a_record())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), EncodeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), EncodeError> {
+        Err(not_a_record())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, EncodeError> {
+        Err(not_a_record())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, EncodeError> {
+        Err(not_a_record())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, EncodeError> {
+        Err(not_a_record())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, EncodeError> {
+        Err(not_a_record())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, EncodeError> {
+        Ok(FieldsWriter::new(
+            self.fields,
+            0,
+            self.scope,
+            self.sink,
+            false,
+        ))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, EncodeError> {
+        self.serialize_map(None)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, EncodeError> {
+        Err(not_a_record())
+    }
+}
+
+/// Writes a field list from the entries of a struct or map, in schema order.
+struct FieldsWriter<'a, 'w, 's> {
+    fields: &'a [CompiledField],
+    base_offset: usize,
+    parent: &'s Scope<'s>,
+    sink: &'w mut Sink,
+    /// Whether entries that name no field are an error, as in a switch case.
+    strict: bool,
+    /// Values of the fields written so far that later fields may refer to.
+    record: BTreeMap<String, Value>,
+    /// Bits of every field written so far, for checksums covering named fields.
+    spans: Vec<(&'a str, Range<usize>)>,
+    /// Checksums to compute once every other field is written, with their start.
+    checksums: Vec<(&'a CompiledField, usize)>,
+    /// Values that arrived before the fields ahead of them were written, by index.
+    early: BTreeMap<usize, Value>,
+    /// Index of the first field not yet written or passed over.
+    next: usize,
+    cursor: usize,
+    end: usize,
+    /// Map key whose value has not arrived yet.
+    key: Option<String>,
+}
+
+impl<'a, 'w, 's> FieldsWriter<'a, 'w, 's> {
+    /// Starts writing `fields` at `base_offset`; references not found among the
+    /// fields are resolved in `parent`.
+    fn new(
+        fields: &'a [CompiledField],
+        base_offset: usize,
+        parent: &'s Scope<'s>,
+        sink: &'w mut Sink,
+        strict: bool,
+    ) -> Self {
+        FieldsWriter {
+            fields,
+            base_offset,
+            parent,
+            sink,
+            strict,
+            record: BTreeMap::new(),
+            spans: Vec::new(),
+            checksums: Vec::new(),
+            early: BTreeMap::new(),
+            next: 0,
+            cursor: base_offset,
+            end: base_offset,
+            key: None,
+        }
+    }
+
+    fn start_of(&self, field: &CompiledField) -> usize {
+        match field.placement {
+            Placement::Fixed => self.base_offset,
+            Placement::Cursor => self.cursor,
+        }
+    }
+
+    fn advance(&mut self, start: usize, end: usize) {
+        self.cursor = start + end;
+        self.end = self.end.max(self.cursor);
+    }
+
+    /// Passes over the fields up to the next present field that takes a value:
+    /// spacers and reserved bits are written, absent fields skipped, and checksums
+    /// set aside for the end.
+    fn settle(&mut self) -> Result<(), EncodeError> {
+        while let Some(field) = self.fields.get(self.next) {
+            let start = self.start_of(field);
+            if let CompiledFieldKind::Align(align_bits) = field.kind {
+                self.cursor =
+                    self.base_offset + (start - self.base_offset).next_multiple_of(align_bits);
+                self.end = self.end.max(self.cursor);
+                self.next += 1;
+                continue;
+            }
+            let scope = self.parent.child(&self.record);
+            let end = if !is_present(field, &scope) {
+                0
+            } else if !field.kind.has_value() {
+                self.sink
+                    .put(&field.kind, &Value::Absent, start, &scope)
+                    .map_err(|e| attach_field_name(e, &field.name))?;
+                field.kind.end_bits()
+            } else if let CompiledFieldKind::Checksum { .. } = field.kind {
+                let end = field.kind.end_bits();
+                self.sink.reserve(start + end);
+                self.checksums.push((field, start));
+                self.spans.push((
+                    field.name.as_str(),
+                    start + field.kind.start_bits()..start + end,
+                ));
+                end
+            } else {
+                return Ok(());
+            };
+            self.advance(start, end);
+            self.next += 1;
+        }
+        Ok(())
+    }
+
+    /// Takes the value of the field `name`: writes it if every field ahead of it
+    /// is settled, and holds it until then otherwise. Entries that name no field
+    /// (or a checksum, which is always computed) are ignored.
+    fn entry<T: Serialize + ?Sized>(&mut self, name: &str, value: &T) -> Result<(), EncodeError> {
+        let Some(index) = self.fields.iter().position(|field| {
+            field.name == name
+                && field.kind.has_value()
+                && !matches!(field.kind, CompiledFieldKind::Checksum { .. })
+        }) else {
+            if self.strict {
+                return Err(WriteError::CaseMismatch(name.to_string()).into());
+            }
+            return Ok(());
+        };
+        self.settle()?;
+        if index == self.next {
+            let field = &self.fields[index];
+            let written = self.write_with(field, |start, scope, sink| {
+                write_serialize(field, value, start, scope, sink)
+            })?;
+            self.finish_field(field, written)?;
+            self.catch_up()?;
+        } else if index > self.next {
+            self.early.insert(index, to_value(value)?);
+        }
+        Ok(())
+    }
+
+    /// Writes `field` at the next position with `write`, resolving references
+    /// among the fields written so far.
+    fn write_with(
+        &mut self,
+        field: &'a CompiledField,
+        write: impl FnOnce(usize, &Scope<'_>, &mut Sink) -> Result<Option<Written>, EncodeError>,
+    ) -> Result<(usize, Option<Written>), EncodeError> {
+        let start = self.start_of(field);
+        let scope = self.parent.child(&self.record);
+        let written = write(start, &scope, self.sink).map_err(|e| in_field(e, &field.name))?;
+        Ok((start, written))
+    }
+
+    /// Records a field just written, or writes its constant if it got no value.
+    fn finish_field(
+        &mut self,
+        field: &'a CompiledField,
+        (start, written): (usize, Option<Written>),
+    ) -> Result<(), EncodeError> {
+        let written = match written {
+            Some(written) => written,
+            None => {
+                let constant = field
+                    .constant
+                    .as_ref()
+                    .ok_or_else(|| WriteError::MissingField(field.name.clone()))?;
+                let scope = self.parent.child(&self.record);
+                let end = self
+                    .sink
+                    .put(&field.kind, constant, start, &scope)
+                    .map_err(|e| attach_field_name(e, &field.name))?;
+                Written {
+                    end,
+                    record: Some(constant.clone()),
+                }
+            }
+        };
+        if let Some(record) = written.record {
+            self.record.insert(field.name.clone(), record);
+        }
+        self.spans.push((
+            field.name.as_str(),
+            start + field.kind.start_bits()..start + written.end,
+        ));
+        self.advance(start, written.end);
+        self.next += 1;
+        Ok(())
+    }
+
+    /// Writes the held values whose turn has come.
+    fn catch_up(&mut self) -> Result<(), EncodeError> {
+        self.settle()?;
+        while let Some(value) = self.early.remove(&self.next) {
+            self.write_next(Some(value))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the next field from `value`, or from its constant.
+    fn write_next(&mut self, value: Option<Value>) -> Result<(), EncodeError> {
+        let field = &self.fields[self.next];
+        let written = self.write_with(field, |start, scope, sink| match value {
+            Some(value) => write_value(field, value, start, scope, sink),
+            None => Ok(None),
+        })?;
+        self.finish_field(field, written)?;
+        self.settle()
+    }
+
+    /// Writes the fields still waiting for a value, then the checksums. Returns
+    /// the end of the list relative to `start`.
+    fn finish(mut self, start: usize) -> Result<Written, EncodeError> {
+        self.settle()?;
+        while self.next < self.fields.len() {
+            let value = self.early.remove(&self.next);
+            self.write_next(value)?;
+        }
+        for (field, at) in &self.checksums {
+            field
+                .write_checksum(&mut self.sink.buf, *at, self.base_offset, &self.spans)
+                .map_err(|e| attach_field_name(e, &field.name))?;
+        }
+        Ok(Written {
+            end: self.end - start,
+            record: Some(Value::Struct(self.record)),
+        })
+    }
+}
+
+impl ser::SerializeMap for FieldsWriter<'_, '_, '_> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), EncodeError> {
+        match to_value(key)? {
+            Value::String(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(EncodeError::Type(
+                "map keys must be field names".to_string(),
+            )),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value is called after serialize_key");
+        self.entry(&key, value)
+    }
+
+    fn end(self) -> Result<(), EncodeError> {
+        self.finish(0).map(|_| ())
+    }
+}
+
+impl ser::SerializeStruct for FieldsWriter<'_, '_, '_> {
+    type Ok = ();
+    type Error = EncodeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), EncodeError> {
+        self.entry(key, value)
+    }
+
+    fn end(self) -> Result<(), EncodeError> {
+        self.finish(0).map(|_| ())
+    }
+}
+
+/// Writes one field from a Rust value. A field with a transform is written whole,
+/// so the transform can be inverted.
+fn write_serialize<T: Serialize + ?Sized>(
+    field: &CompiledField,
+    value: &T,
+    start: usize,
+    scope: &Scope<'_>,
+    sink: &mut Sink,
+) -> Result<Option<Written>, EncodeError> {
+    #[cfg(feature = "transform")]
+    if field.transform.is_some() {
+        return write_value(field, to_value(value)?, start, scope, sink);
+    }
+    value.serialize(KindSerializer {
+        kind: &field.kind,
+        start,
+        scope,
+        sink,
+    })
+}
+
+/// Writes one field from a value already collected, inverting its transforms
+/// first. An absent value writes nothing.
+fn write_value(
+    field: &CompiledField,
+    value: Value,
+    start: usize,
+    scope: &Scope<'_>,
+    sink: &mut Sink,
+) -> Result<Option<Written>, EncodeError> {
+    if value == Value::Absent {
+        return Ok(None);
+    }
+    #[cfg(feature = "transform")]
+    let value =
+        crate::schema::invert_field_transforms(field, value, sink.rounding).map_err(|error| {
+            WriteError::Transform {
+                field: String::new(),
+                error,
+            }
+        })?;
+    let end = sink.put(&field.kind, &value, start, scope)?;
+    Ok(Some(Written {
+        end,
+        record: Some(value),
+    }))
+}
+
+/// Serializer for one field or array element, written into the sink at `start`.
+/// Returns `None` if the value was left out, e.g. `None`.
+struct KindSerializer<'k, 'w, 's> {
+    kind: &'k CompiledFieldKind,
+    start: usize,
+    scope: &'s Scope<'s>,
+    sink: &'w mut Sink,
+}
+
+impl KindSerializer<'_, '_, '_> {
+    /// Writes `value` with the kind as a whole.
+    fn put(self, value: Value) -> Result<Option<Written>, EncodeError> {
+        if value == Value::Absent {
+            return Ok(None);
+        }
+        let end = self.sink.put(self.kind, &value, self.start, self.scope)?;
+        Ok(Some(Written {
+            end,
+            record: Some(value),
+        }))
+    }
+}
+
+macro_rules! write_as_value {
+    ($($method:ident($ty:ty)),* $(,)?) => {$(
+        fn $method(self, v: $ty) -> Result<Option<Written>, EncodeError> {
+            let value = ValueSerializer.$method(v)?;
+            self.put(value)
+        }
+    )*};
+}
+
+impl<'k, 'w, 's> ser::Serializer for KindSerializer<'k, 'w, 's> {
+    type Ok = Option<Written>;
+    type Error = EncodeError;
+    type SerializeSeq = ElementsWriter<'k, 'w, 's>;
+    type SerializeTuple = ElementsWriter<'k, 'w, 's>;
+    type SerializeTupleStruct = ElementsWriter<'k, 'w, 's>;
+    type SerializeTupleVariant = ser::Impossible<Option<Written>, EncodeError>;
+    type SerializeMap = EntriesWriter<'k, 'w, 's>;
+    type SerializeStruct = EntriesWriter<'k, 'w, 's>;
+    type SerializeStructVariant = ser::Impossible<Option<Written>, EncodeError>;
+
+    write_as_value!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_unit_struct(&'static str),
+    );
+
+    fn serialize_none(self) -> Result<Option<Written>, EncodeError> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(
+        self,
+        value: &T,
+    ) -> Result<Option<Written>, EncodeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Option<Written>, EncodeError> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Option<Written>, EncodeError> {
+        let value = ValueSerializer.serialize_unit_variant(name, variant_index, variant)?;
+        self.put(value)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Option<Written>, EncodeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _value: &T,
+    ) -> Result<Option<Written>, EncodeError> {
+        Err(variant_with_data(name, variant))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ElementsWriter<'k, 'w, 's>, EncodeError> {
+        match self.kind {
+            CompiledFieldKind::Array(array) => {
+                let count = array
+                    .resolve_count(self.scope)
+                    .map_err(|_| WriteError::InvalidValue)?;
+                Ok(ElementsWriter::Array {
+                    array,
+                    count,
+                    index: 0,
+                    end: array.offset_bits,
+                    start: self.start,
+                    scope: self.scope,
+                    sink: self.sink,
+                })
+            }
+            _ => Ok(ElementsWriter::Whole(
+                ValueSerializer.serialize_seq(len)?,
+                self,
+            )),
+        }
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ElementsWriter<'k, 'w, 's>, EncodeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<ElementsWriter<'k, 'w, 's>, EncodeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, EncodeError> {
+        Err(variant_with_data(name, variant))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<EntriesWriter<'k, 'w, 's>, EncodeError> {
+        let (fields, offset_bits, strict) = match self.kind {
+            CompiledFieldKind::Struct(group) => (group.fields.as_slice(), group.offset_bits, false),
+            CompiledFieldKind::Switch(switch) => {
+                let Ok(Some(fields)) = switch.select(self.scope) else {
+                    return Err(WriteError::UnmatchedCase(switch.on.clone()).into());
+                };
+                (fields, switch.offset_bits, true)
+            }
+            _ => return Ok(EntriesWriter::Whole(StructValues::default(), self)),
+        };
+        Ok(EntriesWriter::Fields(
+            FieldsWriter::new(
+                fields,
+                self.start + offset_bits,
+                self.scope,
+                self.sink,
+                strict,
+            ),
+            self.start,
+        ))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<EntriesWriter<'k, 'w, 's>, EncodeError> {
+        self.serialize_map(None)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, EncodeError> {
+        Err(variant_with_data(name, variant))
+    }
+}
+
+/// Writes the elements of a sequence: one by one into an array, or collected and
+/// written whole into any other kind.
+enum ElementsWriter<'k, 'w, 's> {
+    Array {
+        array: &'k CompiledArray,
+        count: usize,
+        index: usize,
+        /// One past the last bit written, relative to the array's base.
+        end: usize,
+        start: usize,
+        scope: &'s Scope<'s>,
+        sink: &'w mut Sink,
+    },
+    Whole(SeqValues, KindSerializer<'k, 'w, 's>),
+}
+
+impl ser::SerializeSeq for ElementsWriter<'_, '_, '_> {
+    type Ok = Option<Written>;
+    type Error = EncodeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        match self {
+            ElementsWriter::Array {
+                array,
+                count,
+                index,
+                end,
+                start,
+                scope,
+                sink,
+            } => {
+                if *index == *count {
+                    return Err(WriteError::InvalidValue.into());
+                }
+                let element_start = array.offset_bits + *index * array.stride_bits;
+                let written = value
+                    .serialize(KindSerializer {
+                        kind: &array.element,
+                        start: *start + element_start,
+                        scope,
+                        sink,
+                    })?
+                    .ok_or(WriteError::InvalidValue)?;
+                *end = (*end).max(element_start + written.end);
+                *index += 1;
+                Ok(())
+            }
+            ElementsWriter::Whole(values, _) => values.serialize_element(value),
+        }
+    }
+
+    fn end(self) -> Result<Option<Written>, EncodeError> {
+        match self {
+            ElementsWriter::Array {
+                count, index, end, ..
+            } => {
+                if index != count {
+                    return Err(WriteError::InvalidValue.into());
+                }
+                Ok(Some(Written { end, record: None }))
+            }
+            ElementsWriter::Whole(values, kind) => kind.put(values.end()?),
+        }
+    }
+}
+
+impl ser::SerializeTuple for ElementsWriter<'_, '_, '_> {
+    type Ok = Option<Written>;
+    type Error = EncodeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Option<Written>, EncodeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for ElementsWriter<'_, '_, '_> {
+    type Ok = Option<Written>;
+    type Error = EncodeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Option<Written>, EncodeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// Writes the entries of a struct or map: field by field into a struct or switch
+/// case starting at the given bit, or collected and written whole into any other
+/// kind.
+enum EntriesWriter<'k, 'w, 's> {
+    Fields(FieldsWriter<'k, 'w, 's>, usize),
+    Whole(StructValues, KindSerializer<'k, 'w, 's>),
+}
+
+impl ser::SerializeMap for EntriesWriter<'_, '_, '_> {
+    type Ok = Option<Written>;
+    type Error = EncodeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), EncodeError> {
+        match self {
+            EntriesWriter::Fields(fields, _) => fields.serialize_key(key),
+            EntriesWriter::Whole(values, _) => values.serialize_key(key),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        match self {
+            EntriesWriter::Fields(fields, _) => fields.serialize_value(value),
+            EntriesWriter::Whole(values, _) => values.serialize_value(value),
+        }
+    }
+
+    fn end(self) -> Result<Option<Written>, EncodeError> {
+        match self {
+            EntriesWriter::Fields(fields, start) => fields.finish(start).map(Some),
+            EntriesWriter::Whole(values, kind) => kind.put(ser::SerializeMap::end(values)?),
+        }
+    }
+}
+
+impl ser::SerializeStruct for EntriesWriter<'_, '_, '_> {
+    type Ok = Option<Written>;
+    type Error = EncodeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), EncodeError> {
+        match self {
+            EntriesWriter::Fields(fields, _) => fields.entry(key, value),
+            EntriesWriter::Whole(values, _) => {
+                ser::SerializeStruct::serialize_field(values, key, value)
+            }
+        }
+    }
+
+    fn end(self) -> Result<Option<Written>, EncodeError> {
+        match self {
+            EntriesWriter::Fields(fields, start) => fields.finish(start).map(Some),
+            EntriesWriter::Whole(values, kind) => kind.put(ser::SerializeStruct::end(values)?),
+        }
+    }
+}
+
+/// Serializes `value` into a [`Value`].
+fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, EncodeError> {
+    value.serialize(ValueSerializer)
+}
+
+/// Turns a Rust value into a [`Value`], for fields written whole.
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = EncodeError;
+    type SerializeSeq = SeqValues;
+    type SerializeTuple = SeqValues;
+    type SerializeTupleStruct = SeqValues;
+    type SerializeTupleVariant = ser::Impossible<Value, EncodeError>;
+    type SerializeMap = StructValues;
+    type SerializeStruct = StructValues;
+    type SerializeStructVariant = ser::Impossible<Value, EncodeError>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, EncodeError> {
+        Ok(Value::U64(u64::from(v)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, EncodeError> {
+        Ok(Value::I64(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, EncodeError> {
+        Ok(Value::I64(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, EncodeError> {
+        Ok(Value::I64(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, EncodeError> {
+        Ok(Value::I64(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value, EncodeError> {
+        Ok(i64::try_from(v).map_or(Value::I128(v), Value::I64))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, EncodeError> {
+        Ok(Value::U64(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, EncodeError> {
+        Ok(Value::U64(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, EncodeError> {
+        Ok(Value::U64(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, EncodeError> {
+        Ok(Value::U64(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value, EncodeError> {
+        Ok(u64::try_from(v).map_or(Value::U128(v), Value::U64))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, EncodeError> {
+        Ok(Value::F32(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, EncodeError> {
+        Ok(Value::F64(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, EncodeError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, EncodeError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, EncodeError> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Value, EncodeError> {
+        Ok(Value::Absent)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, EncodeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, EncodeError> {
+        Ok(Value::Absent)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, EncodeError> {
+        Ok(Value::Absent)
+    }
+
+    /// Unit variants become their name, the label an enum-map transform inverts.
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value, EncodeError> {
+        Ok(Value::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, EncodeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _value: &T,
+    ) -> Result<Value, EncodeError> {
+        Err(variant_with_data(name, variant))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqValues, EncodeError> {
+        Ok(SeqValues(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqValues, EncodeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqValues, EncodeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, EncodeError> {
+        Err(variant_with_data(name, variant))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<StructValues, EncodeError> {
+        Ok(StructValues::default())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<StructValues, EncodeError> {
+        Ok(StructValues::default())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, EncodeError> {
+        Err(variant_with_data(name, variant))
+    }
+}
+
+/// Elements of a sequence, collected into a [`Value::Array`].
+struct SeqValues(Vec<Value>);
+
+impl ser::SerializeSeq for SeqValues {
+    type Ok = Value;
+    type Error = EncodeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        self.0.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, EncodeError> {
+        Ok(Value::Array(self.0))
+    }
+}
+
+impl ser::SerializeTuple for SeqValues {
+    type Ok = Value;
+    type Error = EncodeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, EncodeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqValues {
+    type Ok = Value;
+    type Error = EncodeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, EncodeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// Entries of a struct or map, collected into a [`Value::Struct`]. Entries that
+/// serialize to nothing, such as `None`, are left out.
+#[derive(Default)]
+struct StructValues {
+    map: BTreeMap<String, Value>,
+    key: Option<String>,
+}
+
+impl StructValues {
+    fn insert(&mut self, key: String, value: Value) {
+        if value != Value::Absent {
+            self.map.insert(key, value);
+        }
+    }
+}
+
+impl ser::SerializeMap for StructValues {
+    type Ok = Value;
+    type Error = EncodeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), EncodeError> {
+        match to_value(key)? {
+            Value::String(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(EncodeError::Type(
+                "map keys must be field names".to_string(),
+            )),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodeError> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value is called after serialize_key");
+        let value = to_value(value)?;
+        self.insert(key, value);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, EncodeError> {
+        Ok(Value::Struct(self.map))
+    }
+}
+
+impl ser::SerializeStruct for StructValues {
+    type Ok = Value;
+    type Error = EncodeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), EncodeError> {
+        let value = to_value(value)?;
+        self.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, EncodeError> {
+        Ok(Value::Struct(self.map))
+    }
+}
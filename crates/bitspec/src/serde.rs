@@ -7,15 +7,24 @@
 //! The same shapes are expected when you call `Schema::compile` with a JSON string.
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// How individual fragments of bits are assembled into a numeric value.
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
 pub enum AssembleDef {
+    #[default]
     /// Concatenate fragments most‑significant‑bit first.
     ConcatMsb,
     /// Concatenate fragments least‑significant‑bit first.
     ConcatLsb,
+    /// Most significant byte first (`ABCD`).
+    BigEndian,
+    /// Least significant byte first (`DCBA`).
+    LittleEndian,
+    /// Big-endian 16-bit words with their bytes swapped (`BADC`, PDP-11).
+    BigEndianByteSwap,
+    /// Little-endian order of big-endian 16-bit words (`CDAB`).
+    LittleEndianByteSwap,
 }
 
 /// Bit order to use when reading a fragment.
@@ -34,6 +43,25 @@ pub struct WriteConfigDef {
     /// Bit order applied to each output byte when serializing.
     #[serde(default)]
     pub bit_order: BitOrderDef,
+    /// Rounding used when inverting scaled transforms; defaults to `Nearest`.
+    #[serde(default)]
+    pub rounding: RoundingDef,
+}
+
+/// Rounding mode used when inverse transforms produce integers.
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub enum RoundingDef {
+    #[default]
+    /// Nearest integer, ties away from zero.
+    Nearest,
+    /// Nearest integer, ties to even.
+    NearestEven,
+    /// Toward negative infinity.
+    Floor,
+    /// Toward positive infinity.
+    Ceil,
+    /// Toward zero.
+    Truncate,
 }
 
 /// Top‑level schema definition consisting of a list of fields.
@@ -44,6 +72,53 @@ pub struct SchemaDef {
     /// Optional write configuration controlling serialization behavior.
     #[serde(default)]
     pub write_config: Option<WriteConfigDef>,
+    /// How fragment offsets are interpreted; defaults to `Absolute`.
+    #[serde(default)]
+    pub layout: LayoutDef,
+    /// Accept overlapping fields instead of failing to compile; defaults to `false`.
+    #[serde(default)]
+    pub allow_overlap: bool,
+}
+
+/// JSON-friendly form of [`crate::field::Layout`].
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub enum LayoutDef {
+    #[default]
+    /// Offsets are absolute.
+    Absolute,
+    /// Fields follow one another from a running bit cursor.
+    Sequential,
+}
+
+/// JSON-friendly form of [`crate::field::BytesPadding`].
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+pub enum BytesPaddingDef {
+    #[default]
+    /// The low bits of the last byte are spare.
+    Trailing,
+    /// The high bits of the first byte are spare.
+    Leading,
+}
+
+/// JSON-friendly form of [`crate::field::StringFraming`], tagged by `"type"`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "type")]
+pub enum StringFramingDef {
+    /// Byte count `prefix_bits` wide, followed by the bytes.
+    LengthPrefixed {
+        /// Width of the length prefix in bits.
+        prefix_bits: usize,
+    },
+    /// Bytes up to a zero byte.
+    NullTerminated,
+    /// Exactly `len_bytes` bytes, padded with `pad`.
+    Fixed {
+        /// Size of the string in bytes.
+        len_bytes: usize,
+        /// Padding byte; defaults to 0.
+        #[serde(default)]
+        pad: u8,
+    },
 }
 
 /// Description of a single parsed field.
@@ -51,18 +126,64 @@ pub struct SchemaDef {
 pub struct FieldDef {
     /// Human‑readable field name; becomes the key in the output map.
     pub name: String,
-    /// Whether this is a scalar or fixed‑size array field.
+    /// Layout of the field: a scalar, an array, a struct or switch of sub-fields,
+    /// bytes, a string, a skip or align spacer, reserved bits, or a checksum.
     pub kind: FieldKindDef,
     /// Whether the assembled value should be interpreted as signed.
+    #[serde(default)]
     pub signed: bool,
     /// Strategy used to assemble fragments into a single value.
+    #[serde(default)]
     pub assemble: AssembleDef,
-    /// Bit fragments that make up this field.
+    /// Bit fragments that make up this field; may be omitted for struct fields.
+    #[serde(default)]
     pub fragments: Vec<FragmentDef>,
 
     /// Optional post‑processing transform applied after parsing the raw value.
     #[serde(default)]
     pub transform: Option<TransformDef>,
+    /// Optional presence condition on an earlier field.
+    #[serde(default)]
+    pub condition: Option<ConditionDef>,
+    /// Optional raw value written when the input to serialize has none, e.g.
+    /// `{"U64": 1}`.
+    #[serde(default)]
+    pub default: Option<crate::value::Value>,
+    /// Optional raw value the field must hold, e.g. `{"U64": 60304}` for a sync
+    /// word.
+    #[serde(default)]
+    pub constant: Option<crate::value::Value>,
+}
+
+/// JSON-friendly form of [`crate::field::Condition`], tagged by `"type"`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "type")]
+pub enum ConditionDef {
+    /// `field == value`.
+    Equals {
+        /// Name of the field to test.
+        field: String,
+        /// Expected value.
+        value: i64,
+    },
+    /// `field & mask == expected`.
+    Mask {
+        /// Name of the field to test.
+        field: String,
+        /// Bits to compare.
+        mask: u64,
+        /// Expected value of the masked bits.
+        expected: u64,
+    },
+    /// `min <= field <= max`.
+    Range {
+        /// Name of the field to test.
+        field: String,
+        /// Inclusive lower bound.
+        min: i64,
+        /// Inclusive upper bound.
+        max: i64,
+    },
 }
 
 /// Kind of field in the schema.
@@ -71,14 +192,164 @@ pub struct FieldDef {
 pub enum FieldKindDef {
     /// Single scalar value.
     Scalar,
-    /// Fixed‑size array of values laid out with a constant stride.
+    /// Array of values laid out with a constant stride; the count is fixed or taken
+    /// from an earlier field.
     Array {
-        /// Number of elements in the array.
-        count: usize,
+        /// Number of elements: a number, or `{"field": ..., "adjust": ...}`.
+        count: ArrayCountDef,
         /// Distance in bits between consecutive elements.
         stride_bits: usize,
         /// Bit offset of the first element from the start of the payload.
         offset_bits: usize,
+        /// Optional element layout; when omitted each element is the field's fragments.
+        #[serde(default)]
+        element: Option<Box<FieldKindDef>>,
+    },
+    /// Group of named sub-fields whose offsets are relative to the struct base.
+    Struct {
+        /// Sub-fields of the struct.
+        fields: Vec<FieldDef>,
+    },
+    /// Sub-fields chosen by the value of the field named `on`.
+    Switch {
+        /// Name of the discriminator field.
+        on: String,
+        /// Sub-fields for each discriminator value; JSON keys are decimal strings.
+        #[serde(deserialize_with = "deserialize_cases")]
+        cases: BTreeMap<u64, Vec<FieldDef>>,
+        /// Sub-fields used when no case matches.
+        #[serde(default)]
+        default: Option<Vec<FieldDef>>,
+    },
+    /// Bit range read into raw bytes as a whole.
+    Bytes {
+        /// Bit offset where the range starts; defaults to 0.
+        #[serde(default)]
+        offset_bits: usize,
+        /// Length of the range in bits.
+        len_bits: usize,
+        /// Where the spare bits of a partial byte go; defaults to `Trailing`.
+        #[serde(default)]
+        padding: BytesPaddingDef,
+    },
+    /// Text framed by a length prefix, a zero terminator, or a fixed size.
+    String {
+        /// Bit offset where the string starts; defaults to 0.
+        #[serde(default)]
+        offset_bits: usize,
+        /// How the end of the string is found.
+        framing: StringFramingDef,
+        /// How the bytes map to characters; defaults to `Utf8`.
+        #[serde(default)]
+        encoding: EncodingDef,
+    },
+    /// Gap of `bits` bits in a sequential layout; produces no value.
+    Skip {
+        /// Number of bits to skip.
+        bits: usize,
+    },
+    /// Moves the cursor of a sequential layout to the next multiple of `bits`.
+    Align {
+        /// Alignment in bits.
+        bits: usize,
+    },
+    /// Reserved bits at the field's fragments; produces no value.
+    Reserved {
+        /// Bits written on serialize; defaults to 0.
+        #[serde(default)]
+        fill: u64,
+        /// Whether parse checks the bits against `fill`; defaults to `false`.
+        #[serde(default)]
+        check: bool,
+    },
+    /// Checksum at the field's fragments, computed on serialize.
+    Checksum {
+        /// How the value is computed.
+        algorithm: ChecksumAlgorithmDef,
+        /// What the checksum covers.
+        covers: ChecksumCoverageDef,
+        /// Whether parse fails on a mismatch; defaults to `true`.
+        #[serde(default = "default_true")]
+        check: bool,
+    },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// JSON-friendly form of [`crate::checksum::ChecksumAlgorithm`], tagged by `"type"`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "type")]
+pub enum ChecksumAlgorithmDef {
+    /// CRC with catalogue parameters.
+    Crc {
+        /// Number of bits in the CRC, 1 to 64.
+        width: usize,
+        /// Generator polynomial in normal form.
+        poly: u64,
+        /// Initial register value; defaults to 0.
+        #[serde(default)]
+        init: u64,
+        /// Whether input bytes are reflected; defaults to `false`.
+        #[serde(default)]
+        refin: bool,
+        /// Whether the result is reflected; defaults to `false`.
+        #[serde(default)]
+        refout: bool,
+        /// Value XORed into the result; defaults to 0.
+        #[serde(default)]
+        xorout: u64,
+    },
+    /// Fletcher-16.
+    Fletcher16,
+    /// Fletcher-32 over big-endian 16-bit words.
+    Fletcher32,
+    /// Sum of bytes modulo 256.
+    Sum8,
+    /// Exclusive or of bytes.
+    Xor8,
+}
+
+/// JSON-friendly form of [`crate::checksum::ChecksumCoverage`]:
+/// `{"Bits": {"start": 0, "end": 64}}` or `{"Fields": ["header", "payload"]}`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub enum ChecksumCoverageDef {
+    /// Bits relative to the start of the enclosing field list.
+    Bits(std::ops::Range<usize>),
+    /// Sibling fields, in order.
+    Fields(Vec<String>),
+}
+
+/// Reads switch cases keyed by decimal strings. Internally tagged enums buffer
+/// their content, which loses serde_json's string-to-integer key conversion.
+fn deserialize_cases<'de, D>(deserializer: D) -> Result<BTreeMap<u64, Vec<FieldDef>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    BTreeMap::<String, Vec<FieldDef>>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, fields)| {
+            key.parse()
+                .map(|key| (key, fields))
+                .map_err(|_| serde::de::Error::custom(format!("invalid switch case key '{key}'")))
+        })
+        .collect()
+}
+
+/// Element count of an array: a fixed number or a reference to an earlier field.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+pub enum ArrayCountDef {
+    /// Fixed number of elements.
+    Fixed(usize),
+    /// Count read from another field, plus an optional adjustment.
+    FromField {
+        /// Name of the field holding the count.
+        field: String,
+        /// Added to the referenced value; defaults to 0.
+        #[serde(default)]
+        adjust: i64,
     },
 }
 
@@ -94,7 +365,6 @@ pub struct FragmentDef {
     pub bit_order: Option<BitOrderDef>,
 }
 
-
 /// Base type of the value before any transform is applied.
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub enum BaseDef {
@@ -109,12 +379,23 @@ pub enum BaseDef {
 }
 
 /// Text encoding to use when interpreting byte values as strings.
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub enum EncodingDef {
     /// UTF‑8 encoded string.
+    #[default]
     Utf8,
     /// ASCII encoded string.
     Ascii,
+    /// ISO‑8859‑1 (Latin‑1) encoded string.
+    Latin1,
+    /// UTF‑16 encoded string, little‑endian.
+    Utf16Le,
+    /// UTF‑16 encoded string, big‑endian.
+    Utf16Be,
+    /// EBCDIC (code page 037) encoded string.
+    Ebcdic,
+    /// AIS‑style 6‑bit packed ASCII.
+    SixBit,
 }
 
 /// Complete description of how to transform a parsed raw value.
@@ -1,7 +1,7 @@
 //! A [`Transform`] describes how to interpret and optionally modify values:
 //! - **Base type**: How to reinterpret raw bytes (integer, float32, float64, or byte array).
 //! - **Numeric modifiers**: Optional `scale` and `offset` applied as `value * scale + offset`.
-//! - **String decoding**: For byte arrays, optional UTF-8 or ASCII decoding with zero-termination and trim.
+//! - **String decoding**: For byte arrays, optional text decoding (see [`Encoding`]) with zero-termination and trim.
 //! - **Enum mapping**: For integers, optional mapping from numeric values to string labels.
 //!
 //! ## Transform order
@@ -11,12 +11,16 @@
 //! 2. Numeric modifiers (scale, offset)
 //! 3. Enum mapping
 //! 4. String decoding
+//!
+//! [`Transform::invert`] runs the same steps in reverse so that transformed values
+//! can be serialized again.
 
 use std::collections::HashMap;
 
+use crate::bits;
+
 /// Errors that can occur when applying a transform to a raw value.
-#[cfg(feature = "transform")]
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TransformError {
     /// The raw value cannot be interpreted as the requested base type.
     InvalidBase,
@@ -30,11 +34,14 @@ pub enum TransformError {
     InvalidByteValue,
     /// An ASCII-encoded byte is outside 0..=0x7F.
     InvalidAsciiByteValue,
-    /// Scale or offset is non-finite (NaN or infinity).
+    /// Scale or offset is non-finite (NaN or infinity), or scale is zero when inverting.
     InvalidScaleOffset,
+    /// A string label has no entry in the enum map (when inverting).
+    InvalidEnumLabel(String),
+    /// An inverted value does not fit in the raw integer type (e.g. NaN or out of range).
+    NotRepresentable,
 }
 
-#[cfg(feature = "transform")]
 impl std::fmt::Display for TransformError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -45,11 +52,14 @@ impl std::fmt::Display for TransformError {
             Self::InvalidByteValue => write!(f, "byte element is outside 0..=255"),
             Self::InvalidAsciiByteValue => write!(f, "ASCII byte is outside 0..=0x7F"),
             Self::InvalidScaleOffset => write!(f, "scale or offset is non-finite"),
+            Self::InvalidEnumLabel(label) => {
+                write!(f, "label '{label}' has no entry in the enum map")
+            }
+            Self::NotRepresentable => write!(f, "value cannot be represented as a raw integer"),
         }
     }
 }
 
-#[cfg(feature = "transform")]
 impl std::error::Error for TransformError {}
 
 /// Base interpretation for raw assembly values.
@@ -78,6 +88,162 @@ pub enum Encoding {
     Utf8,
     /// ASCII. Every byte must be in 0..=0x7F.
     Ascii,
+    /// ISO-8859-1. Every byte is the code point of the same value, so any bytes
+    /// decode; only characters up to U+00FF encode.
+    Latin1,
+    /// UTF-16, little-endian code units. Surrogates must pair up.
+    Utf16Le,
+    /// UTF-16, big-endian code units. Surrogates must pair up.
+    Utf16Be,
+    /// EBCDIC code page 037 (US/Canada). Covers the same characters as Latin-1.
+    Ebcdic,
+    /// 6-bit packed ASCII as used by AIS: four characters per three bytes, MSB
+    /// first, with values 0..=31 standing for `@`..=`_` and 32..=63 for ` `..=`?`.
+    /// Bits left over after the last whole character are ignored, and trailing `@`
+    /// (value 0), which AIS uses as padding, is dropped on decode. Only upper-case
+    /// letters, digits and the punctuation in 0x20..=0x5F encode.
+    SixBit,
+}
+
+/// Latin-1 code point of each EBCDIC (code page 037) byte.
+const CP037: [u8; 256] = [
+    0x00, 0x01, 0x02, 0x03, 0x9C, 0x09, 0x86, 0x7F, 0x97, 0x8D, 0x8E, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F,
+    0x10, 0x11, 0x12, 0x13, 0x9D, 0x85, 0x08, 0x87, 0x18, 0x19, 0x92, 0x8F, 0x1C, 0x1D, 0x1E, 0x1F,
+    0x80, 0x81, 0x82, 0x83, 0x84, 0x0A, 0x17, 0x1B, 0x88, 0x89, 0x8A, 0x8B, 0x8C, 0x05, 0x06, 0x07,
+    0x90, 0x91, 0x16, 0x93, 0x94, 0x95, 0x96, 0x04, 0x98, 0x99, 0x9A, 0x9B, 0x14, 0x15, 0x9E, 0x1A,
+    0x20, 0xA0, 0xE2, 0xE4, 0xE0, 0xE1, 0xE3, 0xE5, 0xE7, 0xF1, 0xA2, 0x2E, 0x3C, 0x28, 0x2B, 0x7C,
+    0x26, 0xE9, 0xEA, 0xEB, 0xE8, 0xED, 0xEE, 0xEF, 0xEC, 0xDF, 0x21, 0x24, 0x2A, 0x29, 0x3B, 0xAC,
+    0x2D, 0x2F, 0xC2, 0xC4, 0xC0, 0xC1, 0xC3, 0xC5, 0xC7, 0xD1, 0xA6, 0x2C, 0x25, 0x5F, 0x3E, 0x3F,
+    0xF8, 0xC9, 0xCA, 0xCB, 0xC8, 0xCD, 0xCE, 0xCF, 0xCC, 0x60, 0x3A, 0x23, 0x40, 0x27, 0x3D, 0x22,
+    0xD8, 0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0xAB, 0xBB, 0xF0, 0xFD, 0xFE, 0xB1,
+    0xB0, 0x6A, 0x6B, 0x6C, 0x6D, 0x6E, 0x6F, 0x70, 0x71, 0x72, 0xAA, 0xBA, 0xE6, 0xB8, 0xC6, 0xA4,
+    0xB5, 0x7E, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7A, 0xA1, 0xBF, 0xD0, 0xDD, 0xDE, 0xAE,
+    0x5E, 0xA3, 0xA5, 0xB7, 0xA9, 0xA7, 0xB6, 0xBC, 0xBD, 0xBE, 0x5B, 0x5D, 0xAF, 0xA8, 0xB4, 0xD7,
+    0x7B, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0xAD, 0xF4, 0xF6, 0xF2, 0xF3, 0xF5,
+    0x7D, 0x4A, 0x4B, 0x4C, 0x4D, 0x4E, 0x4F, 0x50, 0x51, 0x52, 0xB9, 0xFB, 0xFC, 0xF9, 0xFA, 0xFF,
+    0x5C, 0xF7, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5A, 0xB2, 0xD4, 0xD6, 0xD2, 0xD3, 0xD5,
+    0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0xB3, 0xDB, 0xDC, 0xD9, 0xDA, 0x9F,
+];
+
+impl Encoding {
+    /// Decodes `bytes` into text, or `None` if they are not valid in this encoding.
+    pub(crate) fn decode(&self, bytes: Vec<u8>) -> Option<String> {
+        match self {
+            Encoding::Utf8 => String::from_utf8(bytes).ok(),
+            Encoding::Ascii if bytes.is_ascii() => String::from_utf8(bytes).ok(),
+            Encoding::Ascii => None,
+            Encoding::Latin1 => Some(bytes.iter().map(|b| char::from(*b)).collect()),
+            Encoding::Utf16Le => decode_utf16(&bytes, u16::from_le_bytes),
+            Encoding::Utf16Be => decode_utf16(&bytes, u16::from_be_bytes),
+            Encoding::Ebcdic => Some(
+                bytes
+                    .iter()
+                    .map(|b| char::from(CP037[*b as usize]))
+                    .collect(),
+            ),
+            Encoding::SixBit => {
+                let chars = (0..bytes.len() * 8 / 6).map(|i| {
+                    let value = bits::read_bits_at(&bytes, i * 6, 6).unwrap_or(0) as u8;
+                    char::from(if value < 32 { value + 0x40 } else { value })
+                });
+                let mut text: String = chars.collect();
+                text.truncate(text.trim_end_matches('@').len());
+                Some(text)
+            }
+        }
+    }
+
+    /// Encodes `text` into bytes, or `None` if it holds characters this encoding
+    /// cannot represent.
+    pub(crate) fn encode(&self, text: &str) -> Option<Vec<u8>> {
+        match self {
+            Encoding::Utf8 => Some(text.as_bytes().to_vec()),
+            Encoding::Ascii => text.is_ascii().then(|| text.as_bytes().to_vec()),
+            Encoding::Latin1 => text.chars().map(|c| u8::try_from(c).ok()).collect(),
+            Encoding::Utf16Le => Some(text.encode_utf16().flat_map(u16::to_le_bytes).collect()),
+            Encoding::Utf16Be => Some(text.encode_utf16().flat_map(u16::to_be_bytes).collect()),
+            Encoding::Ebcdic => text
+                .chars()
+                .map(|c| {
+                    let latin1 = u8::try_from(c).ok()?;
+                    CP037.iter().position(|b| *b == latin1).map(|i| i as u8)
+                })
+                .collect(),
+            Encoding::SixBit => {
+                let mut bytes = vec![0u8; (text.len() * 6).div_ceil(8)];
+                for (i, c) in text.chars().enumerate() {
+                    if !matches!(c, ' '..='_') {
+                        return None;
+                    }
+                    bits::write_bits_at(&mut bytes, i * 6, 6, u64::from(c as u8 & 0x3F)).ok()?;
+                }
+                Some(bytes)
+            }
+        }
+    }
+
+    /// Bytes per code unit: 2 for UTF-16, 1 otherwise. Terminators and padding are
+    /// matched a whole code unit at a time.
+    pub(crate) fn unit_bytes(&self) -> usize {
+        match self {
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Decodes UTF-16 from `bytes`, reading each code unit with `unit`. Fails on an odd
+/// byte count or unpaired surrogates.
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Option<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    char::decode_utf16(units).collect::<Result<_, _>>().ok()
+}
+
+#[cfg(feature = "serde")]
+impl From<crate::serde::EncodingDef> for Encoding {
+    fn from(value: crate::serde::EncodingDef) -> Self {
+        match value {
+            crate::serde::EncodingDef::Utf8 => Encoding::Utf8,
+            crate::serde::EncodingDef::Ascii => Encoding::Ascii,
+            crate::serde::EncodingDef::Latin1 => Encoding::Latin1,
+            crate::serde::EncodingDef::Utf16Le => Encoding::Utf16Le,
+            crate::serde::EncodingDef::Utf16Be => Encoding::Utf16Be,
+            crate::serde::EncodingDef::Ebcdic => Encoding::Ebcdic,
+            crate::serde::EncodingDef::SixBit => Encoding::SixBit,
+        }
+    }
+}
+
+/// Rounding applied when an inverse transform turns a scaled value back into an integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rounding {
+    /// Round to the nearest integer, ties away from zero.
+    #[default]
+    Nearest,
+    /// Round to the nearest integer, ties to even.
+    NearestEven,
+    /// Round toward negative infinity.
+    Floor,
+    /// Round toward positive infinity.
+    Ceil,
+    /// Round toward zero.
+    Truncate,
+}
+
+#[cfg(feature = "serde")]
+impl From<crate::serde::RoundingDef> for Rounding {
+    fn from(value: crate::serde::RoundingDef) -> Self {
+        match value {
+            crate::serde::RoundingDef::Nearest => Rounding::Nearest,
+            crate::serde::RoundingDef::NearestEven => Rounding::NearestEven,
+            crate::serde::RoundingDef::Floor => Rounding::Floor,
+            crate::serde::RoundingDef::Ceil => Rounding::Ceil,
+            crate::serde::RoundingDef::Truncate => Rounding::Truncate,
+        }
+    }
 }
 
 /// Configuration for transforming raw [`crate::value::Value`] into [`crate::value::Value`]s.
@@ -134,11 +300,7 @@ impl TryFrom<crate::serde::TransformDef> for Transform {
             },
             scale: value.scale,
             offset: value.offset,
-            encoding: match value.encoding {
-                Some(crate::serde::EncodingDef::Utf8) => Some(Encoding::Utf8),
-                Some(crate::serde::EncodingDef::Ascii) => Some(Encoding::Ascii),
-                None => None,
-            },
+            encoding: value.encoding.map(Into::into),
             zero_terminated: value.zero_terminated,
             trim: value.trim,
             enum_map: value.enum_map.clone(),
@@ -220,7 +382,8 @@ impl Transform {
     /// Transforms a raw value into a [`crate::value::Value`].
     ///
     /// Validates the transform configuration first. For arrays, applies the transform
-    /// to each element. For `Base::Bytes`, expects an array of byte-sized values.
+    /// to each element. For `Base::Bytes`, expects an array of byte-sized values or
+    /// the bytes of a [`crate::field::FieldKind::Bytes`] field.
     pub fn apply(&self, raw: crate::value::Value) -> Result<crate::value::Value, TransformError> {
         use crate::value::Value;
         self.validate()?;
@@ -267,6 +430,52 @@ impl Transform {
 
         Ok(())
     }
+
+    /// Inverts a single scalar value (no array handling).
+    fn invert_scalar(
+        &self,
+        value: crate::value::Value,
+        rounding: Rounding,
+    ) -> Result<crate::value::Value, TransformError> {
+        let mut v = invert_enum(value, &self.enum_map)?;
+        v = invert_numeric_modifiers(v, &self.base, self.scale, self.offset, rounding)?;
+        invert_base(&self.base, v)
+    }
+
+    /// Turns a transformed value back into a raw value accepted by
+    /// [`crate::schema::Schema::serialize`].
+    ///
+    /// Runs the steps of [`apply`](Transform::apply) in reverse: string encoding,
+    /// enum label lookup, scale/offset (rounded to an integer with `rounding` for
+    /// `Base::Int`), then base reinterpretation (floats become their raw bits).
+    /// For `Base::Bytes` the result is an array of byte values. Raw values that
+    /// are already in the output shape of a step pass through it unchanged.
+    pub fn invert(
+        &self,
+        value: crate::value::Value,
+        rounding: Rounding,
+    ) -> Result<crate::value::Value, TransformError> {
+        use crate::value::Value;
+        self.validate()?;
+
+        if self.base == Base::Bytes {
+            let bytes = invert_string(value, &self.encoding)?;
+            return Ok(Value::Array(
+                bytes.into_iter().map(|b| Value::U64(b as u64)).collect(),
+            ));
+        }
+
+        match value {
+            Value::Array(values) => {
+                let mut out = Vec::with_capacity(values.len());
+                for v in values {
+                    out.push(self.invert_scalar(v, rounding)?);
+                }
+                Ok(Value::Array(out))
+            }
+            _ => self.invert_scalar(value, rounding),
+        }
+    }
 }
 
 /// Interprets a raw assembly value according to the given base type (int/float32/float64).
@@ -278,6 +487,8 @@ fn reinterpret_base(base: &Base, value: crate::value::Value) -> Result<crate::va
         // INT: preserve sign (do NOT collapse U64 into I64).
         (Base::Int, Value::U64(v)) => Ok(Value::U64(v)),
         (Base::Int, Value::I64(v)) => Ok(Value::I64(v)),
+        (Base::Int, Value::U128(v)) => Ok(Value::U128(v)),
+        (Base::Int, Value::I128(v)) => Ok(Value::I128(v)),
 
         // FLOAT32: reinterpret low 32 bits of U64 as f32.
         (Base::Float32, Value::U64(v)) => Ok(Value::F32(f32::from_bits(v as u32))),
@@ -292,7 +503,8 @@ fn reinterpret_base(base: &Base, value: crate::value::Value) -> Result<crate::va
     }
 }
 
-/// Extracts a byte vector from an array of byte-sized U64/I64 values.
+/// Extracts a byte vector from an array of byte-sized U64/I64 values, or takes
+/// the bytes of a [`crate::value::Value::Bytes`] as they are.
 #[cfg(feature = "transform")]
 fn extract_bytes(raw: crate::value::Value) -> Result<Vec<u8>, TransformError> {
     use crate::value::Value;
@@ -318,6 +530,7 @@ fn extract_bytes(raw: crate::value::Value) -> Result<Vec<u8>, TransformError> {
             }
             Ok(bytes)
         }
+        Value::Bytes(bytes) => Ok(bytes),
         _ => Err(TransformError::InvalidType),
     }
 }
@@ -339,13 +552,15 @@ fn apply_numeric_modifiers(
     match value {
         Value::U64(v) => Ok(Value::F64(v as f64 * scale + offset)),
         Value::I64(v) => Ok(Value::F64(v as f64 * scale + offset)),
+        Value::U128(v) => Ok(Value::F64(v as f64 * scale + offset)),
+        Value::I128(v) => Ok(Value::F64(v as f64 * scale + offset)),
         Value::F32(v) => Ok(Value::F32(v * scale as f32 + offset as f32)),
         Value::F64(v) => Ok(Value::F64(v * scale + offset)),
         other => Ok(other),
     }
 }
 
-/// If encoding is set, decodes bytes to a string (UTF-8 or ASCII), optionally zero-terminated and trimmed.
+/// If encoding is set, decodes bytes to a string, optionally zero-terminated and trimmed.
 #[cfg(feature = "transform")]
 fn apply_string(
     value: crate::value::Value,
@@ -363,21 +578,14 @@ fn apply_string(
         _ => return Err(TransformError::InvalidType),
     };
     if zero_terminated.unwrap_or(false) {
-        if let Some(pos) = bytes.iter().position(|b| *b == 0) {
-            bytes.truncate(pos);
+        let unit = encoding.unit_bytes();
+        if let Some(pos) = bytes.chunks(unit).position(|c| c.iter().all(|b| *b == 0)) {
+            bytes.truncate(pos * unit);
         }
     }
-    let mut s = match encoding {
-        Encoding::Ascii => {
-            for b in &bytes {
-                if *b > 0x7F {
-                    return Err(TransformError::InvalidAsciiByteValue);
-                }
-            }
-            String::from_utf8(bytes).map_err(|_| TransformError::InvalidEncoding)?
-        }
-        Encoding::Utf8 => String::from_utf8(bytes).map_err(|_| TransformError::InvalidEncoding)?,
-    };
+    let mut s = encoding
+        .decode(bytes)
+        .ok_or_else(|| encoding_error(encoding))?;
     if trim.unwrap_or(false) {
         s = s.trim().to_string();
     }
@@ -408,6 +616,141 @@ fn apply_enum(
     }
 }
 
+/// Maps a string label back to its integer code. Integers pass through unchanged.
+#[cfg(feature = "transform")]
+fn invert_enum(
+    value: crate::value::Value,
+    enum_map: &Option<HashMap<i64, String>>,
+) -> Result<crate::value::Value, TransformError> {
+    use crate::value::Value;
+    let Some(map) = enum_map else {
+        return Ok(value);
+    };
+    match value {
+        Value::String(label) => map
+            .iter()
+            .filter(|(_, l)| **l == label)
+            .map(|(code, _)| *code)
+            .min()
+            .map(Value::I64)
+            .ok_or(TransformError::InvalidEnumLabel(label)),
+        Value::U64(_) | Value::I64(_) | Value::U128(_) | Value::I128(_) => Ok(value),
+        _ => Err(TransformError::InvalidType),
+    }
+}
+
+/// Undoes scale and offset: (value - offset) / scale. For `Base::Int` the result
+/// is rounded to an integer using `rounding`.
+#[cfg(feature = "transform")]
+fn invert_numeric_modifiers(
+    value: crate::value::Value,
+    base: &Base,
+    scale: Option<f64>,
+    offset: Option<f64>,
+    rounding: Rounding,
+) -> Result<crate::value::Value, TransformError> {
+    use crate::value::Value;
+    let scale = scale.unwrap_or(1.0);
+    let offset = offset.unwrap_or(0.0);
+    if scale == 0.0 || !offset.is_finite() {
+        return Err(TransformError::InvalidScaleOffset);
+    }
+
+    let unscaled = match value {
+        Value::F32(v) if *base == Base::Float32 => {
+            return Ok(Value::F32((v - offset as f32) / scale as f32));
+        }
+        Value::F32(v) => (v as f64 - offset) / scale,
+        Value::F64(v) => (v - offset) / scale,
+        Value::U64(_) | Value::I64(_) | Value::U128(_) | Value::I128(_)
+            if scale == 1.0 && offset == 0.0 =>
+        {
+            return Ok(value);
+        }
+        Value::U64(v) => (v as f64 - offset) / scale,
+        Value::I64(v) => (v as f64 - offset) / scale,
+        Value::U128(v) => (v as f64 - offset) / scale,
+        Value::I128(v) => (v as f64 - offset) / scale,
+        other => return Ok(other),
+    };
+
+    match base {
+        Base::Int => round_to_int(unscaled, rounding),
+        Base::Float32 => Ok(Value::F32(unscaled as f32)),
+        _ => Ok(Value::F64(unscaled)),
+    }
+}
+
+/// Rounds `x` to an integer, returning `I64` for negative results and `U64` otherwise.
+#[cfg(feature = "transform")]
+fn round_to_int(x: f64, rounding: Rounding) -> Result<crate::value::Value, TransformError> {
+    use crate::value::Value;
+    let r = match rounding {
+        Rounding::Nearest => x.round(),
+        Rounding::NearestEven => x.round_ties_even(),
+        Rounding::Floor => x.floor(),
+        Rounding::Ceil => x.ceil(),
+        Rounding::Truncate => x.trunc(),
+    };
+    if !r.is_finite() || r < i64::MIN as f64 || r >= u64::MAX as f64 {
+        return Err(TransformError::NotRepresentable);
+    }
+    if r < 0.0 {
+        Ok(Value::I64(r as i64))
+    } else {
+        Ok(Value::U64(r as u64))
+    }
+}
+
+/// Converts a value back to the raw integer expected for `base`. Floats become their IEEE 754 bits.
+#[cfg(feature = "transform")]
+fn invert_base(
+    base: &Base,
+    value: crate::value::Value,
+) -> Result<crate::value::Value, TransformError> {
+    use crate::value::Value;
+    match (base, value) {
+        (_, Value::U64(v)) => Ok(Value::U64(v)),
+        (Base::Int, Value::I64(v)) => Ok(Value::I64(v)),
+        (_, Value::U128(v)) => Ok(Value::U128(v)),
+        (Base::Int, Value::I128(v)) => Ok(Value::I128(v)),
+
+        (Base::Float32, Value::F32(v)) => Ok(Value::U64(v.to_bits() as u64)),
+        (Base::Float32, Value::F64(v)) => Ok(Value::U64((v as f32).to_bits() as u64)),
+
+        (Base::Float64, Value::F64(v)) => Ok(Value::U64(v.to_bits())),
+        (Base::Float64, Value::F32(v)) => Ok(Value::U64((v as f64).to_bits())),
+
+        _ => Err(TransformError::InvalidBase),
+    }
+}
+
+/// Encodes a string (or passes through bytes / byte arrays) back into raw bytes.
+#[cfg(feature = "transform")]
+fn invert_string(
+    value: crate::value::Value,
+    encoding: &Option<Encoding>,
+) -> Result<Vec<u8>, TransformError> {
+    use crate::value::Value;
+    match (value, encoding) {
+        (Value::Bytes(bytes), _) => Ok(bytes),
+        (Value::String(s), Some(encoding)) => {
+            encoding.encode(&s).ok_or_else(|| encoding_error(encoding))
+        }
+        (raw @ Value::Array(_), _) => extract_bytes(raw),
+        _ => Err(TransformError::InvalidType),
+    }
+}
+
+/// Error for text that `encoding` cannot decode or encode.
+#[cfg(feature = "transform")]
+fn encoding_error(encoding: &Encoding) -> TransformError {
+    match encoding {
+        Encoding::Ascii => TransformError::InvalidAsciiByteValue,
+        _ => TransformError::InvalidEncoding,
+    }
+}
+
 #[cfg(all(test, feature = "transform"))]
 use crate::value::Value;
 
@@ -695,3 +1038,170 @@ fn test_byte_array() {
         Value::Bytes(String::from("Hello").as_bytes().to_vec())
     );
 }
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_invert_scale_offset_rounding() {
+    let mut transform = Transform::new(Base::Int);
+    transform.set_scale(0.5).set_offset(10.0);
+
+    assert_eq!(
+        transform
+            .invert(Value::F64(20.0), Rounding::Nearest)
+            .unwrap(),
+        Value::U64(20)
+    );
+    assert_eq!(
+        transform
+            .invert(Value::F64(20.3), Rounding::Nearest)
+            .unwrap(),
+        Value::U64(21)
+    );
+    assert_eq!(
+        transform.invert(Value::F64(20.3), Rounding::Floor).unwrap(),
+        Value::U64(20)
+    );
+    assert_eq!(
+        transform
+            .invert(Value::F64(9.0), Rounding::Nearest)
+            .unwrap(),
+        Value::I64(-2)
+    );
+    assert_eq!(
+        transform.invert(Value::F64(f64::NAN), Rounding::Nearest),
+        Err(TransformError::NotRepresentable)
+    );
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_invert_enum() {
+    let mut transform = Transform::new(Base::Int);
+    transform.set_enum_map(HashMap::from([
+        (1, "one".to_string()),
+        (2, "two".to_string()),
+    ]));
+
+    assert_eq!(
+        transform
+            .invert(Value::String("two".to_string()), Rounding::Nearest)
+            .unwrap(),
+        Value::I64(2)
+    );
+    assert_eq!(
+        transform.invert(Value::String("three".to_string()), Rounding::Nearest),
+        Err(TransformError::InvalidEnumLabel("three".to_string()))
+    );
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_invert_floats_to_bits() {
+    let transform = Transform::new(Base::Float32);
+    assert_eq!(
+        transform
+            .invert(Value::F32(3.5), Rounding::Nearest)
+            .unwrap(),
+        Value::U64(3.5f32.to_bits() as u64)
+    );
+
+    let transform_64 = Transform::new(Base::Float64);
+    assert_eq!(
+        transform_64
+            .invert(Value::F64(-1.25), Rounding::Nearest)
+            .unwrap(),
+        Value::U64((-1.25f64).to_bits())
+    );
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_invert_string() {
+    let mut transform = Transform::new(Base::Bytes);
+    transform.set_encoding(Encoding::Ascii);
+
+    assert_eq!(
+        transform
+            .invert(Value::String("Hi".to_string()), Rounding::Nearest)
+            .unwrap(),
+        Value::Array(vec![Value::U64(b'H' as u64), Value::U64(b'i' as u64)])
+    );
+    assert_eq!(
+        transform.invert(Value::String("Hé".to_string()), Rounding::Nearest),
+        Err(TransformError::InvalidAsciiByteValue)
+    );
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_string_encodings_roundtrip() {
+    let cases: [(Encoding, &str, &[u8]); 6] = [
+        (Encoding::Latin1, "Café", b"Caf\xE9"),
+        (Encoding::Utf16Le, "Hé€", b"H\0\xE9\0\xAC\x20"),
+        (Encoding::Utf16Be, "Hé€", b"\0H\0\xE9\x20\xAC"),
+        (Encoding::Ebcdic, "Hi 1!", b"\xC8\x89\x40\xF1\x5A"),
+        // "AB" is 000001 000010, padded to a byte with zeros.
+        (Encoding::SixBit, "AB", b"\x04\x20"),
+        // Three characters fill 18 bits; the 6 bits of padding decode as `@`.
+        (Encoding::SixBit, "N0?", b"\x3B\x0F\xC0"),
+    ];
+    for (encoding, text, bytes) in cases {
+        let mut transform = Transform::new(Base::Bytes);
+        transform.set_encoding(encoding.clone());
+        assert_eq!(
+            transform.apply(Value::Bytes(bytes.to_vec())).unwrap(),
+            Value::String(text.to_string()),
+            "{encoding:?}"
+        );
+        let raw: Vec<Value> = bytes.iter().map(|b| Value::U64(u64::from(*b))).collect();
+        assert_eq!(
+            transform
+                .invert(Value::String(text.to_string()), Rounding::Nearest)
+                .unwrap(),
+            Value::Array(raw),
+            "{encoding:?}"
+        );
+    }
+
+    let mut transform = Transform::new(Base::Bytes);
+    transform.set_encoding(Encoding::Utf16Le);
+    transform.zero_terminated = Some(true);
+    assert_eq!(
+        transform.apply(Value::Bytes(b"A\0\0\x01\0\0B\0".to_vec())),
+        Ok(Value::String("A\u{100}".to_string()))
+    );
+    assert_eq!(
+        transform.apply(Value::Bytes(b"A\0B".to_vec())),
+        Err(TransformError::InvalidEncoding)
+    );
+
+    for (encoding, text) in [
+        (Encoding::Latin1, "€"),
+        (Encoding::Ebcdic, "€"),
+        (Encoding::SixBit, "abc"),
+    ] {
+        transform.set_encoding(encoding);
+        assert_eq!(
+            transform.invert(Value::String(text.to_string()), Rounding::Nearest),
+            Err(TransformError::InvalidEncoding)
+        );
+    }
+}
+
+#[cfg(feature = "transform")]
+#[test]
+fn test_apply_invert_roundtrip() {
+    let mut transform = Transform::new(Base::Int);
+    transform.set_scale(0.1).set_offset(-40.0);
+
+    for raw in [0u64, 1, 399, 400, 1023] {
+        let cooked = transform.apply(Value::U64(raw)).unwrap();
+        let back = transform.invert(cooked, Rounding::Nearest).unwrap();
+        let back = match back {
+            Value::I64(v) => v as u64,
+            Value::U64(v) => v,
+            other => panic!("unexpected {other:?}"),
+        };
+        assert_eq!(back, raw);
+    }
+}
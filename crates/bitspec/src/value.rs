@@ -1,13 +1,21 @@
 //! Unified `Value` type used across parse, transform, and serialize.
 //!
 //! This type replaces the previous pair of `assembly::Value` / `transform::Value`.
-//! Parse emits `U64`, `I64`, or `Array`. Transforms can additionally produce
-//! `F32`, `F64`, `Bytes`, or `String`. Serialize accepts only `U64`, `I64`, and
-//! `Array` — passing `F32`/`F64`/`Bytes`/`String` returns [`crate::errors::WriteError::UnsupportedValue`].
+//! Parse emits `U64`, `I64`, `Array`, or `Struct`, and `Absent` for conditional
+//! fields whose condition is false. Fields wider than 64 bits (up to 128) parse as
+//! `U128` or `I128`, [`crate::field::FieldKind::Bytes`] fields as `Bytes`, and
+//! [`crate::field::FieldKind::String`] fields as `String`.
+//! Transforms can additionally produce
+//! `F32`, `F64`, `Bytes`, or `String`. Serialize accepts only the integer variants,
+//! `Array`, and `Struct`, plus `Bytes` and `String` for bytes and string fields —
+//! passing `F32`/`F64`, or `Bytes`/`String` to any other field, returns
+//! [`crate::errors::WriteError::UnsupportedValue`].
+//! `Schema::serialize_transformed` (with the `transform` feature) accepts transformed
+//! values by inverting each field's transform first.
 //!
 //! The serde representation is externally tagged: `{"U64": 42}`, `{"I64": -1}`,
-//! `{"F32": 1.5}`, `{"F64": 3.14}`, `{"Bytes": [1, 2, 3]}`, `{"String": "x"}`,
-//! `{"Array": [ ... ]}`. This shape is what the TypeScript wrapper produces.
+//! `{"U128": 42}`, `{"I128": -1}`, `{"F32": 1.5}`, `{"F64": 3.14}`, `{"Bytes": [1, 2, 3]}`, `{"String": "x"}`,
+//! `{"Array": [ ... ]}`, `{"Struct": {"id": {"U64": 1}, ...}}`, `"Absent"`. This shape is what the TypeScript wrapper produces.
 //!
 //! ## Example
 //!
@@ -16,12 +24,14 @@
 //!
 //! fn describe(v: &Value) -> &'static str {
 //!     match v {
-//!         Value::U64(_) => "unsigned int",
-//!         Value::I64(_) => "signed int",
+//!         Value::U64(_) | Value::U128(_) => "unsigned int",
+//!         Value::I64(_) | Value::I128(_) => "signed int",
 //!         Value::F32(_) | Value::F64(_) => "float",
 //!         Value::Bytes(_) => "bytes",
 //!         Value::String(_) => "string",
 //!         Value::Array(_) => "array",
+//!         Value::Struct(_) => "struct",
+//!         Value::Absent => "absent",
 //!     }
 //! }
 //!
@@ -29,6 +39,8 @@
 //! assert_eq!(describe(&Value::String("x".into())), "string");
 //! ```
 
+use std::collections::BTreeMap;
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
@@ -40,6 +52,10 @@ pub enum Value {
     U64(u64),
     /// Signed 64-bit integer. Emitted by parse for signed fields.
     I64(i64),
+    /// Unsigned 128-bit integer. Emitted by parse for unsigned fields wider than 64 bits.
+    U128(u128),
+    /// Signed 128-bit integer. Emitted by parse for signed fields wider than 64 bits.
+    I128(i128),
     /// 32-bit floating-point value. Emitted by transforms with `Base::Float32`.
     F32(f32),
     /// 64-bit floating-point value. Emitted by transforms with `Base::Float64` or scale/offset.
@@ -50,4 +66,54 @@ pub enum Value {
     String(String),
     /// Array of values. Emitted by parse for array fields and propagated by transforms.
     Array(Vec<Value>),
+    /// Named sub-values. Emitted by parse for struct fields.
+    Struct(BTreeMap<String, Value>),
+    /// Placeholder for a conditional field whose condition did not hold.
+    /// Serialize treats it like a missing entry.
+    Absent,
+}
+
+/// A [`Value`] compared bit for bit, which makes it [`Eq`]: floats are equal when
+/// their bits are, so `NaN` equals itself and `0.0` differs from `-0.0`. Errors
+/// that carry values, such as [`crate::errors::ReadError::ConstantMismatch`], hold
+/// them this way so that the error types stay `Eq`.
+///
+/// ```
+/// use bitspec::value::{ExactValue, Value};
+///
+/// assert_eq!(ExactValue(Value::F64(f64::NAN)), ExactValue(Value::F64(f64::NAN)));
+/// assert_ne!(ExactValue(Value::F64(0.0)), ExactValue(Value::F64(-0.0)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExactValue(pub Value);
+
+impl PartialEq for ExactValue {
+    fn eq(&self, other: &Self) -> bool {
+        exact_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for ExactValue {}
+
+impl From<Value> for ExactValue {
+    fn from(value: Value) -> Self {
+        ExactValue(value)
+    }
+}
+
+fn exact_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::F32(a), Value::F32(b)) => a.to_bits() == b.to_bits(),
+        (Value::F64(a), Value::F64(b)) => a.to_bits() == b.to_bits(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| exact_eq(a, b))
+        }
+        (Value::Struct(a), Value::Struct(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|((ka, a), (kb, b))| ka == kb && exact_eq(a, b))
+        }
+        (a, b) => a == b,
+    }
 }
@@ -0,0 +1,221 @@
+//! `#[derive(BitSpec)]`: generated fields, typed parse, and typed serialize.
+
+use std::collections::BTreeMap;
+
+use bitspec::{
+    assembly::{ArrayCount, Assemble, BitOrder},
+    derive::{BitSpec, BitValue, take_field},
+    errors::ReadError,
+    field::{ArraySpec, BytesPadding, Field, FieldKind},
+    fragment::Fragment,
+    schema::Schema,
+    value::Value,
+};
+use bitspec_derive::BitSpec;
+
+#[derive(BitSpec, Debug, PartialEq)]
+struct Header {
+    #[bits(offset = 0, len = 4)]
+    version: u8,
+    #[bits(offset = 4, len = 12, signed)]
+    temperature: i16,
+    flag: bool,
+}
+
+#[derive(BitSpec, Debug, PartialEq)]
+struct Point {
+    x: u8,
+    y: u8,
+}
+
+#[derive(BitSpec, Debug, PartialEq)]
+struct Packet {
+    #[bits(len = 7)]
+    kind: u8,
+    #[bits(lsb, len = 3)]
+    mode: u8,
+    #[bits(offset = 16)]
+    origin: Point,
+    #[bits(len = 4)]
+    nibbles: [u8; 2],
+    #[bits(stride = 24)]
+    points: [Point; 2],
+}
+
+#[derive(BitSpec, Debug, PartialEq)]
+struct Tagged {
+    id: u128,
+    #[bits(len = 96)]
+    time: i128,
+}
+
+/// Holds a bytes field, which the derive has no attribute for, so it implements
+/// the traits by hand.
+#[derive(Debug, PartialEq)]
+struct Blob {
+    items: [u8; 2],
+    payload: Vec<u8>,
+}
+
+impl BitSpec for Blob {
+    fn fields() -> Vec<Field> {
+        let field = |name: &str, kind| Field {
+            name: name.to_string(),
+            kind,
+            signed: false,
+            assemble: Assemble::Concat(BitOrder::MsbFirst),
+            fragments: vec![Fragment::new(0, 8)],
+            transform: None,
+            condition: None,
+            default: None,
+            constant: None,
+        };
+        let items = FieldKind::Array(ArraySpec {
+            count: ArrayCount::Fixed(2),
+            stride_bits: 8,
+            offset_bits: 0,
+            element: None,
+        });
+        let payload = FieldKind::Bytes {
+            offset_bits: 16,
+            len_bits: 16,
+            padding: BytesPadding::Trailing,
+        };
+        vec![field("items", items), field("payload", payload)]
+    }
+
+    fn schema() -> &'static Schema {
+        static SCHEMA: std::sync::OnceLock<Schema> = std::sync::OnceLock::new();
+        SCHEMA.get_or_init(|| Schema::compile(&Self::fields(), None).unwrap())
+    }
+
+    fn from_values(values: &BTreeMap<String, Value>) -> Result<Self, ReadError> {
+        let payload = match values.get("payload") {
+            Some(Value::Bytes(payload)) => payload.clone(),
+            _ => return Err(ReadError::UnknownField("payload".to_string())),
+        };
+        Ok(Blob {
+            items: take_field(values, "items")?,
+            payload,
+        })
+    }
+
+    fn to_values(&self) -> BTreeMap<String, Value> {
+        BTreeMap::from([
+            ("items".to_string(), self.items.to_value()),
+            ("payload".to_string(), Value::Bytes(self.payload.clone())),
+        ])
+    }
+}
+
+impl BitValue for Blob {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Struct(values) => Blob::from_values(values).ok(),
+            _ => None,
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Struct(self.to_values())
+    }
+}
+
+#[derive(BitSpec, Debug, PartialEq)]
+struct Framed {
+    head: u8,
+    blob: Blob,
+    tail: u8,
+}
+
+/// Overlaps a nested struct, whose size the derive cannot see, so the layout is
+/// only rejected when the schema is compiled.
+#[derive(BitSpec)]
+struct Overlapping {
+    #[bits(offset = 0)]
+    a: u8,
+    #[bits(offset = 4)]
+    point: Point,
+}
+
+#[test]
+fn test_derive_parses_and_serializes_scalars() {
+    let header = Header::from_bytes(&[0x1F, 0xFE, 0x80]).unwrap();
+    assert_eq!(
+        header,
+        Header {
+            version: 1,
+            temperature: -2,
+            flag: true,
+        }
+    );
+    assert_eq!(header.to_bytes().unwrap(), vec![0x1F, 0xFE, 0x80]);
+
+    let fields = Header::fields();
+    assert_eq!(fields[2].name, "flag");
+    assert_eq!(fields[2].fragments[0].offset_bits, 16);
+    assert!(fields[1].signed);
+}
+
+#[test]
+fn test_derive_lays_out_nested_structs_and_arrays() {
+    let packet = Packet {
+        kind: 0x05,
+        mode: 0b001,
+        origin: Point { x: 0x12, y: 0x34 },
+        nibbles: [0xA, 0xB],
+        points: [Point { x: 1, y: 2 }, Point { x: 3, y: 4 }],
+    };
+    let bytes = packet.to_bytes().unwrap();
+    assert_eq!(
+        bytes,
+        vec![0x0B, 0x00, 0x12, 0x34, 0xAB, 0x01, 0x02, 0x00, 0x03, 0x04]
+    );
+    assert_eq!(Packet::from_bytes(&bytes).unwrap(), packet);
+
+    let parsed = Packet::schema().parse(&bytes).unwrap();
+    let origin = match &parsed["origin"] {
+        Value::Struct(origin) => origin,
+        other => panic!("expected a struct, got {other:?}"),
+    };
+    assert_eq!(origin["y"], Value::U64(0x34));
+    assert!(matches!(Packet::fields()[4].kind, FieldKind::Array(_)));
+}
+
+#[test]
+fn test_derive_reads_fields_wider_than_64_bits() {
+    let tagged = Tagged {
+        id: 0x0011_2233_4455_6677_8899_AABB_CCDD_EEFF,
+        time: -5,
+    };
+    let bytes = tagged.to_bytes().unwrap();
+    assert_eq!(bytes.len(), 28);
+    assert_eq!(bytes[..2], [0x00, 0x11]);
+    assert_eq!(bytes[16..27], [0xFF; 11]);
+    assert_eq!(bytes[27], 0xFB);
+    assert_eq!(Tagged::from_bytes(&bytes).unwrap(), tagged);
+
+    let parsed = Tagged::schema().parse(&bytes).unwrap();
+    assert_eq!(parsed["time"], Value::I128(-5));
+}
+
+#[test]
+#[should_panic(expected = "invalid bit layout for Overlapping")]
+fn test_derive_panics_on_overlapping_layout() {
+    let _ = Overlapping::schema();
+}
+
+#[test]
+fn test_derive_places_nested_arrays_and_bytes_at_their_offset() {
+    let framed = Framed {
+        head: 1,
+        blob: Blob {
+            items: [2, 3],
+            payload: vec![4, 5],
+        },
+        tail: 6,
+    };
+    let bytes = framed.to_bytes().unwrap();
+    assert_eq!(bytes, vec![1, 2, 3, 4, 5, 6]);
+    assert_eq!(Framed::from_bytes(&bytes).unwrap(), framed);
+}
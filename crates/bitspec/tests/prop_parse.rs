@@ -6,7 +6,7 @@ use bitspec::{
     assembly::{Assemble, BitOrder},
     field::{Field, FieldKind},
     fragment::Fragment,
-    schema::Schema,
+    schema::{CompileOptions, Schema},
 };
 use proptest::prelude::*;
 
@@ -20,6 +20,9 @@ fn arb_field(start_bit: usize) -> BoxedStrategy<(Field, usize)> {
                 assemble: Assemble::Concat(BitOrder::MsbFirst),
                 fragments: vec![Fragment::new(start_bit, len_bits)],
                 transform: None,
+                condition: None,
+                default: None,
+                constant: None,
             };
             Just((field, start_bit + len_bits))
         })
@@ -40,7 +43,13 @@ fn arb_dense_schema() -> BoxedStrategy<Schema> {
                 .collect::<Vec<_>>()
                 .prop_map(|pairs: Vec<(Field, usize)>| {
                     let fields: Vec<Field> = pairs.into_iter().map(|(f, _)| f).collect();
-                    Schema::compile(&fields, None).expect("dense schema compiles")
+                    // Fields start every 8 bits but may be up to 32 bits wide.
+                    let options = CompileOptions {
+                        allow_overlap: true,
+                        ..CompileOptions::default()
+                    };
+                    Schema::compile_with_options(&fields, options, None)
+                        .expect("dense schema compiles")
                 })
                 .boxed()
         })
@@ -25,6 +25,9 @@ fn arb_field_and_value(start: usize) -> BoxedStrategy<(Field, (String, Value), u
                 assemble: Assemble::Concat(BitOrder::MsbFirst),
                 fragments: vec![Fragment::new(start, len_bits)],
                 transform: None,
+                condition: None,
+                default: None,
+                constant: None,
             };
             (field, (name, Value::U64(value)), start + len_bits)
         })
@@ -62,6 +65,9 @@ fn arb_schema_and_obj() -> BoxedStrategy<(Schema, BTreeMap<String, Value>)> {
                         assemble: Assemble::Concat(BitOrder::MsbFirst),
                         fragments: vec![Fragment::new(cursor, len_bits)],
                         transform: None,
+                        condition: None,
+                        default: None,
+                        constant: None,
                     });
                     obj.insert(name, Value::U64(value));
                     cursor += len_bits;
@@ -75,6 +81,9 @@ fn arb_schema_and_obj() -> BoxedStrategy<(Schema, BTreeMap<String, Value>)> {
                         assemble: Assemble::Concat(BitOrder::MsbFirst),
                         fragments: vec![Fragment::new(0, 1)],
                         transform: None,
+                        condition: None,
+                        default: None,
+                        constant: None,
                     });
                     obj.insert("_z".to_string(), Value::U64(0));
                 }
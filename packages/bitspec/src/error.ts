@@ -1,32 +1,63 @@
 export type BitspecErrorCode =
   | "INVALID_ARRAY_STRIDE" | "INVALID_ARRAY_COUNT" | "INVALID_FIELD_SIZE"
   | "INVALID_FRAGMENT"     | "INVALID_FIELD_KIND"  | "EMPTY_ARRAY_ELEMENT"
-  | "INVALID_FIELD_NAME"
+  | "INVALID_BYTE_ORDER"
+  | "INVALID_FIELD_NAME"   | "UNKNOWN_FIELD"       | "OVERLAPPING_FIELDS"
   | "READ_OUT_OF_BOUNDS"   | "TOO_MANY_BITS_READ"  | "PACKET_TOO_SHORT"
+  | "INVALID_COUNT"        | "UNMATCHED_CASE"      | "NOT_SCALAR"
+  | "CONSTANT_MISMATCH"    | "CHECKSUM_MISMATCH"
   | "WRITE_OUT_OF_BOUNDS"  | "INVALID_VALUE"       | "MISSING_FIELD"
-  | "UNSUPPORTED_VALUE"
+  | "UNSUPPORTED_VALUE"    | "CASE_MISMATCH"
   | "INVALID_BASE"         | "INVALID_TYPE"        | "INVALID_ENUM_VALUE"
   | "INVALID_ENCODING"     | "INVALID_BYTE_VALUE"  | "INVALID_ASCII_BYTE_VALUE"
-  | "INVALID_SCALE_OFFSET"
+  | "INVALID_SCALE_OFFSET" | "INVALID_ENUM_LABEL"  | "NOT_REPRESENTABLE"
   | "SCHEMA_JSON_PARSE_ERROR" | "INPUT_CONVERSION_ERROR";
 
 const KNOWN_CODES = new Set<BitspecErrorCode>([
   "INVALID_ARRAY_STRIDE", "INVALID_ARRAY_COUNT", "INVALID_FIELD_SIZE",
   "INVALID_FRAGMENT", "INVALID_FIELD_KIND", "EMPTY_ARRAY_ELEMENT",
-  "INVALID_FIELD_NAME",
+  "INVALID_BYTE_ORDER",
+  "INVALID_FIELD_NAME", "UNKNOWN_FIELD", "OVERLAPPING_FIELDS",
   "READ_OUT_OF_BOUNDS", "TOO_MANY_BITS_READ", "PACKET_TOO_SHORT",
+  "INVALID_COUNT", "UNMATCHED_CASE", "NOT_SCALAR",
+  "CONSTANT_MISMATCH", "CHECKSUM_MISMATCH",
   "WRITE_OUT_OF_BOUNDS", "INVALID_VALUE", "MISSING_FIELD",
-  "UNSUPPORTED_VALUE",
+  "UNSUPPORTED_VALUE", "CASE_MISMATCH",
   "INVALID_BASE", "INVALID_TYPE", "INVALID_ENUM_VALUE",
   "INVALID_ENCODING", "INVALID_BYTE_VALUE", "INVALID_ASCII_BYTE_VALUE",
-  "INVALID_SCALE_OFFSET",
+  "INVALID_SCALE_OFFSET", "INVALID_ENUM_LABEL", "NOT_REPRESENTABLE",
   "SCHEMA_JSON_PARSE_ERROR", "INPUT_CONVERSION_ERROR",
 ]);
 
+/** Where an error happened; each member is present only when known. */
+export interface BitspecErrorContext {
+  /** Path of the field, e.g. `header.flags` or `items[3].id`. */
+  field?: string;
+  /** Index of the innermost array element on the path. */
+  element?: number;
+  /** Index of the fragment within the field. */
+  fragment?: number;
+  /** `[start, end)` bits that were requested. */
+  bitRange?: [number, number];
+  /** Number of bits the input holds. */
+  availableBits?: number;
+}
+
 export class BitspecError extends Error {
-  constructor(public readonly code: BitspecErrorCode, message: string) {
+  readonly field?: string;
+  readonly element?: number;
+  readonly fragment?: number;
+  readonly bitRange?: [number, number];
+  readonly availableBits?: number;
+
+  constructor(public readonly code: BitspecErrorCode, message: string, context: BitspecErrorContext = {}) {
     super(`[${code}] ${message}`);
     this.name = "BitspecError";
+    this.field = context.field;
+    this.element = context.element;
+    this.fragment = context.fragment;
+    this.bitRange = context.bitRange;
+    this.availableBits = context.availableBits;
   }
 }
 
@@ -42,7 +73,9 @@ export function translateError(raw: unknown): BitspecError {
     const code = (raw as { code: string }).code;
     const message = (raw as { message: string }).message;
     if (KNOWN_CODES.has(code as BitspecErrorCode)) {
-      return new BitspecError(code as BitspecErrorCode, message);
+      const { field, element, fragment, bitRange, availableBits } = raw as BitspecErrorContext;
+      return new BitspecError(code as BitspecErrorCode, message,
+        { field, element, fragment, bitRange, availableBits });
     }
     return new BitspecError("INVALID_VALUE", `unknown code '${code}': ${message}`);
   }
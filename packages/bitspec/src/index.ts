@@ -1,5 +1,5 @@
 export { Schema } from "./schema.js";
-export { BitspecError, type BitspecErrorCode } from "./error.js";
+export { BitspecError, type BitspecErrorCode, type BitspecErrorContext } from "./error.js";
 export { init } from "./wasm.js";
 export { floatBits32, floatBits64 } from "./helpers.js";
 export type {
@@ -8,10 +8,18 @@ export type {
   FieldDef,
   FragmentDef,
   FieldKindDef,
+  StringFramingDef,
+  ChecksumAlgorithmDef,
+  ChecksumCoverageDef,
+  ConditionDef,
+  ConstantDef,
   AssembleDef,
   BitOrderDef,
   TransformDef,
   BaseDef,
   EncodingDef,
   WriteConfigDef,
+  LayoutDef,
+  RoundingDef,
+  SerializeOptions,
 } from "./types.js";
@@ -1,4 +1,4 @@
-import type { SchemaDef, Value } from "./types.js";
+import type { SchemaDef, SerializeOptions, Value } from "./types.js";
 import { translateError } from "./error.js";
 import { WasmSchema, valueFromWasm, valueToWasm } from "./wasm.js";
 
@@ -10,7 +10,7 @@ export class Schema {
    * Throws `BitspecError` on invalid input.
    */
   static compile(def: SchemaDef | string): Schema {
-    const json = typeof def === "string" ? def : JSON.stringify(def);
+    const json = typeof def === "string" ? def : schemaToJson(def);
     try {
       return new Schema(new WasmSchema(json));
     } catch (e) {
@@ -26,32 +26,115 @@ export class Schema {
     } catch (e) {
       throw translateError(e);
     }
-    const out: Record<string, Value> = {};
-    // serde_wasm_bindgen serializes Rust maps as JS `Map` objects by default,
-    // so we must iterate via the Map protocol rather than `Object.entries`.
-    if (raw instanceof Map) {
-      for (const [k, v] of raw as Map<string, unknown>) {
-        out[k] = valueFromWasm(v);
-      }
-    } else {
-      for (const [k, v] of Object.entries(raw as Record<string, unknown>)) {
-        out[k] = valueFromWasm(v);
-      }
+    return fromWasmMap(raw, valueFromWasm);
+  }
+
+  /**
+   * Parses a buffer of records laid back to back (e.g. a data logger dump) in a
+   * single call, returning one map per record. A trailing partial record throws
+   * `PACKET_TOO_SHORT`.
+   */
+  parseMany(bytes: Uint8Array): Record<string, Value>[] {
+    let raw: unknown;
+    try {
+      raw = this.inner.parse_many(bytes);
+    } catch (e) {
+      throw translateError(e);
     }
-    return out;
+    return (raw as unknown[]).map((record) => fromWasmMap(record, valueFromWasm));
+  }
+
+  /**
+   * Like `parseMany`, but returns one array per top-level field holding that
+   * field's value for every record.
+   */
+  parseColumns(bytes: Uint8Array): Record<string, Value[]> {
+    let raw: unknown;
+    try {
+      raw = this.inner.parse_columns(bytes);
+    } catch (e) {
+      throw translateError(e);
+    }
+    return fromWasmMap(raw, (column) => (column as unknown[]).map(valueFromWasm));
   }
 
   /** Serializes a map of field names to `Value`s into raw bytes. */
   serialize(obj: Record<string, Value>): Uint8Array {
-    const wasm: Record<string, unknown> = {};
-    for (const [k, v] of Object.entries(obj)) {
-      wasm[k] = valueToWasm(v);
+    try {
+      const result = this.inner.serialize(toWasmMap(obj));
+      return new Uint8Array(result);
+    } catch (e) {
+      throw translateError(e);
     }
+  }
+
+  /**
+   * Serializes values in the shape returned by `parse` (after transforms).
+   * Each field's transform is inverted first, so scaled numbers, enum labels,
+   * floats and decoded strings are accepted.
+   */
+  serializeTransformed(obj: Record<string, Value>): Uint8Array {
+    try {
+      const result = this.inner.serialize_transformed(toWasmMap(obj));
+      return new Uint8Array(result);
+    } catch (e) {
+      throw translateError(e);
+    }
+  }
+
+  /**
+   * Like `serialize`, but fills the fields `obj` leaves out from their `default`
+   * (with `useDefaults`) or from a `template` packet. Entries in `obj` always win.
+   * Throws `MISSING_FIELD` if a field is still missing.
+   */
+  serializeWithOptions(obj: Record<string, Value>, options: SerializeOptions = {}): Uint8Array {
     try {
-      const result = this.inner.serialize(wasm);
+      const result = this.inner.serialize_with_options(
+        toWasmMap(obj),
+        options.useDefaults ?? false,
+        options.template,
+      );
       return new Uint8Array(result);
     } catch (e) {
       throw translateError(e);
     }
   }
 }
+
+/** Marks a bigint in the JSON text until it is unquoted. */
+const BIGINT_MARKER = "__bitspec_bigint__";
+
+/**
+ * `JSON.stringify` for a `SchemaDef`, writing bigints (such as `U128` and `I128`
+ * constants) as plain JSON numbers, which `JSON.stringify` cannot do by itself.
+ */
+function schemaToJson(def: SchemaDef): string {
+  const json = JSON.stringify(def, (_key, value: unknown) =>
+    typeof value === "bigint" ? `${BIGINT_MARKER}${value}` : value,
+  );
+  return json.replace(new RegExp(`"${BIGINT_MARKER}(-?\\d+)"`, "g"), "$1");
+}
+
+function toWasmMap(obj: Record<string, Value>): Record<string, unknown> {
+  const wasm: Record<string, unknown> = {};
+  for (const [k, v] of Object.entries(obj)) {
+    wasm[k] = valueToWasm(v);
+  }
+  return wasm;
+}
+
+function fromWasmMap<T>(raw: unknown, convert: (v: unknown) => T): Record<string, T> {
+  const out: Record<string, T> = {};
+  // serde_wasm_bindgen serializes Rust maps as JS `Map` objects by default,
+  // so we must iterate via the Map protocol rather than `Object.entries`.
+  if (raw instanceof Map) {
+    for (const [k, v] of raw as Map<string, unknown>) {
+      out[k] = convert(v);
+    }
+  } else {
+    for (const [k, v] of Object.entries(raw as Record<string, unknown>)) {
+      out[k] = convert(v);
+    }
+  }
+  return out;
+}
@@ -5,17 +5,32 @@
 export type Value =
   | { kind: "u64"; value: bigint }
   | { kind: "i64"; value: bigint }
+  /** Fields wider than 64 bits. */
+  | { kind: "u128"; value: bigint }
+  | { kind: "i128"; value: bigint }
   | { kind: "f32"; value: number }
   | { kind: "f64"; value: number }
   | { kind: "bytes"; value: Uint8Array }
   | { kind: "string"; value: string }
-  | { kind: "array"; value: Value[] };
+  | { kind: "array"; value: Value[] }
+  | { kind: "struct"; value: Record<string, Value> }
+  /** Conditional field whose condition did not hold. */
+  | { kind: "absent" };
 
 /** Bit order used when reading/writing a fragment. */
 export type BitOrderDef = "MsbFirst" | "LsbFirst";
 
-/** How fragments are concatenated. */
-export type AssembleDef = "ConcatMsb" | "ConcatLsb";
+/**
+ * How fragments are concatenated. The endian modes concatenate MSB first and then
+ * reorder the bytes: `ABCD`, `DCBA`, `BADC`, and `CDAB` for a 32-bit `0xAABBCCDD`.
+ */
+export type AssembleDef =
+  | "ConcatMsb"
+  | "ConcatLsb"
+  | "BigEndian"
+  | "LittleEndian"
+  | "BigEndianByteSwap"
+  | "LittleEndianByteSwap";
 
 /** A contiguous bit range within the payload. */
 export interface FragmentDef {
@@ -24,16 +39,113 @@ export interface FragmentDef {
   bit_order?: BitOrderDef;
 }
 
-/** Scalar or fixed-size array field kind. */
+/**
+ * Field kind: a scalar; an array with a fixed count or one taken from another
+ * field; a struct or switch of sub-fields; bytes; a string; a `Skip` or `Align`
+ * spacer; reserved bits; or a checksum.
+ */
 export type FieldKindDef =
   | { type: "Scalar" }
-  | { type: "Array"; count: number; stride_bits: number; offset_bits: number };
+  | {
+      type: "Array";
+      /** Fixed count, or the value of an earlier field plus `adjust`. */
+      count: number | { field: string; adjust?: number };
+      stride_bits: number;
+      offset_bits: number;
+      /** Element layout; omitted means each element is the field's fragments. */
+      element?: FieldKindDef;
+    }
+  | { type: "Struct"; fields: FieldDef[] }
+  /** Sub-fields chosen by the value of the field named `on`; case keys are decimal strings. */
+  | { type: "Switch"; on: string; cases: Record<string, FieldDef[]>; default?: FieldDef[] }
+  /**
+   * Bit range of any length read as one `{ kind: "bytes" }` value. When `len_bits`
+   * is not a multiple of 8, `padding` (default `"Trailing"`) says whether the
+   * spare bits of the partial byte are the low bits of the last byte or, with
+   * `"Leading"`, the high bits of the first.
+   */
+  | { type: "Bytes"; offset_bits?: number; len_bits: number; padding?: "Trailing" | "Leading" }
+  /**
+   * Text read as a `{ kind: "string" }` value, starting at `offset_bits` (default
+   * 0) and ending as `framing` says. `encoding` defaults to `"Utf8"`.
+   */
+  | {
+      type: "String";
+      offset_bits?: number;
+      framing: StringFramingDef;
+      encoding?: EncodingDef;
+    }
+  /** Gap in a `Sequential` layout; produces no value. */
+  | { type: "Skip"; bits: number }
+  /** Moves the cursor of a `Sequential` layout to the next multiple of `bits`. */
+  | { type: "Align"; bits: number }
+  /**
+   * Reserved bits at the field's fragments; produces no value. Serialize writes
+   * `fill` (default 0); with `check`, parse fails with `CONSTANT_MISMATCH` when
+   * the bits differ from it.
+   */
+  | { type: "Reserved"; fill?: number; check?: boolean }
+  /**
+   * Checksum at the field's fragments, computed by serialize over `covers`. With
+   * `check` (default true), parse fails with `CHECKSUM_MISMATCH` when the stored
+   * value differs.
+   */
+  | {
+      type: "Checksum";
+      algorithm: ChecksumAlgorithmDef;
+      covers: ChecksumCoverageDef;
+      check?: boolean;
+    };
+
+/**
+ * Where a `String` field ends: after a byte count `prefix_bits` wide, at a zero
+ * byte, or after `len_bytes` bytes padded with `pad` (default 0).
+ */
+export type StringFramingDef =
+  | { type: "LengthPrefixed"; prefix_bits: number }
+  | { type: "NullTerminated" }
+  | { type: "Fixed"; len_bytes: number; pad?: number };
+
+/** Checksum algorithm; CRC parameters follow the usual catalogue form. */
+export type ChecksumAlgorithmDef =
+  | {
+      type: "Crc";
+      width: number;
+      poly: number;
+      init?: number;
+      refin?: boolean;
+      refout?: boolean;
+      xorout?: number;
+    }
+  | { type: "Fletcher16" }
+  /** Over big-endian 16-bit words. */
+  | { type: "Fletcher32" }
+  | { type: "Sum8" }
+  | { type: "Xor8" };
+
+/**
+ * Data a checksum covers: bits relative to the start of the enclosing field list,
+ * or sibling fields in the order listed.
+ */
+export type ChecksumCoverageDef =
+  | { Bits: { start: number; end: number } }
+  | { Fields: string[] };
 
 /** Transform base type. */
 export type BaseDef = "Int" | "Float32" | "Float64" | "Bytes";
 
-/** Text encoding applied to a Bytes base. */
-export type EncodingDef = "Utf8" | "Ascii";
+/**
+ * Text encoding of a String field or a Bytes base. `Ebcdic` is code page 037;
+ * `SixBit` is AIS-style 6-bit packed ASCII.
+ */
+export type EncodingDef =
+  | "Utf8"
+  | "Ascii"
+  | "Latin1"
+  | "Utf16Le"
+  | "Utf16Be"
+  | "Ebcdic"
+  | "SixBit";
 
 /** Transform configuration attached to a field. */
 export interface TransformDef {
@@ -50,19 +162,78 @@ export interface TransformDef {
 export interface FieldDef {
   name: string;
   kind: FieldKindDef;
-  signed: boolean;
-  assemble: AssembleDef;
-  fragments: FragmentDef[];
+  signed?: boolean;
+  assemble?: AssembleDef;
+  /** May be omitted for `Struct` fields. */
+  fragments?: FragmentDef[];
   transform?: TransformDef;
+  /** Field is only present when the condition on an earlier field holds. */
+  condition?: ConditionDef;
+  /**
+   * Raw value written in place of a missing entry by `serializeWithOptions` with
+   * `useDefaults`, e.g. `{ U64: 1 }`.
+   */
+  default?: ConstantDef;
+  /**
+   * Value the field must hold, such as a sync word. Parse fails with
+   * `CONSTANT_MISMATCH` when it differs; serialize writes it when omitted.
+   */
+  constant?: ConstantDef;
+}
+
+/** Raw value of a `constant`, in the externally-tagged JSON shape, e.g. `{ U64: 0xEB90 }`. */
+export type ConstantDef =
+  | { U64: number }
+  | { I64: number }
+  /**
+   * Fields wider than 64 bits. A `bigint`, as `parse` returns, so values above
+   * 2^53 keep every bit; `Schema.compile` writes it as a plain JSON number.
+   */
+  | { U128: bigint }
+  | { I128: bigint }
+  | { Array: ConstantDef[] };
+
+/** Options for `serializeWithOptions`: where values missing from the input come from. */
+export interface SerializeOptions {
+  /** Write each field's `default` when the input has no entry for it. */
+  useDefaults?: boolean;
+  /**
+   * Existing packet to start from, e.g. one returned by `serialize`. Fields
+   * missing from the input keep the template's values, and bits no field covers
+   * keep the template's bits. Takes precedence over defaults.
+   */
+  template?: Uint8Array;
 }
 
+/** Test on the integer value of an earlier field. */
+export type ConditionDef =
+  | { type: "Equals"; field: string; value: number }
+  /** Holds when `field & mask == expected`. */
+  | { type: "Mask"; field: string; mask: number; expected: number }
+  /** Holds when `min <= field <= max`. */
+  | { type: "Range"; field: string; min: number; max: number };
+
+/**
+ * `Absolute`: offsets are fixed positions. `Sequential`: each field starts where
+ * the previous one ended, and offsets are relative to that cursor.
+ */
+export type LayoutDef = "Absolute" | "Sequential";
+
+/** Rounding used when inverse transforms turn scaled values back into integers. */
+export type RoundingDef = "Nearest" | "NearestEven" | "Floor" | "Ceil" | "Truncate";
+
 /** Write configuration for serialize. */
 export interface WriteConfigDef {
   bit_order?: BitOrderDef;
+  rounding?: RoundingDef;
 }
 
 /** Top-level schema definition. */
 export interface SchemaDef {
   fields: FieldDef[];
   write_config?: WriteConfigDef;
+  /** How fragment offsets are interpreted; defaults to `"Absolute"`. */
+  layout?: LayoutDef;
+  /** Accept fields that share bits instead of failing with `OVERLAPPING_FIELDS`. */
+  allow_overlap?: boolean;
 }
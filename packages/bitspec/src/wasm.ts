@@ -58,6 +58,10 @@ export function valueToWasm(v: Value): unknown {
       return { U64: v.value };
     case "i64":
       return { I64: v.value };
+    case "u128":
+      return { U128: v.value };
+    case "i128":
+      return { I128: v.value };
     case "f32":
       return { F32: v.value };
     case "f64":
@@ -68,11 +72,24 @@ export function valueToWasm(v: Value): unknown {
       return { String: v.value };
     case "array":
       return { Array: v.value.map(valueToWasm) };
+    case "struct": {
+      const fields: Record<string, unknown> = {};
+      for (const [k, inner] of Object.entries(v.value)) {
+        fields[k] = valueToWasm(inner);
+      }
+      return { Struct: fields };
+    }
+    case "absent":
+      return "Absent";
   }
 }
 
 /** Inverse of `valueToWasm`: converts the Rust-side shape back into the TS union. */
 export function valueFromWasm(raw: unknown): Value {
+  // Unit variants serialize as a bare string.
+  if (raw === "Absent") {
+    return { kind: "absent" };
+  }
   if (raw === null || typeof raw !== "object") {
     throw new Error("invalid value: not an object");
   }
@@ -88,6 +105,10 @@ export function valueFromWasm(raw: unknown): Value {
       return { kind: "u64", value: BigInt(inner as bigint | number | string) };
     case "I64":
       return { kind: "i64", value: BigInt(inner as bigint | number | string) };
+    case "U128":
+      return { kind: "u128", value: BigInt(inner as bigint | number | string) };
+    case "I128":
+      return { kind: "i128", value: BigInt(inner as bigint | number | string) };
     case "F32":
       return { kind: "f32", value: inner as number };
     case "F64":
@@ -101,6 +122,18 @@ export function valueFromWasm(raw: unknown): Value {
         kind: "array",
         value: (inner as unknown[]).map(valueFromWasm),
       };
+    case "Struct": {
+      // Nested maps arrive as JS `Map` objects, like the top-level result.
+      const entries =
+        inner instanceof Map
+          ? Array.from((inner as Map<string, unknown>).entries())
+          : Object.entries(inner as Record<string, unknown>);
+      const fields: Record<string, Value> = {};
+      for (const [k, field] of entries) {
+        fields[k] = valueFromWasm(field);
+      }
+      return { kind: "struct", value: fields };
+    }
     default:
       throw new Error(`unknown Value tag: ${tag}`);
   }
@@ -1,5 +1,5 @@
 import { beforeAll, describe, expect, it } from "vitest";
-import { BitspecError, init, Schema } from "../src/index.js";
+import { BitspecError, init, Schema, type SchemaDef } from "../src/index.js";
 
 beforeAll(async () => { await init(); });
 
@@ -20,6 +20,27 @@ describe("BitspecError", () => {
     }
   });
 
+  it("reports the field and bit range a read failed at", () => {
+    const schema = Schema.compile({
+      fields: [
+        { name: "items", kind: { type: "Array", count: 3, stride_bits: 8, offset_bits: 0 },
+          fragments: [{ offset_bits: 0, len_bits: 8 }] },
+      ],
+    });
+    try {
+      schema.parse(new Uint8Array([1, 2]));
+      expect.fail("should have thrown");
+    } catch (e) {
+      const err = e as BitspecError;
+      expect(err.code).toBe("PACKET_TOO_SHORT");
+      expect(err.field).toBe("items[2]");
+      expect(err.element).toBe(2);
+      expect(err.fragment).toBe(0);
+      expect(err.bitRange).toEqual([16, 24]);
+      expect(err.availableBits).toBe(16);
+    }
+  });
+
   it("throws MISSING_FIELD when serialize is missing a value", () => {
     const schema = Schema.compile({
       fields: [
@@ -45,4 +66,53 @@ describe("BitspecError", () => {
       expect((e as BitspecError).code).toBe("SCHEMA_JSON_PARSE_ERROR");
     }
   });
+
+  it("throws UNKNOWN_FIELD when an array count references a later field", () => {
+    try {
+      Schema.compile({
+        fields: [
+          { name: "items", kind: { type: "Array", count: { field: "len" }, stride_bits: 8, offset_bits: 8 },
+            fragments: [{ offset_bits: 0, len_bits: 8 }] },
+          { name: "len", kind: { type: "Scalar" }, fragments: [{ offset_bits: 0, len_bits: 8 }] },
+        ],
+      });
+      expect.fail("should have thrown");
+    } catch (e) {
+      expect(e).toBeInstanceOf(BitspecError);
+      expect((e as BitspecError).code).toBe("UNKNOWN_FIELD");
+    }
+  });
+
+  it("throws UNMATCHED_CASE when no switch case fits the discriminator", () => {
+    const schema = Schema.compile({
+      fields: [
+        { name: "msg_type", kind: { type: "Scalar" }, fragments: [{ offset_bits: 0, len_bits: 8 }] },
+        { name: "body", kind: { type: "Switch", on: "msg_type", cases: {
+          "1": [{ name: "a", kind: { type: "Scalar" }, fragments: [{ offset_bits: 8, len_bits: 8 }] }],
+        } } },
+      ],
+    });
+    try {
+      schema.parse(new Uint8Array([7, 0]));
+      expect.fail("should have thrown");
+    } catch (e) {
+      expect(e).toBeInstanceOf(BitspecError);
+      expect((e as BitspecError).code).toBe("UNMATCHED_CASE");
+    }
+  });
+
+  it("throws OVERLAPPING_FIELDS unless allow_overlap is set", () => {
+    const fields: SchemaDef["fields"] = [
+      { name: "a", kind: { type: "Scalar" }, fragments: [{ offset_bits: 0, len_bits: 8 }] },
+      { name: "b", kind: { type: "Scalar" }, fragments: [{ offset_bits: 4, len_bits: 8 }] },
+    ];
+    try {
+      Schema.compile({ fields });
+      expect.fail("should have thrown");
+    } catch (e) {
+      expect(e).toBeInstanceOf(BitspecError);
+      expect((e as BitspecError).code).toBe("OVERLAPPING_FIELDS");
+    }
+    expect(() => Schema.compile({ fields, allow_overlap: true })).not.toThrow();
+  });
 });
@@ -1,5 +1,5 @@
 import { beforeAll, describe, expect, it } from "vitest";
-import { init, Schema, type SchemaDef } from "../src/index.js";
+import { init, Schema, type FieldDef, type SchemaDef } from "../src/index.js";
 
 beforeAll(async () => { await init(); });
 
@@ -23,4 +23,139 @@ describe("roundtrip", () => {
     expect(parsed.a).toEqual({ kind: "u64", value: 0b1010n });
     expect(parsed.b).toEqual({ kind: "u64", value: 0b0101n });
   });
+
+  it("roundtrips an array of structs", () => {
+    const def: SchemaDef = {
+      fields: [
+        {
+          name: "sensors",
+          kind: {
+            type: "Array", count: 2, stride_bits: 16, offset_bits: 0,
+            element: {
+              type: "Struct",
+              fields: [
+                { name: "id", kind: { type: "Scalar" }, fragments: [{ offset_bits: 0, len_bits: 8 }] },
+                { name: "temp", kind: { type: "Scalar" }, signed: true,
+                  fragments: [{ offset_bits: 8, len_bits: 8 }] },
+              ],
+            },
+          },
+        },
+      ],
+    };
+    const schema = Schema.compile(def);
+    const parsed = schema.parse(new Uint8Array([1, 21, 2, 0xfe]));
+    expect(parsed.sensors).toEqual({
+      kind: "array",
+      value: [
+        { kind: "struct", value: { id: { kind: "u64", value: 1n }, temp: { kind: "i64", value: 21n } } },
+        { kind: "struct", value: { id: { kind: "u64", value: 2n }, temp: { kind: "i64", value: -2n } } },
+      ],
+    });
+    expect(schema.serialize(parsed)).toEqual(new Uint8Array([1, 21, 2, 0xfe]));
+  });
+
+  it("reads an array whose count comes from a length field", () => {
+    const def: SchemaDef = {
+      fields: [
+        { name: "len", kind: { type: "Scalar" }, fragments: [{ offset_bits: 0, len_bits: 8 }] },
+        {
+          name: "items",
+          kind: { type: "Array", count: { field: "len" }, stride_bits: 8, offset_bits: 8 },
+          fragments: [{ offset_bits: 0, len_bits: 8 }],
+        },
+      ],
+    };
+    const schema = Schema.compile(def);
+    const parsed = schema.parse(new Uint8Array([2, 7, 9]));
+    expect(parsed.items).toEqual({
+      kind: "array",
+      value: [{ kind: "u64", value: 7n }, { kind: "u64", value: 9n }],
+    });
+    expect(schema.serialize(parsed)).toEqual(new Uint8Array([2, 7, 9]));
+  });
+
+  it("places fields one after another in a sequential layout", () => {
+    const def: SchemaDef = {
+      layout: "Sequential",
+      fields: [
+        { name: "version", kind: { type: "Scalar" }, fragments: [{ offset_bits: 0, len_bits: 3 }] },
+        { name: "", kind: { type: "Align", bits: 8 } },
+        { name: "", kind: { type: "Skip", bits: 8 } },
+        { name: "value", kind: { type: "Scalar" }, fragments: [{ offset_bits: 0, len_bits: 16 }] },
+      ],
+    };
+    const schema = Schema.compile(def);
+    const parsed = schema.parse(new Uint8Array([0b1010_0000, 0xff, 0x12, 0x34]));
+    expect(parsed).toEqual({
+      version: { kind: "u64", value: 0b101n },
+      value: { kind: "u64", value: 0x1234n },
+    });
+    expect(schema.serialize(parsed)).toEqual(new Uint8Array([0b1010_0000, 0, 0x12, 0x34]));
+  });
+
+  it("reports a conditional field as absent when its flag is clear", () => {
+    const def: SchemaDef = {
+      layout: "Sequential",
+      fields: [
+        { name: "flags", kind: { type: "Scalar" }, fragments: [{ offset_bits: 0, len_bits: 8 }] },
+        {
+          name: "ext",
+          kind: { type: "Scalar" },
+          fragments: [{ offset_bits: 0, len_bits: 8 }],
+          condition: { type: "Mask", field: "flags", mask: 1, expected: 1 },
+        },
+        { name: "tail", kind: { type: "Scalar" }, fragments: [{ offset_bits: 0, len_bits: 8 }] },
+      ],
+    };
+    const schema = Schema.compile(def);
+    expect(schema.parse(new Uint8Array([1, 0xee, 0x77])).ext).toEqual({ kind: "u64", value: 0xeen });
+
+    const parsed = schema.parse(new Uint8Array([0, 0x77]));
+    expect(parsed.ext).toEqual({ kind: "absent" });
+    expect(schema.serialize(parsed)).toEqual(new Uint8Array([0, 0x77]));
+  });
+
+  it("picks the layout of a switch from its discriminator", () => {
+    const byte = (name: string): FieldDef => ({
+      name,
+      kind: { type: "Scalar" },
+      fragments: [{ offset_bits: 0, len_bits: 8 }],
+    });
+    const def: SchemaDef = {
+      layout: "Sequential",
+      fields: [
+        byte("msg_type"),
+        {
+          name: "body",
+          kind: { type: "Switch", on: "msg_type", cases: { "1": [byte("a")], "2": [byte("x"), byte("y")] } },
+        },
+      ],
+    };
+    const schema = Schema.compile(def);
+    const parsed = schema.parse(new Uint8Array([2, 10, 20]));
+    expect(parsed.body).toEqual({
+      kind: "struct",
+      value: { x: { kind: "u64", value: 10n }, y: { kind: "u64", value: 20n } },
+    });
+    expect(schema.serialize(parsed)).toEqual(new Uint8Array([2, 10, 20]));
+  });
+
+  it("parses back-to-back records as rows and as columns", () => {
+    const schema = Schema.compile({
+      fields: [
+        { name: "id", kind: { type: "Scalar" }, fragments: [{ offset_bits: 0, len_bits: 8 }] },
+        { name: "temp", kind: { type: "Scalar" }, signed: true, fragments: [{ offset_bits: 8, len_bits: 8 }] },
+      ],
+    });
+    const dump = new Uint8Array([1, 0xFF, 2, 20, 3, 21]);
+
+    const records = schema.parseMany(dump);
+    expect(records).toHaveLength(3);
+    expect(records[0]).toEqual({ id: { kind: "u64", value: 1n }, temp: { kind: "i64", value: -1n } });
+
+    const columns = schema.parseColumns(dump);
+    expect(columns.id.map((v) => v.value)).toEqual([1n, 2n, 3n]);
+    expect(columns.temp.map((v) => v.value)).toEqual([-1n, 20n, 21n]);
+  });
 });
@@ -27,4 +27,28 @@ describe("transforms", () => {
     const parsed = schema.parse(new Uint8Array([2]));
     expect(parsed.s).toEqual({ kind: "string", value: "two" });
   });
+
+  it("serializeTransformed inverts scale, enum and string transforms", () => {
+    const schema = Schema.compile({
+      fields: [
+        { name: "t", kind: { type: "Scalar" }, signed: false, assemble: "ConcatMsb",
+          fragments: [{ offset_bits: 0, len_bits: 8 }],
+          transform: { base: "Int", scale: 0.5, offset: 10 } },
+        { name: "s", kind: { type: "Scalar" }, signed: false, assemble: "ConcatMsb",
+          fragments: [{ offset_bits: 8, len_bits: 8 }],
+          transform: { base: "Int", enum_map: { 1: "one", 2: "two" } } },
+        { name: "name", kind: { type: "Array", count: 3, stride_bits: 8, offset_bits: 16 },
+          signed: false, assemble: "ConcatMsb",
+          fragments: [{ offset_bits: 0, len_bits: 8 }],
+          transform: { base: "Bytes", encoding: "Ascii", zero_terminated: true } },
+      ],
+    });
+    const bytes = schema.serializeTransformed({
+      t: { kind: "f64", value: 20 },
+      s: { kind: "string", value: "two" },
+      name: { kind: "string", value: "ok" },
+    });
+    expect(bytes).toEqual(new Uint8Array([20, 2, 0x6f, 0x6b, 0]));
+    expect(schema.parse(bytes).name).toEqual({ kind: "string", value: "ok" });
+  });
 });
@@ -24,8 +24,9 @@ export class WasmSchema {
      * The `schema_json` string must deserialize into [`SchemaDef`], which
      * in turn describes:
      *
-     * - **Fields**: their name, kind (scalar or fixed‑size array),
-     *   signedness and assemble strategy.
+     * - **Fields**: their name, kind (scalar, array, struct, switch, bytes,
+     *   string, spacer, reserved bits or checksum), signedness and assemble
+     *   strategy.
      * - **Fragments**: the bit ranges that make up each field.
      * - **Transforms** (optional): how to post‑process raw values using
      *   `bitspec::transform` (base type, scale/offset, encodings, enums).
@@ -47,6 +48,20 @@ export class WasmSchema {
      * `message` is a human-readable description.
      */
     parse(data: Uint8Array): any;
+    /**
+     * Like [`WasmSchema::parse_many`], but returns one array per top-level field
+     * holding that field's value for every record.
+     */
+    parse_columns(data: Uint8Array): any;
+    /**
+     * Parses a buffer of records laid back to back, such as a data logger dump,
+     * in a single call.
+     *
+     * Returns an array with one object per record, each shaped like the result
+     * of [`WasmSchema::parse`]. A trailing partial record throws
+     * `PACKET_TOO_SHORT` (see [`bitspec::schema::Schema::parse_many`]).
+     */
+    parse_many(data: Uint8Array): any;
     /**
      * Serializes a JavaScript object into bytes according to this schema.
      *
@@ -54,6 +69,24 @@ export class WasmSchema {
      * compatible with [`bitspec::value::Value`].
      */
     serialize(obj: any): Uint8Array;
+    /**
+     * Serializes a JavaScript object of transformed values into bytes.
+     *
+     * This is the inverse of [`WasmSchema::parse`]: each field's transform is
+     * undone first (see [`bitspec::schema::Schema::serialize_transformed`]), so
+     * scaled floats, enum labels and decoded strings are accepted.
+     */
+    serialize_transformed(obj: any): Uint8Array;
+    /**
+     * Serializes a JavaScript object into bytes like [`WasmSchema::serialize`],
+     * filling in the fields it leaves out.
+     *
+     * With `use_defaults`, a missing field takes the `default` of its definition.
+     * With a `template` packet, missing fields and bits no field covers keep the
+     * template's values; the template takes precedence over defaults (see
+     * [`bitspec::schema::Schema::serialize_with_options`]).
+     */
+    serialize_with_options(obj: any, use_defaults: boolean, template?: Uint8Array | null): Uint8Array;
 }
 
 export type InitInput = RequestInfo | URL | Response | BufferSource | WebAssembly.Module;
@@ -63,7 +96,10 @@ export interface InitOutput {
     readonly __wbg_wasmschema_free: (a: number, b: number) => void;
     readonly wasmschema_new: (a: number, b: number) => [number, number, number];
     readonly wasmschema_parse: (a: number, b: number, c: number) => [number, number, number];
+    readonly wasmschema_parse_columns: (a: number, b: number, c: number) => [number, number, number];
+    readonly wasmschema_parse_many: (a: number, b: number, c: number) => [number, number, number];
     readonly wasmschema_serialize: (a: number, b: any) => [number, number, number, number];
+    readonly wasmschema_serialize_transformed: (a: number, b: any) => [number, number, number, number];
     readonly __wbindgen_malloc: (a: number, b: number) => number;
     readonly __wbindgen_realloc: (a: number, b: number, c: number, d: number) => number;
     readonly __wbindgen_exn_store: (a: number) => void;
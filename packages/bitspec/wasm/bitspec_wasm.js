@@ -75,6 +75,40 @@ export class WasmSchema {
         }
         return takeFromExternrefTable0(ret[0]);
     }
+    /**
+     * Like [`WasmSchema::parse_many`], but returns one array per top-level field
+     * holding that field's value for every record.
+     * @param {Uint8Array} data
+     * @returns {any}
+     */
+    parse_columns(data) {
+        const ptr0 = passArray8ToWasm0(data, wasm.__wbindgen_malloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.wasmschema_parse_columns(this.__wbg_ptr, ptr0, len0);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return takeFromExternrefTable0(ret[0]);
+    }
+    /**
+     * Parses a buffer of records laid back to back, such as a data logger dump,
+     * in a single call.
+     *
+     * Returns an array with one object per record, each shaped like the result
+     * of [`WasmSchema::parse`]. A trailing partial record throws
+     * `PACKET_TOO_SHORT` (see [`bitspec::schema::Schema::parse_many`]).
+     * @param {Uint8Array} data
+     * @returns {any}
+     */
+    parse_many(data) {
+        const ptr0 = passArray8ToWasm0(data, wasm.__wbindgen_malloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.wasmschema_parse_many(this.__wbg_ptr, ptr0, len0);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return takeFromExternrefTable0(ret[0]);
+    }
     /**
      * Serializes a JavaScript object into bytes according to this schema.
      *
@@ -92,6 +126,24 @@ export class WasmSchema {
         wasm.__wbindgen_free(ret[0], ret[1] * 1, 1);
         return v1;
     }
+    /**
+     * Serializes a JavaScript object of transformed values into bytes.
+     *
+     * This is the inverse of [`WasmSchema::parse`]: each field's transform is
+     * undone first (see [`bitspec::schema::Schema::serialize_transformed`]), so
+     * scaled floats, enum labels and decoded strings are accepted.
+     * @param {any} obj
+     * @returns {Uint8Array}
+     */
+    serialize_transformed(obj) {
+        const ret = wasm.wasmschema_serialize_transformed(this.__wbg_ptr, obj);
+        if (ret[3]) {
+            throw takeFromExternrefTable0(ret[2]);
+        }
+        var v1 = getArrayU8FromWasm0(ret[0], ret[1]).slice();
+        wasm.__wbindgen_free(ret[0], ret[1] * 1, 1);
+        return v1;
+    }
 }
 if (Symbol.dispose) WasmSchema.prototype[Symbol.dispose] = WasmSchema.prototype.free;
 
@@ -4,7 +4,10 @@ export const memory: WebAssembly.Memory;
 export const __wbg_wasmschema_free: (a: number, b: number) => void;
 export const wasmschema_new: (a: number, b: number) => [number, number, number];
 export const wasmschema_parse: (a: number, b: number, c: number) => [number, number, number];
+export const wasmschema_parse_columns: (a: number, b: number, c: number) => [number, number, number];
+export const wasmschema_parse_many: (a: number, b: number, c: number) => [number, number, number];
 export const wasmschema_serialize: (a: number, b: any) => [number, number, number, number];
+export const wasmschema_serialize_transformed: (a: number, b: any) => [number, number, number, number];
 export const __wbindgen_malloc: (a: number, b: number) => number;
 export const __wbindgen_realloc: (a: number, b: number, c: number, d: number) => number;
 export const __wbindgen_exn_store: (a: number) => void;